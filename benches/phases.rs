@@ -0,0 +1,88 @@
+//! Benchmarks of the phases of the formatter, over a small file, a medium one and one the size of
+//! an amalgamation with a hundred thousand lines. Run them with `cargo bench`, and compare against
+//! a baseline with `cargo bench -- --save-baseline main` and `--baseline main`. A real file can be
+//! measured as well by setting `CFMT_BENCH_FILE` to its path, such as the sqlite amalgamation of
+//! the corpus test.
+
+use cfmt::formatter::formatter::format;
+use cfmt::{format_source, lex, parse, Config};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::{env, fs};
+
+/// A part of a source file with a bit of everything, declaring names which end in `n`.
+fn chunk(n: usize) -> String {
+    format!(
+        "/* A point on the grid. */\n\
+         struct point_{n} {{ int x, y; }};\n\
+         \n\
+         static const char *names_{n}[] = {{ \"zero\", \"one\", \"two\" }};\n\
+         \n\
+         // Walk the points, and return their sum.\n\
+         static long walk_{n}(const struct point_{n} *points, unsigned count, int flags) {{\n\
+         \x20   long sum = 0;\n\
+         \x20   for (unsigned i = 0; i < count; i++) {{\n\
+         \x20       switch (points[i].x & 3) {{\n\
+         \x20       case 0: sum += points[i].y; break;\n\
+         \x20       case 1: sum -= (long)points[i].y * 2; break;\n\
+         \x20       default: if (flags & 0x10 && names_{n}[i % 3][0] != 'z') sum++; else sum--;\n\
+         \x20       }}\n\
+         \x20   }}\n\
+         \x20   while (sum > 1000000L) {{ sum /= 2; }}\n\
+         \x20   return flags ? sum : -sum;  /* flipped */\n\
+         }}\n\
+         \n"
+    )
+}
+
+/// A source file with about as many lines as asked for.
+fn generated(lines: usize) -> String {
+    let chunk_lines = chunk(0).lines().count();
+    let mut source = String::from("#include <stdio.h>\n#include <stdlib.h>\n\n");
+    for n in 0..lines.div_ceil(chunk_lines) {
+        source += &chunk(n);
+    }
+    source
+}
+
+fn phases(c: &mut Criterion) {
+    let mut files = vec![
+        ("small".to_string(), generated(50)),
+        ("medium".to_string(), generated(5_000)),
+        ("amalgamation".to_string(), generated(100_000)),
+    ];
+    if let Some(path) = env::var_os("CFMT_BENCH_FILE") {
+        let name = path.to_string_lossy().into_owned();
+        files.push((
+            name,
+            fs::read_to_string(&path).expect("the file can be read"),
+        ));
+    }
+
+    let config = Config::default();
+    for (name, source) in &files {
+        let lexemes = lex(source).expect("the file can be lexed");
+        let (unit, errors) = parse(lexemes.clone(), source, "", &config);
+        assert!(errors.is_empty(), "{} can be parsed", name);
+
+        let mut group = c.benchmark_group(name.as_str());
+        group.throughput(Throughput::Bytes(source.len() as u64));
+        if source.len() > 1_000_000 {
+            group.sample_size(10);
+        }
+
+        group.bench_function("lex", |b| b.iter(|| lex(source)));
+        group.bench_function("parse", |b| {
+            b.iter_batched(
+                || lexemes.clone(),
+                |lexemes| parse(lexemes, source, "", &config),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_function("print", |b| b.iter(|| format(&unit, &config)));
+        group.bench_function("format", |b| b.iter(|| format_source(source, &config)));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, phases);
+criterion_main!(benches);
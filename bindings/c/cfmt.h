@@ -0,0 +1,42 @@
+/*
+ * The C interface of cfmt, for programs which embed the formatter rather than running it. Build
+ * the library from the root of the repository with
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * which writes it to target/release/libcfmt.so, or libcfmt.dylib or cfmt.dll.
+ */
+
+#ifndef CFMT_H
+#define CFMT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The source was formatted. */
+#define CFMT_OK 0
+
+/* The source could not be formatted, because of the errors described in the output. */
+#define CFMT_ERROR 1
+
+/* The arguments were invalid, such as a null source or text which is not UTF-8. */
+#define CFMT_INVALID_ARGUMENTS 2
+
+/*
+ * Format a null-terminated, UTF-8 encoded source with options written as a JSON object, such as
+ * {"indent_width": 2}, named as in .cfmt.toml. The options may be NULL for the defaults.
+ *
+ * On CFMT_OK, *out is set to the formatted code, and on CFMT_ERROR to the messages of the errors.
+ * Either string must be freed with cfmt_string_free. On CFMT_INVALID_ARGUMENTS, *out is NULL.
+ */
+int cfmt_format(const char *source, const char *config_json, char **out);
+
+/* Free a string returned by cfmt_format. Freeing NULL does nothing. */
+void cfmt_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
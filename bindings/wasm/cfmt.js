@@ -0,0 +1,60 @@
+// Bindings for the formatter compiled to WebAssembly, for use in the browser or in Node.js. Build
+// the module from the root of the repository with
+//
+//     cargo rustc --lib --release --target wasm32-unknown-unknown --crate-type cdylib
+//
+// which writes it to target/wasm32-unknown-unknown/release/cfmt.wasm, and load it with `init`:
+//
+//     import { init, format } from "./cfmt.js";
+//     await init(fetch("cfmt.wasm"));
+//     format("int main(){return 0;}", { indent_width: 2 });
+//
+// The tests in cfmt.test.js run against the built module with `node --test` from this directory.
+
+let exports = null;
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+// Instantiate the module from a response, such as that of `fetch`, or from its bytes.
+export async function init(module) {
+  const source = await module;
+  const { instance } =
+    source instanceof Response
+      ? await WebAssembly.instantiateStreaming(source)
+      : await WebAssembly.instantiate(source);
+  exports = instance.exports;
+}
+
+// Copy a string into the memory of the module, returning its pointer and length.
+function pass(text) {
+  const bytes = encoder.encode(text);
+  const pointer = exports.cfmt_alloc(bytes.length);
+  new Uint8Array(exports.memory.buffer, pointer, bytes.length).set(bytes);
+  return [pointer, bytes.length];
+}
+
+// Format C source code. The options are named as in `.cfmt.toml`, and given either as an object
+// or as the text of a JSON object. Errors are thrown with the messages the command would write.
+export function format(source, config = {}) {
+  if (exports === null) {
+    throw new Error("cfmt: call init() before format()");
+  }
+
+  const json = typeof config === "string" ? config : JSON.stringify(config);
+  const [sourcePointer, sourceLength] = pass(source);
+  const [configPointer, configLength] = pass(json);
+  try {
+    const succeeded = exports.cfmt_format(sourcePointer, sourceLength, configPointer, configLength);
+    const result = decoder.decode(
+      new Uint8Array(exports.memory.buffer, exports.cfmt_result(), exports.cfmt_result_len())
+    );
+    if (!succeeded) {
+      throw new Error(result);
+    }
+    return result;
+  } finally {
+    exports.cfmt_free(sourcePointer, sourceLength);
+    exports.cfmt_free(configPointer, configLength);
+  }
+}
@@ -0,0 +1,30 @@
+// Tests of the bindings, run with `node --test` from this directory once the module is built as
+// described in cfmt.js.
+
+import { readFile } from "node:fs/promises";
+import { test } from "node:test";
+import assert from "node:assert/strict";
+import { init, format } from "./cfmt.js";
+
+const module = new URL("../../target/wasm32-unknown-unknown/release/cfmt.wasm", import.meta.url);
+
+test("format before init", () => {
+  assert.throws(() => format("int a;"), /call init\(\) before format\(\)/);
+});
+
+test("format with options", async () => {
+  await init(readFile(module));
+
+  assert.equal(format("int main(){return 0;}"), "int main() {\n    return 0;\n}\n");
+  assert.equal(format("int main(){return 0;}", { indent_width: 2 }), "int main() {\n  return 0;\n}\n");
+  assert.equal(format("char *s=\"é\";", '{"pointer_alignment": "left"}'), 'char* s = "é";\n');
+  assert.throws(() => format("int a;", { indent: 2 }), /unknown option `indent`/);
+  // Code which does not parse is kept as it was, rather than failing the call.
+  assert.equal(format("int x = ;\nint  y;"), "int x = ;\nint y;\n");
+
+  // The memory of every call is given back, so that many calls do not run out of it.
+  const source = "int x;\n".repeat(1000);
+  for (let i = 0; i < 100; i++) {
+    assert.equal(format(source), source);
+  }
+});
@@ -0,0 +1,119 @@
+//! The C interface of the formatter, for editors and IDEs which embed it rather than running the
+//! command. It is built with the `ffi` feature, as in
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`, and declared for C in
+//! `bindings/c/cfmt.h`. Strings returned by the formatter are owned by the caller, who frees them
+//! with `cfmt_string_free`.
+
+use crate::bindings::format_json;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// The source was formatted.
+const CFMT_OK: c_int = 0;
+
+/// The source could not be formatted, because of the errors which are described in the output.
+const CFMT_ERROR: c_int = 1;
+
+/// The arguments were invalid, such as a null source or text which is not UTF-8.
+const CFMT_INVALID_ARGUMENTS: c_int = 2;
+
+/// Format a null-terminated source with options written as a JSON object, which may be null for
+/// the defaults. The formatted code, or the messages of the errors, are written to `out`.
+///
+/// # Safety
+///
+/// `source` and `config_json` must be null-terminated strings, and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn cfmt_format(
+    source: *const c_char,
+    config_json: *const c_char,
+    out: *mut *mut c_char,
+) -> c_int {
+    if out.is_null() {
+        return CFMT_INVALID_ARGUMENTS;
+    }
+    *out = ptr::null_mut();
+    if source.is_null() {
+        return CFMT_INVALID_ARGUMENTS;
+    }
+
+    let source = CStr::from_ptr(source).to_str();
+    let config = match config_json.is_null() {
+        true => Ok("{}"),
+        false => CStr::from_ptr(config_json).to_str(),
+    };
+    let (Ok(source), Ok(config)) = (source, config) else {
+        return CFMT_INVALID_ARGUMENTS;
+    };
+
+    // A panic must not unwind into the caller, so it is reported like any other error.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| format_json(source, config)))
+        .unwrap_or_else(|_| Err("the formatter crashed".to_string()));
+    let (code, text) = match result {
+        Ok(formatted) => (CFMT_OK, formatted),
+        Err(error) => (CFMT_ERROR, error),
+    };
+
+    match CString::new(text) {
+        Ok(text) => {
+            *out = text.into_raw();
+            code
+        }
+        Err(_) => CFMT_INVALID_ARGUMENTS,
+    }
+}
+
+/// Free a string returned by the formatter. Freeing null does nothing.
+///
+/// # Safety
+///
+/// The string must have been returned by the formatter, and not be freed already.
+#[no_mangle]
+pub unsafe extern "C" fn cfmt_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bindings::ffi::{cfmt_format, cfmt_string_free, CFMT_ERROR, CFMT_OK};
+    use std::ffi::{CStr, CString};
+    use std::ptr;
+
+    fn format(source: &str, config: Option<&str>) -> (i32, String) {
+        let source = CString::new(source).unwrap();
+        let config = config.map(|config| CString::new(config).unwrap());
+        let config = config
+            .as_ref()
+            .map_or(ptr::null(), |config| config.as_ptr());
+
+        let mut out = ptr::null_mut();
+        unsafe {
+            let code = cfmt_format(source.as_ptr(), config, &mut out);
+            let text = CStr::from_ptr(out).to_str().unwrap().to_string();
+            cfmt_string_free(out);
+            (code, text)
+        }
+    }
+
+    #[test]
+    fn strings_are_passed_as_in_c() {
+        assert_eq!(format("int  x;", None), (CFMT_OK, "int x;\n".to_string()));
+        assert_eq!(
+            format(
+                "int x=1;",
+                Some("{\"space_around_assignment_operators\": false}")
+            ),
+            (CFMT_OK, "int x=1;\n".to_string())
+        );
+        assert_eq!(
+            format("int x = ;", None),
+            (CFMT_OK, "int x = ;\n".to_string())
+        );
+        let (code, text) = format("int x;", Some("{\"indent\": 2}"));
+        assert_eq!(code, CFMT_ERROR);
+        assert!(text.starts_with("invalid options"));
+    }
+}
@@ -0,0 +1,45 @@
+//! The entry points of the formatter for programs which are not written in Rust. They are handed
+//! the options as a JSON object, named as in configuration files, and report errors as the command
+//! would.
+
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+use crate::format_source;
+use crate::settings::builder::ConfigBuilder;
+
+/// The name under which the errors of code passed in by another program are reported.
+const INPUT_NAME: &str = "<input>";
+
+/// Format source code with options written as a JSON object, or fail with the messages of the
+/// errors.
+pub fn format_json(source: &str, config: &str) -> Result<String, String> {
+    let config = ConfigBuilder::new()
+        .json(config)
+        .build()
+        .map_err(|error| format!("invalid options: {}", error))?;
+    format_source(source, &config).map_err(|diagnostics| diagnostics.render(INPUT_NAME, source))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bindings::format_json;
+
+    #[test]
+    fn options_are_given_as_json() {
+        assert_eq!(
+            format_json("int main(){}", "{\"brace_style\": \"allman\"}"),
+            Ok("int main()\n{\n}\n".to_string())
+        );
+        assert_eq!(
+            format_json("int main(){}", "{\"indent\": 2}"),
+            Err("invalid options: line 1: unknown option `indent`".to_string())
+        );
+        assert_eq!(
+            format_json("int x = ;", "{}"),
+            Ok("int x = ;\n".to_string())
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! The exports of the formatter when it is compiled to WebAssembly, for the playground and editor
+//! extensions in the browser. Strings are passed through the memory of the module as UTF-8, by a
+//! pointer and a length, and `bindings/wasm/cfmt.js` wraps the exports into
+//! `format(source, configJson)`. The module is built with
+//! `cargo rustc --lib --release --target wasm32-unknown-unknown --crate-type cdylib`.
+
+use crate::bindings::format_json;
+use std::cell::RefCell;
+
+thread_local! {
+    /// The outcome of the last call to `cfmt_format`, which is kept until the next call.
+    static RESULT: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// Reserve memory for a number of bytes, for a string which is written into the module.
+#[no_mangle]
+pub extern "C" fn cfmt_alloc(len: usize) -> *mut u8 {
+    let mut buffer = Vec::<u8>::with_capacity(len);
+    let pointer = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    pointer
+}
+
+/// Free the memory reserved by `cfmt_alloc`.
+///
+/// # Safety
+///
+/// The pointer and the length must be those of a call to `cfmt_alloc`, which was not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn cfmt_free(pointer: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(pointer, 0, len));
+}
+
+/// Format source code with options written as a JSON object, returning whether it succeeded. The
+/// formatted code, or the messages of the errors, are then found at `cfmt_result`.
+///
+/// # Safety
+///
+/// Both pointers must point to as many bytes as their lengths say.
+#[no_mangle]
+pub unsafe extern "C" fn cfmt_format(
+    source: *const u8,
+    source_len: usize,
+    config: *const u8,
+    config_len: usize,
+) -> bool {
+    let source = std::slice::from_raw_parts(source, source_len);
+    let config = std::slice::from_raw_parts(config, config_len);
+    let result = match (std::str::from_utf8(source), std::str::from_utf8(config)) {
+        (Ok(source), Ok(config)) => format_json(source, config),
+        _ => Err("the source and the options must be UTF-8".to_string()),
+    };
+
+    let succeeded = result.is_ok();
+    RESULT.with(|last| *last.borrow_mut() = result.unwrap_or_else(|error| error));
+    succeeded
+}
+
+/// The start of the outcome of the last call to `cfmt_format`.
+#[no_mangle]
+pub extern "C" fn cfmt_result() -> *const u8 {
+    RESULT.with(|last| last.borrow().as_ptr())
+}
+
+/// The length in bytes of the outcome of the last call to `cfmt_format`.
+#[no_mangle]
+pub extern "C" fn cfmt_result_len() -> usize {
+    RESULT.with(|last| last.borrow().len())
+}
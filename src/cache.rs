@@ -0,0 +1,154 @@
+//! A cache of the files which are known to be formatted, so that repeated runs over a large
+//! repository only format the files which changed in between. A file is known by a hash of its
+//! contents together with a hash of its path, the options it was formatted with and the version of
+//! the formatter, so that changing any of those formats the file again. The path is part of it as
+//! header guards and the order of includes depend on it.
+
+use crate::formatter::config::FormatConfig;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::{env, fs, io, process};
+
+/// The contents of a file, and its path and the options and version it was formatted with.
+pub type Key = (u64, u64);
+
+/// Hash bytes with 64 bit FNV-1a, which does not change between versions of Rust.
+fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The key under which a file at the given path with the given contents is known to be formatted
+/// with the given options. Formatting only the tokens gives different code, so it has keys of its
+/// own.
+pub fn key(file_path: &str, contents: &str, config: &FormatConfig, fallback: bool) -> Key {
+    let settings = format!(
+        "{} {:?} {} {}",
+        env!("CARGO_PKG_VERSION"),
+        config,
+        fallback,
+        file_path
+    );
+    (hash(contents.as_bytes()), hash(settings.as_bytes()))
+}
+
+/// The default place of the cache, in the cache directory of the user.
+pub fn default_path() -> Option<PathBuf> {
+    let directory = match env::var_os("XDG_CACHE_HOME") {
+        Some(directory) if !directory.is_empty() => PathBuf::from(directory),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(directory.join("cfmt").join("formatted"))
+}
+
+/// The keys of the files which are known to be formatted, which can be shared between the jobs
+/// formatting the files.
+#[derive(Debug)]
+pub struct Cache {
+    path: PathBuf,
+    keys: Mutex<HashSet<Key>>,
+    /// The number of keys read from the file, to tell whether any were added since.
+    loaded: usize,
+}
+
+impl Cache {
+    /// Read the cache from a file, with one key per line. A missing or unreadable file is an empty
+    /// cache, as is any line which cannot be read.
+    pub fn load(path: &Path) -> Cache {
+        let keys: HashSet<Key> = fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (contents, settings) = line.split_once(' ')?;
+                let contents = u64::from_str_radix(contents, 16).ok()?;
+                let settings = u64::from_str_radix(settings, 16).ok()?;
+                Some((contents, settings))
+            })
+            .collect();
+
+        Cache {
+            path: path.to_path_buf(),
+            loaded: keys.len(),
+            keys: Mutex::new(keys),
+        }
+    }
+
+    pub fn contains(&self, key: Key) -> bool {
+        self.keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&key)
+    }
+
+    pub fn insert(&self, key: Key) {
+        self.keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key);
+    }
+
+    /// Write the cache back to its file if any keys were added. It is written to a temporary file
+    /// which is renamed over it, so that other runs never read it half written.
+    pub fn save(&self) -> io::Result<()> {
+        let keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        if keys.len() == self.loaded {
+            return Ok(());
+        }
+
+        let mut text = String::new();
+        for (contents, settings) in keys.iter() {
+            text += &format!("{:016x} {:016x}\n", contents, settings);
+        }
+
+        if let Some(directory) = self.path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let temporary = self
+            .path
+            .with_file_name(format!(".{}.cfmt-{}", name, process::id()));
+        let result = fs::write(&temporary, text).and_then(|_| fs::rename(&temporary, &self.path));
+        if result.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::{key, Cache};
+    use crate::formatter::config::FormatConfig;
+    use std::fs;
+
+    #[test]
+    fn keys_are_saved() {
+        let path = std::env::temp_dir()
+            .join(format!("cfmt-cache-{}", std::process::id()))
+            .join("formatted");
+        let config = FormatConfig::default();
+        let other = FormatConfig {
+            indent_width: 2,
+            ..FormatConfig::default()
+        };
+
+        let cache = Cache::load(&path);
+        assert!(!cache.contains(key("a.c", "int a;\n", &config, false)));
+        cache.insert(key("a.c", "int a;\n", &config, false));
+        cache.save().unwrap();
+
+        let cache = Cache::load(&path);
+        assert!(cache.contains(key("a.c", "int a;\n", &config, false)));
+        assert!(!cache.contains(key("a.c", "int a;\n", &config, true)));
+        assert!(!cache.contains(key("a.c", "int a;\n", &other, false)));
+        assert!(!cache.contains(key("a.c", "int b;\n", &config, false)));
+
+        // The same code in another file may be formatted differently, as by its header guard.
+        assert!(!cache.contains(key("b.c", "int a;\n", &config, false)));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
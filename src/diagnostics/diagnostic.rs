@@ -0,0 +1,264 @@
+use crate::diagnostics::location::Location;
+use crate::error::Error;
+use crate::lexer::span::Span;
+use crate::parser::parser::ParserError;
+use std::fmt::{Display, Formatter};
+
+/// How severe a diagnostic is.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A single problem found in a source file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    /// The name of the rule which found the problem, if it can be turned off.
+    pub rule: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            span,
+            message,
+            rule: None,
+        }
+    }
+
+    pub fn warning(rule: &'static str, span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message,
+            rule: Some(rule),
+        }
+    }
+
+    /// A warning about code which is kept as it was written, because it could not be lexed, parsed
+    /// or formatted, while the code around it is still formatted.
+    pub fn recovered(span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message: format!("{}, so the code is kept as it was", message),
+            rule: None,
+        }
+    }
+
+    /// A warning about code which could not be parsed, but which is formatted all the same, as only
+    /// the tokens of the file are formatted.
+    pub fn tokens_only(span: Span, message: String) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            span,
+            message: format!("{}, so only the tokens of the code are formatted", message),
+            rule: None,
+        }
+    }
+
+    /// Render the diagnostic in the style of a compiler: the location and message, followed by
+    /// the offending line with a caret underneath the start of the span.
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let location = Location::of(source, self.span.start);
+        let line = source.lines().nth(location.line - 1).unwrap_or("");
+        let indent: String = line
+            .chars()
+            .take(location.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let rule = match self.rule {
+            Some(rule) => format!(" [{}]", rule),
+            None => String::new(),
+        };
+        format!(
+            "{}:{}:{}: {}: {}{}\n{}\n{}^\n",
+            path,
+            location.line,
+            location.column,
+            self.severity.as_str(),
+            self.message,
+            rule,
+            line,
+            indent
+        )
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity.as_str(), self.message)?;
+        if let Some(rule) = self.rule {
+            write!(f, " [{}]", rule)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(error: &ParserError) -> Diagnostic {
+        Diagnostic::error(error.span(), error.to_string())
+    }
+}
+
+/// Errors without a place in the source, such as those of reading a file, are reported at its
+/// start.
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Diagnostic {
+        let span = error.span().unwrap_or(Span::new(0, 0));
+        Diagnostic::error(span, error.to_string())
+    }
+}
+
+/// All diagnostics collected while processing a source file, so that they can be reported at
+/// once instead of stopping at the first one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics::default()
+    }
+
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// The diagnostics in source order.
+    pub fn sorted(&self) -> Vec<&Diagnostic> {
+        let mut diagnostics: Vec<&Diagnostic> = self.diagnostics.iter().collect();
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        diagnostics
+    }
+
+    /// The number of diagnostics with a given severity.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    /// Render all diagnostics in source order, followed by a summary line with the counts.
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let mut result = String::new();
+        for diagnostic in self.sorted() {
+            result.push_str(&diagnostic.render(path, source));
+        }
+
+        let counted = |severity: Severity| {
+            let count = self.count(severity);
+            let plural = if count == 1 { "" } else { "s" };
+            format!("{} {}{}", count, severity.as_str(), plural)
+        };
+        let summary = match self.count(Severity::Warning) {
+            0 => counted(Severity::Error),
+            _ if self.count(Severity::Error) == 0 => counted(Severity::Warning),
+            _ => format!(
+                "{}, {}",
+                counted(Severity::Error),
+                counted(Severity::Warning)
+            ),
+        };
+        result.push_str(&format!("{}: {}\n", path, summary));
+
+        result
+    }
+}
+
+impl FromIterator<Diagnostic> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = Diagnostic>>(iter: I) -> Diagnostics {
+        Diagnostics {
+            diagnostics: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// The diagnostics in source order, one per line, without their locations.
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, diagnostic) in self.sorted().into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Diagnostics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_with_caret() {
+        let source = "int main() {\n\tint x = ;\n}\n";
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Diagnostic::error(
+            Span::new(22, 23),
+            "unexpected `;`".to_string(),
+        ));
+
+        let expected = "main.c:2:10: error: unexpected `;`\n\
+                        \tint x = ;\n\
+                        \t        ^\n\
+                        main.c: 1 error\n";
+        assert_eq!(diagnostics.render("main.c", source), expected);
+    }
+
+    #[test]
+    fn sorted_by_position() {
+        let source = "a;\nb;\n";
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Diagnostic::error(Span::new(3, 4), "second".to_string()));
+        diagnostics.push(Diagnostic::error(Span::new(0, 1), "first".to_string()));
+
+        let rendered = diagnostics.render("x.c", source);
+        assert!(rendered.find("first").unwrap() < rendered.find("second").unwrap());
+        assert!(rendered.ends_with("x.c: 2 errors\n"));
+    }
+
+    #[test]
+    fn warnings_name_their_rule() {
+        let source = "a;\n";
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.push(Diagnostic::warning(
+            "final-newline",
+            Span::new(2, 2),
+            "the file ends badly".to_string(),
+        ));
+        assert_eq!(
+            diagnostics.render("x.c", source),
+            "x.c:1:3: warning: the file ends badly [final-newline]\na;\n  ^\nx.c: 1 warning\n"
+        );
+
+        diagnostics.push(Diagnostic::error(Span::new(0, 1), "first".to_string()));
+        assert!(diagnostics
+            .render("x.c", source)
+            .ends_with("x.c: 1 error, 1 warning\n"));
+    }
+}
@@ -0,0 +1,84 @@
+/// A human readable position in a source file, where both the line and the column start at one.
+/// The column counts characters rather than bytes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// Find the location of a byte offset in the given source. Offsets past the end of the source
+    /// are clamped to the end.
+    pub fn of(source: &str, offset: usize) -> Location {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+
+        Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+/// The offsets at which the lines of a source file start, to look up many locations without
+/// scanning the source from the beginning every time.
+pub struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> LineIndex<'a> {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(index, _)| index + 1));
+        LineIndex { source, starts }
+    }
+
+    /// Find the location of a byte offset, like `Location::of` does.
+    pub fn location(&self, offset: usize) -> Location {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let line_start = self.starts[line - 1];
+
+        Location {
+            line,
+            column: self.source[line_start..offset].chars().count() + 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_character() {
+        assert_eq!(Location::of("abc", 0), Location { line: 1, column: 1 });
+    }
+
+    #[test]
+    fn multiple_lines() {
+        let source = "int x;\nint ée;\n";
+        assert_eq!(Location::of(source, 11), Location { line: 2, column: 5 });
+        assert_eq!(Location::of(source, 100), Location { line: 3, column: 1 });
+    }
+
+    #[test]
+    fn line_index_matches_scanning() {
+        let source = "int x;\nint ée;\n\nx";
+        let index = LineIndex::new(source);
+
+        for offset in 0..source.len() + 2 {
+            assert_eq!(index.location(offset), Location::of(source, offset));
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod diagnostic;
+pub mod location;
+pub mod report;
@@ -0,0 +1,165 @@
+//! Machine readable reports of diagnostics, for continuous integration systems and code review
+//! bots. Diagnostics are collected from all files and written out at once, either as a JSON array
+//! or as a SARIF log.
+
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::diagnostics::location::Location;
+use crate::json::{Json, ToJson};
+
+/// A diagnostic together with the file it was found in and where in that file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    pub path: String,
+    pub diagnostic: Diagnostic,
+    pub start: Location,
+    pub end: Location,
+}
+
+impl Report {
+    pub fn new(path: &str, source: &str, diagnostic: Diagnostic) -> Report {
+        Report {
+            path: path.to_string(),
+            start: Location::of(source, diagnostic.span.start),
+            end: Location::of(source, diagnostic.span.end),
+            diagnostic,
+        }
+    }
+}
+
+impl ToJson for Location {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("line", Json::Number(self.line)),
+            ("column", Json::Number(self.column)),
+        ])
+    }
+}
+
+impl ToJson for Report {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("file", Json::String(self.path.clone())),
+            (
+                "severity",
+                Json::String(self.diagnostic.severity.as_str().to_string()),
+            ),
+            ("message", Json::String(self.diagnostic.message.clone())),
+            (
+                "rule",
+                match self.diagnostic.rule {
+                    Some(rule) => Json::String(rule.to_string()),
+                    None => Json::Null,
+                },
+            ),
+            ("span", self.diagnostic.span.to_json()),
+            ("start", self.start.to_json()),
+            ("end", self.end.to_json()),
+        ])
+    }
+}
+
+/// A log in the Static Analysis Results Interchange Format 2.1.0, with a single run holding all
+/// reports.
+pub fn sarif(reports: &[Report]) -> Json {
+    let results = reports
+        .iter()
+        .map(|report| {
+            let region = Json::object([
+                ("startLine", Json::Number(report.start.line)),
+                ("startColumn", Json::Number(report.start.column)),
+                ("endLine", Json::Number(report.end.line)),
+                ("endColumn", Json::Number(report.end.column)),
+                ("byteOffset", Json::Number(report.diagnostic.span.start)),
+                (
+                    "byteLength",
+                    Json::Number(report.diagnostic.span.end - report.diagnostic.span.start),
+                ),
+            ]);
+            let location = Json::object([(
+                "physicalLocation",
+                Json::object([
+                    (
+                        "artifactLocation",
+                        Json::object([("uri", Json::String(report.path.replace('\\', "/")))]),
+                    ),
+                    ("region", region),
+                ]),
+            )]);
+
+            let mut result = Vec::new();
+            if let Some(rule) = report.diagnostic.rule {
+                result.push(("ruleId".to_string(), Json::String(rule.to_string())));
+            }
+            result.extend([
+                (
+                    "level".to_string(),
+                    Json::String(report.diagnostic.severity.as_str().to_string()),
+                ),
+                (
+                    "message".to_string(),
+                    Json::object([("text", Json::String(report.diagnostic.message.clone()))]),
+                ),
+                ("locations".to_string(), Json::Array(vec![location])),
+            ]);
+            Json::Object(result)
+        })
+        .collect();
+
+    let driver = Json::object([
+        ("name", Json::String("cfmt".to_string())),
+        (
+            "version",
+            Json::String(env!("CARGO_PKG_VERSION").to_string()),
+        ),
+    ]);
+    Json::object([
+        (
+            "$schema",
+            Json::String("https://json.schemastore.org/sarif-2.1.0.json".to_string()),
+        ),
+        ("version", Json::String("2.1.0".to_string())),
+        (
+            "runs",
+            Json::Array(vec![Json::object([
+                ("tool", Json::object([("driver", driver)])),
+                ("results", Json::Array(results)),
+            ])]),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::diagnostic::Diagnostic;
+    use crate::diagnostics::report::{sarif, Report};
+    use crate::json::{Json, ToJson};
+    use crate::lexer::span::Span;
+
+    #[test]
+    fn reports() {
+        let source = "int a;\nint b = ;\n";
+        let diagnostic = Diagnostic::error(Span::new(15, 16), "unexpected `;`".to_string());
+        let report = Report::new("src/x.c", source, diagnostic);
+
+        assert_eq!(
+            report.to_json().to_string(),
+            "{\n  \"file\": \"src/x.c\",\n  \"severity\": \"error\",\n  \
+             \"message\": \"unexpected `;`\",\n  \"rule\": null,\n  \"span\": {\n    \"start\": 15,\n    \
+             \"end\": 16\n  },\n  \"start\": {\n    \"line\": 2,\n    \"column\": 9\n  },\n  \
+             \"end\": {\n    \"line\": 2,\n    \"column\": 10\n  }\n}"
+        );
+
+        let log = sarif(&[report]);
+        let Json::Object(fields) = &log else {
+            panic!("a log is an object");
+        };
+        assert_eq!(
+            fields[1],
+            ("version".to_string(), Json::String("2.1.0".to_string()))
+        );
+        let text = log.to_string();
+        assert!(text.contains("\"uri\": \"src/x.c\""));
+        assert!(text.contains("\"startLine\": 2"));
+        assert!(text.contains("\"level\": \"error\""));
+    }
+}
@@ -0,0 +1,572 @@
+//! Unified diffs between a file and its formatted code, to show what formatting would change
+//! without writing anything, and applying only some of those changes to format part of a file.
+//! The changes can also be given as replacements of bytes, for editors to apply them one by one.
+
+use crate::json::{Json, ToJson};
+use std::ops::Range;
+
+/// The number of unchanged lines shown around every change.
+const CONTEXT: usize = 3;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+
+/// What happens to a line on the way from the old text to the new one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Edit {
+    /// The line at the given indices of both texts is kept.
+    Keep(usize, usize),
+    /// The line at the given index of the old text is removed.
+    Remove(usize),
+    /// The line at the given index of the new text is added.
+    Add(usize),
+}
+
+/// Compute a shortest edit script which turns one list of lines into another, with the linear
+/// space variant of the algorithm by Myers, "An O(ND) Difference Algorithm and Its Variations".
+fn edits(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    compare(old, new, 0, 0, &mut edits);
+    edits
+}
+
+/// Compare a part of both texts, which starts at the given offsets.
+fn compare(old: &[&str], new: &[&str], x: usize, y: usize, edits: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old
+        .iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    edits.extend((0..prefix).map(|i| Edit::Keep(x + i, y + i)));
+    let (x, y) = (x + prefix, y + prefix);
+
+    if old.is_empty() {
+        edits.extend((0..new.len()).map(|j| Edit::Add(y + j)));
+    } else if new.is_empty() {
+        edits.extend((0..old.len()).map(|i| Edit::Remove(x + i)));
+    } else {
+        let (start, end) = middle_snake(old, new);
+        compare(&old[..start.0], &new[..start.1], x, y, edits);
+        edits.extend((start.0..end.0).map(|i| Edit::Keep(x + i, y + i - start.0 + start.1)));
+        compare(&old[end.0..], &new[end.1..], x + end.0, y + end.1, edits);
+    }
+
+    let (x, y) = (x + old.len(), y + new.len());
+    edits.extend((0..suffix).map(|i| Edit::Keep(x + i, y + i)));
+}
+
+/// Find the diagonal run of equal lines in the middle of a shortest edit script, by searching
+/// from both ends at once until the paths meet. Returns its start and end.
+fn middle_snake(old: &[&str], new: &[&str]) -> ((usize, usize), (usize, usize)) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // The furthest x reached on every diagonal k = x - y, where the backward search counts from
+    // the ends of both texts.
+    let mut forward = vec![0; 2 * offset as usize + 1];
+    let mut backward = vec![0; 2 * offset as usize + 1];
+    let index = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[index(k - 1)] < forward[index(k + 1)]) {
+                forward[index(k + 1)]
+            } else {
+                forward[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let start = (x, y);
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x;
+
+            let other = delta - k;
+            if delta % 2 != 0
+                && (-(d - 1)..=d - 1).contains(&other)
+                && x + backward[index(other)] >= n
+            {
+                return (
+                    (start.0 as usize, start.1 as usize),
+                    (x as usize, y as usize),
+                );
+            }
+        }
+
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[index(k - 1)] < backward[index(k + 1)]) {
+                backward[index(k + 1)]
+            } else {
+                backward[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let end = (x, y);
+            while x < n && y < m && old[(n - x - 1) as usize] == new[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[index(k)] = x;
+
+            let other = delta - k;
+            if delta % 2 == 0 && (-d..=d).contains(&other) && x + forward[index(other)] >= n {
+                return (
+                    ((n - x) as usize, (m - y) as usize),
+                    ((n - end.0) as usize, (m - end.1) as usize),
+                );
+            }
+        }
+    }
+
+    unreachable!("the searches meet after at most half of the edits")
+}
+
+/// The range of lines in a hunk header, in which a single line is written without its count and
+/// an empty range by the line in front of it.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// A unified diff from the source of a file to its formatted code, which is empty if they are the
+/// same. Colors are added with ANSI escape codes if asked for.
+pub fn unified_diff(path: &str, old: &str, new: &str, color: bool) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edits(&old_lines, &new_lines);
+    let paint = |code: &str, text: String| {
+        if color {
+            format!("{}{}{}", code, text.trim_end_matches('\n'), RESET) + "\n"
+        } else {
+            text
+        }
+    };
+
+    let changes: Vec<usize> = (0..edits.len())
+        .filter(|&i| !matches!(edits[i], Edit::Keep(..)))
+        .collect();
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < changes.len() {
+        // A hunk holds the changes which are close enough for their context to touch.
+        let mut j = i;
+        while j + 1 < changes.len() && changes[j + 1] - changes[j] <= 2 * CONTEXT + 1 {
+            j += 1;
+        }
+        let start = changes[i].saturating_sub(CONTEXT);
+        let end = (changes[j] + CONTEXT + 1).min(edits.len());
+        let hunk = &edits[start..end];
+
+        let first_old = hunk.iter().find_map(|edit| match *edit {
+            Edit::Keep(x, _) | Edit::Remove(x) => Some(x),
+            Edit::Add(_) => None,
+        });
+        let first_new = hunk.iter().find_map(|edit| match *edit {
+            Edit::Keep(_, y) | Edit::Add(y) => Some(y),
+            Edit::Remove(_) => None,
+        });
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Add(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Remove(_)))
+            .count();
+        // An empty range is numbered by the line in front of it, which is where the changes of
+        // the other text are placed.
+        let old_start = first_old.unwrap_or_else(|| preceding(&edits[..start], true));
+        let new_start = first_new.unwrap_or_else(|| preceding(&edits[..start], false));
+
+        if output.is_empty() {
+            output += &paint(BOLD, format!("--- {}\n", path));
+            output += &paint(BOLD, format!("+++ {}\n", path));
+        }
+        output += &paint(
+            CYAN,
+            format!(
+                "@@ -{} +{} @@\n",
+                range(old_start, old_count),
+                range(new_start, new_count)
+            ),
+        );
+
+        for edit in hunk {
+            let (prefix, line, code) = match *edit {
+                Edit::Keep(x, _) => (' ', old_lines[x], ""),
+                Edit::Remove(x) => ('-', old_lines[x], RED),
+                Edit::Add(y) => ('+', new_lines[y], GREEN),
+            };
+            let mut text = format!("{}{}", prefix, line);
+            if !line.ends_with('\n') {
+                text.push('\n');
+            }
+            output += &match code {
+                "" => text,
+                code => paint(code, text),
+            };
+            if !line.ends_with('\n') {
+                output += "\\ No newline at end of file\n";
+            }
+        }
+
+        i = j + 1;
+    }
+
+    output
+}
+
+/// The old text with only those changes towards the new text which touch the given ranges of its
+/// lines, counted from zero. A change which only adds lines touches a range if it adds them in
+/// between two of its lines. Lines which only differ in their whitespace are paired up, so that a
+/// change to the indentation of a line stays with that line.
+pub fn apply_within(old: &str, new: &str, lines: &[Range<usize>]) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let words = |lines: &[&str]| -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.split_whitespace().collect())
+            .collect()
+    };
+    let (old_words, new_words) = (words(&old_lines), words(&new_lines));
+    let old_words: Vec<&str> = old_words.iter().map(String::as_str).collect();
+    let new_words: Vec<&str> = new_words.iter().map(String::as_str).collect();
+    let edits = edits(&old_words, &new_words);
+    let mut output = String::new();
+    let mut i = 0;
+
+    while i < edits.len() {
+        if let Edit::Keep(x, y) = edits[i] {
+            match lines.iter().any(|range| range.contains(&x)) {
+                true => output += new_lines[y],
+                false => output += old_lines[x],
+            }
+            i += 1;
+            continue;
+        }
+
+        let end = (i..edits.len())
+            .find(|&j| matches!(edits[j], Edit::Keep(..)))
+            .unwrap_or(edits.len());
+        let change = &edits[i..end];
+        let position = preceding(&edits[..i], true);
+        let touched = lines.iter().any(|range| {
+            change
+                .iter()
+                .any(|edit| matches!(edit, Edit::Remove(x) if range.contains(x)))
+                || (range.start < position && position < range.end)
+        });
+
+        for edit in change {
+            match *edit {
+                Edit::Remove(x) if !touched => output += old_lines[x],
+                Edit::Add(y) if touched => output += new_lines[y],
+                _ => {}
+            }
+        }
+        i = end;
+    }
+
+    output
+}
+
+/// A change to a text, which replaces a number of bytes from an offset with other text.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Replacement {
+    pub offset: usize,
+    pub length: usize,
+    pub text: String,
+}
+
+impl ToJson for Replacement {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("offset", Json::Number(self.offset)),
+            ("length", Json::Number(self.length)),
+            ("replacement", Json::String(self.text.clone())),
+        ])
+    }
+}
+
+/// The largest number of bytes in a run of changed lines which is compared character by character
+/// as a whole. Comparing characters takes time quadratic in the number of them which differ.
+const CHARACTER_LIMIT: usize = 4096;
+
+/// The replacements which turn the old text into the new one, in order of their offsets in the
+/// old text. The lines which changed are compared once more character by character, so that every
+/// replacement only covers characters which differ. A run which changes as many lines as it
+/// replaces is compared line by line, and another run which is too long is replaced as a whole.
+pub fn replacements(old: &str, new: &str) -> Vec<Replacement> {
+    let mut replacements = Vec::new();
+    for (offset, removed, added) in changes(old, new, lines) {
+        let (removed_lines, added_lines) = (lines(&removed), lines(&added));
+        let pairs: Vec<(&str, &str)> = if removed_lines.len() == added_lines.len() {
+            removed_lines.into_iter().zip(added_lines).collect()
+        } else if removed.len() + added.len() <= CHARACTER_LIMIT {
+            vec![(&removed, &added)]
+        } else {
+            replacements.push(Replacement {
+                offset,
+                length: removed.len(),
+                text: added,
+            });
+            continue;
+        };
+
+        let mut offset = offset;
+        for (removed, added) in pairs {
+            for (start, part, text) in changes(removed, added, characters) {
+                replacements.push(Replacement {
+                    offset: offset + start,
+                    length: part.len(),
+                    text,
+                });
+            }
+            offset += removed.len();
+        }
+    }
+    replacements
+}
+
+/// The number of lines of the old text which are changed or removed on the way to the new one.
+pub fn changed_lines(old: &str, new: &str) -> usize {
+    edits(&lines(old), &lines(new))
+        .iter()
+        .filter(|edit| matches!(edit, Edit::Remove(_)))
+        .count()
+}
+
+/// A text split into its lines, which keep their line breaks.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// A text split into its characters.
+fn characters(text: &str) -> Vec<&str> {
+    let starts: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    (0..starts.len())
+        .map(|i| &text[starts[i]..starts.get(i + 1).copied().unwrap_or(text.len())])
+        .collect()
+}
+
+/// The runs of changes from the old text to the new one when they are split into parts, with the
+/// offset in the old text at which every run starts and the text it removes and adds.
+fn changes(old: &str, new: &str, split: fn(&str) -> Vec<&str>) -> Vec<(usize, String, String)> {
+    let (old_parts, new_parts) = (split(old), split(new));
+    let edits = edits(&old_parts, &new_parts);
+    let mut changes = Vec::new();
+    let mut offset = 0;
+    let mut i = 0;
+
+    while i < edits.len() {
+        if let Edit::Keep(x, _) = edits[i] {
+            offset += old_parts[x].len();
+            i += 1;
+            continue;
+        }
+
+        let (mut removed, mut added) = (String::new(), String::new());
+        while let Some(edit) = edits.get(i) {
+            match *edit {
+                Edit::Keep(..) => break,
+                Edit::Remove(x) => removed += old_parts[x],
+                Edit::Add(y) => added += new_parts[y],
+            }
+            i += 1;
+        }
+        let length = removed.len();
+        changes.push((offset, removed, added));
+        offset += length;
+    }
+
+    changes
+}
+
+/// The number of lines of the old or the new text in front of a hunk.
+fn preceding(edits: &[Edit], old: bool) -> usize {
+    edits
+        .iter()
+        .filter(|edit| match edit {
+            Edit::Keep(..) => true,
+            Edit::Remove(_) => old,
+            Edit::Add(_) => !old,
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diff::{
+        apply_within, changed_lines, edits, replacements, unified_diff, Edit, Replacement,
+    };
+
+    #[test]
+    fn unified() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\nn\n";
+        assert_eq!(
+            unified_diff("x.c", old, new, false),
+            "--- x.c\n+++ x.c\n@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -11,4 +11,4 @@\n k\n l\n m\n-n\n\\ No newline at end of file\n+n\n"
+        );
+        assert_eq!(unified_diff("x.c", "a\n", "a\n", false), "");
+        assert_eq!(
+            unified_diff("x.c", "", "a\n", false),
+            "--- x.c\n+++ x.c\n@@ -0,0 +1 @@\n+a\n"
+        );
+        assert_eq!(
+            unified_diff("x.c", "a\nb\n", "b\n", true),
+            "\x1b[1m--- x.c\x1b[0m\n\x1b[1m+++ x.c\x1b[0m\n\x1b[36m@@ -1,2 +1 @@\x1b[0m\n\
+             \x1b[31m-a\x1b[0m\n b\n"
+        );
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn changes_within_ranges() {
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "A\nb\nC\nx\nd\nE\n";
+        assert_eq!(apply_within(old, new, &[]), old);
+        assert_eq!(apply_within(old, new, &[0..5]), new);
+        assert_eq!(apply_within(old, new, &[2..3]), "a\nb\nC\nx\nd\ne\n");
+        assert_eq!(apply_within(old, new, &[0..1, 4..5]), "A\nb\nc\nd\nE\n");
+
+        // Lines which are only added are part of a range if they are added inside of it.
+        assert_eq!(apply_within("a\nb\n", "a\nx\nb\n", &[0..1]), "a\nb\n");
+        assert_eq!(apply_within("a\nb\n", "a\nx\nb\n", &[0..2]), "a\nx\nb\n");
+    }
+
+    #[test]
+    fn lines_are_counted() {
+        assert_eq!(changed_lines("a\nb\nc\n", "a\nb\nc\n"), 0);
+        assert_eq!(changed_lines("a\nb\nc\n", "a\nB\nc\nd\n"), 1);
+        assert_eq!(changed_lines("a\nb\nc", "a\nb\nc\n"), 1);
+        assert_eq!(changed_lines("a\nb\nc\n", "a\n"), 2);
+    }
+
+    #[test]
+    fn replacements_are_narrowed() {
+        let replacement = |offset, length, text: &str| Replacement {
+            offset,
+            length,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            replacements("int  a;\nint b;\nint c;", "int a;\nint b;\n\nint c;\n"),
+            vec![
+                replacement(4, 1, ""),
+                replacement(15, 0, "\n"),
+                replacement(21, 0, "\n")
+            ]
+        );
+        assert_eq!(replacements("é  x\n", "é x\n"), vec![replacement(3, 1, "")]);
+        assert_eq!(replacements("a\n", "a\n"), vec![]);
+
+        let old = "int f( ) {\nreturn  0;}\n";
+        let new = "int f() {\n    return 0;\n}\n";
+        let mut text = old.to_string();
+        for replacement in replacements(old, new).iter().rev() {
+            let range = replacement.offset..replacement.offset + replacement.length;
+            text.replace_range(range, &replacement.text);
+        }
+        assert_eq!(text, new);
+    }
+
+    #[test]
+    fn long_runs_of_changes() {
+        let apply = |old: &str, replacements: &[Replacement]| {
+            let mut text = old.to_string();
+            for replacement in replacements.iter().rev() {
+                let range = replacement.offset..replacement.offset + replacement.length;
+                text.replace_range(range, &replacement.text);
+            }
+            text
+        };
+
+        // Every line changes, which is compared line by line.
+        let old: String = (0..3000).map(|i| format!("int  x{};\n", i)).collect();
+        let new: String = (0..3000).map(|i| format!("int x{};\n", i)).collect();
+        let changes = replacements(&old, &new);
+        assert_eq!(changes.len(), 3000);
+        assert!(changes.iter().all(|change| change.length == 1));
+        assert_eq!(apply(&old, &changes), new);
+
+        // The lines are joined, which is replaced as a whole.
+        let new: String = (0..3000).map(|i| format!("int x{}; ", i)).collect();
+        let changes = replacements(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(apply(&old, &changes), new);
+    }
+
+    /// The length of a longest common subsequence, which a shortest edit script keeps.
+    fn common(old: &[&str], new: &[&str]) -> usize {
+        let mut lengths = vec![vec![0; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lengths[i][j] = if old[i] == new[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        lengths[0][0]
+    }
+
+    #[test]
+    fn shortest_edit_scripts() {
+        let mut seed: u32 = 1;
+        let mut random = |bound: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) % bound
+        };
+        let lines = ["a", "b", "c", "d"];
+
+        for _ in 0..500 {
+            let old: Vec<&str> = (0..random(12)).map(|_| lines[random(4) as usize]).collect();
+            let new: Vec<&str> = (0..random(12)).map(|_| lines[random(4) as usize]).collect();
+            let edits = edits(&old, &new);
+
+            let (mut x, mut y) = (0, 0);
+            for edit in &edits {
+                match *edit {
+                    Edit::Keep(i, j) => {
+                        assert_eq!((i, j), (x, y));
+                        assert_eq!(old[i], new[j]);
+                        x += 1;
+                        y += 1;
+                    }
+                    Edit::Remove(i) => {
+                        assert_eq!(i, x);
+                        x += 1;
+                    }
+                    Edit::Add(j) => {
+                        assert_eq!(j, y);
+                        y += 1;
+                    }
+                }
+            }
+            assert_eq!((x, y), (old.len(), new.len()));
+
+            let kept = edits
+                .iter()
+                .filter(|edit| matches!(edit, Edit::Keep(..)))
+                .count();
+            assert_eq!(kept, common(&old, &new), "{:?} {:?}", old, new);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! The errors of the formatter, gathered into a single type, so that programs which call it as a
+//! library can report them, or pass them on with `?`, like any other error.
+
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::lexer::LexerError;
+use crate::lexer::span::Span;
+use crate::parser::parser::ParserError;
+use crate::settings::file::ConfigError;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The source could not be split into tokens. The span is empty and points to the end of the
+    /// last token which could be lexed.
+    Lex(LexerError, Span),
+
+    /// The source does not fit the grammar.
+    Parse(ParserError),
+
+    /// The formatted code does not have the same meaning as the source, or does not stay the same
+    /// when it is formatted again. These are bugs in the formatter.
+    Format(Diagnostic),
+
+    /// A configuration file could not be read, or has an invalid option.
+    Config(ConfigError),
+
+    /// A file could not be read or written.
+    Io(io::Error),
+}
+
+impl Error {
+    /// The part of the source the error was found at, if it has one.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Lex(_, span) => Some(*span),
+            Error::Parse(error) => Some(error.span()),
+            Error::Format(diagnostic) => Some(diagnostic.span),
+            Error::Config(_) | Error::Io(_) => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Lex(error, _) => write!(f, "{}", error),
+            Error::Parse(error) => write!(f, "{}", error),
+            Error::Format(diagnostic) => write!(f, "{}", diagnostic.message),
+            Error::Config(error) => write!(f, "{}", error),
+            Error::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Lex(error, _) => Some(error),
+            Error::Parse(error) => Some(error),
+            Error::Config(error) => Some(error),
+            Error::Io(error) => Some(error),
+            Error::Format(_) => None,
+        }
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(error: ParserError) -> Error {
+        Error::Parse(error)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(error: ConfigError) -> Error {
+        Error::Config(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::diagnostic::Diagnostic;
+    use crate::error::Error;
+    use crate::lex;
+    use crate::lexer::span::Span;
+    use std::error::Error as _;
+    use std::io;
+
+    #[test]
+    fn errors_have_messages_and_spans() {
+        let error = lex("int a = 1;\nint b = `;").unwrap_err();
+        assert!(matches!(error, Error::Lex(..)));
+        assert_eq!(error.to_string(), "unknown character");
+        assert_eq!(error.span(), Some(Span::new(18, 18)));
+        assert!(error.source().is_some());
+        assert_eq!(
+            Diagnostic::from(&error).to_string(),
+            "error: unknown character"
+        );
+
+        let error = Error::from(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        assert_eq!(error.to_string(), "no such file");
+        assert_eq!(error.span(), None);
+    }
+}
@@ -0,0 +1,261 @@
+//! Finding the files to format from the paths given on the command line.
+
+use crate::ignore::IgnoreRules;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::{fs, io, process};
+
+/// The extensions of the files which are formatted when a directory is given.
+pub const DEFAULT_EXTENSIONS: &[&str] = &["c", "h"];
+
+/// Collect the files to format from a list of paths. Files are taken as they are, whatever their
+/// extension, while directories are searched recursively for files with one of the extensions.
+/// Hidden files and directories are skipped, as are paths matched by a `.gitignore` or
+/// `.cfmtignore` file, and the files of a directory are sorted by name so that the order does not
+/// depend on the file system. Symbolic links to directories are followed, but every directory is
+/// only searched once, and every file is only collected once.
+pub fn collect_files<S: AsRef<str>>(
+    paths: &[PathBuf],
+    extensions: &[S],
+) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut visited = HashSet::new();
+    for path in paths {
+        if path.is_dir() {
+            let absolute = fs::canonicalize(path)?;
+            let mut rules = IgnoreRules::new();
+            for ancestor in repository(&absolute) {
+                rules.read(ancestor)?;
+            }
+            visit(
+                path,
+                &absolute,
+                extensions,
+                &mut rules,
+                &mut visited,
+                &mut files,
+            )?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut collected = HashSet::new();
+    files.retain(|file| collected.insert(fs::canonicalize(file).unwrap_or_else(|_| file.clone())));
+    Ok(files)
+}
+
+/// The directories above a directory up to the root of the Git repository it is in, from the
+/// outermost to the innermost, since their ignore files apply to it as well. Outside of a
+/// repository there are none.
+fn repository(directory: &Path) -> Vec<&Path> {
+    let mut ancestors: Vec<&Path> = directory.ancestors().skip(1).collect();
+    if directory.join(".git").exists() {
+        return Vec::new();
+    }
+    match ancestors
+        .iter()
+        .position(|ancestor| ancestor.join(".git").exists())
+    {
+        Some(root) => {
+            ancestors.truncate(root + 1);
+            ancestors.reverse();
+            ancestors
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Search a directory, given both as it was written and as an absolute path for the ignore rules,
+/// unless its canonical path is among the directories which were visited already.
+fn visit<S: AsRef<str>>(
+    directory: &Path,
+    absolute: &Path,
+    extensions: &[S],
+    rules: &mut IgnoreRules,
+    visited: &mut HashSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    if !visited.insert(fs::canonicalize(directory)?) {
+        return Ok(());
+    }
+
+    let len = rules.len();
+    rules.read(absolute)?;
+
+    let mut names = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<io::Result<Vec<_>>>()?;
+    names.sort();
+
+    for name in names {
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let path = directory.join(&name);
+        let is_dir = path.is_dir();
+        if rules.is_ignored(&absolute.join(&name), is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            let absolute = absolute.join(&name);
+            visit(&path, &absolute, extensions, rules, visited, files)?;
+        } else if has_extension(&path, extensions) {
+            files.push(path);
+        }
+    }
+
+    rules.truncate(len);
+    Ok(())
+}
+
+/// Read a list of paths, as given with `--files-from`, with one path per line or, as written by
+/// `git ls-files -z` and `find -print0`, separated by NUL characters. Empty entries are skipped.
+/// On Unix, the paths keep their bytes even if they are not valid UTF-8.
+pub fn read_file_list(list: &[u8], nul: bool) -> Vec<PathBuf> {
+    let separator = if nul { b'\0' } else { b'\n' };
+    list.split(|&byte| byte == separator)
+        .map(|path| match nul {
+            true => path,
+            false => path.strip_suffix(b"\r").unwrap_or(path),
+        })
+        .filter(|path| !path.is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn has_extension<S: AsRef<str>>(path: &Path, extensions: &[S]) -> bool {
+    path.extension().is_some_and(|extension| {
+        let extension = extension.to_string_lossy();
+        extensions.iter().any(|other| other.as_ref() == extension)
+    })
+}
+
+/// Replace the contents of a file by writing them to a temporary file next to it, which is then
+/// renamed over it, so that the file is never left half written. The permissions of the file are
+/// kept, and a symbolic link is followed rather than replaced.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let permissions = fs::metadata(&path)?.permissions();
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temporary = path.with_file_name(format!(".{}.cfmt-{}", name, process::id()));
+
+    let result = fs::write(&temporary, contents)
+        .and_then(|_| fs::set_permissions(&temporary, permissions))
+        .and_then(|_| fs::rename(&temporary, &path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::files::{collect_files, read_file_list, DEFAULT_EXTENSIONS};
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn directories_are_searched_recursively() {
+        let root = std::env::temp_dir().join(format!("cfmt-files-{}", std::process::id()));
+        for directory in ["src/nested", "src/build", ".git", "docs", "vendor"] {
+            fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        for file in [
+            "main.c",
+            "src/list.h",
+            "src/nested/list.c",
+            "src/notes.txt",
+            ".git/hook.c",
+            "docs/README",
+            "src/build/out.c",
+            "src/generated.c",
+            "src/nested/generated.c",
+            "vendor/lib.c",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "build/\n/vendor\ngenerated.c\n").unwrap();
+        fs::write(root.join("src/.cfmtignore"), "!nested/generated.c\n").unwrap();
+
+        let paths = [
+            root.join("src"),
+            root.join("docs/README"),
+            root.join("main.c"),
+        ];
+        let files = collect_files(&paths, DEFAULT_EXTENSIONS).unwrap();
+        let expected: Vec<PathBuf> = [
+            "src/list.h",
+            "src/nested/generated.c",
+            "src/nested/list.c",
+            "docs/README",
+            "main.c",
+        ]
+        .iter()
+        .map(|file| root.join(file))
+        .collect();
+        assert_eq!(files, expected);
+
+        let files = collect_files(std::slice::from_ref(&root), &["txt"]).unwrap();
+        assert_eq!(files, vec![root.join("src/notes.txt")]);
+
+        // Files given by name are formatted even if they are ignored.
+        let files = collect_files(&[root.join("vendor/lib.c")], DEFAULT_EXTENSIONS).unwrap();
+        assert_eq!(files, vec![root.join("vendor/lib.c")]);
+
+        // Files are only collected once, however often they are found.
+        let paths = [
+            root.join("main.c"),
+            root.join("docs/../main.c"),
+            root.join("src"),
+        ];
+        let files = collect_files(&paths, DEFAULT_EXTENSIONS).unwrap();
+        assert_eq!(files.len(), 4);
+
+        // Symbolic links which lead back to a directory which was searched are not followed.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(root.join("src"), root.join("src/nested/loop")).unwrap();
+            std::os::unix::fs::symlink(root.join("src"), root.join("docs/src")).unwrap();
+            let paths = [root.join("src"), root.join("docs")];
+            let files = collect_files(&paths, DEFAULT_EXTENSIONS).unwrap();
+            assert_eq!(files.len(), 3);
+        }
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn lists_of_files() {
+        assert_eq!(
+            read_file_list(b"a.c\r\nsrc/b c.h\n\n", false),
+            vec![PathBuf::from("a.c"), PathBuf::from("src/b c.h")]
+        );
+        assert_eq!(
+            read_file_list(b"a.c\0with\nnewline.c\0", true),
+            vec![PathBuf::from("a.c"), PathBuf::from("with\nnewline.c")]
+        );
+        assert!(read_file_list(b"", true).is_empty());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let paths = read_file_list(b"caf\xe9.c\n", false);
+            assert_eq!(paths[0].as_os_str().as_bytes(), b"caf\xe9.c");
+        }
+    }
+}
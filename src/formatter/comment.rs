@@ -0,0 +1,144 @@
+//! Splitting the text of comments into paragraphs, so that their words can be wrapped to the
+//! column limit. Anything which does not read as prose, such as code fences, indented code and
+//! lines without any letters or digits, is kept as it is.
+
+/// One or more lines of a comment, without the delimiters and the leading `*` of each line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Section {
+    /// An empty line, which separates paragraphs.
+    Empty,
+
+    /// A line which is written as it was.
+    Verbatim(String),
+
+    /// Words which are wrapped to the column limit.
+    Paragraph(Paragraph),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Paragraph {
+    /// The number of spaces in front of the paragraph.
+    pub indent: usize,
+    /// The bullet of a list item, such as `-` or `1.`, or nothing for ordinary paragraphs.
+    /// Continuation lines are aligned with the text after it.
+    pub bullet: String,
+    pub words: Vec<String>,
+}
+
+impl Paragraph {
+    /// The number of spaces in front of the lines after the first.
+    pub fn continuation(&self) -> usize {
+        if self.bullet.is_empty() {
+            self.indent
+        } else {
+            self.indent + self.bullet.chars().count() + 1
+        }
+    }
+}
+
+/// Whether a line starts a list item, returning the bullet.
+fn bullet(line: &str) -> Option<&str> {
+    let (bullet, rest) = line.split_once(' ')?;
+    let numbered = bullet.len() > 1
+        && (bullet.ends_with('.') || bullet.ends_with(')'))
+        && bullet[..bullet.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_digit());
+
+    match (bullet, rest.trim_start()) {
+        (_, "") => None,
+        ("-" | "*" | "+", _) => Some(bullet),
+        _ if numbered => Some(bullet),
+        _ => None,
+    }
+}
+
+/// Whether a line starts with a Doxygen command, such as `@param` or `\return`.
+fn is_command(line: &str) -> bool {
+    let mut chars = line.chars();
+    matches!(chars.next(), Some('@' | '\\')) && chars.next().is_some_and(|c| c.is_alphabetic())
+}
+
+/// Split the lines of a comment into paragraphs. A line continues the paragraph before it if it
+/// is indented as far as its continuation lines, and does not start a list item or command.
+pub fn sections<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut fenced = false;
+    let mut base = None;
+
+    for line in lines {
+        let line = line.trim_end();
+        let text = line.trim_start();
+        let indent = line.len() - text.len();
+
+        if text.starts_with("```") || fenced {
+            fenced ^= text.starts_with("```");
+            sections.push(Section::Verbatim(line.to_string()));
+            continue;
+        }
+
+        if text.is_empty() {
+            sections.push(Section::Empty);
+            continue;
+        }
+
+        let words = text.split_whitespace().map(str::to_string);
+        let base = *base.get_or_insert(indent);
+        let bullet = bullet(text);
+
+        if let Some(Section::Paragraph(paragraph)) = sections.last_mut() {
+            if bullet.is_none() && !is_command(text) && indent == paragraph.continuation() {
+                paragraph.words.extend(words);
+                continue;
+            }
+        }
+
+        if indent > base || !text.chars().any(char::is_alphanumeric) {
+            sections.push(Section::Verbatim(line.to_string()));
+        } else {
+            let bullet = bullet.unwrap_or("");
+            sections.push(Section::Paragraph(Paragraph {
+                indent,
+                bullet: bullet.to_string(),
+                words: words.skip(usize::from(!bullet.is_empty())).collect(),
+            }));
+        }
+    }
+
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::comment::{sections, Paragraph, Section};
+
+    fn paragraph(indent: usize, bullet: &str, words: &str) -> Section {
+        Section::Paragraph(Paragraph {
+            indent,
+            bullet: bullet.to_string(),
+            words: words.split(' ').map(str::to_string).collect(),
+        })
+    }
+
+    #[test]
+    fn paragraphs_lists_and_code() {
+        let text = " Returns the sum\n of both.\n\n - first\n   item\n 2. second\n @param a the\n \
+                    first\n ```\n   keep  this\n ```\n     indented  code\n ------";
+
+        assert_eq!(
+            sections(text.split('\n')),
+            vec![
+                paragraph(1, "", "Returns the sum of both."),
+                Section::Empty,
+                paragraph(1, "-", "first item"),
+                paragraph(1, "2.", "second"),
+                paragraph(1, "", "@param a the first"),
+                Section::Verbatim(" ```".to_string()),
+                Section::Verbatim("   keep  this".to_string()),
+                Section::Verbatim(" ```".to_string()),
+                Section::Verbatim("     indented  code".to_string()),
+                Section::Verbatim(" ------".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,616 @@
+//! The options which control the layout of formatted code.
+
+use crate::formatter::width::char_width;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FormatConfig {
+    /// The number of columns per indentation level.
+    pub indent_width: usize,
+
+    /// The number of columns by which lines are indented when code is wrapped onto them, such as
+    /// the arguments of a call or the operands of a long expression.
+    pub continuation_indent: usize,
+
+    /// Whether indentation is written with tabs rather than spaces. Indentation which is not a
+    /// multiple of `tab_width` ends in spaces.
+    pub use_tabs: bool,
+
+    /// The number of columns up to the next multiple of which a tab reaches, both in the source
+    /// and in the formatted code, so that lines with tabs are measured as they are displayed.
+    /// Zero makes tabs as wide as `indent_width`.
+    pub tab_width: usize,
+
+    /// The column after which code is wrapped onto the next line, where possible.
+    pub max_line_length: usize,
+
+    /// Where opening braces are placed.
+    pub brace_style: BraceStyle,
+
+    /// Which side of the space between a type and a name the `*` of a pointer is written on.
+    pub pointer_alignment: PointerAlignment,
+
+    /// Whether binary operators are surrounded by spaces, as in `a + b`. Spaces are kept where
+    /// leaving them out would change the meaning, as in `a - -b`.
+    pub space_around_binary_operators: bool,
+
+    /// Whether assignment operators are surrounded by spaces, including the `=` of initializers.
+    pub space_around_assignment_operators: bool,
+
+    /// Whether a binary expression which is broken over several lines has its operators at the
+    /// start of the lines, rather than at the end.
+    pub break_before_binary_operators: bool,
+
+    /// Whether a conditional expression which is broken over several lines has its `?` and `:`
+    /// at the start of the lines, rather than at the end.
+    pub break_before_ternary_operators: bool,
+
+    /// Whether commas are followed by a space.
+    pub space_after_comma: bool,
+
+    /// Whether the semicolons in the header of a `for` loop are followed by a space.
+    pub space_after_for_semicolon: bool,
+
+    /// Whether `if`, `for`, `while` and `switch` are followed by a space before their condition,
+    /// as in `if (x)`, and `return` before a parenthesized value.
+    pub space_after_control_keywords: bool,
+
+    /// Whether function calls and macro invocations have a space before their arguments, as in
+    /// `f (x)`.
+    pub space_before_call_parentheses: bool,
+
+    /// Whether the insides of parentheses are padded with spaces, as in `if ( x )` and `f( a )`.
+    /// This does not apply to casts.
+    pub spaces_in_parentheses: bool,
+
+    /// Whether casts are followed by a space, as in `(int) x`.
+    pub space_after_cast: bool,
+
+    /// Whether `sizeof` is followed by a space before parentheses, as in `sizeof (int)`.
+    pub space_after_sizeof: bool,
+
+    /// Whether the operand of `sizeof` is always enclosed in parentheses, as in `sizeof(x)`
+    /// rather than `sizeof x`.
+    pub sizeof_parentheses: bool,
+
+    /// Whether the insides of brackets are padded with spaces, as in `a[ i ]`.
+    pub spaces_in_brackets: bool,
+
+    /// Whether the insides of initializer lists written on a single line are padded with spaces,
+    /// as in `{ 1, 2 }`.
+    pub spaces_in_braces: bool,
+
+    /// The largest number of consecutive empty lines which is kept. Longer runs of empty lines in
+    /// between statements and declarations are collapsed to this many.
+    pub max_blank_lines: usize,
+
+    /// Whether empty lines right after the opening brace and right before the closing brace of a
+    /// block are removed.
+    pub strip_blank_lines_at_block_edges: bool,
+
+    /// The smallest number of empty lines around function definitions and definitions of structs,
+    /// unions and enums at file scope.
+    pub blank_lines_around_definitions: usize,
+
+    /// The smallest number of empty lines in between groups of items at file scope: includes,
+    /// other preprocessor lines, types, global variables and prototypes, and function
+    /// definitions.
+    pub blank_lines_between_groups: usize,
+
+    /// How the arguments of calls and the parameters of functions are broken over several lines
+    /// if they do not fit on one.
+    pub argument_wrapping: ArgumentWrapping,
+
+    /// How initializer lists are broken over several lines if they do not fit on one.
+    pub initializer_layout: InitializerLayout,
+
+    /// Whether initializer lists with a comma after their last item are always broken over
+    /// several lines, so that the comma decides the layout.
+    pub break_initializers_with_trailing_comma: bool,
+
+    /// Where the `else` after the closing brace of an `if` is placed.
+    pub else_placement: KeywordPlacement,
+
+    /// Where the `while` after the closing brace of a `do` loop is placed.
+    pub do_while_placement: KeywordPlacement,
+
+    /// Whether `case` and `default` labels are indented one level inside the braces of a
+    /// `switch`. Otherwise, they are at the same indentation as the `switch` itself.
+    pub indent_case_labels: bool,
+
+    /// Whether the statements following a `case` or `default` label are indented one level
+    /// further than the label.
+    pub indent_case_bodies: bool,
+
+    /// Where the braces of a block directly following a `case` or `default` label are placed.
+    pub case_braces: CaseBraces,
+
+    /// The column at which the labels targeted by `goto` are written.
+    pub label_placement: LabelPlacement,
+
+    /// How preprocessor directives are indented according to the number of conditional groups
+    /// around them. The include guard of a header does not count towards it.
+    pub directive_indentation: DirectiveIndentation,
+
+    /// Where the backslashes at the ends of the lines of multi-line directives, such as the
+    /// definition of a long macro, are placed.
+    pub backslash_alignment: BackslashAlignment,
+
+    /// Whether consecutive `#include` lines at file scope are sorted, and how.
+    pub include_sorting: IncludeSorting,
+
+    /// How headers are protected against being included more than once. Only headers which are
+    /// protected already are changed.
+    pub header_guards: HeaderGuards,
+
+    /// Whether an `if` without an `else` stays on a single line if its body is a simple
+    /// statement and it fits, as in `if (!p) return;`.
+    pub allow_short_if_on_single_line: bool,
+
+    /// Whether `while` and `for` loops stay on a single line if their body is a simple statement
+    /// and they fit.
+    pub allow_short_loops_on_single_line: bool,
+
+    /// Whether function definitions stay on a single line if their body is empty or a single
+    /// simple statement and they fit, as in `int zero(void) { return 0; }`.
+    pub allow_short_functions_on_single_line: bool,
+
+    /// Whether the paragraphs of comments on lines of their own are wrapped to the maximum line
+    /// length. List items, code fences, indented code and Doxygen commands keep their own lines.
+    pub reflow_comments: bool,
+
+    /// Whether string literals which do not fit are split into adjacent literals in between
+    /// words, and adjacent literals are joined again where they fit. A literal which ends in `\n`
+    /// always stays apart from the next one.
+    pub split_string_literals: bool,
+
+    /// Whether number literals are written in a single way: with a lowercase prefix and uppercase
+    /// digits for hexadecimal numbers, as in `0xDEADBEEF`, a lowercase exponent, uppercase
+    /// suffixes, as in `100UL`, and a digit in front of the period, as in `0.5`.
+    pub normalize_number_literals: bool,
+
+    /// Whether the names in declarations on consecutive lines are aligned with each other, as
+    /// long as they still fit in the maximum line length.
+    pub align_consecutive_declarations: bool,
+
+    /// Whether the names of the members of structs and unions are aligned into a column, as long
+    /// as they still fit in the maximum line length. Their trailing comments are aligned if
+    /// `align_trailing_comments` is set.
+    pub align_struct_members: bool,
+
+    /// Enums with up to this many enumerators are written on a single line if they fit and have no
+    /// comments, as in `enum side { LEFT, RIGHT };`. Larger enums always have every enumerator on
+    /// a line of its own.
+    pub max_enumerators_on_single_line: usize,
+
+    /// Whether the `=` of enumerators with an explicit value are aligned with each other.
+    pub align_enum_values: bool,
+
+    /// Whether the assignment operators of assignments and initialized declarations on
+    /// consecutive lines are aligned with each other, as long as they still fit in the maximum
+    /// line length.
+    pub align_consecutive_assignments: bool,
+
+    /// Whether the comments after code on consecutive lines, of either style, are aligned with
+    /// each other, as long as they still fit in the maximum line length.
+    pub align_trailing_comments: bool,
+
+    /// Whether whitespace at the end of lines is removed, including inside comments.
+    pub trim_trailing_whitespace: bool,
+
+    /// Whether the file ends with a single line break. Otherwise, it ends without one.
+    pub insert_final_newline: bool,
+
+    /// The line breaks which are written.
+    pub line_ending: LineEnding,
+
+    /// The character encoding which source files are read and written in.
+    pub encoding: Encoding,
+
+    /// The rules of `--lint` which are turned off, as in `line-too-long = false` in the `[lint]`
+    /// table of a configuration file. They have no effect on formatting.
+    pub disabled_rules: Vec<String>,
+}
+
+/// The placement of braces around function bodies, the bodies of control statements, and the
+/// bodies of struct, union and enum definitions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BraceStyle {
+    /// Opening braces are always on the same line as the code before them.
+    Attach,
+
+    /// The style of "The C Programming Language": function bodies start on a line of their own,
+    /// while all other opening braces are attached.
+    KernighanRitchie,
+
+    /// The style of the Linux kernel, which places braces like K&R.
+    Linux,
+
+    /// Opening braces are always on a line of their own, at the indentation of the code before
+    /// them.
+    Allman,
+
+    /// Opening braces are on a line of their own. Those of control statements are indented one
+    /// level, and the statements inside them another.
+    Gnu,
+
+    /// Opening braces are on a line of their own and indented one level, together with the
+    /// statements inside them.
+    Whitesmiths,
+}
+
+impl Default for FormatConfig {
+    fn default() -> FormatConfig {
+        FormatConfig {
+            indent_width: 4,
+            continuation_indent: 4,
+            use_tabs: false,
+            tab_width: 0,
+            max_line_length: 80,
+            brace_style: BraceStyle::Attach,
+            pointer_alignment: PointerAlignment::Right,
+            space_around_binary_operators: true,
+            space_around_assignment_operators: true,
+            break_before_binary_operators: false,
+            break_before_ternary_operators: true,
+            space_after_comma: true,
+            space_after_for_semicolon: true,
+            space_after_control_keywords: true,
+            space_before_call_parentheses: false,
+            spaces_in_parentheses: false,
+            space_after_cast: false,
+            space_after_sizeof: false,
+            sizeof_parentheses: false,
+            spaces_in_brackets: false,
+            spaces_in_braces: true,
+            max_blank_lines: 1,
+            strip_blank_lines_at_block_edges: true,
+            blank_lines_around_definitions: 1,
+            blank_lines_between_groups: 0,
+            argument_wrapping: ArgumentWrapping::OnePerLine,
+            initializer_layout: InitializerLayout::OnePerLine,
+            break_initializers_with_trailing_comma: false,
+            else_placement: KeywordPlacement::BraceStyle,
+            do_while_placement: KeywordPlacement::BraceStyle,
+            indent_case_labels: true,
+            indent_case_bodies: true,
+            case_braces: CaseBraces::Control,
+            label_placement: LabelPlacement::Indented,
+            directive_indentation: DirectiveIndentation::None,
+            backslash_alignment: BackslashAlignment::Keep,
+            include_sorting: IncludeSorting::Keep,
+            header_guards: HeaderGuards::Keep,
+            allow_short_if_on_single_line: false,
+            allow_short_loops_on_single_line: false,
+            allow_short_functions_on_single_line: false,
+            reflow_comments: false,
+            split_string_literals: false,
+            normalize_number_literals: false,
+            align_consecutive_declarations: false,
+            max_enumerators_on_single_line: 0,
+            align_enum_values: false,
+            align_consecutive_assignments: false,
+            align_struct_members: false,
+            align_trailing_comments: true,
+            trim_trailing_whitespace: true,
+            insert_final_newline: true,
+            line_ending: LineEnding::Auto,
+            encoding: Encoding::Auto,
+            disabled_rules: Vec::new(),
+        }
+    }
+}
+
+impl FormatConfig {
+    /// The number of columns of a tab, which is the indentation width unless `tab_width` is set.
+    pub fn tab_columns(&self) -> usize {
+        match self.tab_width {
+            0 => self.indent_width,
+            width => width,
+        }
+    }
+
+    /// The column at which text which starts at a column ends when it is displayed, where tabs
+    /// reach up to the next multiple of the width of a tab.
+    pub fn column_after(&self, column: usize, text: &str) -> usize {
+        let tab = self.tab_columns();
+        text.chars().fold(column, |column, c| match c {
+            '\t' if tab > 0 => (column / tab + 1) * tab,
+            c => column + char_width(c),
+        })
+    }
+}
+
+/// A named set of options which matches a well-known style of C.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Style {
+    /// The style of "The C Programming Language".
+    KernighanRitchie,
+
+    /// The style of the Linux kernel, indented with tabs.
+    Linux,
+
+    /// The style of the GNU coding standards.
+    Gnu,
+
+    /// The style of the LLVM project, as in the preset of clang-format.
+    Llvm,
+
+    /// The style of Google, as in the preset of clang-format.
+    Google,
+}
+
+impl Style {
+    /// Look up a style by the name it is selected with, such as `linux`.
+    pub fn from_name(name: &str) -> Option<Style> {
+        match name {
+            "kr" => Some(Style::KernighanRitchie),
+            "linux" => Some(Style::Linux),
+            "gnu" => Some(Style::Gnu),
+            "llvm" => Some(Style::Llvm),
+            "google" => Some(Style::Google),
+            _ => None,
+        }
+    }
+
+    /// The options of the style. Options on which the style has no opinion keep their default.
+    #[cfg(test)]
+    pub fn config(self) -> FormatConfig {
+        self.apply(FormatConfig::default())
+    }
+
+    /// Apply the style to other options. Options on which the style has no opinion keep their
+    /// value.
+    pub fn apply(self, base: FormatConfig) -> FormatConfig {
+        match self {
+            Style::KernighanRitchie => FormatConfig {
+                brace_style: BraceStyle::KernighanRitchie,
+                indent_case_labels: false,
+                ..base
+            },
+            Style::Linux => FormatConfig {
+                indent_width: 8,
+                continuation_indent: 8,
+                use_tabs: true,
+                brace_style: BraceStyle::Linux,
+                argument_wrapping: ArgumentWrapping::Align,
+                indent_case_labels: false,
+                label_placement: LabelPlacement::FirstColumn,
+                ..base
+            },
+            Style::Gnu => FormatConfig {
+                indent_width: 2,
+                continuation_indent: 2,
+                max_line_length: 79,
+                brace_style: BraceStyle::Gnu,
+                break_before_binary_operators: true,
+                space_before_call_parentheses: true,
+                argument_wrapping: ArgumentWrapping::Align,
+                ..base
+            },
+            Style::Llvm => FormatConfig {
+                indent_width: 2,
+                spaces_in_braces: false,
+                argument_wrapping: ArgumentWrapping::Align,
+                indent_case_labels: false,
+                include_sorting: IncludeSorting::Alphabetical,
+                allow_short_functions_on_single_line: true,
+                ..base
+            },
+            Style::Google => FormatConfig {
+                indent_width: 2,
+                pointer_alignment: PointerAlignment::Left,
+                spaces_in_braces: false,
+                argument_wrapping: ArgumentWrapping::Align,
+                include_sorting: IncludeSorting::Grouped,
+                allow_short_if_on_single_line: true,
+                allow_short_loops_on_single_line: true,
+                allow_short_functions_on_single_line: true,
+                ..base
+            },
+        }
+    }
+}
+
+/// The layout of arguments and parameters which do not fit on a single line.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ArgumentWrapping {
+    /// Moved to the next line and indented, with every one of them on a line of its own.
+    OnePerLine,
+
+    /// As many as fit on every line, with the lines after the first indented.
+    BinPack,
+
+    /// As many as fit on every line, with the lines after the first aligned with the opening
+    /// parenthesis.
+    Align,
+}
+
+/// The layout of initializer lists which do not fit on a single line. In every layout, the items
+/// start on the line after the opening brace, and lists with comments in between their items have
+/// every item on a line of its own.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum InitializerLayout {
+    /// Every item on a line of its own.
+    OnePerLine,
+
+    /// As many items as fit on every line.
+    Compact,
+
+    /// As many items as fit on every line, padded to the width of the widest item so that they
+    /// line up in columns, as in a lookup table.
+    Columns,
+}
+
+/// The placement of a keyword which follows a closing brace, such as `else`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeywordPlacement {
+    /// On the same line as the closing brace if the brace style attaches the braces of control
+    /// statements, and on the next line otherwise.
+    BraceStyle,
+
+    /// On the same line as the closing brace, as in `} else {`.
+    SameLine,
+
+    /// On the line after the closing brace.
+    NextLine,
+}
+
+/// The placement of the braces of a block directly following a `case` or `default` label.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CaseBraces {
+    /// The same as the braces of control statements, according to the brace style.
+    Control,
+
+    /// On the same line as the label, as in `case 1: {`.
+    Attach,
+
+    /// On the line after the label, at the same indentation.
+    NextLine,
+
+    /// On the line after the label, indented like the statements following a label.
+    Indented,
+}
+
+/// The placement of the labels targeted by `goto`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LabelPlacement {
+    /// At the indentation of the statements around them.
+    Indented,
+
+    /// One indentation level less deep than the statements around them.
+    Outdented,
+
+    /// At the first column, regardless of the indentation.
+    FirstColumn,
+}
+
+/// The indentation of preprocessor directives inside conditional groups.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DirectiveIndentation {
+    /// Not indented, as in `#define`.
+    None,
+
+    /// Indented in between the hash and the name, as in `#  define`.
+    AfterHash,
+
+    /// Indented in front of the hash, as in `  #define`.
+    BeforeHash,
+}
+
+/// The placement of the backslashes which continue a directive on the next line.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BackslashAlignment {
+    /// Where they were written.
+    Keep,
+
+    /// Aligned in the column after the longest line of the directive.
+    Left,
+
+    /// Aligned in the last column allowed by the maximum line length, unless a line of the
+    /// directive is longer than that.
+    Right,
+}
+
+/// The order of consecutive `#include` lines.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum IncludeSorting {
+    /// The order in which they were written.
+    Keep,
+
+    /// Sorted by the name of the header.
+    Alphabetical,
+
+    /// The header corresponding to the source file first, followed by the system headers in
+    /// angle brackets and then the other headers in quotes, each sorted by name.
+    Grouped,
+}
+
+/// The protection of headers against being included more than once.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum HeaderGuards {
+    /// Either an include guard or `#pragma once`, as it was written.
+    Keep,
+
+    /// An include guard, with a macro named after the path of the header, as in `SRC_LIST_H`
+    /// for `src/list.h`.
+    Macro,
+
+    /// A `#pragma once` at the start of the header.
+    PragmaOnce,
+}
+
+/// The placement of the `*` of pointers in declarations, parameters, casts and return types.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PointerAlignment {
+    /// Next to the name, as in `int *p`.
+    Right,
+
+    /// Next to the type, as in `int* p`.
+    Left,
+
+    /// Surrounded by spaces, as in `int * p`.
+    Middle,
+}
+
+/// The characters which end lines.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LineEnding {
+    /// A line feed, as on Unix.
+    Lf,
+
+    /// A carriage return followed by a line feed, as on Windows.
+    CrLf,
+
+    /// The line breaks of the platform the formatter runs on.
+    Native,
+
+    /// The line breaks which most lines of the source file end with, so that formatting does not
+    /// change them. A file without line breaks gets line feeds.
+    Auto,
+}
+
+impl LineEnding {
+    /// The line breaks which are written for a source file, which are either line feeds or
+    /// carriage returns followed by line feeds.
+    pub fn resolve(self, source: &str) -> LineEnding {
+        match self {
+            LineEnding::Native if cfg!(windows) => LineEnding::CrLf,
+            LineEnding::Native => LineEnding::Lf,
+            LineEnding::Auto => {
+                let crlf = source.matches("\r\n").count();
+                match crlf > source.matches('\n').count() - crlf {
+                    true => LineEnding::CrLf,
+                    false => LineEnding::Lf,
+                }
+            }
+            ending => ending,
+        }
+    }
+}
+
+/// The character encodings of source files. Files are written in the encoding they were read in,
+/// so that the text of their comments and strings is kept byte for byte.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// UTF-8, which includes ASCII.
+    Utf8,
+
+    /// ISO 8859-1, as in the comments of older European code bases, in which every byte is a
+    /// character.
+    Latin1,
+
+    /// UTF-8 for a file which is valid UTF-8, and Latin-1 otherwise, so that every file can be
+    /// read.
+    Auto,
+}
+
+impl Encoding {
+    /// The encoding which a source file is read in, which is either UTF-8 or Latin-1.
+    pub fn resolve(self, bytes: &[u8]) -> Encoding {
+        match self {
+            Encoding::Auto if std::str::from_utf8(bytes).is_ok() => Encoding::Utf8,
+            Encoding::Auto => Encoding::Latin1,
+            encoding => encoding,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Following the cursor of an editor through formatting, so that it can be put back at the same
+//! place in the code after the editor replaces its text with the formatted code.
+
+/// The offset in the formatted code which corresponds to an offset in the source. Formatting
+/// mostly changes whitespace, so the cursor is kept after the same number of other characters. A
+/// cursor on a character stays on it, while one in whitespace ends up right after the character in
+/// front of it.
+pub fn map_cursor(source: &str, formatted: &str, offset: usize) -> usize {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let before = source[..offset]
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count();
+    let mut characters = formatted.char_indices().filter(|(_, c)| !c.is_whitespace());
+
+    if source[offset..]
+        .chars()
+        .next()
+        .is_some_and(|c| !c.is_whitespace())
+    {
+        return characters
+            .nth(before)
+            .map_or(formatted.len(), |(index, _)| index);
+    }
+    match before {
+        0 => 0,
+        _ => characters
+            .nth(before - 1)
+            .map_or(formatted.len(), |(index, c)| index + c.len_utf8()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::cursor::map_cursor;
+
+    #[test]
+    fn cursors_stay_with_the_code() {
+        let source = "int  main( ) {\nreturn  0;}\n";
+        let formatted = "int main() {\n    return 0;\n}\n";
+
+        // On a character.
+        assert_eq!(map_cursor(source, formatted, 5), 4);
+        assert_eq!(map_cursor(source, formatted, 15), 17);
+        assert_eq!(map_cursor(source, formatted, 25), 27);
+        // In whitespace.
+        assert_eq!(map_cursor(source, formatted, 4), 3);
+        assert_eq!(map_cursor(source, formatted, 14), 12);
+        // At the ends.
+        assert_eq!(map_cursor(source, formatted, 0), 0);
+        assert_eq!(map_cursor(source, formatted, 100), formatted.len() - 1);
+        assert_eq!(map_cursor(" x", "x", 0), 0);
+    }
+}
@@ -0,0 +1,756 @@
+//! A small document model for pretty printing, in the style of Wadler's "A prettier printer". The
+//! formatter describes its output as a tree of text, possible line breaks and groups, and the
+//! renderer decides which line breaks to take. A group is written on a single line if it fits in
+//! the remaining width, and otherwise every line break directly inside it is taken.
+
+use crate::formatter::config::FormatConfig;
+use crate::formatter::width::text_width;
+use std::io::{self, Write};
+use std::mem;
+
+/// The kinds of positions which are aligned across lines. They are aligned in this order, so that
+/// for example the assignments after aligned names are aligned afterwards.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Anchor {
+    /// The name in a declaration, after its type.
+    Name,
+    /// An assignment operator, including the `=` of an initializer.
+    Assignment,
+    /// A comment at the end of a line.
+    Comment,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Doc {
+    /// Text without any line breaks.
+    Text(String),
+
+    /// Text which may span several lines, such as a block comment or a region of the source file
+    /// which could not be parsed. It is written exactly as given, so its continuation lines are
+    /// not indented.
+    Verbatim(String),
+
+    /// Text which always starts at the first column of a line of its own, regardless of the
+    /// indentation, such as a preprocessor directive.
+    Flush(String),
+
+    /// Text which starts a line of its own, one indentation level less deep than the lines
+    /// around it, such as a label.
+    Outdent(String),
+
+    /// A space, or a line break if the enclosing group does not fit.
+    Line,
+
+    /// Nothing, or a line break if the enclosing group does not fit.
+    SoftLine,
+
+    /// A line break which is always taken.
+    HardLine,
+
+    /// Forces all enclosing groups to break, without writing anything. This is used after line
+    /// comments, which must always be followed by a line break.
+    BreakParent,
+
+    /// One of two documents, depending on whether the enclosing group is broken.
+    IfBreak(Box<Doc>, Box<Doc>),
+
+    /// Documents written one after the other.
+    Concat(Vec<Doc>),
+
+    /// Increases the indentation of the lines started inside the document.
+    Indent(Box<Doc>),
+
+    /// Increases the indentation of the lines started inside the document by the continuation
+    /// indent, for code which is wrapped onto the next line rather than nested in a block.
+    Continuation(Box<Doc>),
+
+    /// Indents the lines started inside the document up to the column at which it starts.
+    Align(Box<Doc>),
+
+    /// Documents whose line breaks are either all taken or none at all.
+    Group(Box<Doc>),
+
+    /// Documents whose line breaks are never taken, even if they do not fit, apart from those
+    /// which are always taken. This keeps code on a single line with a marker comment after it.
+    Flat(Box<Doc>),
+
+    /// A position which is aligned with the anchors of the same kind on the lines directly
+    /// before and after it, as long as they have the same indentation and still fit in the
+    /// maximum line length.
+    Anchor(Anchor),
+
+    /// Contents separated by line breaks, such as the words of a paragraph. Unlike in a group,
+    /// each separator is only broken if the contents after it do not fit on the current line.
+    /// The contents are at the even positions, and the separators at the odd ones.
+    Fill(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Doc {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    pub fn indent(doc: Doc) -> Doc {
+        Doc::Indent(Box::new(doc))
+    }
+
+    pub fn continuation(doc: Doc) -> Doc {
+        Doc::Continuation(Box::new(doc))
+    }
+
+    pub fn align(doc: Doc) -> Doc {
+        Doc::Align(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Doc {
+        Doc::Group(Box::new(doc))
+    }
+
+    pub fn flat(doc: Doc) -> Doc {
+        Doc::Flat(Box::new(doc))
+    }
+
+    /// A document which is only written if the enclosing group is broken.
+    pub fn if_break(doc: Doc) -> Doc {
+        Doc::IfBreak(Box::new(doc), Box::new(Doc::Concat(Vec::new())))
+    }
+
+    /// Write `broken` if the enclosing group is broken, and `flat` otherwise.
+    pub fn choice(broken: Doc, flat: Doc) -> Doc {
+        Doc::IfBreak(Box::new(broken), Box::new(flat))
+    }
+
+    /// The width of the document when written on a single line, or `None` if it always contains
+    /// a line break.
+    pub fn flat_width(&self) -> Option<usize> {
+        match self {
+            Doc::Text(text) => Some(text_width(text)),
+            Doc::Verbatim(text) if !text.contains('\n') => Some(text_width(text)),
+            Doc::Verbatim(_)
+            | Doc::Flush(_)
+            | Doc::Outdent(_)
+            | Doc::HardLine
+            | Doc::BreakParent => None,
+            Doc::Anchor(_) => Some(0),
+            Doc::Line => Some(1),
+            Doc::SoftLine => Some(0),
+            Doc::IfBreak(_, flat) => flat.flat_width(),
+            Doc::Concat(docs) | Doc::Fill(docs) => docs.iter().map(Doc::flat_width).sum(),
+            Doc::Indent(doc)
+            | Doc::Continuation(doc)
+            | Doc::Align(doc)
+            | Doc::Group(doc)
+            | Doc::Flat(doc) => doc.flat_width(),
+        }
+    }
+
+    /// Whether the document contains a line break when every line break in it is taken, together
+    /// with the width of its last line.
+    fn last_line(&self) -> (bool, usize) {
+        match self {
+            Doc::Line | Doc::SoftLine | Doc::HardLine => (true, 0),
+            Doc::IfBreak(broken, _) => broken.last_line(),
+            Doc::Concat(docs) | Doc::Fill(docs) => {
+                docs.iter()
+                    .fold((false, 0), |(broken, width), doc| match doc.last_line() {
+                        (true, last) => (true, last),
+                        (false, extra) => (broken, width + extra),
+                    })
+            }
+            Doc::Indent(doc)
+            | Doc::Continuation(doc)
+            | Doc::Align(doc)
+            | Doc::Group(doc)
+            | Doc::Flat(doc) => doc.last_line(),
+            doc => (false, doc.flat_width().unwrap_or(0)),
+        }
+    }
+
+    /// Join documents with a separator in between every pair.
+    pub fn join(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Doc {
+        Doc::Concat(interleave(docs, separator))
+    }
+
+    /// Fill lines with documents, with a separator in between every pair.
+    pub fn fill(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Doc {
+        Doc::Fill(interleave(docs, separator))
+    }
+}
+
+fn interleave(docs: impl IntoIterator<Item = Doc>, separator: Doc) -> Vec<Doc> {
+    let mut joined = Vec::new();
+
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i > 0 {
+            joined.push(separator.clone());
+        }
+        joined.push(doc);
+    }
+
+    joined
+}
+
+/// Whether the line breaks of a group are taken.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+/// A document which remains to be written, or the remaining parts of a fill.
+#[derive(Copy, Clone, Debug)]
+enum Part<'a> {
+    Doc(&'a Doc),
+    Fill(&'a [Doc]),
+}
+
+/// A part which remains to be written, together with its indentation and the mode of the group
+/// it is part of.
+type Command<'a> = (usize, Mode, Part<'a>);
+
+/// Writes documents, keeping track of the current column. The output is written to the sink a few
+/// lines at a time, as soon as the anchors on them can no longer be aligned with those after them.
+struct Renderer<'a, W: Write> {
+    sink: &'a mut W,
+    /// The lines which have not been written to the sink yet, starting at the start of a line.
+    output: String,
+    /// The number of line breaks in the output which has not been written yet.
+    line: usize,
+    /// Whether the current line has an anchor on it.
+    anchored: bool,
+    /// Whether the next text starts a new line, as nothing was written yet or the last thing
+    /// which was written is a line break.
+    line_start: bool,
+    column: usize,
+    config: &'a FormatConfig,
+    /// The indentation for the next text, if it starts a new line. Indentation is only written
+    /// together with text, so that empty lines do not end up with trailing whitespace.
+    pending: Option<usize>,
+    marks: Vec<Mark>,
+}
+
+/// An anchor which has been written, so that it can be aligned afterwards.
+struct Mark {
+    anchor: Anchor,
+    /// The position of the anchor in the output.
+    offset: usize,
+    line: usize,
+    column: usize,
+    indent: usize,
+}
+
+impl<W: Write> Renderer<'_, W> {
+    fn write(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        if let Some(indent) = self.pending.take() {
+            self.indent(indent);
+        }
+
+        self.output.push_str(text);
+        self.line += text.matches('\n').count();
+        self.line_start = text.ends_with('\n');
+        self.column = match text.rfind('\n') {
+            Some(newline) => self.config.column_after(0, &text[newline + 1..]),
+            None => self.config.column_after(self.column, text),
+        };
+    }
+
+    /// Write the indentation of a new line. With tabs, any columns left over after the last full
+    /// tab are filled with spaces.
+    fn indent(&mut self, indent: usize) {
+        if self.config.use_tabs {
+            let width = self.config.tab_columns().max(1);
+            self.output
+                .extend(std::iter::repeat_n('\t', indent / width));
+            self.output.extend(std::iter::repeat_n(' ', indent % width));
+        } else {
+            self.output.extend(std::iter::repeat_n(' ', indent));
+        }
+        self.column = indent;
+        self.line_start = indent == 0 && self.line_start;
+    }
+
+    fn newline(&mut self, indent: usize) -> io::Result<()> {
+        self.end_line()?;
+        self.pending = Some(indent);
+        Ok(())
+    }
+
+    /// Write a line break. Once a line without anchors is finished, the lines before it are
+    /// aligned and written to the sink, since no anchor after it is aligned with them.
+    fn end_line(&mut self) -> io::Result<()> {
+        self.output.push('\n');
+        self.line += 1;
+        self.column = 0;
+        self.line_start = true;
+        if !mem::take(&mut self.anchored) {
+            self.write_lines()?;
+        }
+        Ok(())
+    }
+
+    /// Align the anchors of the output which has not been written yet, and write it to the sink.
+    fn write_lines(&mut self) -> io::Result<()> {
+        let mut output = mem::take(&mut self.output);
+        for anchor in [Anchor::Name, Anchor::Assignment, Anchor::Comment] {
+            output = align(output, &mut self.marks, anchor, self.config);
+        }
+        self.marks.clear();
+        self.line = 0;
+        self.sink.write_all(output.as_bytes())
+    }
+
+    /// The column at which the next text starts.
+    fn position(&self) -> usize {
+        self.pending.unwrap_or(self.column)
+    }
+
+    /// Start a new line at the first column, unless the output already is at the start of one.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.line_start {
+            self.end_line()?;
+        }
+        self.column = 0;
+        self.pending = None;
+        Ok(())
+    }
+}
+
+/// Check whether `next` fits in the maximum line length when written flat from a column, followed
+/// by the remaining `rest` up to its first possible line break.
+fn fits(next: Command<'_>, rest: &[Command<'_>], column: usize, config: &FormatConfig) -> bool {
+    let width = config.max_line_length;
+    // Text which starts past the maximum line length fits if it is empty.
+    let mut column = column.min(width);
+    let mut stack = vec![next];
+    let mut rest = rest.iter().rev();
+
+    while column <= width {
+        let (indent, mode, part) = match stack.pop() {
+            Some(command) => command,
+            None => match rest.next() {
+                Some(command) => *command,
+                None => return true,
+            },
+        };
+
+        let doc = match part {
+            Part::Doc(doc) => doc,
+            Part::Fill(parts) => {
+                stack.extend(parts.iter().rev().map(|doc| (indent, mode, Part::Doc(doc))));
+                continue;
+            }
+        };
+
+        match doc {
+            Doc::Text(text) => column = config.column_after(column, text),
+            Doc::Anchor(_) => {}
+            Doc::Verbatim(text) if text.contains('\n') => return mode == Mode::Break,
+            Doc::Verbatim(text) => column = config.column_after(column, text),
+            Doc::Flush(_) | Doc::Outdent(_) | Doc::HardLine => return mode == Mode::Break,
+            Doc::BreakParent => {
+                if mode == Mode::Flat {
+                    return false;
+                }
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Break => return true,
+            Doc::Line => column += 1,
+            Doc::SoftLine => {}
+            Doc::IfBreak(broken, flat) => {
+                let doc = if mode == Mode::Break { broken } else { flat };
+                stack.push((indent, mode, Part::Doc(doc)));
+            }
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indent, mode, Part::Doc(doc))))
+            }
+            Doc::Indent(doc) | Doc::Continuation(doc) | Doc::Align(doc) => {
+                stack.push((indent, mode, Part::Doc(doc)))
+            }
+            Doc::Group(doc) | Doc::Flat(doc) => stack.push((indent, Mode::Flat, Part::Doc(doc))),
+            Doc::Fill(parts) => stack.push((indent, mode, Part::Fill(parts))),
+        }
+    }
+
+    false
+}
+
+/// Write a document, breaking groups which do not fit in the maximum line length.
+pub fn render(doc: &Doc, config: &FormatConfig) -> String {
+    let mut output = Vec::new();
+    render_to(doc, config, &mut output).expect("writing to memory does not fail");
+    String::from_utf8(output).expect("the output is made of strings")
+}
+
+/// Write a document to a sink, such as a file, without keeping all of the output in memory.
+pub fn render_to(doc: &Doc, config: &FormatConfig, sink: &mut impl Write) -> io::Result<()> {
+    let mut renderer = Renderer {
+        sink,
+        output: String::new(),
+        line: 0,
+        anchored: false,
+        line_start: true,
+        column: 0,
+        config,
+        pending: None,
+        marks: Vec::new(),
+    };
+    let mut stack: Vec<Command<'_>> = vec![(0, Mode::Break, Part::Doc(doc))];
+
+    while let Some((indent, mode, part)) = stack.pop() {
+        let doc = match part {
+            Part::Doc(doc) => doc,
+            // Contents which are written flat are so all the way through.
+            Part::Fill(parts) if mode == Mode::Flat => {
+                stack.extend(parts.iter().rev().map(|doc| (indent, mode, Part::Doc(doc))));
+                continue;
+            }
+            Part::Fill(parts) => {
+                fill(&mut stack, indent, mode, parts, renderer.position(), config);
+                continue;
+            }
+        };
+
+        match doc {
+            Doc::Text(text) | Doc::Verbatim(text) => renderer.write(text),
+            Doc::Anchor(anchor) => {
+                if let Some(indent) = renderer.pending.take() {
+                    renderer.indent(indent);
+                }
+                renderer.marks.push(Mark {
+                    anchor: *anchor,
+                    offset: renderer.output.len(),
+                    line: renderer.line,
+                    column: renderer.column,
+                    indent,
+                });
+                renderer.anchored = true;
+            }
+            Doc::Flush(text) => {
+                renderer.flush()?;
+                renderer.write(text);
+            }
+            Doc::Outdent(text) => {
+                renderer.flush()?;
+                renderer.pending = Some(indent.saturating_sub(config.indent_width));
+                renderer.write(text);
+            }
+            Doc::Line if mode == Mode::Flat => renderer.write(" "),
+            Doc::SoftLine if mode == Mode::Flat => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => renderer.newline(indent)?,
+            Doc::BreakParent => {}
+            Doc::IfBreak(broken, flat) => {
+                let doc = if mode == Mode::Break { broken } else { flat };
+                stack.push((indent, mode, Part::Doc(doc)));
+            }
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indent, mode, Part::Doc(doc))))
+            }
+            Doc::Indent(doc) => stack.push((indent + config.indent_width, mode, Part::Doc(doc))),
+            Doc::Continuation(doc) => {
+                stack.push((indent + config.continuation_indent, mode, Part::Doc(doc)))
+            }
+            Doc::Align(doc) => stack.push((renderer.position(), mode, Part::Doc(doc))),
+            Doc::Group(doc) => {
+                let flat = (indent, Mode::Flat, Part::Doc(doc));
+                let fits = mode == Mode::Flat || fits(flat, &stack, renderer.position(), config);
+                stack.push((
+                    indent,
+                    if fits { Mode::Flat } else { Mode::Break },
+                    Part::Doc(doc),
+                ));
+            }
+            Doc::Flat(doc) => stack.push((indent, Mode::Flat, Part::Doc(doc))),
+            Doc::Fill(parts) => stack.push((indent, mode, Part::Fill(parts))),
+        }
+    }
+
+    renderer.write_lines()
+}
+
+/// Align the anchors of one kind on consecutive lines with the same indentation, by moving them
+/// to the column of the rightmost one. An anchor after which the line would no longer fit in the
+/// maximum line length starts a new run of aligned anchors instead. The other marks are moved
+/// along with the text they are in.
+fn align(output: String, marks: &mut [Mark], anchor: Anchor, config: &FormatConfig) -> String {
+    // The column to align with, the longest text after an anchor, and the previous anchor.
+    let mut run: Option<(usize, usize, usize)> = None;
+    let mut targets = vec![None; marks.len()];
+    let mut start = 0;
+
+    for i in 0..marks.len() {
+        let mark = &marks[i];
+        if mark.anchor != anchor {
+            continue;
+        }
+
+        let end = output[mark.offset..]
+            .find('\n')
+            .map_or(output.len(), |end| mark.offset + end);
+        let length = config.column_after(mark.column, &output[mark.offset..end]) - mark.column;
+
+        if let Some((column, longest, previous)) = run {
+            let previous = &marks[previous];
+            let consecutive = mark.line == previous.line + 1 && mark.indent == previous.indent;
+            let width = column.max(mark.column) + longest.max(length);
+            if consecutive && width <= config.max_line_length {
+                run = Some((column.max(mark.column), longest.max(length), i));
+                continue;
+            }
+
+            targets[start..i].fill(Some(column));
+        }
+
+        start = i;
+        run = Some((mark.column, length, i));
+    }
+    if let Some((column, _, _)) = run {
+        targets[start..].fill(Some(column));
+    }
+
+    let mut aligned = String::with_capacity(output.len());
+    let mut written = 0;
+    let mut shift = 0;
+    let mut line = 0;
+    let mut line_shift = 0;
+
+    for (mark, target) in marks.iter_mut().zip(targets) {
+        if mark.line != line {
+            line = mark.line;
+            line_shift = 0;
+        }
+
+        if let Some(target) = target.filter(|_| mark.anchor == anchor) {
+            let padding = target.saturating_sub(mark.column + line_shift);
+            aligned.push_str(&output[written..mark.offset]);
+            aligned.extend(std::iter::repeat_n(' ', padding));
+            written = mark.offset;
+            shift += padding;
+            line_shift += padding;
+        }
+
+        mark.offset += shift;
+        mark.column += line_shift;
+    }
+
+    aligned.push_str(&output[written..]);
+    aligned
+}
+
+/// Schedule the first contents of a fill and the separator after them, followed by the rest of
+/// the fill. The separator is only broken if the next contents do not fit after the `column`
+/// together with it, and with the text which ends their line if the separator after them is
+/// broken, such as a comma. For the last contents, the code after the fill has to fit as well.
+///
+/// Text which would not fit in the maximum line length on a line of its own either, such as a
+/// long URL in a comment, is never moved onto the next line, since that would not bring it within
+/// the limit.
+fn fill<'a>(
+    stack: &mut Vec<Command<'a>>,
+    indent: usize,
+    mode: Mode,
+    parts: &'a [Doc],
+    column: usize,
+    config: &FormatConfig,
+) {
+    let Some(contents) = parts.first() else {
+        return;
+    };
+
+    let mode_of = |measured: &'a [Doc], stack: &[Command<'a>]| {
+        let flat = (indent, Mode::Flat, Part::Fill(measured));
+        let fitting = match parts.get(measured.len()) {
+            Some(separator) => fits(
+                flat,
+                &[(indent, Mode::Break, Part::Doc(separator))],
+                column,
+                config,
+            ),
+            None => fits(flat, stack, column, config),
+        };
+        if fitting {
+            Mode::Flat
+        } else {
+            Mode::Break
+        }
+    };
+
+    let contents_mode = mode_of(&parts[..1], stack);
+    if let Some(separator) = parts.get(1) {
+        let mut separator_mode = mode_of(&parts[..parts.len().min(3)], stack);
+        if let (Some(Doc::Text(next)), (true, start)) = (parts.get(2), separator.last_line()) {
+            if config.column_after(indent + start, next) > config.max_line_length {
+                separator_mode = Mode::Flat;
+            }
+        }
+        stack.push((indent, mode, Part::Fill(&parts[2..])));
+        stack.push((indent, separator_mode, Part::Doc(separator)));
+    }
+
+    stack.push((indent, contents_mode, Part::Doc(contents)));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::FormatConfig;
+    use crate::formatter::doc::{render, render_to, Anchor, Doc};
+    use std::io::{self, Write};
+
+    /// A sink which keeps every write apart.
+    struct Writes(Vec<String>);
+
+    impl Write for Writes {
+        fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+            self.0.push(String::from_utf8(buffer.to_vec()).unwrap());
+            Ok(buffer.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn list(items: &[&str]) -> Doc {
+        Doc::group(Doc::concat([
+            Doc::text("{"),
+            Doc::indent(Doc::concat([
+                Doc::Line,
+                Doc::join(
+                    items.iter().map(|item| Doc::text(*item)),
+                    Doc::concat([Doc::text(","), Doc::Line]),
+                ),
+                Doc::if_break(Doc::text(",")),
+            ])),
+            Doc::Line,
+            Doc::text("}"),
+        ]))
+    }
+
+    #[test]
+    fn groups_break_when_too_wide() {
+        let doc = list(&["alpha", "beta"]);
+        let config = FormatConfig {
+            indent_width: 2,
+            max_line_length: 20,
+            ..FormatConfig::default()
+        };
+        assert_eq!(render(&doc, &config), "{ alpha, beta }");
+
+        let config = FormatConfig {
+            max_line_length: 10,
+            ..config
+        };
+        assert_eq!(render(&doc, &config), "{\n  alpha,\n  beta,\n}");
+    }
+
+    #[test]
+    fn break_parent_and_flush() {
+        let doc = Doc::concat([
+            Doc::text("a"),
+            Doc::indent(Doc::concat([
+                Doc::HardLine,
+                Doc::Flush("#if X".to_string()),
+                Doc::HardLine,
+                Doc::group(Doc::concat([
+                    Doc::text("b"),
+                    Doc::Line,
+                    Doc::text("// c"),
+                    Doc::BreakParent,
+                ])),
+                Doc::HardLine,
+                Doc::HardLine,
+                Doc::text("d"),
+            ])),
+        ]);
+
+        let config = FormatConfig::default();
+        assert_eq!(render(&doc, &config), "a\n#if X\n    b\n    // c\n\n    d");
+
+        let config = FormatConfig {
+            use_tabs: true,
+            ..FormatConfig::default()
+        };
+        assert_eq!(render(&doc, &config), "a\n#if X\n\tb\n\t// c\n\n\td");
+    }
+
+    #[test]
+    fn fills_break_where_needed() {
+        let words = ["one", "two", "three", "four", "five"].map(Doc::text);
+        let separator = Doc::concat([Doc::Line, Doc::if_break(Doc::text("> "))]);
+        let doc = Doc::indent(Doc::concat([Doc::text("> "), Doc::fill(words, separator)]));
+
+        let config = FormatConfig {
+            max_line_length: 16,
+            ..FormatConfig::default()
+        };
+        assert_eq!(render(&doc, &config), "> one two three\n    > four five");
+    }
+
+    #[test]
+    fn lines_are_written_once_aligned() {
+        let line = |code: &str, comment: &str| {
+            Doc::concat([
+                Doc::text(code),
+                Doc::Anchor(Anchor::Comment),
+                Doc::text(" "),
+                Doc::text(comment),
+                Doc::HardLine,
+            ])
+        };
+        let doc = Doc::concat([
+            line("a;", "// one"),
+            line("bcd;", "// two"),
+            Doc::HardLine,
+            line("e;", "// three"),
+            Doc::text("f;"),
+        ]);
+
+        // Every write holds whole lines, and the comments on consecutive lines are aligned.
+        let mut writes = Writes(Vec::new());
+        render_to(&doc, &FormatConfig::default(), &mut writes).unwrap();
+        let Writes(writes) = writes;
+        assert_eq!(writes, ["a;   // one\nbcd; // two\n\n", "e; // three\nf;"]);
+        assert_eq!(render(&doc, &FormatConfig::default()), writes.concat());
+    }
+
+    #[test]
+    fn tabs_reach_the_next_tab_stop() {
+        let config = FormatConfig {
+            use_tabs: true,
+            tab_width: 8,
+            max_line_length: 11,
+            ..FormatConfig::default()
+        };
+
+        // The tab takes up six columns, so the group no longer fits.
+        let doc = Doc::group(Doc::concat([Doc::text("a\tb"), Doc::Line, Doc::text("cd")]));
+        assert_eq!(render(&doc, &config), "a\tb\ncd");
+
+        // Indentation is written with as many tabs as fit, and comments are aligned by the
+        // columns they are displayed at.
+        let line = |code: &str| {
+            Doc::concat([
+                Doc::text(code),
+                Doc::Anchor(Anchor::Comment),
+                Doc::text(" //"),
+                Doc::HardLine,
+            ])
+        };
+        let doc = Doc::indent(Doc::indent(Doc::indent(Doc::concat([
+            Doc::HardLine,
+            line("a"),
+            line("\tb"),
+        ]))));
+        let config = FormatConfig {
+            max_line_length: 80,
+            ..config
+        };
+        assert_eq!(render(&doc, &config), "\n\t    a     //\n\t    \tb //\n");
+    }
+}
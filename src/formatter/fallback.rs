@@ -0,0 +1,448 @@
+//! A formatter which works on tokens alone, for code which the formatter based on the syntax tree
+//! cannot handle. It indents on braces, puts every statement on a line of its own and normalizes
+//! the spacing around operators. Line breaks inside statements are kept where they were written,
+//! since there is no syntax tree to decide where else to break long lines.
+
+use crate::formatter::config::FormatConfig;
+use crate::formatter::formatter::{finish, spaced};
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::token::Token::{
+    Ampersand, Brace, Bracket, Colon, Comma, Directive, Disabled, Equal, Identifier, Keyword,
+    Minus, MinusMinus, Number, Parenthesis, Plus, PlusPlus, Question, Semicolon, SlashSlash,
+    SlashStar, Star,
+};
+use crate::lexer::token::{Lexeme, Token, TokenKeyword};
+
+/// What an opening parenthesis belongs to, which decides the spacing after the closing one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Opener {
+    /// The condition of a control statement, such as `if (x)`, after which a statement follows.
+    Control,
+    /// The arguments of a call, or the parameters of a declaration.
+    Call,
+    /// Anything else, including casts.
+    Other,
+}
+
+/// What an opening brace belongs to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Opening {
+    /// A block of statements, or the members of a struct or union.
+    Block,
+    /// An initializer list or the enumerators of an enum, whose items are separated by commas,
+    /// with the number of parentheses the brace is in.
+    List(usize),
+}
+
+/// Writes the tokens one line at a time.
+struct Writer<'a> {
+    config: &'a FormatConfig,
+    output: String,
+    line: String,
+    /// The number of blocks the current line is nested in.
+    depth: usize,
+    /// What each enclosing brace belongs to.
+    braces: Vec<Opening>,
+    parentheses: Vec<Opener>,
+    /// Whether the current statement has started, so that line breaks inside it are indented as
+    /// continuation lines.
+    statement: bool,
+    /// The number of `?` in the current statement which have not been matched by a `:` yet.
+    conditionals: usize,
+    /// Whether the current statement started with `case` or `default`.
+    case: bool,
+    /// Whether the next token has to start a new line, such as after a `;`.
+    pending: bool,
+}
+
+impl Writer<'_> {
+    /// Write text, starting with the indentation if it starts a line.
+    fn write(&mut self, text: &str) {
+        if self.line.is_empty() {
+            let mut columns = self.depth * self.config.indent_width;
+            if self.statement {
+                columns += self.config.continuation_indent;
+            }
+
+            if self.config.use_tabs {
+                let width = self.config.tab_columns().max(1);
+                self.line.extend(std::iter::repeat_n('\t', columns / width));
+                self.line.extend(std::iter::repeat_n(' ', columns % width));
+            } else {
+                self.line.extend(std::iter::repeat_n(' ', columns));
+            }
+        }
+
+        self.line.push_str(text);
+    }
+
+    /// End the current line, if anything was written on it, followed by a number of empty lines.
+    fn newline(&mut self, blank_lines: usize) {
+        if !self.line.is_empty() {
+            self.output.push_str(&self.line);
+            self.output.push('\n');
+            self.line.clear();
+        }
+
+        if !self.output.is_empty() {
+            let blank_lines = blank_lines.min(self.config.max_blank_lines);
+            self.output.extend(std::iter::repeat_n('\n', blank_lines));
+        }
+        self.pending = false;
+    }
+
+    /// Finish a statement, so that the next token starts a new line.
+    fn end_statement(&mut self) {
+        self.statement = false;
+        self.conditionals = 0;
+        self.case = false;
+        self.pending = true;
+    }
+
+    fn in_initializer(&self) -> bool {
+        matches!(self.braces.last(), Some(Opening::List(_)))
+    }
+
+    /// Whether a comma separates the items of the innermost initializer list or enum.
+    fn separates_items(&self) -> bool {
+        self.braces.last() == Some(&Opening::List(self.parentheses.len()))
+    }
+}
+
+/// Whether a token can start a declaration, so that a `*` after an identifier following it is
+/// part of a pointer declarator, as in `int *p` or `struct node *next`.
+fn starts_declaration(token: Option<&Token>) -> bool {
+    match token {
+        None => true,
+        Some(Keyword(keyword)) => matches!(
+            keyword,
+            TokenKeyword::Const
+                | TokenKeyword::Volatile
+                | TokenKeyword::Extern
+                | TokenKeyword::Static
+                | TokenKeyword::Auto
+                | TokenKeyword::Struct
+                | TokenKeyword::Union
+                | TokenKeyword::Enum
+                | TokenKeyword::Typedef
+                | TokenKeyword::Register
+                | TokenKeyword::Inline
+                | TokenKeyword::Restrict
+                | TokenKeyword::Unsigned
+        ),
+        Some(token) => matches!(
+            token,
+            Semicolon | Brace(_) | Parenthesis(Left) | Comma | Directive(_)
+        ),
+    }
+}
+
+/// Whether a token ends a statement, so that an identifier followed by a `:` after it is a label.
+fn starts_statement(token: Option<&Token>) -> bool {
+    matches!(token, None | Some(Semicolon | Brace(_) | Colon))
+}
+
+/// Whether an operator is written directly in front of its operand, such as the `-` of `-x` or
+/// the `*` of `int *p`, given the two tokens before it.
+fn is_prefix(before: Option<&Token>, previous: Option<&Token>, operator: &Token) -> bool {
+    match operator {
+        PlusPlus | MinusMinus => !previous.is_some_and(Token::ends_operand),
+        Star if matches!(previous, Some(Identifier(_))) => starts_declaration(before),
+        Star if matches!(previous, Some(Star)) => true,
+        Plus | Minus | Star | Ampersand => !previous.is_some_and(Token::ends_operand),
+        Token::Bang | Token::Tilde => true,
+        _ => false,
+    }
+}
+
+/// Format source code from its tokens alone.
+pub fn format_tokens(lexemes: &[Lexeme], config: &FormatConfig) -> String {
+    let mut writer = Writer {
+        config,
+        output: String::new(),
+        line: String::new(),
+        depth: 0,
+        braces: Vec::new(),
+        parentheses: Vec::new(),
+        statement: false,
+        conditionals: 0,
+        case: false,
+        pending: false,
+    };
+
+    // The token before the previous one, the previous one, and whether the previous one was a
+    // prefix operator.
+    let mut before: Option<&Token> = None;
+    let mut previous: Option<&Token> = None;
+    let mut prefix = false;
+    // Whether the last parenthesis which was closed belonged to a cast.
+    let mut cast = false;
+
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        let token = &lexeme.token;
+        let next = lexemes.get(i + 1).map(|lexeme| &lexeme.token);
+        let blank_lines = lexeme.newlines.saturating_sub(1);
+
+        // Comments after code stay on its line, even if a line break is pending.
+        if matches!(token, SlashSlash(_) | SlashStar(_)) && lexeme.newlines == 0 && i > 0 {
+            writer.write(" ");
+            writer.write(&token.to_string());
+            writer.pending |= matches!(token, SlashSlash(_));
+            continue;
+        }
+
+        let closing =
+            matches!(token, Brace(Right)) && writer.braces.last() == Some(&Opening::Block);
+        // Code after a closing brace may continue its line, unless a comment came in between.
+        let follows_brace = i > 0 && lexemes[i - 1].token == Brace(Right) && writer.pending;
+        let attached = follows_brace
+            && (matches!(token, Semicolon | Comma | Keyword(TokenKeyword::Else))
+                || lexeme.newlines == 0
+                    && matches!(token, Identifier(_) | Star | Keyword(TokenKeyword::While)));
+
+        if matches!(token, Directive(_) | Disabled(_)) || closing {
+            writer.newline(if closing { 0 } else { blank_lines });
+        } else if attached {
+            writer.pending = false;
+            if !matches!(token, Semicolon | Comma) {
+                writer.write(" ");
+            }
+        } else if writer.pending || lexeme.newlines > 0 {
+            writer.newline(blank_lines);
+        } else if let Some(previous) = previous {
+            let space = match (previous, token) {
+                // Operators which would merge into a different one, as in `- -x`.
+                (Minus, Minus | MinusMinus) | (Plus, Plus | PlusPlus) => true,
+                (Ampersand, Ampersand) => true,
+                _ if prefix => false,
+                (Parenthesis(Right), _) if cast => config.space_after_cast,
+                (Parenthesis(Right), Parenthesis(Left)) => false,
+                (Identifier(_) | Bracket(Right), PlusPlus | MinusMinus) => false,
+                (_, Colon) if writer.case && writer.conditionals == 0 => false,
+                (Identifier(_), Colon) if starts_statement(before) => false,
+                _ => spaced(previous, token),
+            };
+            if space {
+                writer.write(" ");
+            }
+        }
+
+        match token {
+            Directive(text) => {
+                writer.line.push('#');
+                writer.line.push_str(text.trim_end());
+                writer.newline(0);
+                // A directive in the middle of a statement does not end it.
+                writer.pending = !writer.statement;
+            }
+            Disabled(text) => {
+                writer.line.push_str(text.trim_end_matches('\n'));
+                writer.newline(0);
+            }
+            SlashSlash(_) | SlashStar(_) => {
+                writer.write(&token.to_string());
+                writer.pending = true;
+            }
+            Brace(Left) => {
+                let initializer = writer.in_initializer()
+                    || !writer.parentheses.is_empty()
+                    || matches!(previous, Some(Equal));
+                let enumeration = matches!(previous, Some(Keyword(TokenKeyword::Enum)))
+                    || matches!(
+                        (before, previous),
+                        (Some(Keyword(TokenKeyword::Enum)), Some(Identifier(_)))
+                    );
+                if !initializer && !writer.line.is_empty() && !writer.line.ends_with(' ') {
+                    writer.line.push(' ');
+                }
+                writer.statement &= initializer;
+                writer.write("{");
+                // The items of a list are indented like statements when they are on lines of
+                // their own, but stay on the line of the brace if they were written there.
+                writer.depth += 1;
+                if initializer || enumeration {
+                    writer.braces.push(Opening::List(writer.parentheses.len()));
+                    writer.statement = false;
+                } else {
+                    writer.braces.push(Opening::Block);
+                    writer.end_statement();
+                }
+            }
+            Brace(Right) => {
+                writer.depth = writer.depth.saturating_sub(1);
+                writer.statement = false;
+                writer.write("}");
+                writer.braces.pop();
+                if closing {
+                    writer.end_statement();
+                } else {
+                    writer.statement = true;
+                }
+            }
+            Parenthesis(Left) => {
+                let opener = match previous {
+                    Some(Keyword(
+                        TokenKeyword::If
+                        | TokenKeyword::For
+                        | TokenKeyword::While
+                        | TokenKeyword::Switch,
+                    )) => Opener::Control,
+                    Some(Identifier(_) | Parenthesis(Right) | Keyword(_)) => Opener::Call,
+                    _ => Opener::Other,
+                };
+                writer.parentheses.push(opener);
+                writer.write("(");
+                writer.statement = true;
+            }
+            Parenthesis(Right) => {
+                let opener = writer.parentheses.pop();
+                writer.write(")");
+                cast = opener == Some(Opener::Other)
+                    && matches!(previous, Some(Identifier(_) | Star | Keyword(_)))
+                    && next.is_some_and(|next| {
+                        matches!(
+                            next,
+                            Identifier(_) | Number(_) | Parenthesis(Left) | Keyword(_)
+                        )
+                    });
+                if opener == Some(Opener::Control) && writer.parentheses.is_empty() {
+                    // The statement after the condition starts on the same line, as it was
+                    // written, unless it is a block.
+                    writer.statement = !matches!(next, Some(Brace(Left)));
+                }
+            }
+            Semicolon => {
+                writer.write(";");
+                if writer.parentheses.is_empty() && !writer.in_initializer() {
+                    writer.end_statement();
+                }
+            }
+            Comma if writer.separates_items() => {
+                writer.write(",");
+                writer.statement = false;
+            }
+            Question => {
+                writer.conditionals += 1;
+                writer.write("?");
+            }
+            Colon if writer.conditionals > 0 => {
+                writer.conditionals -= 1;
+                writer.write(":");
+            }
+            Colon => {
+                writer.write(":");
+                // Labels are followed by the statement they label, on a line of its own.
+                let label = writer.case
+                    || matches!(previous, Some(Identifier(_))) && starts_statement(before);
+                if label && writer.parentheses.is_empty() && !writer.in_initializer() {
+                    writer.end_statement();
+                }
+            }
+            _ => {
+                if !writer.statement
+                    && matches!(token, Keyword(TokenKeyword::Case | TokenKeyword::Default))
+                {
+                    writer.case = true;
+                }
+                writer.write(&token.to_string());
+                writer.statement = true;
+            }
+        }
+
+        if !matches!(token, SlashSlash(_) | SlashStar(_)) {
+            prefix = is_prefix(before, previous, token);
+            before = previous;
+            previous = Some(token);
+        }
+    }
+
+    writer.newline(0);
+    finish(writer.output, config)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::FormatConfig;
+    use crate::formatter::fallback::format_tokens;
+    use crate::lexer::lexer::Lexer;
+    use crate::lexer::token::Lexeme;
+
+    fn test(input: &str, expected: &str) {
+        let format = |input: &str| {
+            let lexemes: Vec<Lexeme> = Lexer::new(input)
+                .lexemes()
+                .map(|lexeme| lexeme.unwrap())
+                .collect();
+            format_tokens(&lexemes, &FormatConfig::default())
+        };
+
+        assert_eq!(format(input), expected);
+        assert_eq!(format(expected), expected);
+    }
+
+    #[test]
+    fn statements_blocks_and_operators() {
+        test(
+            "#include <stdio.h>\nstatic int counts[]={1,2,3};\n\
+             struct node{int value;struct node*next;};\n\
+             int main(int argc,char**argv){ int x=-1,*p=&x; // trailing\n\
+             if(argc>1){printf(\"%d\\n\",argc);}else if(argc==0)return 1;else{x++;}\n\
+             switch(x){case 1:x=(int)sizeof(long);break;default:x=a?b:c;}\n\
+             do{x--;}while(x>0);\n    long_call(a,\n        b, c);\n\
+             end: return - -x;\n#if 0\n  not  C\n#endif\n}\n",
+            "#include <stdio.h>\n\
+             static int counts[] = { 1, 2, 3 };\n\
+             struct node {\n\
+             \x20   int value;\n\
+             \x20   struct node *next;\n\
+             };\n\
+             int main(int argc, char **argv) {\n\
+             \x20   int x = -1, *p = &x; // trailing\n\
+             \x20   if (argc > 1) {\n\
+             \x20       printf(\"%d\\n\", argc);\n\
+             \x20   } else if (argc == 0) return 1;\n\
+             \x20   else {\n\
+             \x20       x++;\n\
+             \x20   }\n\
+             \x20   switch (x) {\n\
+             \x20       case 1:\n\
+             \x20       x = (int)sizeof(long);\n\
+             \x20       break;\n\
+             \x20       default:\n\
+             \x20       x = a ? b : c;\n\
+             \x20   }\n\
+             \x20   do {\n\
+             \x20       x--;\n\
+             \x20   } while (x > 0);\n\
+             \x20   long_call(a,\n\
+             \x20       b, c);\n\
+             \x20   end:\n\
+             \x20   return - -x;\n\
+             #if 0\n\
+             \x20 not  C\n\
+             #endif\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn comments_keep_code_apart() {
+        test(
+            "if (x) {\n}\n// why\nelse {\ny; /* a */ }",
+            "if (x) {\n}\n// why\nelse {\n    y; /* a */\n}\n",
+        );
+    }
+
+    #[test]
+    fn lists_are_indented_like_blocks() {
+        test(
+            "int x[] = {\n1,\n  2, 3,\n};\nstruct p q = { f(1,\n2), { 3, 4 } };",
+            "int x[] = {\n    1,\n    2, 3,\n};\nstruct p q = { f(1,\n        2), { 3, 4 } };\n",
+        );
+        test(
+            "enum color {\nRED,\n// between\nGREEN = 2,\n/* last */\n};\nenum { A, B } e;",
+            "enum color {\n    RED,\n    // between\n    GREEN = 2,\n    /* last */\n};\n\
+             enum { A, B } e;\n",
+        );
+    }
+}
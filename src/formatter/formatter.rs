@@ -0,0 +1,3836 @@
+use crate::formatter::comment::{sections, Section};
+use crate::formatter::config::{
+    ArgumentWrapping, BackslashAlignment, BraceStyle, CaseBraces, DirectiveIndentation,
+    FormatConfig, InitializerLayout, KeywordPlacement, LabelPlacement, LineEnding,
+    PointerAlignment,
+};
+use crate::formatter::doc::{render, render_to, Anchor, Doc};
+use crate::formatter::guard::header_guard;
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::Token::{
+    Arrow, Bang, Bracket, Comma, Dot, Identifier, Keyword, Parenthesis, Semicolon, Tilde,
+};
+use crate::lexer::token::{Token, TokenKeyword};
+use crate::parser::ast::{
+    Annotated, Asm, Attribute, AttributeStyle, Block, Comment, CommentStyle, ConditionalGroup,
+    Declaration, Declarator, Designator, DirectDeclarator, Enum, Enumerator, Expression,
+    ExternalDeclaration, FunctionDefinition, GenericAssociation, InitDeclarator, Initializer,
+    InitializerItem, InitializerList, MacroCall, Member, MemberDeclarator, Nodes, Parameter,
+    Record, RecordKind, Specifier, Statement, StatementId, StaticAssert, TranslationUnit, TypeName,
+    UnaryOperator,
+};
+use std::cell::Cell;
+use std::io::{self, Write};
+
+/// Format an entire translation unit as C source code. Regions which could not be parsed must
+/// have been salvaged beforehand, so that their original text can be written.
+pub fn format(unit: &TranslationUnit, config: &FormatConfig) -> String {
+    let mut output = Vec::new();
+    format_to(unit, config, &mut output).expect("writing to memory does not fail");
+    String::from_utf8(output).expect("the output is made of strings")
+}
+
+/// Format an entire translation unit as C source code, writing it to a sink such as a file or the
+/// standard output as it goes, rather than holding all of it in memory. Only the lines which may
+/// still be aligned with the ones after them are held back.
+///
+/// Nothing checks the code written this way. The command and `format_file` do not use it, as they
+/// check that the formatted code keeps the meaning of the source before any of it is written, and
+/// that takes all of it, so they still hold the whole of the formatted code in memory.
+pub fn format_to(
+    unit: &TranslationUnit,
+    config: &FormatConfig,
+    sink: &mut impl Write,
+) -> io::Result<()> {
+    let formatter = Formatter {
+        config,
+        nodes: &unit.nodes,
+        depth: Cell::new(0),
+    };
+    let doc = formatter.translation_unit(unit);
+    let mut finisher = Finisher::new(sink, config);
+    render_to(&doc, config, &mut finisher)?;
+    finisher.finish()
+}
+
+/// Format a sequence of statements and declarations as C source code, as if they made up a block
+/// at the outermost level of indentation.
+pub fn format_statements(block: &Block, nodes: &Nodes, config: &FormatConfig) -> String {
+    let formatter = Formatter {
+        config,
+        nodes,
+        depth: Cell::new(0),
+    };
+    let doc = formatter.block_contents(block).unwrap_or(Doc::text(""));
+    let text = render(&doc, config);
+    finish(text.trim_start_matches('\n').to_string(), config)
+}
+
+/// Format a single expression as C source code, without a line break after it.
+pub fn format_expression(expression: &Expression, nodes: &Nodes, config: &FormatConfig) -> String {
+    let formatter = Formatter {
+        config,
+        nodes,
+        depth: Cell::new(0),
+    };
+    let doc = formatter.expression(expression);
+    let text = finish(render(&doc, config), config);
+    text.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Whether a statement is simple enough to be written on the same line as the code before it,
+/// rather than containing other statements or starting a line of its own.
+fn is_simple(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::Declaration(_)
+            | Statement::StaticAssert(_)
+            | Statement::Macro { .. }
+            | Statement::Asm(_)
+            | Statement::Expression(_)
+            | Statement::Goto(_)
+            | Statement::Continue
+            | Statement::Break
+            | Statement::Return(_)
+    )
+}
+
+fn has_comments<T>(annotated: &Annotated<T>) -> bool {
+    !annotated.leading.is_empty() || !annotated.trailing.is_empty()
+}
+
+/// The characters which turn a comment into a documentation comment, such as the third `/` of
+/// `///` or the `*` of `/**`.
+fn marker(text: &str) -> &str {
+    let end = text
+        .find(|c| !matches!(c, '/' | '*' | '!'))
+        .unwrap_or(text.len());
+    &text[..end]
+}
+
+/// Whether a comment is drawn rather than written, such as a banner or a box, because one of its
+/// lines has a run of three or more of the same symbol. Its layout is kept as it is.
+fn is_banner(text: &str) -> bool {
+    text.split('\n').any(|line| {
+        let chars: Vec<char> = line.chars().collect();
+        chars.windows(3).any(|run| {
+            let c = run[0];
+            run.iter().all(|&other| other == c)
+                && !c.is_alphanumeric()
+                && !c.is_whitespace()
+                && c != '.'
+        })
+    })
+}
+
+/// Remove up to a number of columns of indentation from the start of a line.
+fn dedent(line: &str, columns: usize) -> &str {
+    let start = line
+        .char_indices()
+        .take(columns)
+        .take_while(|(_, c)| c.is_whitespace())
+        .map(|(i, c)| i + c.len_utf8())
+        .last()
+        .unwrap_or(0);
+    &line[start..]
+}
+
+/// Clean up the end of every line and the end of the file. Comments, directives and verbatim
+/// regions are written as they were, so they may still contain trailing whitespace.
+pub fn finish(text: String, config: &FormatConfig) -> String {
+    let mut output = Vec::new();
+    let mut finisher = Finisher::new(&mut output, config);
+    finisher
+        .write_all(text.as_bytes())
+        .and_then(|_| finisher.finish())
+        .expect("writing to memory does not fail");
+    String::from_utf8(output).expect("lines are only split at line feeds")
+}
+
+/// Cleans up the end of every line and the end of the file, as `finish` does, while the code is
+/// written to a sink. Only the current line and the empty lines before it are held back, since
+/// empty lines at the end of the file are left out.
+struct Finisher<'a, W: Write> {
+    sink: &'a mut W,
+    trim: bool,
+    final_newline: bool,
+    /// The line break to write, which is a line feed unless the caller resolved it to CRLF.
+    newline: &'static [u8],
+    /// The current line, up to the last line break which was written to the finisher.
+    line: Vec<u8>,
+    /// The number of line breaks which end the code written so far.
+    newlines: usize,
+    /// Whether the last line which was written ends in a carriage return which was held back.
+    carriage_return: bool,
+    /// Whether anything other than line breaks was written.
+    written: bool,
+}
+
+impl<'a, W: Write> Finisher<'a, W> {
+    fn new(sink: &'a mut W, config: &FormatConfig) -> Finisher<'a, W> {
+        Finisher {
+            sink,
+            trim: config.trim_trailing_whitespace,
+            final_newline: config.insert_final_newline,
+            // The line breaks of the source are resolved by the caller, which has the source.
+            // Without it, they are line feeds.
+            newline: match config.line_ending.resolve("") {
+                LineEnding::CrLf => b"\r\n",
+                _ => b"\n",
+            },
+            line: Vec::new(),
+            newlines: 0,
+            carriage_return: false,
+            written: false,
+        }
+    }
+
+    /// Write the line breaks which were held back, in front of more code.
+    fn write_newlines(&mut self) -> io::Result<()> {
+        for _ in 0..self.newlines {
+            self.carriage_return = false;
+            self.sink.write_all(self.newline)?;
+        }
+        self.newlines = 0;
+        Ok(())
+    }
+
+    /// Write the current line once it is finished. With CRLF line breaks, a carriage return at
+    /// its end is held back, as the line break after it replaces it.
+    fn write_line(&mut self) -> io::Result<()> {
+        let mut end = self.line.len();
+        if self.trim {
+            while end > 0 && matches!(self.line[end - 1], b' ' | b'\t' | b'\r') {
+                end -= 1;
+            }
+        }
+        if end == 0 {
+            return Ok(());
+        }
+
+        self.write_newlines()?;
+        self.carriage_return = self.newline == b"\r\n" && self.line[end - 1] == b'\r';
+        self.sink
+            .write_all(&self.line[..end - self.carriage_return as usize])?;
+        self.written = true;
+        self.line.clear();
+        Ok(())
+    }
+
+    /// Write what is left of the code, dropping the empty lines at its end.
+    fn finish(mut self) -> io::Result<()> {
+        self.write_line()?;
+        self.newlines = (self.final_newline && self.written) as usize;
+        self.write_newlines()?;
+        if self.carriage_return {
+            self.sink.write_all(b"\r")?;
+        }
+        self.sink.flush()
+    }
+}
+
+impl<W: Write> Write for Finisher<'_, W> {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let mut lines = buffer.split(|&byte| byte == b'\n');
+        if let Some(first) = lines.next() {
+            self.line.extend_from_slice(first);
+        }
+        for line in lines {
+            self.write_line()?;
+            self.line.clear();
+            self.newlines += 1;
+            self.line.extend_from_slice(line);
+        }
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.sink.flush()
+    }
+}
+
+/// Turns the nodes of a syntax tree into documents.
+struct Formatter<'a> {
+    config: &'a FormatConfig,
+    nodes: &'a Nodes,
+    /// The number of conditional groups around the code being written, by which directives are
+    /// indented.
+    depth: Cell<usize>,
+}
+
+/// The kinds of code which is enclosed in braces, as brace styles treat them differently.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Braces {
+    Function,
+    Control,
+    /// The body of a `switch`, whose case labels need not be indented.
+    Switch,
+    Definition,
+}
+
+/// Where a pair of braces is written, relative to the code before them.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Placement {
+    /// On the same line, as in `if (x) {`.
+    Attach,
+    /// On the next line, at the same indentation.
+    NextLine,
+    /// On the next line, indented together with the statements inside them.
+    Indented,
+    /// On the next line, indented with the statements indented another level.
+    HalfIndented,
+}
+
+fn placement(style: BraceStyle, braces: Braces) -> Placement {
+    match (style, braces) {
+        (BraceStyle::Attach, _) => Placement::Attach,
+        (BraceStyle::KernighanRitchie | BraceStyle::Linux, Braces::Function) => Placement::NextLine,
+        (BraceStyle::KernighanRitchie | BraceStyle::Linux, _) => Placement::Attach,
+        (BraceStyle::Allman, _) => Placement::NextLine,
+        (BraceStyle::Gnu, Braces::Control | Braces::Switch) => Placement::HalfIndented,
+        (BraceStyle::Gnu, _) => Placement::NextLine,
+        (BraceStyle::Whitesmiths, _) => Placement::Indented,
+    }
+}
+
+/// Concatenate documents, turning string slices into text.
+macro_rules! concat {
+    ($($doc:expr),* $(,)?) => {
+        Doc::concat([$(Doc::from($doc)),*])
+    };
+}
+
+impl From<&str> for Doc {
+    fn from(text: &str) -> Doc {
+        Doc::text(text)
+    }
+}
+
+impl From<String> for Doc {
+    fn from(text: String) -> Doc {
+        Doc::Text(text)
+    }
+}
+
+/// Whether there should be a space in between two uninterpreted tokens.
+pub fn spaced(previous: &Token, next: &Token) -> bool {
+    let glued_after = matches!(
+        previous,
+        Parenthesis(Left) | Bracket(Left) | Dot | Arrow | Bang | Tilde
+    );
+    let glued_before = matches!(
+        next,
+        Parenthesis(Right) | Bracket(Left) | Bracket(Right) | Comma | Semicolon | Dot | Arrow
+    );
+    let call = matches!(next, Parenthesis(Left))
+        && matches!(
+            previous,
+            Identifier(_)
+                | Parenthesis(Right)
+                | Keyword(TokenKeyword::Sizeof | TokenKeyword::Alignof | TokenKeyword::Generic)
+        );
+
+    !glued_after && !glued_before && !call
+}
+
+/// Write a sequence of uninterpreted tokens on a single line. Prefix operators, such as the `&` of
+/// `&x`, are written directly in front of their operand.
+fn tokens(tokens: &[Token]) -> String {
+    let mut text = String::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let prefix = i > 0 && tokens[i - 1].is_prefix_after(i.checked_sub(2).map(|i| &tokens[i]));
+        if i > 0 && !prefix && spaced(&tokens[i - 1], token) {
+            text.push(' ');
+        }
+        text.push_str(&token.to_string());
+    }
+
+    text
+}
+
+/// Whether an item at file scope defines a function or the body of a type, which are set apart
+/// from their surroundings by empty lines.
+fn is_definition(item: &ExternalDeclaration) -> bool {
+    match item {
+        ExternalDeclaration::FunctionDefinition(_) => true,
+        ExternalDeclaration::Declaration(declaration) => defines_type(&declaration.specifiers),
+        _ => false,
+    }
+}
+
+/// Whether specifiers define the body of a type, as `struct s { int x; }` does.
+fn defines_type(specifiers: &[Specifier]) -> bool {
+    specifiers.iter().any(|specifier| match specifier {
+        Specifier::Record(record) => record.members.is_some(),
+        Specifier::Enum(enumeration) => enumeration.enumerators.is_some(),
+        _ => false,
+    })
+}
+
+/// Whether the name of a declarator is wrapped in parentheses, as that of a pointer to a function
+/// is in `void (*callback)(int)`.
+fn is_parenthesized(direct: &DirectDeclarator) -> bool {
+    match direct {
+        DirectDeclarator::Parenthesized(_) => true,
+        DirectDeclarator::Array(inner, _) | DirectDeclarator::Function(inner, _, _) => {
+            is_parenthesized(inner)
+        }
+        DirectDeclarator::Identifier(_) | DirectDeclarator::Abstract => false,
+    }
+}
+
+/// Whether a directive is written exactly as it was, as its spacing may matter to the compiler
+/// or to the tools which generated it: a `#pragma`, a `#line`, or a line marker such as
+/// `# 1 "list.c"`. Such a directive belongs to the item after it.
+fn is_exact_directive(line: &str) -> bool {
+    let line = line.trim_start();
+    let name = line
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or_default();
+    matches!(name, "pragma" | "line") || name.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// The kinds of items at file scope which are kept together, and which can be set apart from
+/// each other by empty lines.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Group {
+    Includes,
+    Macros,
+    Types,
+    Globals,
+    Functions,
+}
+
+/// The group an item at file scope belongs to, if any.
+fn group(item: &ExternalDeclaration) -> Option<Group> {
+    match item {
+        ExternalDeclaration::Directive(line) if line.trim_start().starts_with("include") => {
+            Some(Group::Includes)
+        }
+        ExternalDeclaration::Directive(_) => Some(Group::Macros),
+        ExternalDeclaration::Declaration(declaration) => {
+            let typedef = declaration
+                .specifiers
+                .contains(&Specifier::Keyword(TokenKeyword::Typedef));
+
+            if typedef || declaration.declarators.is_empty() {
+                Some(Group::Types)
+            } else {
+                Some(Group::Globals)
+            }
+        }
+        ExternalDeclaration::FunctionDefinition(_) => Some(Group::Functions),
+        _ => None,
+    }
+}
+
+/// How the text of a string literal ends, as far as joining it with the next one is concerned.
+enum Ending {
+    Plain,
+    LineBreak,
+    /// An escape sequence which goes on for as long as there are hexadecimal digits.
+    Hexadecimal,
+    /// An escape sequence of fewer than three octal digits.
+    Octal,
+}
+
+fn ending(literal: &str) -> Ending {
+    let mut chars = literal.chars().peekable();
+    let mut ending = Ending::Plain;
+
+    while let Some(c) = chars.next() {
+        ending = Ending::Plain;
+        if c != '\\' {
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => ending = Ending::LineBreak,
+            Some('x') => {
+                while chars.next_if(char::is_ascii_hexdigit).is_some() {}
+                ending = Ending::Hexadecimal;
+            }
+            Some('0'..='7') => {
+                let octal = |c: &char| ('0'..='7').contains(c);
+                if chars.next_if(octal).is_none() || chars.next_if(octal).is_none() {
+                    ending = Ending::Octal;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ending
+}
+
+/// Whether two adjacent string literals are joined into one. This is not done after a line
+/// break, nor where the escape sequence at the end of the first literal would take in the start
+/// of the second one, as in `"\x1" "2"`.
+fn joinable(first: &str, second: &str) -> bool {
+    match ending(first) {
+        Ending::Plain => true,
+        Ending::LineBreak => false,
+        Ending::Hexadecimal => !second.starts_with(|c: char| c.is_ascii_hexdigit()),
+        Ending::Octal => !second.starts_with(|c: char| ('0'..='7').contains(&c)),
+    }
+}
+
+/// Split the text of a string literal after every run of spaces.
+fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in text.char_indices() {
+        if c == ' ' && !text[i + 1..].starts_with(' ') {
+            words.push(&text[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < text.len() || words.is_empty() {
+        words.push(&text[start..]);
+    }
+
+    words
+}
+
+/// Adjacent string literals, joined where possible, and split into more of them in between
+/// words where they do not fit. The literals on continuation lines are aligned with the first.
+fn string(parts: &[String]) -> Doc {
+    let mut literals: Vec<String> = Vec::new();
+    for part in parts {
+        match literals.last_mut() {
+            Some(literal) if joinable(literal, part) => literal.push_str(part),
+            _ => literals.push(part.clone()),
+        }
+    }
+
+    let separator = Doc::choice(concat!("\"", Doc::Line, "\""), Doc::Concat(Vec::new()));
+    let literals = literals.iter().map(|literal| {
+        let words = words(literal).into_iter().map(Doc::text);
+        concat!("\"", Doc::fill(words, separator.clone()), "\"")
+    });
+    Doc::align(Doc::group(Doc::join(literals, Doc::Line)))
+}
+
+/// A number literal with a lowercase prefix, exponent and hexadecimal digits in uppercase, a
+/// suffix in uppercase, and a digit in front of the period.
+pub fn number_literal(literal: &str) -> String {
+    let lower = literal.to_ascii_lowercase();
+    let (prefix, rest) = match lower.get(..2) {
+        Some(prefix @ ("0x" | "0b")) => (prefix, &literal[2..]),
+        _ => ("", literal),
+    };
+    let hexadecimal = prefix == "0x";
+    let is_digit = |c: char| {
+        c == '.' || c == '\'' || c.is_ascii_digit() || hexadecimal && c.is_ascii_hexdigit()
+    };
+
+    let end = rest.find(|c| !is_digit(c)).unwrap_or(rest.len());
+    let (digits, mut rest) = rest.split_at(end);
+
+    let mut result = String::from(prefix);
+    if digits.starts_with('.') {
+        result.push('0');
+    }
+    result.push_str(&digits.to_ascii_uppercase());
+
+    let exponent = if hexadecimal { 'p' } else { 'e' };
+    if rest.starts_with([exponent, exponent.to_ascii_uppercase()]) {
+        let sign = usize::from(rest[1..].starts_with(['+', '-']));
+        let end = rest[1 + sign..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(rest.len(), |end| end + 1 + sign);
+        result.push(exponent);
+        result.push_str(&rest[1..end]);
+        rest = &rest[end..];
+    }
+
+    result.push_str(&rest.to_ascii_uppercase());
+    result
+}
+
+/// Original text which is written as it was. Text which starts with its own indentation, such as
+/// a region where formatting is turned off, starts at the first column rather than the current
+/// indentation.
+fn verbatim(text: &str) -> Doc {
+    if text.starts_with([' ', '\t']) {
+        Doc::Flush(text.to_string())
+    } else {
+        Doc::Verbatim(text.to_string())
+    }
+}
+
+/// The number of empty lines in front of a node, including its leading comments.
+fn blank_lines_before<T>(annotated: &Annotated<T>) -> usize {
+    match annotated.leading.first() {
+        Some(comment) => comment.blank_lines,
+        None => annotated.blank_lines,
+    }
+}
+
+/// Whether a direct declarator starts without a name or parentheses, as in the `[]` of `int []`.
+fn is_abstract(direct: &DirectDeclarator) -> bool {
+    match direct {
+        DirectDeclarator::Abstract => true,
+        DirectDeclarator::Array(inner, _) | DirectDeclarator::Function(inner, _, _) => {
+            is_abstract(inner)
+        }
+        DirectDeclarator::Identifier(_) | DirectDeclarator::Parenthesized(_) => false,
+    }
+}
+
+/// Whether an operator followed directly by a prefix operator would be read as a different
+/// token, as in `a - -b` or `a / *p`.
+fn merges(operator: &str, next: Option<&str>) -> bool {
+    let Some(next) = next else {
+        return false;
+    };
+
+    matches!(
+        (operator.chars().last(), next.chars().next()),
+        (Some('+'), Some('+'))
+            | (Some('-'), Some('-'))
+            | (Some('&'), Some('&'))
+            | (Some('/'), Some('*'))
+    )
+}
+
+impl Formatter<'_> {
+    /// Write a comment as it is. The lines after the first line of a block comment on a line of
+    /// its own keep their position relative to the opening delimiter, so that it can be indented
+    /// without breaking up a box or the column of `*`s.
+    fn comment(&self, comment: &Comment) -> Doc {
+        let text = format!("/*{}*/", comment.text);
+        match comment.style {
+            CommentStyle::Line => concat!(format!("//{}", comment.text), Doc::BreakParent),
+            CommentStyle::Block if comment.own_line && text.contains('\n') => {
+                let mut lines = text.split('\n');
+                let first = Doc::text(lines.next().unwrap_or_default());
+                let rest = lines.map(|line| match dedent(line, comment.column) {
+                    line if line.trim().is_empty() => Doc::HardLine,
+                    line => concat!(Doc::HardLine, line),
+                });
+                concat!(first, Doc::concat(rest))
+            }
+            CommentStyle::Block => Doc::Verbatim(text),
+        }
+    }
+
+    /// Split comments into the runs which are written together. When comments are reflowed, line
+    /// comments on consecutive lines of their own form a single run, so that their paragraphs are
+    /// wrapped as a whole. Markers such as `// NOLINTNEXTLINE` are runs of their own.
+    fn runs<'c>(&self, comments: &'c [Comment]) -> Vec<&'c [Comment]> {
+        let joined = |a: &Comment, b: &Comment| {
+            self.config.reflow_comments
+                && !a.is_marker()
+                && !b.is_marker()
+                && a.style == CommentStyle::Line
+                && b.style == CommentStyle::Line
+                && a.own_line
+                && b.own_line
+                && b.blank_lines == 0
+                && marker(&a.text) == marker(&b.text)
+        };
+
+        comments.chunk_by(|a, b| joined(a, b)).collect()
+    }
+
+    /// Write a run of comments, wrapping their paragraphs if comments are reflowed. Comments after
+    /// code are never reflowed, and neither are Doxygen blocks such as `/** */` and `/*! */`,
+    /// banners, markers for other tools, or block comments unless each of their lines starts with
+    /// a `*`.
+    fn comments(&self, run: &[Comment]) -> Doc {
+        let first = &run[0];
+        let marker = marker(&first.text);
+        let drawn = run
+            .iter()
+            .any(|comment| is_banner(&comment.text[marker.len()..]));
+        let doxygen = first.style == CommentStyle::Block && !marker.is_empty();
+        if !self.config.reflow_comments || !first.own_line || drawn || doxygen || first.is_marker()
+        {
+            return Doc::concat(run.iter().map(|comment| self.comment(comment)));
+        }
+
+        match first.style {
+            CommentStyle::Line => {
+                let lines = run.iter().map(|comment| &comment.text[marker.len()..]);
+                let prefix = format!("//{}", marker);
+                concat!(
+                    self.reflow(&prefix, &prefix, sections(lines)),
+                    Doc::BreakParent
+                )
+            }
+            CommentStyle::Block => {
+                let mut text = first.text.split('\n');
+                let mut lines = vec![text.next().unwrap_or_default()];
+                for line in text {
+                    match line.trim_start().strip_prefix('*') {
+                        Some(line) => lines.push(line),
+                        None if line.trim().is_empty() => lines.push(""),
+                        None => return self.comment(first),
+                    }
+                }
+
+                // The closing delimiter stays on a line of its own if it was on one before.
+                let own_line = lines.len() > 1 && lines.last().is_some_and(|l| l.trim().is_empty());
+                if own_line {
+                    lines.pop();
+                }
+
+                let mut sections = sections(lines);
+                let closing = match sections.last_mut() {
+                    _ if own_line => concat!(Doc::HardLine, " */"),
+                    Some(Section::Paragraph(paragraph)) => {
+                        if let Some(word) = paragraph.words.last_mut() {
+                            word.push_str(" */");
+                        }
+                        Doc::text("")
+                    }
+                    _ => Doc::text(" */"),
+                };
+
+                concat!(self.reflow("/*", " *", sections), closing)
+            }
+        }
+    }
+
+    /// Write the lines of a comment, starting each with a prefix such as `//`. The first line may
+    /// have a different prefix, such as the opening delimiter of a block comment.
+    fn reflow(&self, first: &str, prefix: &str, sections: Vec<Section>) -> Doc {
+        let lines = sections.into_iter().enumerate().map(|(i, section)| {
+            let start = if i == 0 { first } else { prefix };
+            match section {
+                Section::Empty => Doc::text(start),
+                Section::Verbatim(line) => Doc::text(format!("{}{}", start, line)),
+                Section::Paragraph(paragraph) => {
+                    let continuation = " ".repeat(paragraph.continuation());
+                    let separator = concat!(
+                        Doc::Line,
+                        Doc::if_break(Doc::text(format!("{}{}", prefix, continuation)))
+                    );
+                    let bullet = match paragraph.bullet.as_str() {
+                        "" => String::new(),
+                        bullet => format!("{} ", bullet),
+                    };
+
+                    concat!(
+                        format!("{}{}{}", start, " ".repeat(paragraph.indent), bullet),
+                        Doc::fill(paragraph.words.into_iter().map(Doc::text), separator)
+                    )
+                }
+            }
+        });
+
+        Doc::join(lines, Doc::HardLine)
+    }
+
+    /// Surround a node with its comments. The suffix, such as the comma of a list item, is written
+    /// right after the node, so that it never ends up inside a trailing comment.
+    fn annotated<T>(&self, annotated: &Annotated<T>, doc: Doc, suffix: Doc) -> Doc {
+        let mut docs = Vec::new();
+        let mut own_line = false;
+
+        // Empty lines in front of the first leading comment are written by the caller, since they
+        // separate this node from the previous one. A block comment is followed by a line break if
+        // the comment after it started a line of its own, or if it did so itself when it is last.
+        let runs = self.runs(&annotated.leading);
+        for (i, run) in runs.iter().enumerate() {
+            if i > 0 && own_line {
+                docs.push(self.blank_lines(run[0].blank_lines));
+            }
+
+            docs.push(self.comments(run));
+            let comment = &run[run.len() - 1];
+            let next = runs.get(i + 1).map_or(comment, |next| &next[0]);
+            own_line = next.own_line || comment.style == CommentStyle::Line;
+            if own_line {
+                docs.push(Doc::HardLine);
+            } else {
+                docs.push(Doc::text(" "));
+            }
+        }
+
+        if own_line {
+            docs.push(self.blank_lines(annotated.blank_lines));
+        }
+
+        // A marker after the node, such as `// NOLINT`, is about the code on its line, so the node
+        // is kept on that line rather than wrapped, unless it always spans several lines.
+        let marked = annotated.trailing.iter().any(Comment::is_marker);
+        match doc.flat_width() {
+            Some(_) if marked => docs.push(Doc::flat(concat!(doc, suffix))),
+            _ => docs.extend([doc, suffix]),
+        }
+
+        // The comments which end the line are aligned from the first one, whatever their style,
+        // unless one of them spans several lines.
+        let aligned = self.config.align_trailing_comments
+            && !annotated
+                .trailing
+                .iter()
+                .any(|comment| comment.text.contains('\n'));
+        for (i, comment) in annotated.trailing.iter().enumerate() {
+            docs.push(Doc::text(" "));
+            if i == 0 && aligned {
+                docs.push(Doc::Anchor(Anchor::Comment));
+            }
+            docs.push(self.comment(comment));
+        }
+
+        Doc::Concat(docs)
+    }
+
+    /// Up to the maximum number of empty lines, to be written after a line break.
+    fn blank_lines(&self, count: usize) -> Doc {
+        let count = count.min(self.config.max_blank_lines);
+        Doc::concat(std::iter::repeat_n(Doc::HardLine, count))
+    }
+
+    /// Write items on lines of their own, followed by the comments after the last one. The empty
+    /// lines in between items are kept, and more are inserted if `separation` requires so for
+    /// two items. Those in front of the first item are only kept if `leading` is set.
+    fn lines<T>(
+        &self,
+        items: &[Annotated<T>],
+        comments: &[Comment],
+        leading: bool,
+        item: impl Fn(&T) -> Doc,
+        separation: impl Fn(&T, &T) -> usize,
+    ) -> Doc {
+        let mut docs = Vec::new();
+
+        for (i, annotated) in items.iter().enumerate() {
+            let mut blank_lines = blank_lines_before(annotated).min(self.config.max_blank_lines);
+            if i > 0 {
+                docs.push(Doc::HardLine);
+                blank_lines = blank_lines.max(separation(&items[i - 1].node, &annotated.node));
+            }
+
+            if i > 0 || leading {
+                docs.push(Doc::concat(std::iter::repeat_n(Doc::HardLine, blank_lines)));
+            }
+            docs.push(self.annotated(annotated, item(&annotated.node), Doc::text("")));
+        }
+
+        for run in self.runs(comments) {
+            if !docs.is_empty() {
+                docs.push(Doc::HardLine);
+            }
+            if !docs.is_empty() || leading {
+                docs.push(self.blank_lines(run[0].blank_lines));
+            }
+            docs.push(self.comments(run));
+        }
+
+        Doc::Concat(docs)
+    }
+
+    /// Write a preprocessor directive at the start of the line, indented by the number of
+    /// conditional groups around it if the options say so. Pragmas and line markers are written
+    /// exactly as they were.
+    fn directive(&self, line: &str) -> Doc {
+        if is_exact_directive(line) {
+            return Doc::Flush(format!("#{}", line));
+        }
+
+        let indent = " ".repeat(self.depth.get() * self.config.indent_width);
+        let line = line.trim_start();
+
+        let text = match self.config.directive_indentation {
+            DirectiveIndentation::None => format!("#{}", line),
+            DirectiveIndentation::AfterHash => format!("#{}{}", indent, line),
+            DirectiveIndentation::BeforeHash => format!("{}#{}", indent, line),
+        };
+        Doc::Flush(self.backslashes(text))
+    }
+
+    /// Align the backslashes at the ends of the lines of a multi-line directive into a column.
+    /// Directives with line breaks inside comments are left alone.
+    fn backslashes(&self, text: String) -> String {
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        let Some(last) = lines.pop() else {
+            return text;
+        };
+        let continued: Option<Vec<&str>> = lines
+            .iter()
+            .map(|line| Some(line.strip_suffix('\\')?.trim_end()))
+            .collect();
+
+        let Some(continued) = continued else {
+            return text;
+        };
+        let width = |line: &str| self.config.column_after(0, line);
+        let Some(longest) = continued.iter().map(|line| width(line)).max() else {
+            return text;
+        };
+        let column = match self.config.backslash_alignment {
+            BackslashAlignment::Keep => return text,
+            BackslashAlignment::Left => longest + 1,
+            BackslashAlignment::Right => {
+                (longest + 1).max(self.config.max_line_length.saturating_sub(1))
+            }
+        };
+
+        let mut output = String::new();
+        for line in continued {
+            let padding = column - width(line);
+            output.push_str(&format!("{}{}\\\n", line, " ".repeat(padding)));
+        }
+        output.push_str(last);
+        output
+    }
+
+    /// Write the branches of a conditional group, with the directives at the start of the line.
+    /// The directives inside it are indented by `nesting` more levels.
+    fn conditional<T>(
+        &self,
+        group: &ConditionalGroup<T>,
+        nesting: usize,
+        item: impl Fn(&T) -> Doc,
+        separation: impl Fn(&T, &T) -> usize,
+    ) -> Doc {
+        let mut docs = Vec::new();
+        let depth = self.depth.get();
+
+        for branch in &group.branches {
+            docs.push(self.directive(&branch.directive));
+            if let Some(text) = &branch.disabled {
+                docs.push(Doc::Flush(text.clone()));
+                docs.push(Doc::HardLine);
+            }
+            if !branch.items.is_empty() || !branch.comments.is_empty() {
+                self.depth.set(depth + nesting);
+                docs.push(Doc::HardLine);
+                docs.push(self.lines(&branch.items, &branch.comments, true, &item, &separation));
+                // The directive after the branch starts a line of its own without a line break.
+                let edges = !self.config.strip_blank_lines_at_block_edges;
+                if edges && branch.closing_blank_lines > 0 {
+                    docs.push(Doc::HardLine);
+                    docs.push(self.blank_lines(branch.closing_blank_lines));
+                }
+                self.depth.set(depth);
+            }
+        }
+
+        docs.push(self.directive(&group.end));
+        Doc::Concat(docs)
+    }
+
+    fn translation_unit(&self, unit: &TranslationUnit) -> Doc {
+        let guarded = header_guard(unit).is_some();
+        let doc = self.lines(
+            &unit.items,
+            &unit.comments,
+            false,
+            |item| match item {
+                ExternalDeclaration::Conditional(group) if guarded => self.conditional(
+                    group,
+                    0,
+                    |item| self.external_declaration(item),
+                    |a, b| self.separation(a, b),
+                ),
+                item => self.external_declaration(item),
+            },
+            |a, b| self.separation(a, b),
+        );
+
+        if unit.items.is_empty() && unit.comments.is_empty() {
+            doc
+        } else {
+            concat!(doc, Doc::HardLine)
+        }
+    }
+
+    /// The number of empty lines required in between two consecutive items at file scope.
+    fn separation(&self, a: &ExternalDeclaration, b: &ExternalDeclaration) -> usize {
+        if matches!(a, ExternalDeclaration::Directive(line) if is_exact_directive(line)) {
+            return 0;
+        }
+
+        let mut blank_lines = 0;
+
+        if is_definition(a) || is_definition(b) {
+            blank_lines = self.config.blank_lines_around_definitions;
+        }
+        if matches!((group(a), group(b)), (Some(a), Some(b)) if a != b) {
+            blank_lines = blank_lines.max(self.config.blank_lines_between_groups);
+        }
+
+        blank_lines
+    }
+
+    fn external_declaration(&self, item: &ExternalDeclaration) -> Doc {
+        match item {
+            ExternalDeclaration::Directive(line) => self.directive(line),
+            ExternalDeclaration::Declaration(declaration) => self.declaration(declaration, true),
+            ExternalDeclaration::FunctionDefinition(function) => self.function(function),
+            ExternalDeclaration::Conditional(group) => self.conditional(
+                group,
+                1,
+                |item| self.external_declaration(item),
+                |a, b| self.separation(a, b),
+            ),
+            ExternalDeclaration::Macro { call, semicolon } => self.macro_call(call, *semicolon),
+            ExternalDeclaration::Asm(asm) => self.asm(asm),
+            ExternalDeclaration::StaticAssert(assertion) => self.static_assert(assertion),
+            ExternalDeclaration::Empty => Doc::text(";"),
+            ExternalDeclaration::LinkageWrapper {
+                condition,
+                language,
+                end,
+            } => {
+                let line = match language {
+                    Some(language) => format!("extern \"{}\" {{", language),
+                    None => "}".to_string(),
+                };
+                concat!(
+                    self.directive(condition),
+                    Doc::HardLine,
+                    line,
+                    Doc::HardLine,
+                    self.directive(end)
+                )
+            }
+            ExternalDeclaration::Invalid(_) => unreachable!("invalid regions are salvaged first"),
+            ExternalDeclaration::Verbatim(text) => verbatim(text),
+        }
+    }
+
+    fn function(&self, function: &FunctionDefinition) -> Doc {
+        let body = self.braces(Braces::Function, self.block_contents(&function.body));
+        let short = match function.body.statements.as_slice() {
+            _ if !self.config.allow_short_functions_on_single_line => None,
+            _ if !function.body.opening.is_empty() || !function.body.comments.is_empty() => None,
+            [] => Some(Doc::text(" {}")),
+            [statement] if is_simple(&statement.node) && !has_comments(statement) => {
+                Some(concat!(" { ", self.statement(&statement.node), " }"))
+            }
+            _ => None,
+        };
+
+        concat!(
+            self.typed(&function.specifiers, &function.declarator),
+            match short {
+                Some(short) => Doc::group(Doc::choice(body, short)),
+                None => body,
+            }
+        )
+    }
+
+    /// Write a declaration. If it is `aligned`, its name and the `=` of its first declarator may
+    /// be aligned with those of the declarations around it.
+    fn declaration(&self, declaration: &Declaration, aligned: bool) -> Doc {
+        let Some(first) = declaration.declarators.first() else {
+            return concat!(self.specifiers(&declaration.specifiers), ";");
+        };
+
+        let declarators = self.list(declaration.declarators.iter().enumerate().map(
+            |(i, declarator)| {
+                let anchored = aligned && i == 0 && self.config.align_consecutive_assignments;
+                self.init_declarator(declarator, self.alignment(i), anchored)
+            },
+        ));
+
+        if declaration.specifiers.is_empty() {
+            concat!(declarators, ";")
+        } else {
+            let anchor = if aligned && self.config.align_consecutive_declarations {
+                Doc::Anchor(Anchor::Name)
+            } else {
+                Doc::text("")
+            };
+
+            concat!(
+                self.specifiers(&declaration.specifiers),
+                self.separator(&first.declarator),
+                anchor,
+                declarators,
+                ";"
+            )
+        }
+    }
+
+    fn init_declarator(
+        &self,
+        declarator: &InitDeclarator,
+        alignment: PointerAlignment,
+        anchored: bool,
+    ) -> Doc {
+        let anchor = if anchored {
+            Doc::Anchor(Anchor::Assignment)
+        } else {
+            Doc::text("")
+        };
+
+        match &declarator.initializer {
+            Some(initializer) => concat!(
+                self.declarator(&declarator.declarator, alignment),
+                anchor,
+                self.assign("="),
+                self.initializer(initializer),
+            ),
+            None => self.declarator(&declarator.declarator, alignment),
+        }
+    }
+
+    fn initializer(&self, initializer: &Initializer) -> Doc {
+        match initializer {
+            Initializer::Expression(expression) => self.expression(&self.nodes[*expression]),
+            Initializer::List(list) => self.initializer_list(list),
+        }
+    }
+
+    /// Write an initializer list on a single line if it fits, and otherwise according to the
+    /// initializer layout. A trailing comma is only kept in the latter case.
+    fn initializer_list(&self, list: &InitializerList) -> Doc {
+        if list.items.is_empty() && list.comments.is_empty() {
+            return Doc::text("{}");
+        }
+
+        let padding = if self.config.spaces_in_braces {
+            Doc::Line
+        } else {
+            Doc::SoftLine
+        };
+
+        let values: Vec<Doc> = list
+            .items
+            .iter()
+            .map(|item| self.initializer_item(&item.node))
+            .collect();
+        // Comments in between items would end up out of place if the items were filled into lines.
+        let layout = match self.config.initializer_layout {
+            _ if list.items.iter().any(has_comments) || !list.comments.is_empty() => {
+                InitializerLayout::OnePerLine
+            }
+            layout => layout,
+        };
+        let filled = layout != InitializerLayout::OnePerLine;
+        let widths: Option<Vec<usize>> = values.iter().map(Doc::flat_width).collect();
+
+        let items: Vec<Doc> = list
+            .items
+            .iter()
+            .zip(values)
+            .enumerate()
+            .map(|(i, (item, value))| {
+                let suffix = if i + 1 < list.items.len() {
+                    Doc::text(",")
+                } else if list.trailing_comma && !filled {
+                    Doc::if_break(Doc::text(","))
+                } else {
+                    Doc::text("")
+                };
+                self.annotated(item, value, suffix)
+            })
+            .collect();
+
+        let items = match (layout, widths) {
+            (InitializerLayout::OnePerLine, _) => Doc::join(items, self.line()),
+            (InitializerLayout::Columns, Some(widths)) => {
+                let widest = widths.iter().copied().max().unwrap_or_default();
+                let mut parts = Vec::new();
+                for (item, width) in items.into_iter().zip(widths) {
+                    // The padding is part of the separator after the item, so that it is left out
+                    // at the end of a line.
+                    let padding = " ".repeat(widest - width);
+                    parts.push(item);
+                    parts.push(Doc::choice(self.line(), concat!(padding, self.line())));
+                }
+                parts.pop();
+                Doc::Fill(parts)
+            }
+            _ => Doc::fill(items, self.line()),
+        };
+        let trailing = if list.trailing_comma && filled {
+            Doc::if_break(Doc::text(","))
+        } else {
+            Doc::text("")
+        };
+        // The comments after the last item stay in front of the closing brace.
+        let comments = self.runs(&list.comments).into_iter().map(|run| {
+            let line = match list.items.is_empty() {
+                true => Doc::text(""),
+                false => concat!(Doc::HardLine, self.blank_lines(run[0].blank_lines)),
+            };
+            concat!(line, self.comments(run))
+        });
+        let items = concat!(items, Doc::concat(comments));
+
+        let forced = if self.config.break_initializers_with_trailing_comma && list.trailing_comma {
+            Doc::BreakParent
+        } else {
+            Doc::text("")
+        };
+
+        Doc::group(concat!(
+            "{",
+            Doc::indent(concat!(padding.clone(), items, trailing)),
+            padding,
+            "}",
+            forced,
+        ))
+    }
+
+    fn initializer_item(&self, item: &InitializerItem) -> Doc {
+        if item.designators.is_empty() {
+            return self.initializer(&item.value);
+        }
+
+        let designators = item.designators.iter().map(|designator| match designator {
+            Designator::Member(name) => Doc::text(format!(".{}", name)),
+            Designator::Index(index) => self.bracketed(self.expression(&self.nodes[*index])),
+        });
+
+        concat!(
+            Doc::concat(designators),
+            self.assign("="),
+            self.initializer(&item.value)
+        )
+    }
+
+    fn specifiers(&self, specifiers: &[Specifier]) -> Doc {
+        Doc::join(
+            specifiers.iter().map(|specifier| self.specifier(specifier)),
+            Doc::text(" "),
+        )
+    }
+
+    fn specifier(&self, specifier: &Specifier) -> Doc {
+        match specifier {
+            Specifier::Keyword(keyword) => Doc::text(keyword.as_str()),
+            Specifier::Type(name) => Doc::text(name.as_str()),
+            Specifier::Record(record) => self.record(record),
+            Specifier::Enum(enumeration) => self.enumeration(enumeration),
+            Specifier::Attribute(attribute) => self.attribute(attribute),
+        }
+    }
+
+    fn attribute(&self, attribute: &Attribute) -> Doc {
+        match attribute.style {
+            AttributeStyle::Standard => Doc::text(format!("[[{}]]", tokens(&attribute.tokens))),
+            AttributeStyle::Gnu => {
+                Doc::text(format!("__attribute__(({}))", tokens(&attribute.tokens)))
+            }
+        }
+    }
+
+    /// Write the keyword, attributes and tag of a record or enum.
+    fn tag(&self, keyword: &str, attributes: &[Attribute], name: &Option<Symbol>) -> Doc {
+        let mut docs = vec![Doc::text(keyword)];
+
+        for attribute in attributes {
+            docs.push(Doc::text(" "));
+            docs.push(self.attribute(attribute));
+        }
+
+        if let Some(name) = name {
+            docs.push(Doc::text(format!(" {}", name)));
+        }
+
+        Doc::Concat(docs)
+    }
+
+    /// Write the body of a record or enum, with every item on a line of its own.
+    fn body(&self, lines: Doc, empty: bool) -> Doc {
+        let contents = (!empty).then(|| concat!(Doc::HardLine, lines));
+        self.braces(Braces::Definition, contents)
+    }
+
+    fn record(&self, record: &Record) -> Doc {
+        let keyword = match record.kind {
+            RecordKind::Struct => "struct",
+            RecordKind::Union => "union",
+        };
+        let tag = self.tag(keyword, &record.attributes, &record.name);
+
+        match &record.members {
+            Some(members) => {
+                let lines = self.lines(
+                    members,
+                    &record.comments,
+                    false,
+                    |member| self.member(member),
+                    |_, _| 0,
+                );
+                let empty = members.is_empty() && record.comments.is_empty();
+                concat!(tag, self.body(lines, empty))
+            }
+            None => tag,
+        }
+    }
+
+    fn member(&self, member: &Member) -> Doc {
+        match member {
+            Member::Field {
+                specifiers,
+                declarators,
+            } => {
+                // The name of a member which defines a type comes after its closing brace, and
+                // that of a pointer to a function after a parenthesis, so neither is aligned.
+                let aligned = self.config.align_struct_members
+                    && !defines_type(specifiers)
+                    && !declarators.iter().any(|member| {
+                        member
+                            .declarator
+                            .as_ref()
+                            .is_some_and(|declarator| is_parenthesized(&declarator.direct))
+                    });
+                let specifiers = self.specifiers(specifiers);
+                let separator = match declarators.first() {
+                    Some(MemberDeclarator {
+                        declarator: Some(declarator),
+                        ..
+                    }) => self.separator(declarator),
+                    Some(_) => " ",
+                    None => return concat!(specifiers, ";"),
+                };
+
+                let declarators = declarators
+                    .iter()
+                    .enumerate()
+                    .map(|(i, declarator)| self.member_declarator(declarator, self.alignment(i)));
+                let anchor = if aligned {
+                    Doc::Anchor(Anchor::Name)
+                } else {
+                    Doc::text("")
+                };
+
+                concat!(
+                    specifiers,
+                    separator,
+                    anchor,
+                    Doc::join(declarators, Doc::text(self.comma())),
+                    ";"
+                )
+            }
+            Member::Directive(line) => self.directive(line),
+            Member::Conditional(group) => {
+                self.conditional(group, 1, |member| self.member(member), |_, _| 0)
+            }
+            Member::Macro { call, semicolon } => self.macro_call(call, *semicolon),
+        }
+    }
+
+    fn member_declarator(&self, declarator: &MemberDeclarator, alignment: PointerAlignment) -> Doc {
+        match (&declarator.declarator, &declarator.width) {
+            (Some(declarator), Some(width)) => concat!(
+                self.declarator(declarator, alignment),
+                " : ",
+                self.expression(&self.nodes[*width])
+            ),
+            (Some(declarator), None) => self.declarator(declarator, alignment),
+            (None, Some(width)) => concat!(": ", self.expression(&self.nodes[*width])),
+            (None, None) => Doc::text(""),
+        }
+    }
+
+    fn enumeration(&self, enumeration: &Enum) -> Doc {
+        let tag = self.tag("enum", &enumeration.attributes, &enumeration.name);
+
+        let Some(enumerators) = &enumeration.enumerators else {
+            return tag;
+        };
+
+        let lines = enumerators.iter().enumerate().map(|(i, enumerator)| {
+            // Comments after the last enumerator follow its comma.
+            let last = i + 1 == enumerators.len() && enumeration.comments.is_empty();
+            let suffix = if last { "" } else { "," };
+            let blank_lines = match i {
+                0 => Doc::text(""),
+                _ => concat!(
+                    Doc::HardLine,
+                    self.blank_lines(blank_lines_before(enumerator))
+                ),
+            };
+
+            concat!(
+                blank_lines,
+                self.annotated(
+                    enumerator,
+                    self.enumerator(&enumerator.node),
+                    Doc::text(suffix),
+                )
+            )
+        });
+
+        let comments = self.runs(&enumeration.comments).into_iter().map(|run| {
+            let blank_lines = match enumerators.is_empty() {
+                true => Doc::text(""),
+                false => concat!(Doc::HardLine, self.blank_lines(run[0].blank_lines)),
+            };
+            concat!(blank_lines, self.comments(run))
+        });
+        let lines = Doc::concat(lines.chain(comments));
+
+        let empty = enumerators.is_empty() && enumeration.comments.is_empty();
+        let body = self.body(lines, empty);
+        let short = !enumerators.is_empty()
+            && enumerators.len() <= self.config.max_enumerators_on_single_line
+            && !enumerators.iter().any(has_comments)
+            && enumeration.comments.is_empty();
+        if !short {
+            return concat!(tag, body);
+        }
+
+        let padding = if self.config.spaces_in_braces {
+            " "
+        } else {
+            ""
+        };
+        let enumerators = enumerators
+            .iter()
+            .map(|enumerator| self.enumerator(&enumerator.node));
+        let flat = concat!(
+            " {",
+            padding,
+            Doc::join(enumerators, Doc::text(self.comma())),
+            padding,
+            "}"
+        );
+        concat!(tag, Doc::group(Doc::choice(body, flat)))
+    }
+
+    fn enumerator(&self, enumerator: &Enumerator) -> Doc {
+        let anchor = if self.config.align_enum_values {
+            Doc::Anchor(Anchor::Assignment)
+        } else {
+            Doc::text("")
+        };
+
+        match &enumerator.value {
+            Some(value) => concat!(
+                enumerator.name.as_str(),
+                anchor,
+                self.assign("="),
+                self.expression(&self.nodes[*value])
+            ),
+            None => Doc::text(enumerator.name.as_str()),
+        }
+    }
+
+    /// The pointer alignment of the declarator at the given position in a declaration. Only the
+    /// first one follows the configured alignment, as in `int* p, *q`, since the pointers of the
+    /// others cannot be next to the type.
+    fn alignment(&self, position: usize) -> PointerAlignment {
+        match position {
+            0 => self.config.pointer_alignment,
+            _ => PointerAlignment::Right,
+        }
+    }
+
+    /// The space in between specifiers and the first declarator following them. There is none if
+    /// the declarator is empty, starts with a bracket, or starts with a pointer next to the type.
+    fn separator(&self, declarator: &Declarator) -> &'static str {
+        let glued = if declarator.pointers.is_empty() {
+            declarator.attributes.is_empty() && is_abstract(&declarator.direct)
+        } else {
+            self.config.pointer_alignment == PointerAlignment::Left
+        };
+
+        if glued {
+            ""
+        } else {
+            " "
+        }
+    }
+
+    /// Write specifiers followed by a declarator, as in parameters and type names.
+    fn typed(&self, specifiers: &[Specifier], declarator: &Declarator) -> Doc {
+        let alignment = self.config.pointer_alignment;
+
+        if specifiers.is_empty() {
+            self.declarator(declarator, alignment)
+        } else {
+            concat!(
+                self.specifiers(specifiers),
+                self.separator(declarator),
+                self.declarator(declarator, alignment)
+            )
+        }
+    }
+
+    /// Write the pointers of a declarator, including the space in between them and the rest of
+    /// the declarator, if any.
+    fn pointers(&self, declarator: &Declarator, alignment: PointerAlignment) -> String {
+        // Stars are written next to each other, while the spaces around qualifiers depend on the
+        // alignment, as in `*const *p`, `* const* p` and `* const * p`.
+        let mut text = String::new();
+        let spaced = alignment != PointerAlignment::Right;
+
+        for pointer in &declarator.pointers {
+            if !text.is_empty() && !text.ends_with('*') && alignment != PointerAlignment::Left {
+                text.push(' ');
+            }
+            text.push('*');
+
+            for qualifier in &pointer.qualifiers {
+                if !text.ends_with('*') || spaced {
+                    text.push(' ');
+                }
+                text.push_str(qualifier.as_str());
+            }
+        }
+
+        let named = !matches!(declarator.direct, DirectDeclarator::Abstract);
+        if !text.is_empty() && named && (!text.ends_with('*') || spaced) {
+            text.push(' ');
+        }
+
+        text
+    }
+
+    fn declarator(&self, declarator: &Declarator, alignment: PointerAlignment) -> Doc {
+        let mut docs = vec![
+            Doc::Text(self.pointers(declarator, alignment)),
+            self.direct_declarator(&declarator.direct),
+        ];
+
+        for attribute in &declarator.attributes {
+            docs.push(Doc::text(" "));
+            docs.push(self.attribute(attribute));
+        }
+
+        Doc::Concat(docs)
+    }
+
+    fn direct_declarator(&self, direct: &DirectDeclarator) -> Doc {
+        match direct {
+            DirectDeclarator::Identifier(name) => Doc::text(name.as_str()),
+            DirectDeclarator::Abstract => Doc::text(""),
+            DirectDeclarator::Parenthesized(inner) => {
+                self.parenthesized(self.declarator(inner, PointerAlignment::Right))
+            }
+            DirectDeclarator::Array(inner, size) => match size {
+                Some(size) => concat!(
+                    self.direct_declarator(inner),
+                    self.bracketed(self.expression(&self.nodes[*size])),
+                ),
+                None => concat!(self.direct_declarator(inner), "[]"),
+            },
+            DirectDeclarator::Function(inner, parameters, variadic) => {
+                if parameters.iter().any(has_comments) {
+                    return concat!(
+                        self.direct_declarator(inner),
+                        self.commented_parameters(parameters, *variadic)
+                    );
+                }
+
+                let mut docs: Vec<Doc> = parameters
+                    .iter()
+                    .map(|parameter| self.parameter(&parameter.node))
+                    .collect();
+                if *variadic {
+                    docs.push(Doc::text("..."));
+                }
+
+                concat!(self.direct_declarator(inner), self.arguments(docs))
+            }
+        }
+    }
+
+    /// Write a parameter list with comments in between its parameters. Each comma is written right
+    /// after its parameter so that it never ends up inside a comment, and the list is broken one
+    /// parameter per line if a line comment ends one of them.
+    fn commented_parameters(&self, parameters: &[Annotated<Parameter>], variadic: bool) -> Doc {
+        let mut docs: Vec<Doc> = parameters
+            .iter()
+            .enumerate()
+            .map(|(i, parameter)| {
+                let suffix = if i + 1 < parameters.len() || variadic {
+                    Doc::text(",")
+                } else {
+                    Doc::text("")
+                };
+                self.annotated(parameter, self.parameter(&parameter.node), suffix)
+            })
+            .collect();
+        if variadic {
+            docs.push(Doc::text("..."));
+        }
+
+        let (line, padding) = if self.config.spaces_in_parentheses {
+            (Doc::Line, " ")
+        } else {
+            (Doc::SoftLine, "")
+        };
+        // A line comment after the last parameter is followed by the closing parenthesis on the
+        // next line.
+        let closing = match parameters.last() {
+            Some(last)
+                if !variadic
+                    && last
+                        .trailing
+                        .last()
+                        .is_some_and(|c| c.style == CommentStyle::Line) =>
+            {
+                Doc::HardLine
+            }
+            _ => Doc::text(padding),
+        };
+
+        Doc::group(concat!(
+            "(",
+            Doc::continuation(concat!(line, Doc::join(docs, self.line()))),
+            closing,
+            ")"
+        ))
+    }
+
+    fn parameter(&self, parameter: &Parameter) -> Doc {
+        self.typed(&parameter.specifiers, &parameter.declarator)
+    }
+
+    fn type_name(&self, type_name: &TypeName) -> Doc {
+        self.typed(&type_name.specifiers, &type_name.declarator)
+    }
+
+    fn macro_call(&self, call: &MacroCall, semicolon: bool) -> Doc {
+        let arguments: Vec<String> = call.arguments.iter().map(|arg| tokens(arg)).collect();
+        let semicolon = if semicolon { ";" } else { "" };
+        Doc::text(format!(
+            "{}{}({}){}",
+            call.name,
+            self.call_separator(),
+            arguments.join(self.comma()),
+            semicolon
+        ))
+    }
+
+    fn asm(&self, asm: &Asm) -> Doc {
+        let mut text = asm.keyword.to_string();
+
+        for qualifier in &asm.qualifiers {
+            text.push(' ');
+            text.push_str(qualifier);
+        }
+
+        text.push('(');
+        for (i, section) in asm.sections.iter().enumerate() {
+            if i > 0 {
+                text.push_str(" :");
+            }
+            if i > 0 && !section.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(&tokens(section));
+        }
+        text.push_str(");");
+
+        Doc::Text(text)
+    }
+
+    fn static_assert(&self, assertion: &StaticAssert) -> Doc {
+        match &assertion.message {
+            Some(message) => concat!(
+                "_Static_assert(",
+                self.expression(&self.nodes[assertion.condition]),
+                self.comma(),
+                self.expression(&self.nodes[*message]),
+                ");",
+            ),
+            None => concat!(
+                "_Static_assert(",
+                self.expression(&self.nodes[assertion.condition]),
+                ");"
+            ),
+        }
+    }
+
+    /// Write a pair of braces after the code before them, placed according to the brace style.
+    /// The contents start every line with a line break, and are `None` for empty braces.
+    fn braces(&self, braces: Braces, contents: Option<Doc>) -> Doc {
+        let indented = braces != Braces::Switch || self.config.indent_case_labels;
+        self.placed(
+            placement(self.config.brace_style, braces),
+            contents,
+            indented,
+        )
+    }
+
+    /// Write a pair of braces at the given placement, with their contents indented one level
+    /// further than the braces themselves if `indented` is set.
+    fn placed(&self, placement: Placement, contents: Option<Doc>, indented: bool) -> Doc {
+        let indent = |contents: Doc| {
+            if indented {
+                Doc::indent(contents)
+            } else {
+                contents
+            }
+        };
+        let inner = |contents: Option<Doc>| match contents {
+            Some(contents) => concat!("{", contents, Doc::HardLine, "}"),
+            None => concat!("{", Doc::HardLine, "}"),
+        };
+
+        match placement {
+            Placement::Attach => match contents {
+                Some(contents) => concat!(" {", indent(contents), Doc::HardLine, "}"),
+                None => Doc::text(" {}"),
+            },
+            Placement::NextLine => concat!(Doc::HardLine, inner(contents.map(indent))),
+            Placement::Indented => Doc::indent(concat!(Doc::HardLine, inner(contents))),
+            Placement::HalfIndented => {
+                Doc::indent(concat!(Doc::HardLine, inner(contents.map(indent))))
+            }
+        }
+    }
+
+    /// Write a pair of braces with their contents indented, or `{}` if they are empty.
+    fn enclosed(&self, contents: Option<Doc>) -> Doc {
+        match contents {
+            Some(contents) => concat!("{", Doc::indent(contents), Doc::HardLine, "}"),
+            None => Doc::text("{}"),
+        }
+    }
+
+    /// Whether the body of a control statement is a block whose closing brace is followed by the
+    /// next keyword on the same line, as in `} else {`.
+    fn attached(&self, id: StatementId, keyword: KeywordPlacement) -> bool {
+        let attached = match keyword {
+            KeywordPlacement::BraceStyle => {
+                placement(self.config.brace_style, Braces::Control) == Placement::Attach
+            }
+            KeywordPlacement::SameLine => true,
+            KeywordPlacement::NextLine => false,
+        };
+
+        attached && matches!(self.nodes[id], Statement::Compound(_))
+    }
+
+    fn block_contents(&self, block: &Block) -> Option<Doc> {
+        if block.opening.is_empty() && block.statements.is_empty() && block.comments.is_empty() {
+            return None;
+        }
+
+        // The comments around the opening brace follow it on its line, except for those after a
+        // line comment, which start the lines after it.
+        let mut docs = Vec::new();
+        for (i, comment) in block.opening.iter().enumerate() {
+            match i.checked_sub(1).map(|i| &block.opening[i]) {
+                Some(previous) if previous.style == CommentStyle::Line => docs.push(Doc::HardLine),
+                _ => docs.push(Doc::text(" ")),
+            }
+            docs.push(self.comment(comment));
+        }
+
+        // The statements following a case label are indented one level deeper than the label,
+        // even though only the first of them is part of the labeled statement.
+        let mut in_case = false;
+        let edges = !self.config.strip_blank_lines_at_block_edges;
+
+        for (i, statement) in block.statements.iter().enumerate() {
+            let label = matches!(statement.node, Statement::Case(..) | Statement::Default(_));
+            let blank_lines = if i > 0 || edges {
+                self.blank_lines(blank_lines_before(statement))
+            } else {
+                Doc::text("")
+            };
+            let doc = concat!(
+                Doc::HardLine,
+                blank_lines,
+                self.annotated(statement, self.statement(&statement.node), Doc::text("")),
+            );
+
+            docs.push(if in_case && !label && self.config.indent_case_bodies {
+                Doc::indent(doc)
+            } else {
+                doc
+            });
+            in_case |= label;
+        }
+
+        for run in self.runs(&block.comments) {
+            let blank_lines = if !block.statements.is_empty() || edges {
+                self.blank_lines(run[0].blank_lines)
+            } else {
+                Doc::text("")
+            };
+            let doc = concat!(Doc::HardLine, blank_lines, self.comments(run));
+            docs.push(if in_case && self.config.indent_case_bodies {
+                Doc::indent(doc)
+            } else {
+                doc
+            });
+        }
+
+        if edges {
+            docs.push(self.blank_lines(block.closing_blank_lines));
+        }
+
+        Some(Doc::Concat(docs))
+    }
+
+    /// Write the body of a control statement. Blocks are placed according to the brace style,
+    /// while other statements are indented on the next line.
+    fn body_of(&self, id: StatementId) -> Doc {
+        match &self.nodes[id] {
+            Statement::Compound(block) => self.braces(Braces::Control, self.block_contents(block)),
+            statement => Doc::indent(concat!(Doc::HardLine, self.statement(statement))),
+        }
+    }
+
+    /// Write the body of a control statement, keeping a simple statement on the same line if
+    /// `allowed` and it fits.
+    fn short_body_of(&self, id: StatementId, allowed: bool) -> Doc {
+        match &self.nodes[id] {
+            statement if allowed && is_simple(statement) => {
+                Doc::group(Doc::indent(concat!(Doc::Line, self.statement(statement))))
+            }
+            _ => self.body_of(id),
+        }
+    }
+
+    /// Write the statement following a case label. Further labels stay on the same indentation
+    /// level, blocks are placed according to the case braces, and other statements are indented
+    /// if case bodies are.
+    fn labeled(&self, label: Doc, id: StatementId) -> Doc {
+        let (markers, id) = self.markers(id);
+        let label = concat!(label, markers);
+        match &self.nodes[id] {
+            Statement::Case(..) | Statement::Default(_) => {
+                concat!(label, Doc::HardLine, self.statement(&self.nodes[id]))
+            }
+            Statement::Compound(block) => {
+                let placement = match self.config.case_braces {
+                    CaseBraces::Control => placement(self.config.brace_style, Braces::Control),
+                    CaseBraces::Attach => Placement::Attach,
+                    CaseBraces::NextLine => Placement::NextLine,
+                    CaseBraces::Indented if self.config.indent_case_bodies => {
+                        Placement::HalfIndented
+                    }
+                    CaseBraces::Indented => Placement::NextLine,
+                };
+                concat!(
+                    label,
+                    self.placed(placement, self.block_contents(block), true)
+                )
+            }
+            statement if self.config.indent_case_bodies => concat!(
+                label,
+                Doc::indent(concat!(Doc::HardLine, self.statement(statement)))
+            ),
+            statement => concat!(label, Doc::HardLine, self.statement(statement)),
+        }
+    }
+
+    /// Write the markers which follow a label on its line, each after a space, returning the
+    /// statement after them.
+    fn markers(&self, id: StatementId) -> (Doc, StatementId) {
+        match &self.nodes[id] {
+            Statement::Marked(comments, statement) => {
+                let comments = comments
+                    .iter()
+                    .map(|comment| concat!(" ", self.comment(comment)));
+                (Doc::concat(comments), *statement)
+            }
+            _ => (Doc::Concat(Vec::new()), id),
+        }
+    }
+
+    fn statement(&self, statement: &Statement) -> Doc {
+        match statement {
+            Statement::Compound(block) => self.enclosed(self.block_contents(block)),
+            Statement::Declaration(declaration) => self.declaration(declaration, true),
+            Statement::StaticAssert(assertion) => self.static_assert(assertion),
+            Statement::Conditional(group) => {
+                self.conditional(group, 1, |item| self.statement(item), |_, _| 0)
+            }
+            Statement::Macro { call, semicolon } => self.macro_call(call, *semicolon),
+            Statement::Asm(asm) => self.asm(asm),
+            Statement::MacroLoop(call, body) => match &self.nodes[*body] {
+                Statement::Compound(_) => {
+                    concat!(self.macro_call(call, false), self.body_of(*body))
+                }
+                // Other statements stay on the line of the call, which makes them its body.
+                statement => concat!(self.macro_call(call, false), " ", self.statement(statement)),
+            },
+            Statement::Attributed(attributes, statement) => {
+                let attributes = attributes.iter().map(|attribute| self.attribute(attribute));
+                let attributes = Doc::join(attributes, Doc::text(" "));
+                match &self.nodes[*statement] {
+                    Statement::Expression(None) => concat!(attributes, ";"),
+                    statement => concat!(attributes, " ", self.statement(statement)),
+                }
+            }
+            Statement::Expression(Some(expression)) => match &self.nodes[*expression] {
+                Expression::Assignment(operator, target, value)
+                    if self.config.align_consecutive_assignments =>
+                {
+                    concat!(
+                        self.expression(&self.nodes[*target]),
+                        Doc::Anchor(Anchor::Assignment),
+                        self.assign(operator.as_str()),
+                        self.expression(&self.nodes[*value]),
+                        ";"
+                    )
+                }
+                expression => concat!(self.expression(expression), ";"),
+            },
+            Statement::Expression(None) => Doc::text(";"),
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let short = self.config.allow_short_if_on_single_line && otherwise.is_none();
+                let mut docs = vec![
+                    self.keyword("if", self.expression(&self.nodes[*condition])),
+                    self.short_body_of(*then, short),
+                ];
+
+                if let Some(otherwise) = otherwise {
+                    if self.attached(*then, self.config.else_placement) {
+                        docs.push(Doc::text(" else"));
+                    } else {
+                        docs.push(concat!(Doc::HardLine, "else"));
+                    }
+
+                    match &self.nodes[*otherwise] {
+                        Statement::If { .. } => {
+                            docs.push(concat!(" ", self.statement(&self.nodes[*otherwise])))
+                        }
+                        _ => docs.push(self.body_of(*otherwise)),
+                    }
+                }
+
+                Doc::Concat(docs)
+            }
+            Statement::Switch { condition, body } => {
+                let body = match &self.nodes[*body] {
+                    Statement::Compound(block) => {
+                        self.braces(Braces::Switch, self.block_contents(block))
+                    }
+                    _ => self.body_of(*body),
+                };
+                concat!(
+                    self.keyword("switch", self.expression(&self.nodes[*condition])),
+                    body
+                )
+            }
+            Statement::While { condition, body } => concat!(
+                self.keyword("while", self.expression(&self.nodes[*condition])),
+                self.short_body_of(*body, self.config.allow_short_loops_on_single_line)
+            ),
+            Statement::DoWhile { body, condition } => {
+                let separator = if self.attached(*body, self.config.do_while_placement) {
+                    Doc::text(" ")
+                } else {
+                    Doc::HardLine
+                };
+                concat!(
+                    "do",
+                    self.body_of(*body),
+                    separator,
+                    self.keyword("while", self.expression(&self.nodes[*condition])),
+                    ";",
+                )
+            }
+            Statement::For {
+                initializer,
+                condition,
+                step,
+                body,
+            } => {
+                let mut docs = Vec::new();
+
+                match initializer {
+                    Some(initializer) => docs.push(match &self.nodes[*initializer] {
+                        Statement::Declaration(declaration) => self.declaration(declaration, false),
+                        Statement::Expression(Some(expression)) => {
+                            concat!(self.expression(&self.nodes[*expression]), ";")
+                        }
+                        statement => self.statement(statement),
+                    }),
+                    None => docs.push(Doc::text(";")),
+                }
+                let separator = if self.config.space_after_for_semicolon {
+                    " "
+                } else {
+                    ""
+                };
+                if let Some(condition) = condition {
+                    docs.push(concat!(separator, self.expression(&self.nodes[*condition])));
+                }
+                docs.push(Doc::text(";"));
+                if let Some(step) = step {
+                    docs.push(concat!(separator, self.expression(&self.nodes[*step])));
+                }
+
+                concat!(
+                    self.keyword("for", Doc::Concat(docs)),
+                    self.short_body_of(*body, self.config.allow_short_loops_on_single_line)
+                )
+            }
+            Statement::Goto(label) => Doc::text(format!("goto {};", label)),
+            Statement::Continue => Doc::text("continue;"),
+            Statement::Break => Doc::text("break;"),
+            Statement::Return(Some(value)) => match &self.nodes[*value] {
+                value @ Expression::Parenthesized(_)
+                    if !self.config.space_after_control_keywords =>
+                {
+                    concat!("return", self.expression(value), ";")
+                }
+                value => concat!("return ", self.expression(value), ";"),
+            },
+            Statement::Return(None) => Doc::text("return;"),
+            Statement::Labeled(label, statement) => {
+                let (markers, statement) = self.markers(*statement);
+                // An empty statement is kept on the line of its label, so that a label at the end
+                // of a block does not look like it is missing one.
+                let (label, statement) = match &self.nodes[statement] {
+                    Statement::Expression(None) => {
+                        (format!("{}:;", label), Doc::Concat(Vec::new()))
+                    }
+                    statement => (
+                        format!("{}:", label),
+                        concat!(Doc::HardLine, self.statement(statement)),
+                    ),
+                };
+                let label = match self.config.label_placement {
+                    LabelPlacement::Indented => Doc::Text(label),
+                    LabelPlacement::Outdented => Doc::Outdent(label),
+                    LabelPlacement::FirstColumn => Doc::Flush(label),
+                };
+                concat!(label, markers, statement)
+            }
+            Statement::Case(value, statement) => self.labeled(
+                concat!("case ", self.expression(&self.nodes[*value]), ":"),
+                *statement,
+            ),
+            Statement::Default(statement) => self.labeled(Doc::text("default:"), *statement),
+            Statement::Marked(comments, statement) => concat!(
+                Doc::concat(comments.iter().map(|comment| self.comment(comment))),
+                Doc::HardLine,
+                self.statement(&self.nodes[*statement])
+            ),
+            Statement::Directive(line) => self.directive(line),
+            Statement::Invalid(_) => unreachable!("invalid regions are salvaged first"),
+            Statement::Verbatim(text) => verbatim(text),
+        }
+    }
+
+    /// The prefix operator at the very start of an expression, if any.
+    fn leftmost_operator(&self, expression: &Expression) -> Option<&'static str> {
+        match expression {
+            Expression::Unary(operator, _) if !operator.is_postfix() => Some(operator.as_str()),
+            Expression::Unary(_, operand)
+            | Expression::Binary(_, operand, _)
+            | Expression::Assignment(_, operand, _)
+            | Expression::Conditional(operand, _, _)
+            | Expression::Call(operand, _)
+            | Expression::Index(operand, _)
+            | Expression::Member(operand, _, _) => self.leftmost_operator(&self.nodes[*operand]),
+            Expression::Comma(expressions) => {
+                self.leftmost_operator(&self.nodes[*expressions.first()?])
+            }
+            _ => None,
+        }
+    }
+
+    /// Write the operand of a prefix operator, separated by a space if the two would otherwise
+    /// merge into a different token, as in `- -x`.
+    fn prefix(&self, operator: UnaryOperator, operand: &Expression) -> Doc {
+        let merged = merges(operator.as_str(), self.leftmost_operator(operand));
+        let separator = if merged { " " } else { "" };
+        concat!(operator.as_str(), separator, self.expression(operand))
+    }
+
+    /// The separator in between the items of a list, as in `f(a, b)`.
+    fn comma(&self) -> &'static str {
+        if self.config.space_after_comma {
+            ", "
+        } else {
+            ","
+        }
+    }
+
+    /// A control flow keyword followed by its parenthesized condition.
+    fn keyword(&self, keyword: &str, condition: Doc) -> Doc {
+        let separator = if self.config.space_after_control_keywords {
+            " "
+        } else {
+            ""
+        };
+        concat!(keyword, separator, self.parenthesized(condition))
+    }
+
+    /// Enclose a document in parentheses, padded with spaces if configured.
+    fn parenthesized(&self, doc: Doc) -> Doc {
+        let padding = if self.config.spaces_in_parentheses {
+            " "
+        } else {
+            ""
+        };
+        concat!("(", padding, doc, padding, ")")
+    }
+
+    /// The `sizeof` in front of parentheses, followed by a space if configured.
+    fn sizeof(&self) -> &'static str {
+        if self.config.space_after_sizeof {
+            "sizeof "
+        } else {
+            "sizeof"
+        }
+    }
+
+    /// Enclose a document in brackets, padded with spaces if configured.
+    fn bracketed(&self, doc: Doc) -> Doc {
+        let padding = if self.config.spaces_in_brackets {
+            " "
+        } else {
+            ""
+        };
+        concat!("[", padding, doc, padding, "]")
+    }
+
+    /// The space in between a function and the parenthesis of the arguments in a call, if any.
+    fn call_separator(&self) -> &'static str {
+        if self.config.space_before_call_parentheses {
+            " "
+        } else {
+            ""
+        }
+    }
+
+    /// The line break after a comma, which is a space when not taken unless configured otherwise.
+    fn line(&self) -> Doc {
+        if self.config.space_after_comma {
+            Doc::Line
+        } else {
+            Doc::SoftLine
+        }
+    }
+
+    /// The separator in between the items of a list which may be broken over several lines.
+    fn comma_break(&self) -> Doc {
+        concat!(",", self.line())
+    }
+
+    /// An assignment operator, including the spaces around it.
+    fn assign(&self, operator: &str) -> String {
+        if self.config.space_around_assignment_operators {
+            format!(" {} ", operator)
+        } else {
+            operator.to_string()
+        }
+    }
+
+    /// Write items separated by commas, breaking after every comma if they do not fit. The
+    /// items after the first are indented.
+    fn list(&self, items: impl IntoIterator<Item = Doc>) -> Doc {
+        let items = items.into_iter().enumerate().map(|(i, item)| match i {
+            0 => item,
+            _ => Doc::continuation(concat!(self.comma_break(), item)),
+        });
+        Doc::group(Doc::concat(items))
+    }
+
+    /// Write the arguments of a call or the parameters of a function, which are broken over
+    /// several lines according to the argument wrapping if they do not fit.
+    fn arguments(&self, arguments: impl IntoIterator<Item = Doc>) -> Doc {
+        let arguments: Vec<Doc> = arguments.into_iter().collect();
+        if arguments.is_empty() {
+            return Doc::text("()");
+        }
+
+        let (line, padding) = if self.config.spaces_in_parentheses {
+            (Doc::Line, " ")
+        } else {
+            (Doc::SoftLine, "")
+        };
+
+        match self.config.argument_wrapping {
+            ArgumentWrapping::OnePerLine => Doc::group(concat!(
+                "(",
+                Doc::continuation(concat!(line, Doc::join(arguments, self.comma_break()))),
+                padding,
+                ")"
+            )),
+            ArgumentWrapping::BinPack => Doc::continuation(concat!(
+                "(",
+                padding,
+                Doc::fill(arguments, self.comma_break()),
+                padding,
+                ")"
+            )),
+            ArgumentWrapping::Align => concat!(
+                "(",
+                padding,
+                Doc::align(Doc::fill(arguments, self.comma_break())),
+                padding,
+                ")"
+            ),
+        }
+    }
+
+    /// Collect the operands of a chain of binary operators with the same precedence, as in
+    /// `a + b - c`, so that it is broken before every operand rather than only the last one.
+    fn operands(&self, precedence: u8, expression: &Expression, docs: &mut Vec<Doc>) {
+        match expression {
+            Expression::Binary(operator, left, right) if operator.precedence() == precedence => {
+                self.operands(precedence, &self.nodes[*left], docs);
+
+                // Without spaces, a space is still needed after an operator which would otherwise
+                // merge with the operand after it.
+                let right = &self.nodes[*right];
+                let spaced = self.config.space_around_binary_operators;
+                let separated = spaced || merges(operator.as_str(), self.leftmost_operator(right));
+                let line = |space| if space { Doc::Line } else { Doc::SoftLine };
+                let operator = if self.config.break_before_binary_operators {
+                    let after = if separated { " " } else { "" };
+                    concat!(line(spaced), format!("{}{}", operator.as_str(), after))
+                } else {
+                    let before = if spaced { " " } else { "" };
+                    concat!(format!("{}{}", before, operator.as_str()), line(separated))
+                };
+
+                docs.push(Doc::continuation(concat!(operator, self.expression(right))));
+            }
+            _ => docs.push(self.expression(expression)),
+        }
+    }
+
+    fn expression(&self, expression: &Expression) -> Doc {
+        let nodes = self.nodes;
+
+        match expression {
+            Expression::Number(number) if self.config.normalize_number_literals => {
+                Doc::text(number_literal(number))
+            }
+            Expression::Identifier(name) => Doc::text(name.as_str()),
+            Expression::Number(text) => Doc::text(text.as_str()),
+            Expression::Str(parts) if self.config.split_string_literals => string(parts),
+            Expression::Str(parts) => {
+                let parts: Vec<String> = parts.iter().map(|part| format!("\"{}\"", part)).collect();
+                Doc::Text(parts.join(" "))
+            }
+            Expression::Character(character) => Doc::text(format!("'{}'", character)),
+            Expression::Unary(operator, operand) if operator.is_postfix() => {
+                concat!(self.expression(&nodes[*operand]), operator.as_str())
+            }
+            Expression::Unary(operator, operand) => self.prefix(*operator, &nodes[*operand]),
+            Expression::Binary(operator, _, _) => {
+                let mut operands = Vec::new();
+                self.operands(operator.precedence(), expression, &mut operands);
+                Doc::group(Doc::Concat(operands))
+            }
+            Expression::Assignment(operator, target, value) => concat!(
+                self.expression(&nodes[*target]),
+                self.assign(operator.as_str()),
+                self.expression(&nodes[*value]),
+            ),
+            Expression::Conditional(condition, then, otherwise)
+                if self.config.break_before_ternary_operators =>
+            {
+                Doc::group(concat!(
+                    self.expression(&nodes[*condition]),
+                    Doc::continuation(concat!(
+                        Doc::Line,
+                        "? ",
+                        self.expression(&nodes[*then]),
+                        Doc::Line,
+                        ": ",
+                        self.expression(&nodes[*otherwise]),
+                    )),
+                ))
+            }
+            Expression::Conditional(condition, then, otherwise) => Doc::group(concat!(
+                self.expression(&nodes[*condition]),
+                " ?",
+                Doc::continuation(concat!(
+                    Doc::Line,
+                    self.expression(&nodes[*then]),
+                    " :",
+                    Doc::Line,
+                    self.expression(&nodes[*otherwise]),
+                )),
+            )),
+            Expression::Comma(expressions) => Doc::join(
+                expressions
+                    .iter()
+                    .map(|expression| self.expression(&self.nodes[*expression])),
+                Doc::text(self.comma()),
+            ),
+            Expression::Call(function, arguments) => concat!(
+                self.expression(&nodes[*function]),
+                self.call_separator(),
+                self.arguments(
+                    arguments
+                        .iter()
+                        .map(|argument| self.expression(&self.nodes[*argument]))
+                ),
+            ),
+            Expression::Index(array, index) => concat!(
+                self.expression(&nodes[*array]),
+                self.bracketed(self.expression(&nodes[*index])),
+            ),
+            Expression::Member(object, member, arrow) => concat!(
+                self.expression(&nodes[*object]),
+                if *arrow { "->" } else { "." },
+                member.as_str(),
+            ),
+            Expression::Parenthesized(inner) => self.parenthesized(self.expression(&nodes[*inner])),
+            Expression::Cast(type_name, operand) => concat!(
+                "(",
+                self.type_name(type_name),
+                ")",
+                if self.config.space_after_cast {
+                    " "
+                } else {
+                    ""
+                },
+                self.expression(&nodes[*operand]),
+            ),
+            Expression::CompoundLiteral(type_name, list) => concat!(
+                "(",
+                self.type_name(type_name),
+                ")",
+                self.initializer_list(list),
+            ),
+            Expression::Sizeof(operand) => match &nodes[*operand] {
+                operand @ Expression::Parenthesized(_) => {
+                    concat!(self.sizeof(), self.expression(operand))
+                }
+                operand if self.config.sizeof_parentheses => {
+                    concat!(self.sizeof(), self.parenthesized(self.expression(operand)))
+                }
+                operand => concat!("sizeof ", self.expression(operand)),
+            },
+            Expression::SizeofType(type_name) => {
+                concat!(self.sizeof(), "(", self.type_name(type_name), ")")
+            }
+            Expression::Alignof(type_name) => {
+                concat!("_Alignof(", self.type_name(type_name), ")")
+            }
+            Expression::Type(type_name) => self.type_name(type_name),
+            Expression::Generic(controlling, associations) => concat!(
+                "_Generic(",
+                self.expression(&nodes[*controlling]),
+                self.comma(),
+                Doc::join(
+                    associations
+                        .iter()
+                        .map(|association| self.generic_association(association)),
+                    Doc::text(self.comma()),
+                ),
+                ")",
+            ),
+        }
+    }
+
+    fn generic_association(&self, association: &GenericAssociation) -> Doc {
+        match &association.type_name {
+            Some(type_name) => concat!(
+                self.type_name(type_name),
+                ": ",
+                self.expression(&self.nodes[association.value])
+            ),
+            None => concat!("default: ", self.expression(&self.nodes[association.value])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{
+        ArgumentWrapping, BackslashAlignment, BraceStyle, CaseBraces, DirectiveIndentation,
+        FormatConfig, InitializerLayout, KeywordPlacement, LabelPlacement, LineEnding,
+        PointerAlignment, Style,
+    };
+    use crate::formatter::formatter::{finish, format, format_to, Finisher};
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+    use crate::parser::salvage::salvage;
+    use std::io::Write;
+
+    fn format_str(input: &str, config: &FormatConfig) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+        salvage(&mut unit, input);
+        format(&unit, config)
+    }
+
+    fn test_with(config: &FormatConfig, input: &str, expected: &str) {
+        let result = format_str(input, config);
+        assert_eq!(result, expected);
+
+        // Formatting must be stable, so the output is left unchanged by a second pass.
+        assert_eq!(format_str(&result, config), expected);
+    }
+
+    fn test(input: &str, expected: &str) {
+        test_with(&FormatConfig::default(), input, expected);
+    }
+
+    #[test]
+    fn declarations() {
+        test(
+            "static const char*names[ 3 ],**p=NULL ;int(*f)(int,char*,...);\n\
+             unsigned long x=sizeof(int)+sizeof x;",
+            "static const char *names[3], **p = NULL;\n\
+             int (*f)(int, char *, ...);\n\
+             unsigned long x = sizeof(int) + sizeof x;\n",
+        );
+    }
+
+    #[test]
+    fn functions_and_control_flow() {
+        test(
+            "int main(void){int i;for(i=0;i<10;i++)if(i%2)continue;else{x=-i?*p:- -i;}\n\
+             while(1);do{i--;}while(i>0);for(;;)break;return(int)i;}",
+            "int main(void) {\n\
+             \x20   int i;\n\
+             \x20   for (i = 0; i < 10; i++)\n\
+             \x20       if (i % 2)\n\
+             \x20           continue;\n\
+             \x20       else {\n\
+             \x20           x = -i ? *p : - -i;\n\
+             \x20       }\n\
+             \x20   while (1)\n\
+             \x20       ;\n\
+             \x20   do {\n\
+             \x20       i--;\n\
+             \x20   } while (i > 0);\n\
+             \x20   for (;;)\n\
+             \x20       break;\n\
+             \x20   return (int)i;\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn switch_and_labels() {
+        test(
+            "void f(){switch(x){case 1:case 2:a();break;default:{b();}}out:return;}",
+            "void f() {\n\
+             \x20   switch (x) {\n\
+             \x20       case 1:\n\
+             \x20       case 2:\n\
+             \x20           a();\n\
+             \x20           break;\n\
+             \x20       default: {\n\
+             \x20           b();\n\
+             \x20       }\n\
+             \x20   }\n\
+             \x20   out:\n\
+             \x20   return;\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn label_placement() {
+        let input = "void f(){if(x){goto out;}a();out:b();if(y){goto end;end:}}";
+
+        test(
+            input,
+            "void f() {\n\
+             \x20   if (x) {\n\
+             \x20       goto out;\n\
+             \x20   }\n\
+             \x20   a();\n\
+             \x20   out:\n\
+             \x20   b();\n\
+             \x20   if (y) {\n\
+             \x20       goto end;\n\
+             \x20       end:;\n\
+             \x20   }\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            label_placement: LabelPlacement::Outdented,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "void f() {\n\
+             \x20   if (x) {\n\
+             \x20       goto out;\n\
+             \x20   }\n\
+             \x20   a();\n\
+             out:\n\
+             \x20   b();\n\
+             \x20   if (y) {\n\
+             \x20       goto end;\n\
+             \x20   end:;\n\
+             \x20   }\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            label_placement: LabelPlacement::FirstColumn,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "void f() {\n\
+             \x20   if (x) {\n\
+             \x20       goto out;\n\
+             \x20   }\n\
+             \x20   a();\n\
+             out:\n\
+             \x20   b();\n\
+             \x20   if (y) {\n\
+             \x20       goto end;\n\
+             end:;\n\
+             \x20   }\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn case_indentation() {
+        let input = "void f(){switch(x){case 1:a();break;case 2:{b();}default:c();}}";
+
+        let config = FormatConfig {
+            indent_case_labels: false,
+            case_braces: CaseBraces::NextLine,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "void f() {\n\
+             \x20   switch (x) {\n\
+             \x20   case 1:\n\
+             \x20       a();\n\
+             \x20       break;\n\
+             \x20   case 2:\n\
+             \x20   {\n\
+             \x20       b();\n\
+             \x20   }\n\
+             \x20   default:\n\
+             \x20       c();\n\
+             \x20   }\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            indent_case_bodies: false,
+            case_braces: CaseBraces::Attach,
+            brace_style: BraceStyle::Allman,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "void f()\n\
+             {\n\
+             \x20   switch (x)\n\
+             \x20   {\n\
+             \x20       case 1:\n\
+             \x20       a();\n\
+             \x20       break;\n\
+             \x20       case 2: {\n\
+             \x20           b();\n\
+             \x20       }\n\
+             \x20       default:\n\
+             \x20       c();\n\
+             \x20   }\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            case_braces: CaseBraces::Indented,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(){switch(x){case 2:{b();}}}",
+            "void f() {\n\
+             \x20   switch (x) {\n\
+             \x20       case 2:\n\
+             \x20           {\n\
+             \x20               b();\n\
+             \x20           }\n\
+             \x20   }\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn short_statements_on_a_single_line() {
+        let input = "int zero(void){return 0;}void stub(){}\n\
+                     void f(){if(!p)return;if(x)a();else b();while(n)n--;\
+                     for(;;)if(long_condition_name)long_function_name(first_argument);}";
+
+        let config = FormatConfig {
+            brace_style: BraceStyle::Allman,
+            allow_short_if_on_single_line: true,
+            allow_short_loops_on_single_line: true,
+            allow_short_functions_on_single_line: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "int zero(void) { return 0; }\n\
+             \n\
+             void stub() {}\n\
+             \n\
+             void f()\n\
+             {\n\
+             \x20   if (!p) return;\n\
+             \x20   if (x)\n\
+             \x20       a();\n\
+             \x20   else\n\
+             \x20       b();\n\
+             \x20   while (n) n--;\n\
+             \x20   for (;;)\n\
+             \x20       if (long_condition_name) long_function_name(first_argument);\n\
+             }\n",
+        );
+
+        test(
+            "int zero(void){return 0;}void f(){if(!p)return;}",
+            "int zero(void) {\n\
+             \x20   return 0;\n\
+             }\n\
+             \n\
+             void f() {\n\
+             \x20   if (!p)\n\
+             \x20       return;\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn records_and_enums() {
+        test(
+            "typedef struct point{int x,y;unsigned flags:4;}point_t;enum color{RED,GREEN=2};\n\
+             struct empty{};int last;",
+            "typedef struct point {\n\
+             \x20   int x, y;\n\
+             \x20   unsigned flags : 4;\n\
+             } point_t;\n\
+             \n\
+             enum color {\n\
+             \x20   RED,\n\
+             \x20   GREEN = 2\n\
+             };\n\
+             \n\
+             struct empty {};\n\
+             \n\
+             int last;\n",
+        );
+    }
+
+    #[test]
+    fn initializer_lists_break_when_too_long() {
+        test(
+            "int a[]={1,2,3,};struct s v={.name=\"a rather long name\",.value=12345678,\
+             .next=&other_value};",
+            "int a[] = { 1, 2, 3 };\n\
+             struct s v = {\n\
+             \x20   .name = \"a rather long name\",\n\
+             \x20   .value = 12345678,\n\
+             \x20   .next = &other_value\n\
+             };\n",
+        );
+    }
+
+    #[test]
+    fn initializer_layouts() {
+        let input = "int a[]={1,22,333,4,55,666,7};int b[]={1,2,};";
+        let config = |initializer_layout| FormatConfig {
+            max_line_length: 24,
+            initializer_layout,
+            break_initializers_with_trailing_comma: true,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config(InitializerLayout::Compact),
+            input,
+            "int a[] = {\n\
+             \x20   1, 22, 333, 4, 55,\n\
+             \x20   666, 7\n\
+             };\n\
+             int b[] = {\n\
+             \x20   1, 2,\n\
+             };\n",
+        );
+        test_with(
+            &config(InitializerLayout::Columns),
+            input,
+            "int a[] = {\n\
+             \x20   1,   22,  333, 4,\n\
+             \x20   55,  666, 7\n\
+             };\n\
+             int b[] = {\n\
+             \x20   1, 2,\n\
+             };\n",
+        );
+    }
+
+    #[test]
+    fn enum_layouts() {
+        let config = FormatConfig {
+            max_enumerators_on_single_line: 3,
+            align_enum_values: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "enum side{LEFT,RIGHT};enum mode{READ=1,WRITE=2,READ_WRITE=3,NONE=0};\n\
+             enum note{A, // first\nB};",
+            "enum side { LEFT, RIGHT };\n\
+             \n\
+             enum mode {\n\
+             \x20   READ       = 1,\n\
+             \x20   WRITE      = 2,\n\
+             \x20   READ_WRITE = 3,\n\
+             \x20   NONE       = 0\n\
+             };\n\
+             \n\
+             enum note {\n\
+             \x20   A, // first\n\
+             \x20   B\n\
+             };\n",
+        );
+    }
+
+    #[test]
+    fn comments_and_directives() {
+        test(
+            "#include <stdio.h>\n// leading\nint x; // trailing\n\
+             void f() {\n#ifdef DEBUG\n  log(x); /* note */\n#endif\n  // last\n}\n",
+            "#include <stdio.h>\n\
+             // leading\n\
+             int x; // trailing\n\
+             \n\
+             void f() {\n\
+             #ifdef DEBUG\n\
+             \x20   log(x); /* note */\n\
+             #endif\n\
+             \x20   // last\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn prefix_operators_in_macro_arguments() {
+        test(
+            "DEFINE_MUTEX(& lock, - 1);\nvoid f() {\nLIST_FOREACH(item, & list, entries) { g(); }\nCHECK(err, return - 1);\nCHECK(a - b, * p * 2);\n}\n",
+            "DEFINE_MUTEX(&lock, -1);\n\n\
+             void f() {\n\
+             \x20   LIST_FOREACH(item, &list, entries) {\n\
+             \x20       g();\n\
+             \x20   }\n\
+             \x20   CHECK(err, return -1);\n\
+             \x20   CHECK(a - b, *p * 2);\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn macros_without_semicolons() {
+        test(
+            "void f() {\nTRACE(x)\nreturn 0;\nFOREACH(x) if (x) g();\nFOREACH(y)\n{ h(); }\n}\n",
+            "void f() {\n\
+             \x20   TRACE(x)\n\
+             \x20   return 0;\n\
+             \x20   FOREACH(x) if (x)\n\
+             \x20       g();\n\
+             \x20   FOREACH(y) {\n\
+             \x20       h();\n\
+             \x20   }\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn comments_before_closing_braces() {
+        test(
+            "struct s {int a; // a\n// last\n};\n",
+            "struct s {\n\
+             \x20   int a; // a\n\
+             \x20   // last\n\
+             };\n",
+        );
+        test(
+            "enum e { A,\n\n// end\n};\nenum f {\n// none yet\n};\n",
+            "enum e {\n\
+             \x20   A,\n\
+             \n\
+             \x20   // end\n\
+             };\n\
+             \n\
+             enum f {\n\
+             \x20   // none yet\n\
+             };\n",
+        );
+        test(
+            "int x[] = {1, 2,\n// end\n};\n",
+            "int x[] = {\n\
+             \x20   1,\n\
+             \x20   2,\n\
+             \x20   // end\n\
+             };\n",
+        );
+    }
+
+    #[test]
+    fn comments_around_opening_braces() {
+        test(
+            "int f(int x) /* c */\n{\nreturn x;\n}\nint g(void) // c\n{\n}\n",
+            "int f(int x) { /* c */\n\
+             \x20   return x;\n\
+             }\n\
+             \n\
+             int g(void) { // c\n\
+             }\n",
+        );
+        test(
+            "void f(int y) {\nif (y) /* c */\n{\ng();\n}\nwhile (y) { /* d */\ny--;\n}\n}\n",
+            "void f(int y) {\n\
+             \x20   if (y) { /* c */\n\
+             \x20       g();\n\
+             \x20   }\n\
+             \x20   while (y) { /* d */\n\
+             \x20       y--;\n\
+             \x20   }\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn comments_in_parameter_lists() {
+        test(
+            "int f(int a, /* first */ int b /* second */);\n",
+            "int f(int a, /* first */ int b /* second */);\n",
+        );
+        test(
+            "int f(int a, // first\nint b // second\n);\nint g(int a /* x */, ...);\n",
+            "int f(\n\
+             \x20   int a, // first\n\
+             \x20   int b  // second\n\
+             );\n\
+             int g(int a, /* x */ ...);\n",
+        );
+    }
+
+    #[test]
+    fn markers_stay_in_place() {
+        let config = FormatConfig {
+            max_line_length: 40,
+            reflow_comments: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(int x) {\n\
+             switch (x) {\n\
+             case 1: /* FALLTHRU */\n\
+             case 2: // three\n\
+             g(x);\n\
+             }\n\
+             // Check that the first argument is within its range.\n\
+             // NOLINTNEXTLINE(readability-magic-numbers)\n\
+             check(first_argument, 42); // NOLINT\n\
+             check(first_argument, second_argument); // cppcheck-suppress knownArgument\n\
+             check(first_argument, second_argument); // nothing\n\
+             }\n",
+            "void f(int x) {\n\
+             \x20   switch (x) {\n\
+             \x20       case 1: /* FALLTHRU */\n\
+             \x20       case 2:\n\
+             \x20           g(x); // three\n\
+             \x20   }\n\
+             \x20   // Check that the first argument is\n\
+             \x20   // within its range.\n\
+             \x20   // NOLINTNEXTLINE(readability-magic-numbers)\n\
+             \x20   check(first_argument, 42); // NOLINT\n\
+             \x20   check(first_argument, second_argument); // cppcheck-suppress knownArgument\n\
+             \x20   check(\n\
+             \x20       first_argument,\n\
+             \x20       second_argument); // nothing\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn directive_indentation() {
+        let input = "#ifndef A_H\n#define A_H\n#if X\n#  if Y\nint y;\n#endif\n#else\n\
+                     void f() {\n#ifdef Z\nz();\n#endif\n}\n#endif\n#endif\n";
+
+        let config = FormatConfig {
+            directive_indentation: DirectiveIndentation::AfterHash,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "#ifndef A_H\n\
+             #define A_H\n\
+             #if X\n\
+             #    if Y\n\
+             int y;\n\
+             #    endif\n\
+             #else\n\
+             void f() {\n\
+             #    ifdef Z\n\
+             \x20   z();\n\
+             #    endif\n\
+             }\n\
+             #endif\n\
+             #endif\n",
+        );
+
+        let config = FormatConfig {
+            directive_indentation: DirectiveIndentation::BeforeHash,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "#if X\n#define Y\n#endif\n",
+            "#if X\n\x20   #define Y\n#endif\n",
+        );
+        test("#if X\n#  define Y\n#endif\n", "#if X\n#define Y\n#endif\n");
+    }
+
+    #[test]
+    fn extern_c_wrappers() {
+        let config = FormatConfig {
+            directive_indentation: DirectiveIndentation::AfterHash,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "#ifndef A_H\n#define A_H\n#ifdef __cplusplus\nextern \"C\" {\n#endif\n\
+             int f(void);\n#ifdef __cplusplus\n}\n#endif\n#endif\n",
+            "#ifndef A_H\n\
+             #define A_H\n\
+             #ifdef __cplusplus\n\
+             extern \"C\" {\n\
+             #endif\n\
+             int f(void);\n\
+             #ifdef __cplusplus\n\
+             }\n\
+             #endif\n\
+             #endif\n",
+        );
+    }
+
+    #[test]
+    fn string_literals_are_split() {
+        let config = FormatConfig {
+            split_string_literals: true,
+            max_line_length: 40,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(){puts(\"The quick brown fox jumps over the lazy dog.\");\
+             puts(\"a\" \"b\\n\" \"\\x1\" \"2\");\
+             x = \"short\" \" one\";}",
+            "void f() {\n\
+             \x20   puts(\n\
+             \x20       \"The quick brown fox jumps \"\n\
+             \x20       \"over the lazy dog.\");\n\
+             \x20   puts(\"ab\\n\" \"\\x1\" \"2\");\n\
+             \x20   x = \"short one\";\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn number_literals_are_normalized() {
+        let config = FormatConfig {
+            normalize_number_literals: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "int a[] = {0XdeadBeef, 100ul, .5, 1.5E-3f, 0x1.8P+3f, 0B101u, 017, 2.};",
+            "int a[] = { 0xDEADBEEF, 100UL, 0.5, 1.5e-3F, 0x1.8p+3F, 0b101U, 017, 2. };\n",
+        );
+    }
+
+    #[test]
+    fn cast_and_sizeof_spacing() {
+        let input = "size_t n = (size_t)sizeof *p + sizeof(x) + sizeof(int);";
+        test(
+            input,
+            "size_t n = (size_t)sizeof *p + sizeof(x) + sizeof(int);\n",
+        );
+
+        let config = FormatConfig {
+            space_after_cast: true,
+            space_after_sizeof: true,
+            sizeof_parentheses: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "size_t n = (size_t) sizeof (*p) + sizeof (x) + sizeof (int);\n",
+        );
+    }
+
+    #[test]
+    fn backslash_alignment() {
+        let input = "#define F(x) \\\n  do { \\\n    g(x);   \\\n  } while (0)\n";
+        test(input, input);
+
+        let config = FormatConfig {
+            backslash_alignment: BackslashAlignment::Left,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "#define F(x) \\\n  do {       \\\n    g(x);    \\\n  } while (0)\n",
+        );
+
+        let config = FormatConfig {
+            backslash_alignment: BackslashAlignment::Right,
+            max_line_length: 20,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "#define F(x)       \\\n  do {             \\\n    g(x);          \\\n  } while (0)\n",
+        );
+
+        // Lines indented with tabs are measured with the width of a tab.
+        let input = "#define F(x) \\\n\tdo { \\\n\t\tg(x); \\\n\t} while (0)\n";
+        let config = FormatConfig {
+            backslash_alignment: BackslashAlignment::Left,
+            tab_width: 8,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "#define F(x)          \\\n\tdo {          \\\n\t\tg(x); \\\n\t} while (0)\n",
+        );
+    }
+
+    #[test]
+    fn disabled_code_is_kept() {
+        let input = "void f(){\n#if 0 // off\n    don't  {\n\n#else\nx  =  1;\n#endif\n}\n";
+        test(
+            input,
+            "void f() {\n\
+             #if 0 // off\n\
+             \x20   don't  {\n\
+             \n\
+             #else\n\
+             \x20   x = 1;\n\
+             #endif\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn unparseable_regions_are_kept() {
+        test("int   x = = 1;\nint y;", "int   x = = 1;\nint y;\n");
+    }
+
+    #[test]
+    fn indentation() {
+        let input = "void f(){if(x){y();}}";
+
+        let config = FormatConfig {
+            indent_width: 2,
+            ..FormatConfig::default()
+        };
+        test_with(&config, input, "void f() {\n  if (x) {\n    y();\n  }\n}\n");
+
+        let config = FormatConfig {
+            use_tabs: true,
+            ..FormatConfig::default()
+        };
+        test_with(&config, input, "void f() {\n\tif (x) {\n\t\ty();\n\t}\n}\n");
+    }
+
+    #[test]
+    fn brace_styles() {
+        let input = "struct s{int x;};void f(){if(x){y();}else{z();}do{}while(0);}";
+        let test_style = |brace_style, expected| {
+            let config = FormatConfig {
+                brace_style,
+                ..FormatConfig::default()
+            };
+            test_with(&config, input, expected);
+        };
+
+        test_style(
+            BraceStyle::Linux,
+            "struct s {\n    int x;\n};\n\n\
+             void f()\n{\n    if (x) {\n        y();\n    } else {\n        z();\n    }\n\
+             \x20   do {} while (0);\n}\n",
+        );
+        test_style(
+            BraceStyle::Allman,
+            "struct s\n{\n    int x;\n};\n\n\
+             void f()\n{\n    if (x)\n    {\n        y();\n    }\n    else\n    {\n\
+             \x20       z();\n    }\n    do\n    {\n    }\n    while (0);\n}\n",
+        );
+        test_style(
+            BraceStyle::Gnu,
+            "struct s\n{\n    int x;\n};\n\n\
+             void f()\n{\n    if (x)\n        {\n            y();\n        }\n    else\n\
+             \x20       {\n            z();\n        }\n    do\n        {\n        }\n\
+             \x20   while (0);\n}\n",
+        );
+        test_style(
+            BraceStyle::Whitesmiths,
+            "struct s\n    {\n    int x;\n    };\n\n\
+             void f()\n    {\n    if (x)\n        {\n        y();\n        }\n    else\n\
+             \x20       {\n        z();\n        }\n    do\n        {\n        }\n\
+             \x20   while (0);\n    }\n",
+        );
+    }
+
+    #[test]
+    fn styles() {
+        let input = "int f(char*s){switch(*s){case 'a':return g(s,1);}int a[]={1,2};}";
+        let test_style = |name, expected| {
+            test_with(&Style::from_name(name).unwrap().config(), input, expected);
+        };
+
+        test_style(
+            "linux",
+            "int f(char *s)\n{\n\tswitch (*s) {\n\tcase 'a':\n\t\treturn g(s, 1);\n\t}\n\
+             \tint a[] = { 1, 2 };\n}\n",
+        );
+        test_style(
+            "google",
+            "int f(char* s) {\n  switch (*s) {\n    case 'a':\n      return g(s, 1);\n  }\n\
+             \x20 int a[] = {1, 2};\n}\n",
+        );
+        assert_eq!(Style::from_name("kr"), Some(Style::KernighanRitchie));
+        assert_eq!(Style::from_name("bsd"), None);
+    }
+
+    #[test]
+    fn else_and_do_while_placement() {
+        let input = "void f(){if(x){a();}else{b();}do{c();}while(y);}";
+
+        let config = FormatConfig {
+            else_placement: KeywordPlacement::NextLine,
+            do_while_placement: KeywordPlacement::NextLine,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "void f() {\n\
+             \x20   if (x) {\n\
+             \x20       a();\n\
+             \x20   }\n\
+             \x20   else {\n\
+             \x20       b();\n\
+             \x20   }\n\
+             \x20   do {\n\
+             \x20       c();\n\
+             \x20   }\n\
+             \x20   while (y);\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            brace_style: BraceStyle::Allman,
+            else_placement: KeywordPlacement::SameLine,
+            do_while_placement: KeywordPlacement::SameLine,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "void f()\n\
+             {\n\
+             \x20   if (x)\n\
+             \x20   {\n\
+             \x20       a();\n\
+             \x20   } else\n\
+             \x20   {\n\
+             \x20       b();\n\
+             \x20   }\n\
+             \x20   do\n\
+             \x20   {\n\
+             \x20       c();\n\
+             \x20   } while (y);\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn long_lines_are_wrapped() {
+        let config = FormatConfig {
+            max_line_length: 40,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "int compute(const char *name, unsigned long size);\n\
+             void f(){if(first_value&&second_value||third_value){x=y?first_value:second_value;}\n\
+             total=first_value+second_value*third_value-fourth;}",
+            "int compute(\n\
+             \x20   const char *name,\n\
+             \x20   unsigned long size);\n\
+             \n\
+             void f() {\n\
+             \x20   if (first_value && second_value ||\n\
+             \x20       third_value) {\n\
+             \x20       x = y\n\
+             \x20           ? first_value\n\
+             \x20           : second_value;\n\
+             \x20   }\n\
+             \x20   total = first_value +\n\
+             \x20       second_value * third_value -\n\
+             \x20       fourth;\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn argument_wrapping() {
+        let input = "int compute(int first, int second, int third, int fourth);\n\
+                     void f(){result=compute(alpha,beta,gamma,delta);}";
+        let config = |argument_wrapping| FormatConfig {
+            max_line_length: 30,
+            argument_wrapping,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config(ArgumentWrapping::OnePerLine),
+            input,
+            "int compute(\n\
+             \x20   int first,\n\
+             \x20   int second,\n\
+             \x20   int third,\n\
+             \x20   int fourth);\n\
+             \n\
+             void f() {\n\
+             \x20   result = compute(\n\
+             \x20       alpha,\n\
+             \x20       beta,\n\
+             \x20       gamma,\n\
+             \x20       delta);\n\
+             }\n",
+        );
+        test_with(
+            &config(ArgumentWrapping::BinPack),
+            input,
+            "int compute(int first,\n\
+             \x20   int second, int third,\n\
+             \x20   int fourth);\n\
+             \n\
+             void f() {\n\
+             \x20   result = compute(alpha,\n\
+             \x20       beta, gamma, delta);\n\
+             }\n",
+        );
+        test_with(
+            &config(ArgumentWrapping::Align),
+            input,
+            "int compute(int first,\n\
+             \x20           int second,\n\
+             \x20           int third,\n\
+             \x20           int fourth);\n\
+             \n\
+             void f() {\n\
+             \x20   result = compute(alpha,\n\
+             \x20                    beta,\n\
+             \x20                    gamma,\n\
+             \x20                    delta);\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn operators_at_the_start_of_lines() {
+        let config = FormatConfig {
+            max_line_length: 30,
+            break_before_binary_operators: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(){if(first_value&&second_value||third)x=alpha+beta-gamma*delta-epsilon;}",
+            "void f() {\n\
+             \x20   if (first_value\n\
+             \x20       && second_value\n\
+             \x20       || third)\n\
+             \x20       x = alpha\n\
+             \x20           + beta\n\
+             \x20           - gamma * delta\n\
+             \x20           - epsilon;\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            max_line_length: 10,
+            space_around_binary_operators: false,
+            ..config
+        };
+        test_with(
+            &config,
+            "x=alpha- -beta+gamma;",
+            "x = alpha\n    - -beta\n    +gamma;\n",
+        );
+    }
+
+    #[test]
+    fn continuation_indent() {
+        let config = FormatConfig {
+            max_line_length: 24,
+            continuation_indent: 8,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(){if(x){total=first_value+second_value;g(alphabet,betamax,gamma);}}",
+            "void f() {\n\
+             \x20   if (x) {\n\
+             \x20       total = first_value +\n\
+             \x20               second_value;\n\
+             \x20       g(\n\
+             \x20               alphabet,\n\
+             \x20               betamax,\n\
+             \x20               gamma);\n\
+             \x20   }\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn conditional_expressions() {
+        let input = "int x=is_enabled(device)?read_register(device):default_value;";
+        let config = FormatConfig {
+            max_line_length: 40,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "int x = is_enabled(device)\n\
+             \x20   ? read_register(device)\n\
+             \x20   : default_value;\n",
+        );
+
+        let config = FormatConfig {
+            break_before_ternary_operators: false,
+            ..config
+        };
+        test_with(
+            &config,
+            input,
+            "int x = is_enabled(device) ?\n\
+             \x20   read_register(device) :\n\
+             \x20   default_value;\n",
+        );
+    }
+
+    #[test]
+    fn pointer_alignment() {
+        let input = "char*const*p,*q;struct s{int*x;};char*f(void*data,int(*g)(char**));\n\
+                     void h(){x=(char*)y;}";
+        let test_alignment = |pointer_alignment, expected| {
+            let config = FormatConfig {
+                pointer_alignment,
+                ..FormatConfig::default()
+            };
+            test_with(&config, input, expected);
+        };
+
+        test_alignment(
+            PointerAlignment::Right,
+            "char *const *p, *q;\n\nstruct s {\n    int *x;\n};\n\n\
+             char *f(void *data, int (*g)(char **));\n\nvoid h() {\n    x = (char *)y;\n}\n",
+        );
+        test_alignment(
+            PointerAlignment::Left,
+            "char* const* p, *q;\n\nstruct s {\n    int* x;\n};\n\n\
+             char* f(void* data, int (*g)(char**));\n\nvoid h() {\n    x = (char*)y;\n}\n",
+        );
+        test_alignment(
+            PointerAlignment::Middle,
+            "char * const * p, *q;\n\nstruct s {\n    int * x;\n};\n\n\
+             char * f(void * data, int (*g)(char **));\n\nvoid h() {\n    x = (char *)y;\n}\n",
+        );
+    }
+
+    #[test]
+    fn operator_spacing() {
+        let config = FormatConfig {
+            space_around_binary_operators: false,
+            space_around_assignment_operators: false,
+            space_after_comma: false,
+            space_after_for_semicolon: false,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "int a = 1, b[] = {1, 2};\n\
+             void f(int x, int y){for(i = 0; i < n; i++)a = b + c*d - -e / *p, f(x, y);}",
+            "int a=1,b[]={ 1,2 };\n\
+             \n\
+             void f(int x,int y) {\n\
+             \x20   for (i=0;i<n;i++)\n\
+             \x20       a=b+c*d- -e/ *p,f(x,y);\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn keyword_spacing() {
+        let input = "int f(){if(x)while(y)g(1);for(;;)switch(z){}do{}while(0);return(x);}";
+
+        let config = FormatConfig {
+            space_after_control_keywords: false,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "int f() {\n\
+             \x20   if(x)\n\
+             \x20       while(y)\n\
+             \x20           g(1);\n\
+             \x20   for(;;)\n\
+             \x20       switch(z) {}\n\
+             \x20   do {} while(0);\n\
+             \x20   return(x);\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            space_before_call_parentheses: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(){g(1);LIST(x);return x;}",
+            "void f() {\n    g (1);\n    LIST (x);\n    return x;\n}\n",
+        );
+    }
+
+    #[test]
+    fn padding() {
+        let config = FormatConfig {
+            spaces_in_parentheses: true,
+            spaces_in_brackets: true,
+            spaces_in_braces: false,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "int a[3]={[0]=1,2},b[];void f(int(*g)(void)){if(x)y=(int)(a[i]+f(1,2)*g());}",
+            "int a[ 3 ] = {[ 0 ] = 1, 2}, b[];\n\
+             \n\
+             void f( int ( *g )( void ) ) {\n\
+             \x20   if ( x )\n\
+             \x20       y = (int)( a[ i ] + f( 1, 2 ) * g() );\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn blank_lines() {
+        let input = "\n\n// header\n\n\n#include <a.h>\nint a;\n\n\n\nint b;\n\
+                     void f() {\n\n    x();\n\n\n    // c\n\n    y();\n\n}\n";
+
+        test(
+            input,
+            "// header\n\
+             \n\
+             #include <a.h>\n\
+             int a;\n\
+             \n\
+             int b;\n\
+             \n\
+             void f() {\n\
+             \x20   x();\n\
+             \n\
+             \x20   // c\n\
+             \n\
+             \x20   y();\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            max_blank_lines: 2,
+            strip_blank_lines_at_block_edges: false,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "// header\n\
+             \n\
+             \n\
+             #include <a.h>\n\
+             int a;\n\
+             \n\
+             \n\
+             int b;\n\
+             \n\
+             void f() {\n\
+             \n\
+             \x20   x();\n\
+             \n\
+             \n\
+             \x20   // c\n\
+             \n\
+             \x20   y();\n\
+             \n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn blank_lines_before_closing_directives() {
+        let input = "#ifdef A\nint a;\n\n#else\nint b;\n\n\n#endif\n\
+                     void f() {\n#if X\n    x();\n\n#endif\n}\n";
+
+        test(
+            input,
+            "#ifdef A\n\
+             int a;\n\
+             #else\n\
+             int b;\n\
+             #endif\n\
+             \n\
+             void f() {\n\
+             #if X\n\
+             \x20   x();\n\
+             #endif\n\
+             }\n",
+        );
+
+        let config = FormatConfig {
+            strip_blank_lines_at_block_edges: false,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "#ifdef A\n\
+             int a;\n\
+             \n\
+             #else\n\
+             int b;\n\
+             \n\
+             #endif\n\
+             \n\
+             void f() {\n\
+             #if X\n\
+             \x20   x();\n\
+             \n\
+             #endif\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn blank_lines_between_definitions_and_groups() {
+        let config = FormatConfig {
+            blank_lines_around_definitions: 2,
+            blank_lines_between_groups: 1,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "#include <a.h>\n#include <b.h>\n#define N 1\ntypedef int t;\nint x;\nint f(void);\n\
+             void f(void) {}\nvoid g(void) {}",
+            "#include <a.h>\n\
+             #include <b.h>\n\
+             \n\
+             #define N 1\n\
+             \n\
+             typedef int t;\n\
+             \n\
+             int x;\n\
+             int f(void);\n\
+             \n\
+             \n\
+             void f(void) {}\n\
+             \n\
+             \n\
+             void g(void) {}\n",
+        );
+    }
+
+    #[test]
+    fn trailing_whitespace_and_final_newline() {
+        let input = "#include <a.h>  \r\n// comment \t\nint x; /* a  \n b */\n\n\n";
+        test(input, "#include <a.h>\n// comment\nint x; /* a\n b */\n");
+
+        let config = FormatConfig {
+            trim_trailing_whitespace: false,
+            insert_final_newline: false,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "#include <a.h>  \r\n// comment \t\nint x; /* a  \n b */",
+        );
+    }
+
+    #[test]
+    fn line_endings() {
+        let config = FormatConfig {
+            line_ending: LineEnding::CrLf,
+            ..FormatConfig::default()
+        };
+        let input = "int  x;\n/* a\r\n b */\r\nint y;\n";
+        test_with(&config, input, "int x;\r\n/* a\r\n b */\r\nint y;\r\n");
+
+        // The most common line breaks of the source are kept.
+        let auto = LineEnding::Auto;
+        assert_eq!(auto.resolve("a\r\nb\r\nc\n"), LineEnding::CrLf);
+        assert_eq!(auto.resolve("a\r\nb\nc\n"), LineEnding::Lf);
+        assert_eq!(auto.resolve("a"), LineEnding::Lf);
+        assert_eq!(LineEnding::CrLf.resolve("a\n"), LineEnding::CrLf);
+    }
+
+    #[test]
+    fn output_is_written_as_it_goes() {
+        let input = "int  x; // a\nint longer; // b\n\nstruct s { int a; };\n";
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let unit = Parser::new(tokens).parse_translation_unit();
+        let config = FormatConfig::default();
+        let mut output = Vec::new();
+        format_to(&unit, &config, &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), format(&unit, &config));
+
+        // The ends of lines are cleaned up the same however the code is split into writes.
+        let text = "a  \r\n\r\n\t b\r\n\n\n\r";
+        for (trim, line_ending) in [(true, LineEnding::Lf), (false, LineEnding::CrLf)] {
+            let config = FormatConfig {
+                trim_trailing_whitespace: trim,
+                line_ending,
+                ..FormatConfig::default()
+            };
+            let mut output = Vec::new();
+            let mut finisher = Finisher::new(&mut output, &config);
+            for byte in text.as_bytes() {
+                finisher.write_all(&[*byte]).unwrap();
+            }
+            finisher.finish().unwrap();
+            let expected = finish(text.to_string(), &config);
+            assert_eq!(String::from_utf8(output).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn reflowed_comments() {
+        let config = FormatConfig {
+            max_line_length: 30,
+            reflow_comments: true,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "// A long paragraph which is\n// wrapped again.\n//\n// - a list item which is wrapped\n\
+             int x; // trailing comments are not wrapped\n\
+             void f() {\n    /**\n     * @param a\n     *   - code  \n     */\n    \
+             /* a block comment which is wrapped */\n}\n",
+            "// A long paragraph which is\n\
+             // wrapped again.\n\
+             //\n\
+             // - a list item which is\n\
+             //   wrapped\n\
+             int x; // trailing comments are not wrapped\n\
+             \n\
+             void f() {\n\
+             \x20   /**\n\
+             \x20    * @param a\n\
+             \x20    *   - code\n\
+             \x20    */\n\
+             \x20   /* a block comment which\n\
+             \x20    * is wrapped */\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn unbreakable_text_exceeds_the_limit() {
+        let config = FormatConfig {
+            max_line_length: 40,
+            reflow_comments: true,
+            split_string_literals: true,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "#include \"a/very/long/path/to/a/header/that/is/too/long.h\"\n\
+             // See https://example.com/a/very/long/url/to/the/details for more.\n\
+             char *s = \"see https://example.com/a/very/long/url/to/it and more\";\n",
+            "#include \"a/very/long/path/to/a/header/that/is/too/long.h\"\n\
+             // See https://example.com/a/very/long/url/to/the/details\n\
+             // for more.\n\
+             char *s = \"see https://example.com/a/very/long/url/to/it \"\n\
+             \x20         \"and more\";\n",
+        );
+    }
+
+    #[test]
+    fn doxygen_and_banner_comments_keep_their_layout() {
+        let config = FormatConfig {
+            max_line_length: 30,
+            reflow_comments: true,
+            ..FormatConfig::default()
+        };
+
+        test_with(
+            &config,
+            "/*! A long Doxygen paragraph which is not wrapped.\n *   @param  x   aligned\n */\n\
+             void f() {\n/*\n * +------+\n * | box  |\n * +------+\n */\n        \
+             // === a banner which is far too long ===\n}\n",
+            "/*! A long Doxygen paragraph which is not wrapped.\n\
+             \x20*   @param  x   aligned\n\
+             \x20*/\n\
+             void f() {\n\
+             \x20   /*\n\
+             \x20    * +------+\n\
+             \x20    * | box  |\n\
+             \x20    * +------+\n\
+             \x20    */\n\
+             \x20   // === a banner which is far too long ===\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn consecutive_declarations_and_assignments_are_aligned() {
+        let config = FormatConfig {
+            align_consecutive_declarations: true,
+            align_consecutive_assignments: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "void f(){int x=1;unsigned long total=2; // sum\nchar*name;\n\
+             x=3;longer_name=4;for(i=0;i<n;i++){}}",
+            "void f() {\n\
+             \x20   int           x     = 1;\n\
+             \x20   unsigned long total = 2; // sum\n\
+             \x20   char          *name;\n\
+             \x20   x           = 3;\n\
+             \x20   longer_name = 4;\n\
+             \x20   for (i = 0; i < n; i++) {}\n\
+             }\n",
+        );
+    }
+
+    #[test]
+    fn struct_members_are_aligned() {
+        let config = FormatConfig {
+            align_struct_members: true,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            "struct packet{uint8_t type; // kind\nuint16_t length; // bytes\nconst char*payload;\n\
+             struct{int x;long offset;}position;\nstruct{int a;}in;\nint count;\n\
+             void(*callback)(int);\nchar c;};",
+            "struct packet {\n\
+             \x20   uint8_t    type;   // kind\n\
+             \x20   uint16_t   length; // bytes\n\
+             \x20   const char *payload;\n\
+             \x20   struct {\n\
+             \x20       int  x;\n\
+             \x20       long offset;\n\
+             \x20   } position;\n\
+             \x20   struct {\n\
+             \x20       int a;\n\
+             \x20   } in;\n\
+             \x20   int count;\n\
+             \x20   void (*callback)(int);\n\
+             \x20   char c;\n\
+             };\n",
+        );
+    }
+
+    #[test]
+    fn trailing_comments_are_aligned() {
+        let input = "enum { A = 1, // one\n LONGER = 2 // two\n};\n\
+                     int x; // a\nint longer_name; // b\n\nint y; // c\n";
+
+        test(
+            input,
+            "enum {\n\
+             \x20   A = 1,     // one\n\
+             \x20   LONGER = 2 // two\n\
+             };\n\
+             \n\
+             int x;           // a\n\
+             int longer_name; // b\n\
+             \n\
+             int y; // c\n",
+        );
+
+        let config = FormatConfig {
+            max_line_length: 20,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "enum {\n\
+             \x20   A = 1, // one\n\
+             \x20   LONGER = 2 // two\n\
+             };\n\
+             \n\
+             int x; // a\n\
+             int longer_name; // b\n\
+             \n\
+             int y; // c\n",
+        );
+
+        let config = FormatConfig {
+            align_trailing_comments: false,
+            ..FormatConfig::default()
+        };
+        test_with(
+            &config,
+            input,
+            "enum {\n\
+             \x20   A = 1, // one\n\
+             \x20   LONGER = 2 // two\n\
+             };\n\
+             \n\
+             int x; // a\n\
+             int longer_name; // b\n\
+             \n\
+             int y; // c\n",
+        );
+    }
+
+    #[test]
+    fn trailing_comments_of_both_styles_are_aligned() {
+        test(
+            "struct point {\nint x; /* across */\nlong height; // up\nchar *name; /* a */ // b\n};\n\
+             int a = 1; /* one */\nint longer = 2; // two\n",
+            "struct point {\n\
+             \x20   int x;       /* across */\n\
+             \x20   long height; // up\n\
+             \x20   char *name;  /* a */ // b\n\
+             };\n\
+             \n\
+             int a = 1;      /* one */\n\
+             int longer = 2; // two\n",
+        );
+    }
+}
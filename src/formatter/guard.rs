@@ -0,0 +1,187 @@
+//! Include guards and `#pragma once`, which protect a header against being included more than
+//! once. Either one can be converted into the other, and the macros of include guards can be
+//! renamed after the path of the header.
+
+use crate::formatter::config::HeaderGuards;
+use crate::parser::ast::{
+    Annotated, ConditionalBranch, ConditionalGroup, ExternalDeclaration, TranslationUnit,
+};
+use std::mem;
+use std::path::{Component, Path};
+
+/// The macro of the include guard around the whole translation unit, if there is one: a single
+/// `#ifndef NAME` without other branches, which starts with `#define NAME`.
+pub fn header_guard(unit: &TranslationUnit) -> Option<&str> {
+    let [item] = unit.items.as_slice() else {
+        return None;
+    };
+    let ExternalDeclaration::Conditional(group) = &item.node else {
+        return None;
+    };
+    let [branch] = group.branches.as_slice() else {
+        return None;
+    };
+    let ExternalDeclaration::Directive(line) = &branch.items.first()?.node else {
+        return None;
+    };
+
+    let mut condition = branch.directive.split_whitespace();
+    let mut definition = line.split_whitespace();
+    let (Some("ifndef"), Some(name)) = (condition.next(), condition.next()) else {
+        return None;
+    };
+    (definition.next() == Some("define") && definition.next() == Some(name)).then_some(name)
+}
+
+/// Whether the first item of the translation unit is a `#pragma once`.
+fn has_pragma_once(unit: &TranslationUnit) -> bool {
+    matches!(
+        unit.items.first().map(|item| &item.node),
+        Some(ExternalDeclaration::Directive(line))
+            if line.split_whitespace().eq(["pragma", "once"])
+    )
+}
+
+/// The name of the macro of an include guard for the header at `file_path`, made up of its
+/// components in upper case, with anything other than letters and digits replaced by `_`.
+fn guard_name(file_path: &str) -> String {
+    let components = Path::new(file_path)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        });
+
+    let name: String = components
+        .collect::<Vec<_>>()
+        .join("_")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+/// Replace the include guard around the translation unit with a `#pragma once`.
+fn to_pragma_once(unit: &mut TranslationUnit) {
+    let Some(mut item) = unit.items.pop() else {
+        return;
+    };
+    let ExternalDeclaration::Conditional(group) = &mut item.node else {
+        unreachable!("the unit is guarded");
+    };
+    let mut branch = group.branches.remove(0);
+
+    // The definition of the macro is replaced by the pragma, which takes the place of the group.
+    branch.items[0] = Annotated {
+        node: ExternalDeclaration::Directive("pragma once".to_string()),
+        trailing: Vec::new(),
+        ..item
+    };
+    unit.items = branch.items;
+    branch.comments.append(&mut unit.comments);
+    unit.comments = branch.comments;
+}
+
+/// Replace the `#pragma once` at the start of the translation unit with an include guard.
+fn to_macro(unit: &mut TranslationUnit, name: &str) {
+    let mut items = mem::take(&mut unit.items);
+    let pragma = &mut items[0];
+    let leading = mem::take(&mut pragma.leading);
+    let blank_lines = mem::replace(&mut pragma.blank_lines, 0);
+    let span = pragma.span;
+
+    pragma.node = ExternalDeclaration::Directive(format!("define {}", name));
+    pragma.trailing.clear();
+
+    let group = ConditionalGroup {
+        branches: vec![ConditionalBranch {
+            directive: format!("ifndef {}", name),
+            disabled: None,
+            items,
+            comments: mem::take(&mut unit.comments),
+            closing_blank_lines: 0,
+        }],
+        end: format!("endif /* {} */", name),
+    };
+    unit.items = vec![Annotated {
+        node: ExternalDeclaration::Conditional(group),
+        span,
+        leading,
+        trailing: Vec::new(),
+        blank_lines,
+    }];
+}
+
+/// Rename the macro of the include guard around the translation unit.
+fn rename(unit: &mut TranslationUnit, from: &str, to: &str) {
+    let ExternalDeclaration::Conditional(group) = &mut unit.items[0].node else {
+        unreachable!("the unit is guarded");
+    };
+    let branch = &mut group.branches[0];
+
+    branch.directive = format!("ifndef {}", to);
+    branch.items[0].node = ExternalDeclaration::Directive(format!("define {}", to));
+    group.end = group.end.replace(from, to);
+}
+
+/// Convert the protection of the header at `file_path` as far as the option asks for.
+pub fn normalize_header_guard(unit: &mut TranslationUnit, guards: HeaderGuards, file_path: &str) {
+    if !file_path.ends_with(".h") {
+        return;
+    }
+
+    let guard = header_guard(unit).map(str::to_string);
+    let name = guard_name(file_path);
+
+    match (guards, guard) {
+        (HeaderGuards::PragmaOnce, Some(_)) => to_pragma_once(unit),
+        (HeaderGuards::Macro, Some(guard)) if guard != name => rename(unit, &guard, &name),
+        (HeaderGuards::Macro, None) if has_pragma_once(unit) => to_macro(unit, &name),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{FormatConfig, HeaderGuards};
+    use crate::formatter::formatter::format;
+    use crate::formatter::guard::normalize_header_guard;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    fn normalized(input: &str, guards: HeaderGuards) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+
+        normalize_header_guard(&mut unit, guards, "./src/list-1.h");
+        format(&unit, &FormatConfig::default())
+    }
+
+    #[test]
+    fn guards_and_pragmas_are_converted() {
+        let guarded = "// List.\n#ifndef LIST_H\n#define LIST_H\n\nint x;\n#endif // LIST_H\n";
+        let pragma = "// List.\n#pragma once\n\nint x;\n";
+
+        assert_eq!(normalized(guarded, HeaderGuards::PragmaOnce), pragma);
+        assert_eq!(normalized(guarded, HeaderGuards::Keep), guarded);
+        assert_eq!(
+            normalized(guarded, HeaderGuards::Macro),
+            "// List.\n#ifndef SRC_LIST_1_H\n#define SRC_LIST_1_H\n\nint x;\n#endif // SRC_LIST_1_H\n"
+        );
+        assert_eq!(
+            normalized(pragma, HeaderGuards::Macro),
+            "// List.\n#ifndef SRC_LIST_1_H\n#define SRC_LIST_1_H\n\nint x;\n#endif /* SRC_LIST_1_H */\n"
+        );
+    }
+}
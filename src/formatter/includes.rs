@@ -0,0 +1,146 @@
+//! Sorting of the `#include` lines at file scope. Only includes on consecutive lines are sorted
+//! among each other, so that blank lines, comments, other directives and conditional groups keep
+//! separating them. In particular, headers are never moved in or out of a conditional group.
+
+use crate::formatter::config::IncludeSorting;
+use crate::parser::ast::{Annotated, ExternalDeclaration, TranslationUnit};
+use std::mem;
+use std::path::Path;
+
+/// The order in which the headers are sorted.
+struct Order<'a> {
+    sorting: IncludeSorting,
+    /// The name of the source file without its extension, such as `list` for `src/list.c`.
+    stem: Option<&'a str>,
+}
+
+impl Order<'_> {
+    /// The group of a header, and the name by which it is sorted within it.
+    fn key<'h>(&self, header: &'h str) -> (usize, &'h str) {
+        if self.sorting != IncludeSorting::Grouped {
+            return (0, header);
+        }
+
+        let name = Path::new(&header[1..header.len() - 1])
+            .file_stem()
+            .and_then(|stem| stem.to_str());
+        match header.chars().next() {
+            Some('"') if name.is_some() && name == self.stem => (0, header),
+            Some('<') => (1, header),
+            _ => (2, header),
+        }
+    }
+}
+
+/// The header named by an `#include` directive, including its delimiters, as in `<stdio.h>`.
+/// Includes of macros are not considered, as their header is unknown.
+pub(crate) fn header(item: &ExternalDeclaration) -> Option<&str> {
+    let ExternalDeclaration::Directive(line) = item else {
+        return None;
+    };
+    let header = line.trim_start().strip_prefix("include")?.trim_start();
+    let closing = match header.chars().next()? {
+        '<' => '>',
+        '"' => '"',
+        _ => return None,
+    };
+    let end = header[1..].find(closing)? + 2;
+
+    // Anything after the header can only be a comment.
+    let rest = header[end..].trim_start();
+    (rest.is_empty() || rest.starts_with("//") || rest.starts_with("/*")).then_some(&header[..end])
+}
+
+/// Sort the runs of consecutive includes among the items, including those in conditional groups.
+/// Comments in front of the first include of a run stay in front of the run, while those on the
+/// same line as an include move along with it.
+fn sort(items: &mut [Annotated<ExternalDeclaration>], order: &Order) {
+    let mut start = 0;
+
+    while start < items.len() {
+        if let ExternalDeclaration::Conditional(group) = &mut items[start].node {
+            for branch in &mut group.branches {
+                sort(&mut branch.items, order);
+            }
+        }
+
+        if header(&items[start].node).is_none() {
+            start += 1;
+            continue;
+        }
+
+        let length = items[start + 1..]
+            .iter()
+            .take_while(|item| {
+                header(&item.node).is_some() && item.leading.is_empty() && item.blank_lines == 0
+            })
+            .count();
+        let run = &mut items[start..=start + length];
+
+        let leading = mem::take(&mut run[0].leading);
+        let blank_lines = mem::replace(&mut run[0].blank_lines, 0);
+        run.sort_by(|a, b| {
+            let a = header(&a.node).expect("runs only contain includes");
+            let b = header(&b.node).expect("runs only contain includes");
+            order.key(a).cmp(&order.key(b))
+        });
+        run[0].leading = leading;
+        run[0].blank_lines = blank_lines;
+
+        start += length + 1;
+    }
+}
+
+/// Sort the includes of a translation unit read from `file_path`, as far as the option asks for.
+pub fn sort_includes(unit: &mut TranslationUnit, sorting: IncludeSorting, file_path: &str) {
+    if sorting == IncludeSorting::Keep {
+        return;
+    }
+
+    let stem = Path::new(file_path)
+        .file_stem()
+        .and_then(|stem| stem.to_str());
+    sort(&mut unit.items, &Order { sorting, stem });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{FormatConfig, IncludeSorting};
+    use crate::formatter::formatter::format;
+    use crate::formatter::includes::sort_includes;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    fn sorted(input: &str, sorting: IncludeSorting) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+
+        sort_includes(&mut unit, sorting, "src/list.c");
+        format(&unit, &FormatConfig::default())
+    }
+
+    #[test]
+    fn includes_are_sorted_within_runs() {
+        let input =
+            "// first\n#include \"util.h\"\n#include <string.h>\n#include \"list.h\" // own\n\
+                     #include <stdio.h>\n\n#include <b.h>\n#include <a.h>\n\
+                     #ifdef X\n#include <d.h>\n#include <c.h>\n#endif\n#include <aa.h>\n";
+
+        assert_eq!(
+            sorted(input, IncludeSorting::Alphabetical),
+            "// first\n#include \"list.h\" // own\n#include \"util.h\"\n#include <stdio.h>\n\
+             #include <string.h>\n\n#include <a.h>\n#include <b.h>\n\
+             #ifdef X\n#include <c.h>\n#include <d.h>\n#endif\n#include <aa.h>\n"
+        );
+        assert_eq!(
+            sorted(input, IncludeSorting::Grouped),
+            "// first\n#include \"list.h\" // own\n#include <stdio.h>\n#include <string.h>\n\
+             #include \"util.h\"\n\n#include <a.h>\n#include <b.h>\n\
+             #ifdef X\n#include <c.h>\n#include <d.h>\n#endif\n#include <aa.h>\n"
+        );
+        assert_eq!(
+            sorted("#include <b.h>\n#include <a.h>\n", IncludeSorting::Keep),
+            "#include <b.h>\n#include <a.h>\n"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Guessing the style of existing code, so that a file can be formatted consistently with the
+//! code around it rather than with a style of its own. Options without any evidence in the file
+//! keep their default.
+
+use crate::formatter::config::{BraceStyle, FormatConfig, PointerAlignment};
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::token::Token::{Brace, Comma, Identifier, Keyword, Parenthesis, Semicolon, Star};
+use crate::lexer::token::{Lexeme, Token};
+
+/// The most common value, preferring the first one in case of a tie.
+fn most_common<T: Copy + PartialEq>(values: &[T]) -> Option<T> {
+    let count = |value: T| values.iter().filter(|&&other| other == value).count();
+    values
+        .iter()
+        .copied()
+        .fold(None, |best: Option<T>, value| match best {
+            Some(best) if count(best) >= count(value) => Some(best),
+            _ => Some(value),
+        })
+}
+
+/// Whether the indentation uses tabs, and the most common step in between the indentation of
+/// consecutive lines. The lines of block comments are skipped, since they tend to be aligned with
+/// their opening delimiter instead.
+fn indentation(source: &str) -> (Option<bool>, Option<usize>) {
+    let mut tabs = Vec::new();
+    let mut steps = Vec::new();
+    let mut previous = 0;
+
+    for line in source.lines() {
+        let text = line.trim_start();
+        if text.is_empty() || text.starts_with('*') || text.starts_with('#') {
+            continue;
+        }
+
+        let indent = &line[..line.len() - text.len()];
+        if let Some(first) = indent.chars().next() {
+            tabs.push(first == '\t');
+        }
+
+        if !indent.contains('\t') {
+            let width = indent.len();
+            if width > previous && width - previous <= 8 {
+                steps.push(width - previous);
+            }
+            previous = width;
+        }
+    }
+
+    (most_common(&tabs), most_common(&steps))
+}
+
+/// The placement of braces, from whether the opening braces of function bodies and of other
+/// blocks start lines of their own.
+fn brace_style(lexemes: &[Lexeme]) -> Option<BraceStyle> {
+    let mut functions = Vec::new();
+    let mut blocks = Vec::new();
+    let mut depth = 0;
+
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        match lexeme.token {
+            Brace(Left) => {
+                let own_line = lexeme.newlines > 0;
+                let previous = i.checked_sub(1).map(|i| &lexemes[i].token);
+                match previous {
+                    Some(Parenthesis(Right)) if depth == 0 => functions.push(own_line),
+                    Some(Parenthesis(Right) | Keyword(_)) => blocks.push(own_line),
+                    Some(Identifier(_)) if depth == 0 => blocks.push(own_line),
+                    _ => {}
+                }
+                depth += 1;
+            }
+            Brace(Right) => depth -= 1,
+            _ => {}
+        }
+    }
+
+    match (most_common(&functions), most_common(&blocks)) {
+        (Some(true), Some(true)) => Some(BraceStyle::Allman),
+        (Some(true), _) => Some(BraceStyle::KernighanRitchie),
+        (_, Some(true)) => Some(BraceStyle::Allman),
+        (Some(false), _) | (_, Some(false)) => Some(BraceStyle::Attach),
+        (None, None) => None,
+    }
+}
+
+/// The placement of the `*` in declarations of pointers such as `int *p`, which are recognized as
+/// a name, a `*` and another name at the start of a declaration or parameter.
+fn pointer_alignment(lexemes: &[Lexeme]) -> Option<PointerAlignment> {
+    let starts = |token: Option<&Token>| {
+        matches!(
+            token,
+            None | Some(Semicolon | Brace(_) | Parenthesis(Left) | Comma | Keyword(_))
+        )
+    };
+
+    let alignments: Vec<PointerAlignment> = lexemes
+        .windows(3)
+        .enumerate()
+        .filter_map(|(i, window)| match window {
+            [name, star, declarator]
+                if matches!(name.token, Identifier(_))
+                    && star.token == Star
+                    && matches!(declarator.token, Identifier(_))
+                    && starts(i.checked_sub(1).map(|i| &lexemes[i].token)) =>
+            {
+                let before = star.span.start > name.span.end;
+                let after = declarator.span.start > star.span.end;
+                match (before, after) {
+                    (true, false) => Some(PointerAlignment::Right),
+                    (false, true) => Some(PointerAlignment::Left),
+                    (true, true) => Some(PointerAlignment::Middle),
+                    (false, false) => None,
+                }
+            }
+            _ => None,
+        })
+        .collect();
+
+    most_common(&alignments)
+}
+
+/// Guess the options which match the style of a source file.
+pub fn infer_style(lexemes: &[Lexeme], source: &str) -> FormatConfig {
+    let mut config = FormatConfig::default();
+    let (use_tabs, step) = indentation(source);
+
+    if let Some(use_tabs) = use_tabs {
+        config.use_tabs = use_tabs;
+        if use_tabs {
+            config.indent_width = 8;
+            config.continuation_indent = 8;
+        }
+    }
+    if let Some(step) = step.filter(|_| !config.use_tabs) {
+        config.indent_width = step;
+        config.continuation_indent = step;
+    }
+    if let Some(brace_style) = brace_style(lexemes) {
+        config.brace_style = brace_style;
+    }
+    if let Some(pointer_alignment) = pointer_alignment(lexemes) {
+        config.pointer_alignment = pointer_alignment;
+    }
+
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{BraceStyle, FormatConfig, PointerAlignment};
+    use crate::formatter::infer::infer_style;
+    use crate::lexer::lexer::Lexer;
+    use crate::lexer::token::Lexeme;
+
+    fn infer(source: &str) -> FormatConfig {
+        let lexemes: Vec<Lexeme> = Lexer::new(source)
+            .lexemes()
+            .map(|lexeme| lexeme.unwrap())
+            .collect();
+        infer_style(&lexemes, source)
+    }
+
+    #[test]
+    fn spaces_braces_and_pointers() {
+        let config = infer(
+            "/*\n * A comment.\n */\nint f(char* s)\n{\n  if (s) {\n    g(s);\n  }\n  \
+             for (;;) {\n    char* t = s;\n  }\n}\n",
+        );
+
+        assert!(!config.use_tabs);
+        assert_eq!(config.indent_width, 2);
+        assert_eq!(config.brace_style, BraceStyle::KernighanRitchie);
+        assert_eq!(config.pointer_alignment, PointerAlignment::Left);
+    }
+
+    #[test]
+    fn tabs_and_allman_braces() {
+        let config = infer("void f(int *p)\n{\n\tif (p)\n\t{\n\t\tg();\n\t}\n}\n");
+
+        assert!(config.use_tabs);
+        assert_eq!(config.indent_width, 8);
+        assert_eq!(config.brace_style, BraceStyle::Allman);
+        assert_eq!(config.pointer_alignment, PointerAlignment::Right);
+    }
+
+    #[test]
+    fn defaults_without_evidence() {
+        assert_eq!(infer("int x;\n"), FormatConfig::default());
+    }
+}
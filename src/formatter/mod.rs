@@ -0,0 +1,16 @@
+pub mod comment;
+pub mod config;
+pub mod cursor;
+pub mod doc;
+pub mod fallback;
+#[allow(clippy::module_inception)]
+pub mod formatter;
+pub mod guard;
+pub mod includes;
+pub mod infer;
+pub mod passthrough;
+pub mod pragma;
+pub mod range;
+pub mod suppression;
+pub mod verify;
+pub mod width;
@@ -0,0 +1,158 @@
+//! Keeping the code which the formatter gets wrong as it was written, rather than giving up on the
+//! whole file. When the formatted code does not have the same meaning as the source, the innermost
+//! statement or item around the first difference is kept as it was, and the file is formatted
+//! again, until its meaning stays the same.
+
+use crate::diagnostics::diagnostic::{Diagnostic, Diagnostics};
+use crate::formatter::config::FormatConfig;
+use crate::formatter::formatter::format;
+use crate::formatter::verify::verify_tokens;
+use crate::lexer::span::Span;
+use crate::parser::ast::{
+    Annotated, Block, ExternalDeclaration, Nodes, Statement, TranslationUnit,
+};
+use crate::parser::visit_mut::{walk_external_declaration_mut, walk_statement_mut, VisitMut};
+
+/// Replaces the innermost statement or item around an offset of the source with its text.
+struct Keep<'a> {
+    source: &'a str,
+    offset: usize,
+    /// Whether a statement or item has been replaced.
+    kept: bool,
+}
+
+impl Keep<'_> {
+    fn contains(&self, span: Span) -> bool {
+        span.start <= self.offset && self.offset < span.end
+    }
+
+    fn items(&mut self, nodes: &mut Nodes, items: &mut [Annotated<ExternalDeclaration>]) {
+        for item in items {
+            if !self.contains(item.span) {
+                continue;
+            }
+
+            match &mut item.node {
+                ExternalDeclaration::Conditional(group) => {
+                    for branch in &mut group.branches {
+                        self.items(nodes, &mut branch.items);
+                    }
+                }
+                node => walk_external_declaration_mut(self, nodes, node),
+            }
+            if !self.kept && !matches!(item.node, ExternalDeclaration::Verbatim(_)) {
+                let text = &self.source[item.span.start..item.span.end];
+                item.node = ExternalDeclaration::Verbatim(text.to_string());
+                self.kept = true;
+            }
+        }
+    }
+
+    fn statements(&mut self, nodes: &mut Nodes, statements: &mut [Annotated<Statement>]) {
+        for statement in statements {
+            if !self.contains(statement.span) {
+                continue;
+            }
+
+            match &mut statement.node {
+                Statement::Conditional(group) => {
+                    for branch in &mut group.branches {
+                        self.statements(nodes, &mut branch.items);
+                    }
+                }
+                node => walk_statement_mut(self, nodes, node),
+            }
+            if !self.kept && !matches!(statement.node, Statement::Verbatim(_)) {
+                let text = &self.source[statement.span.start..statement.span.end];
+                statement.node = Statement::Verbatim(text.to_string());
+                self.kept = true;
+            }
+        }
+    }
+}
+
+impl VisitMut for Keep<'_> {
+    fn visit_translation_unit_mut(&mut self, node: &mut TranslationUnit) {
+        self.items(&mut node.nodes, &mut node.items);
+    }
+
+    fn visit_block_mut(&mut self, nodes: &mut Nodes, node: &mut Block) {
+        self.statements(nodes, &mut node.statements);
+    }
+}
+
+/// Format a translation unit, keeping the statements and items which the formatter would change
+/// the meaning of as they were written in the source, each with a warning. Fails with the error of
+/// verifying the formatted code if the change is not within a statement or item.
+pub fn format_with_passthrough(
+    unit: &mut TranslationUnit,
+    source: &str,
+    config: &FormatConfig,
+    diagnostics: &mut Diagnostics,
+) -> Result<String, Diagnostic> {
+    loop {
+        let formatted = format(unit, config);
+        let Some(error) = verify_tokens(source, &formatted, config) else {
+            return Ok(formatted);
+        };
+
+        let mut keep = Keep {
+            source,
+            offset: error.span.start,
+            kept: false,
+        };
+        keep.visit_translation_unit_mut(unit);
+        if !keep.kept {
+            return Err(error);
+        }
+        diagnostics.push(Diagnostic::recovered(error.span, error.message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::diagnostic::{Diagnostics, Severity};
+    use crate::formatter::config::FormatConfig;
+    use crate::formatter::passthrough::format_with_passthrough;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::ast::{Expression, Nodes};
+    use crate::parser::parser::Parser;
+    use crate::parser::visit_mut::{walk_expression_mut, VisitMut};
+
+    /// Renames a variable wherever it is used, which the formatter would never do.
+    struct Rename;
+
+    impl VisitMut for Rename {
+        fn visit_expression_mut(&mut self, nodes: &mut Nodes, node: &mut Expression) {
+            if *node == Expression::Identifier("x".into()) {
+                *node = Expression::Identifier("y".into());
+            }
+            walk_expression_mut(self, nodes, node);
+        }
+    }
+
+    #[test]
+    fn changed_code_is_kept() {
+        let input = "int  a=1;\nvoid f(){int x=1;\n  if (x)   return  x+1;}\n";
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+        Rename.visit_translation_unit_mut(&mut unit);
+
+        let config = FormatConfig::default();
+        let mut diagnostics = Diagnostics::new();
+        let formatted = format_with_passthrough(&mut unit, input, &config, &mut diagnostics);
+        assert_eq!(
+            formatted.unwrap(),
+            "int a = 1;\n\nvoid f() {\n    int x = 1;\n    if (x)   return  x+1;\n}\n"
+        );
+        assert_eq!(diagnostics.count(Severity::Warning), 1);
+
+        // A change outside of every statement and item cannot be kept.
+        unit.insert(2, "int z;").unwrap();
+        let error = format_with_passthrough(&mut unit, input, &config, &mut diagnostics);
+        assert_eq!(
+            error.unwrap_err().message,
+            "formatting would add `int` here"
+        );
+    }
+}
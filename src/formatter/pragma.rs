@@ -0,0 +1,159 @@
+//! The `_Pragma` operators which stand on their own, as in `_Pragma("GCC unroll 4")` in front of a
+//! loop. Like the `#pragma` directives they stand for, they are written exactly as they were,
+//! since the compiler reads their string as it is. Those in between struct members are formatted
+//! as macro invocations, as there is nothing to replace them with.
+
+use crate::parser::ast::{
+    Annotated, Block, ExternalDeclaration, MacroCall, Nodes, Statement, TranslationUnit,
+};
+use crate::parser::visit_mut::{
+    walk_block_mut, walk_external_declaration_mut, walk_statement_mut, walk_translation_unit_mut,
+    VisitMut,
+};
+
+fn is_pragma(call: &MacroCall) -> bool {
+    call.name == "_Pragma"
+}
+
+/// Replace the pragmas in a list of items with their original text.
+fn keep<T>(
+    items: &mut [Annotated<T>],
+    source: &str,
+    pragma: impl Fn(&T) -> bool,
+    verbatim: impl Fn(String) -> T,
+) {
+    for item in items {
+        if pragma(&item.node) {
+            item.node = verbatim(source[item.span.start..item.span.end].to_string());
+        }
+    }
+}
+
+fn is_pragma_item(item: &ExternalDeclaration) -> bool {
+    matches!(item, ExternalDeclaration::Macro { call, .. } if is_pragma(call))
+}
+
+fn is_pragma_statement(statement: &Statement) -> bool {
+    matches!(statement, Statement::Macro { call, .. } if is_pragma(call))
+}
+
+struct Pragmas<'a> {
+    source: &'a str,
+}
+
+impl VisitMut for Pragmas<'_> {
+    fn visit_translation_unit_mut(&mut self, node: &mut TranslationUnit) {
+        keep(
+            &mut node.items,
+            self.source,
+            is_pragma_item,
+            ExternalDeclaration::Verbatim,
+        );
+        walk_translation_unit_mut(self, node);
+    }
+
+    fn visit_external_declaration_mut(
+        &mut self,
+        nodes: &mut Nodes,
+        node: &mut ExternalDeclaration,
+    ) {
+        if let ExternalDeclaration::Conditional(group) = node {
+            for branch in &mut group.branches {
+                keep(
+                    &mut branch.items,
+                    self.source,
+                    is_pragma_item,
+                    ExternalDeclaration::Verbatim,
+                );
+            }
+        }
+        walk_external_declaration_mut(self, nodes, node);
+    }
+
+    fn visit_block_mut(&mut self, nodes: &mut Nodes, node: &mut Block) {
+        keep(
+            &mut node.statements,
+            self.source,
+            is_pragma_statement,
+            Statement::Verbatim,
+        );
+        walk_block_mut(self, nodes, node);
+    }
+
+    fn visit_statement_mut(&mut self, nodes: &mut Nodes, node: &mut Statement) {
+        if let Statement::Conditional(group) = node {
+            for branch in &mut group.branches {
+                keep(
+                    &mut branch.items,
+                    self.source,
+                    is_pragma_statement,
+                    Statement::Verbatim,
+                );
+            }
+        }
+        walk_statement_mut(self, nodes, node);
+    }
+}
+
+/// Replace the `_Pragma` operators on their own in a translation unit with the text of `source`
+/// they cover.
+pub fn keep_pragmas(unit: &mut TranslationUnit, source: &str) {
+    Pragmas { source }.visit_translation_unit_mut(unit);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{DirectiveIndentation, FormatConfig};
+    use crate::formatter::formatter::format;
+    use crate::formatter::pragma::keep_pragmas;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    #[test]
+    fn pragmas_are_kept_as_written() {
+        let input = "#include <a.h>\n\
+                     #  pragma pack(push,  1)\n\
+                     struct s { char a; int b; };\n\
+                     #pragma pack(pop)\n\
+                     #line 10   \"list.c\"\n\
+                     void f(void) {\n\
+                     _Pragma ( \"GCC unroll 4\" )\n\
+                     for (;;) {}\n\
+                     #if X\n\
+                     #ifdef Y\n\
+                     #pragma omp   parallel\n\
+                     #endif\n\
+                     #endif\n\
+                     x  =  1;\n\
+                     }\n";
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+        keep_pragmas(&mut unit, input);
+
+        let config = FormatConfig {
+            directive_indentation: DirectiveIndentation::AfterHash,
+            blank_lines_between_groups: 1,
+            ..FormatConfig::default()
+        };
+        assert_eq!(
+            format(&unit, &config),
+            "#include <a.h>\n\
+             \n\
+             #  pragma pack(push,  1)\n\
+             struct s {\n    char a;\n    int b;\n};\n\
+             \n\
+             #pragma pack(pop)\n\
+             #line 10   \"list.c\"\n\
+             void f(void) {\n\
+             \x20   _Pragma ( \"GCC unroll 4\" )\n\
+             \x20   for (;;) {}\n\
+             #if X\n\
+             #    ifdef Y\n\
+             #pragma omp   parallel\n\
+             #    endif\n\
+             #endif\n\
+             \x20   x = 1;\n\
+             }\n"
+        );
+    }
+}
@@ -0,0 +1,300 @@
+//! Formatting only part of a file, as editors do to format a selection. The selected bytes or
+//! lines are widened to the statements and items they touch, and only the changes to those lines
+//! are taken from the formatted file, so that the rest of it is left exactly as it was.
+
+use crate::diff::apply_within;
+use crate::lex;
+use crate::lexer::direction::Direction;
+use crate::lexer::span::Span;
+use crate::lexer::token::Token;
+use crate::parser::ast::{Block, Nodes, TranslationUnit};
+use crate::parser::visit::{walk_block, walk_translation_unit, Visit};
+use std::ops::Range;
+
+/// A part of a file to format, as given on the command line.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Selection {
+    /// The lines from the first to the last, both included and counted from one.
+    Lines(usize, usize),
+    /// The bytes from an offset, up to the end of the file if no length is given.
+    Bytes(usize, Option<usize>),
+    /// The code around a character which was just typed at an offset, as editors format while
+    /// typing: the block which a `}` closes, or otherwise the statement the character is part of,
+    /// such as the one a `;` ends.
+    Typed(usize),
+}
+
+impl Selection {
+    /// Read a range of lines such as `120:180`.
+    pub fn lines(text: &str) -> Option<Selection> {
+        let (first, last) = text.split_once(':')?;
+        let (first, last) = (first.parse().ok()?, last.parse().ok()?);
+        match 0 < first && first <= last {
+            true => Some(Selection::Lines(first, last)),
+            false => None,
+        }
+    }
+
+    /// The bytes of a source file which are selected, clamped to its end. Whitespace around the
+    /// selection is left out, as it belongs to no unit in particular.
+    fn span(self, source: &str) -> Span {
+        let (start, end) = match self {
+            Selection::Lines(first, last) => {
+                (line_start(source, first - 1), line_start(source, last))
+            }
+            Selection::Bytes(offset, length) => {
+                let start = offset.min(source.len());
+                let end = length.map_or(source.len(), |length| start.saturating_add(length));
+                (start, end.min(source.len()))
+            }
+            Selection::Typed(offset) => {
+                let offset = offset.min(source.len());
+                (
+                    opening_brace(source, offset).unwrap_or(offset),
+                    (offset + 1).min(source.len()),
+                )
+            }
+        };
+
+        let text = &source.as_bytes()[start..end];
+        match text.iter().position(|byte| !byte.is_ascii_whitespace()) {
+            Some(leading) => {
+                let trailing = text
+                    .iter()
+                    .rev()
+                    .take_while(|byte| byte.is_ascii_whitespace());
+                Span::new(start + leading, end - trailing.count())
+            }
+            None => Span::new(start, end),
+        }
+    }
+}
+
+/// The offset at which a line starts, counted from zero, or the end of the source if it has fewer
+/// lines.
+fn line_start(source: &str, line: usize) -> usize {
+    match line {
+        0 => 0,
+        _ => source
+            .match_indices('\n')
+            .nth(line - 1)
+            .map_or(source.len(), |(index, _)| index + 1),
+    }
+}
+
+/// The offset of the `{` which the `}` at an offset closes, if there is one. Braces in comments
+/// and literals are not counted.
+fn opening_brace(source: &str, offset: usize) -> Option<usize> {
+    let lexemes = lex(source).ok()?;
+    let mut before = lexemes
+        .iter()
+        .rev()
+        .skip_while(|lexeme| lexeme.span.start > offset);
+    if before.next()?.span != Span::new(offset, offset + 1) || source.as_bytes()[offset] != b'}' {
+        return None;
+    }
+
+    let mut depth = 0;
+    for lexeme in before {
+        match lexeme.token {
+            Token::Brace(Direction::Right) => depth += 1,
+            Token::Brace(Direction::Left) if depth == 0 => return Some(lexeme.span.start),
+            Token::Brace(Direction::Left) => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The spans of all items at file scope and all statements in blocks, which are the units a
+/// selection is widened to.
+struct Units {
+    spans: Vec<Span>,
+}
+
+impl<'ast> Visit<'ast> for Units {
+    fn visit_translation_unit(&mut self, node: &'ast TranslationUnit) {
+        self.spans.extend(node.items.iter().map(|item| item.span));
+        walk_translation_unit(self, node);
+    }
+
+    fn visit_block(&mut self, nodes: &'ast Nodes, node: &'ast Block) {
+        self.spans
+            .extend(node.statements.iter().map(|statement| statement.span));
+        walk_block(self, nodes, node);
+    }
+}
+
+/// Widen a selection to the innermost units around both of its ends, so that a statement or item
+/// is never formatted in part.
+fn widen(spans: &[Span], selection: Span) -> Span {
+    let innermost = |contains: &dyn Fn(&Span) -> bool| {
+        spans
+            .iter()
+            .filter(|span| contains(span))
+            .min_by_key(|span| span.end - span.start)
+            .copied()
+    };
+
+    let start = innermost(&|span| span.start <= selection.start && selection.start < span.end);
+    let end = innermost(&|span| span.start < selection.end && selection.end <= span.end);
+    Span::new(
+        start.map_or(selection.start, |span| span.start),
+        end.map_or(selection.end, |span| span.end),
+    )
+}
+
+/// The lines, counted from zero, which a span of a source file covers, including the line it ends
+/// on.
+fn lines(source: &str, span: Span) -> Range<usize> {
+    let line = |offset: usize| source[..offset].matches('\n').count();
+    let end = match span.end > span.start {
+        true => line(span.end - 1),
+        false => line(span.end),
+    };
+    line(span.start)..end + 1
+}
+
+/// The source of a file with only the selected parts of it formatted, given the syntax tree and the
+/// formatted code of the whole file.
+pub fn format_selections(
+    unit: &TranslationUnit,
+    source: &str,
+    formatted: &str,
+    selections: &[Selection],
+) -> String {
+    let mut units = Units { spans: Vec::new() };
+    units.visit_translation_unit(unit);
+
+    let ranges: Vec<Range<usize>> = selections
+        .iter()
+        .map(|selection| lines(source, widen(&units.spans, selection.span(source))))
+        .collect();
+    apply_within(source, formatted, &ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::FormatConfig;
+    use crate::formatter::formatter::format;
+    use crate::formatter::range::{format_selections, Selection};
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    fn formatted(input: &str, selection: Selection) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let unit = Parser::new(tokens).parse_translation_unit();
+
+        let formatted = format(&unit, &FormatConfig::default());
+        format_selections(&unit, input, &formatted, &[selection])
+    }
+
+    #[test]
+    fn selections() {
+        assert_eq!(
+            Selection::lines("120:180"),
+            Some(Selection::Lines(120, 180))
+        );
+        assert_eq!(Selection::lines("0:2"), None);
+        assert_eq!(Selection::lines("3:2"), None);
+        assert_eq!(Selection::lines("3"), None);
+    }
+
+    #[test]
+    fn only_selected_units_are_formatted() {
+        let input = "int  a;\n\
+                     int f(int x) {\n\
+                     \x20 if (x)\n\
+                     \x20   {\n\
+                     \x20     return  1;\n\
+                     \x20   }\n\
+                     \x20 return  0;\n\
+                     }\n\
+                     int  b;\n";
+
+        // The statement on the selected line is formatted as a whole, while the lines around it
+        // keep their indentation.
+        assert_eq!(
+            formatted(input, Selection::Lines(4, 4)),
+            "int  a;\n\
+             int f(int x) {\n\
+             \x20   if (x) {\n\
+             \x20       return 1;\n\
+             \x20   }\n\
+             \x20 return  0;\n\
+             }\n\
+             int  b;\n"
+        );
+        assert_eq!(
+            formatted(input, Selection::Bytes(input.len() - 4, Some(1))),
+            "int  a;\n\
+             int f(int x) {\n\
+             \x20 if (x)\n\
+             \x20   {\n\
+             \x20     return  1;\n\
+             \x20   }\n\
+             \x20 return  0;\n\
+             }\n\
+             int b;\n"
+        );
+        assert_eq!(
+            formatted(input, Selection::Bytes(0, None)),
+            formatted(input, Selection::Lines(1, 100))
+        );
+        assert_eq!(
+            formatted(input, Selection::Lines(7, 7)),
+            "int  a;\n\
+             int f(int x) {\n\
+             \x20 if (x)\n\
+             \x20   {\n\
+             \x20     return  1;\n\
+             \x20   }\n\
+             \x20   return 0;\n\
+             }\n\
+             int  b;\n"
+        );
+    }
+
+    #[test]
+    fn typed_characters_format_around_them() {
+        let input = "int f(int x) {\n\
+                     \x20 if (x)\n\
+                     \x20   {\n\
+                     \x20     return  1;\n\
+                     \x20   }\n\
+                     \x20 return  0;\n\
+                     }\n\
+                     char *s = \"}\";\n";
+
+        // The block which a brace closes is formatted with the statement it belongs to.
+        let brace = input.find("}\n  return").unwrap();
+        assert_eq!(
+            formatted(input, Selection::Typed(brace)),
+            "int f(int x) {\n\
+             \x20   if (x) {\n\
+             \x20       return 1;\n\
+             \x20   }\n\
+             \x20 return  0;\n\
+             }\n\
+             char *s = \"}\";\n"
+        );
+        let semicolon = input.find("0;").unwrap() + 1;
+        assert_eq!(
+            formatted(input, Selection::Typed(semicolon)),
+            "int f(int x) {\n\
+             \x20 if (x)\n\
+             \x20   {\n\
+             \x20     return  1;\n\
+             \x20   }\n\
+             \x20   return 0;\n\
+             }\n\
+             char *s = \"}\";\n"
+        );
+        // A brace in a string literal closes nothing.
+        let quoted = input.rfind('}').unwrap();
+        assert_eq!(
+            formatted(input, Selection::Typed(quoted)),
+            formatted(input, Selection::Lines(8, 8))
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Regions where formatting is turned off by comments. The items in between `// cfmt: off` and
+//! `// cfmt: on` in the same block, and the item right after a `// cfmt: skip` or with one on its
+//! line, are replaced by their original text, just like the regions which could not be parsed.
+//! Struct members cannot be suppressed, as there is nothing to replace them with.
+
+use crate::lexer::span::Span;
+use crate::parser::ast::{
+    Annotated, Block, Comment, ExternalDeclaration, Nodes, Statement, TranslationUnit,
+};
+use crate::parser::visit_mut::{
+    walk_block_mut, walk_external_declaration_mut, walk_statement_mut, walk_translation_unit_mut,
+    VisitMut,
+};
+
+/// A comment which turns formatting on or off.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Switch {
+    Off,
+    On,
+    Skip,
+}
+
+fn switch(comment: &Comment) -> Option<Switch> {
+    match comment.text.split_whitespace().collect::<String>().as_str() {
+        "cfmt:off" => Some(Switch::Off),
+        "cfmt:on" => Some(Switch::On),
+        "cfmt:skip" => Some(Switch::Skip),
+        _ => None,
+    }
+}
+
+/// The original text of a region. If only whitespace precedes it on its first line, that
+/// indentation is included, so that the whole region keeps its layout.
+fn original(source: &str, span: Span) -> String {
+    let line_start = source[..span.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let start = if source[line_start..span.start].trim().is_empty() {
+        line_start
+    } else {
+        span.start
+    };
+
+    source[start..span.end].to_string()
+}
+
+/// The end of the comments which follow the code of a region on its last line, of which there
+/// are at most as many as the trailing comments of its last item.
+fn trailing_end(source: &str, end: usize, comments: usize) -> usize {
+    let mut end = end;
+    for _ in 0..comments {
+        let rest = &source[end..];
+        let start = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        let comment = &rest[start..];
+        let length = if comment.starts_with("//") {
+            comment.find('\n').unwrap_or(comment.len())
+        } else if let Some(body) = comment.strip_prefix("/*") {
+            match body.find("*/") {
+                Some(close) => close + 4,
+                None => break,
+            }
+        } else {
+            break;
+        };
+        end += start + length;
+    }
+    end
+}
+
+/// Replace the suppressed items in a list with their original text. Consecutive suppressed items
+/// are merged into one, so that the text in between them is kept as well.
+fn suppress<T>(items: &mut Vec<Annotated<T>>, source: &str, verbatim: impl Fn(String) -> T) {
+    let mut off = false;
+    // Whether the last item is a region which the next one may be merged into.
+    let mut open = false;
+    let mut suppressed: Vec<Annotated<T>> = Vec::with_capacity(items.len());
+
+    for mut item in items.drain(..) {
+        let mut skip = false;
+        for comment in &item.leading {
+            match switch(comment) {
+                Some(Switch::Off) => off = true,
+                Some(Switch::On) => off = false,
+                Some(Switch::Skip) => skip = true,
+                None => {}
+            }
+        }
+        skip |= item
+            .trailing
+            .iter()
+            .any(|comment| switch(comment) == Some(Switch::Skip));
+
+        let region = off;
+        for comment in &item.trailing {
+            match switch(comment) {
+                Some(Switch::Off) => off = true,
+                Some(Switch::On) => off = false,
+                _ => {}
+            }
+        }
+
+        // The comments after the code of a region are kept as written along with it.
+        let end = trailing_end(source, item.span.end, item.trailing.len());
+        match suppressed.last_mut() {
+            Some(last) if open && region => {
+                last.span = Span::new(last.span.start, end);
+                last.node = verbatim(original(source, last.span));
+                last.trailing = Vec::new();
+            }
+            _ if region || skip => {
+                item.span = Span::new(item.span.start, end);
+                item.node = verbatim(original(source, item.span));
+                item.trailing = Vec::new();
+                suppressed.push(item);
+            }
+            _ => suppressed.push(item),
+        }
+        open = region;
+    }
+
+    *items = suppressed;
+}
+
+struct Suppression<'a> {
+    source: &'a str,
+}
+
+impl VisitMut for Suppression<'_> {
+    fn visit_translation_unit_mut(&mut self, node: &mut TranslationUnit) {
+        suppress(&mut node.items, self.source, ExternalDeclaration::Verbatim);
+        walk_translation_unit_mut(self, node);
+    }
+
+    fn visit_external_declaration_mut(
+        &mut self,
+        nodes: &mut Nodes,
+        node: &mut ExternalDeclaration,
+    ) {
+        if let ExternalDeclaration::Conditional(group) = node {
+            for branch in &mut group.branches {
+                suppress(
+                    &mut branch.items,
+                    self.source,
+                    ExternalDeclaration::Verbatim,
+                );
+            }
+        }
+        walk_external_declaration_mut(self, nodes, node);
+    }
+
+    fn visit_block_mut(&mut self, nodes: &mut Nodes, node: &mut Block) {
+        suppress(&mut node.statements, self.source, Statement::Verbatim);
+        walk_block_mut(self, nodes, node);
+    }
+
+    fn visit_statement_mut(&mut self, nodes: &mut Nodes, node: &mut Statement) {
+        if let Statement::Conditional(group) = node {
+            for branch in &mut group.branches {
+                suppress(&mut branch.items, self.source, Statement::Verbatim);
+            }
+        }
+        walk_statement_mut(self, nodes, node);
+    }
+}
+
+/// Replace the items where formatting is turned off in a translation unit with the text of
+/// `source` they cover.
+pub fn suppress_formatting(unit: &mut TranslationUnit, source: &str) {
+    Suppression { source }.visit_translation_unit_mut(unit);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::FormatConfig;
+    use crate::formatter::formatter::format;
+    use crate::formatter::suppression::suppress_formatting;
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    fn formatted(input: &str) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+
+        suppress_formatting(&mut unit, input);
+        format(&unit, &FormatConfig::default())
+    }
+
+    #[test]
+    fn regions_are_kept_as_written() {
+        let region = "int table[] = {\n  1,   2,\n  30, 40,\n};\n\
+                      int   b;  /* same */  // line\n";
+        let input = format!(
+            "int a  =  1;\n\
+             // cfmt: off\n\
+             {}\
+             // cfmt: on\n\
+             int   c;\n\
+             void f() {{\n\
+             \x20 x  =  1;  // cfmt: skip\n\
+             \x20 y  =  2;\n\
+             \x20 /* cfmt: skip */\n\
+             \x20    z  =  3;\n\
+             }}\n",
+            region
+        );
+
+        assert_eq!(
+            formatted(&input),
+            format!(
+                "int a = 1;\n\
+                 // cfmt: off\n\
+                 {}\
+                 // cfmt: on\n\
+                 int c;\n\
+                 \n\
+                 void f() {{\n\
+                 \x20 x  =  1;  // cfmt: skip\n\
+                 \x20   y = 2;\n\
+                 \x20   /* cfmt: skip */\n\
+                 \x20    z  =  3;\n\
+                 }}\n",
+                region
+            )
+        );
+    }
+}
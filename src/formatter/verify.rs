@@ -0,0 +1,284 @@
+//! Checks on the output of the formatter: formatting must never change the meaning of the code,
+//! and it must be idempotent, so that formatting code which was already formatted leaves it
+//! exactly as it is.
+
+use crate::diagnostics::diagnostic::{Diagnostic, Diagnostics};
+use crate::formatter::config::{FormatConfig, HeaderGuards, IncludeSorting};
+use crate::formatter::formatter::number_literal;
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::lexer::Lexer;
+use crate::lexer::span::Span;
+use crate::lexer::token::{Token, TokenKeyword};
+
+/// The tokens which make up the meaning of code, together with their spans. Comments are left
+/// out, adjacent string literals are joined, and number literals and preprocessor lines are
+/// written in a single way, so that the changes the formatter makes to them on purpose do not
+/// count. Text which cannot be lexed is kept as an invalid token, so that it must stay the same.
+fn meaningful<'a>(source: &'a str, config: &FormatConfig) -> Vec<(Token<'a>, Span)> {
+    // Sorting includes and rewriting header guards change the preprocessor lines on purpose.
+    let directives = config.include_sorting == IncludeSorting::Keep
+        && config.header_guards == HeaderGuards::Keep;
+    let mut tokens: Vec<(Token, Span)> = Vec::new();
+
+    for lexeme in Lexer::new(source).recovering_lexemes(&mut Diagnostics::new()) {
+        let token = match lexeme.token {
+            Token::SlashSlash(_) | Token::SlashStar(_) => continue,
+            Token::Directive(_) if !directives => continue,
+            Token::Str(text) => match tokens.last_mut() {
+                Some((Token::Str(previous), span)) => {
+                    previous.to_mut().push_str(&text);
+                    span.end = lexeme.span.end;
+                    continue;
+                }
+                _ => Token::Str(text),
+            },
+            Token::Number(number) => Token::Number(number_literal(&number).into()),
+            Token::Directive(text) => Token::Directive(normalize_whitespace(&text).into()),
+            Token::Disabled(text) => Token::Disabled(normalize_whitespace(&text).into()),
+            Token::Invalid(text) => Token::Invalid(normalize_whitespace(&text).into()),
+            token => token,
+        };
+        tokens.push((token, lexeme.span));
+    }
+
+    // An empty statement is added after a label at the end of a block, and parentheses around the
+    // operand of `sizeof` if configured.
+    let mut i = 0;
+    while i < tokens.len() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i].0);
+        let next = tokens.get(i + 1).map(|(token, _)| token);
+        match (previous, &tokens[i].0, next) {
+            (Some(Token::Colon), Token::Semicolon, Some(Token::Brace(Right))) => {
+                tokens.remove(i);
+            }
+            (_, Token::Keyword(TokenKeyword::Sizeof), Some(Token::Parenthesis(Left)))
+                if config.sizeof_parentheses =>
+            {
+                let mut depth = 0;
+                let close = tokens[i + 1..].iter().position(|(token, _)| {
+                    match token {
+                        Token::Parenthesis(Left) => depth += 1,
+                        Token::Parenthesis(Right) => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                });
+                if let Some(close) = close {
+                    tokens.remove(i + 1 + close);
+                    tokens.remove(i + 1);
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    tokens
+}
+
+/// Collapse the whitespace of a preprocessor line, including escaped line breaks, into single
+/// spaces.
+fn normalize_whitespace(text: &str) -> String {
+    let text = text.replace("\\\r\n", " ").replace("\\\n", " ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Compare the tokens of the source with those of the formatted code, returning an error at the
+/// first token of the source which the formatter would change. The error refers to the source.
+pub fn verify_tokens(source: &str, formatted: &str, config: &FormatConfig) -> Option<Diagnostic> {
+    let before = meaningful(source, config);
+    let after = meaningful(formatted, config);
+
+    let end = Span::new(source.len(), source.len());
+    for i in 0..before.len().max(after.len()) {
+        let message = match (before.get(i), after.get(i)) {
+            (Some((a, _)), Some((b, _))) if a == b => continue,
+            (Some((a, _)), Some((b, _))) => format!("formatting would change `{}` into `{}`", a, b),
+            (Some((a, _)), None) => format!("formatting would remove `{}`", a),
+            (None, Some((b, _))) => format!("formatting would add `{}` here", b),
+            (None, None) => unreachable!("the index is within one of the streams"),
+        };
+        let span = before.get(i).map_or(end, |(_, span)| *span);
+        return Some(Diagnostic::error(span, message));
+    }
+
+    None
+}
+
+/// Compare formatted code with the result of formatting it once more, returning an error at the
+/// first line on which they differ. The error refers to the formatted code, not the source file.
+pub fn verify_idempotency(formatted: &str, again: &str) -> Option<Diagnostic> {
+    if formatted == again {
+        return None;
+    }
+
+    let offset = formatted
+        .bytes()
+        .zip(again.bytes())
+        .position(|(a, b)| a != b)
+        .unwrap_or(formatted.len().min(again.len()));
+    let start = formatted[..offset]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line = again[start..].lines().next().unwrap_or("");
+
+    Some(Diagnostic::error(
+        Span::new(offset, offset),
+        format!(
+            "formatting is not idempotent: formatting the output again turns this line into `{}`",
+            line
+        ),
+    ))
+}
+
+/// Compare a file with its formatted code, returning an error at the start of the first line which
+/// formatting would change.
+pub fn check_formatted(contents: &str, formatted: &str) -> Option<Diagnostic> {
+    if contents == formatted {
+        return None;
+    }
+
+    let offset = contents
+        .bytes()
+        .zip(formatted.bytes())
+        .position(|(a, b)| a != b)
+        .unwrap_or(contents.len().min(formatted.len()));
+    let start = contents.as_bytes()[..offset]
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+
+    Some(Diagnostic::error(
+        Span::new(start, start),
+        "the file is not formatted".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{
+        ArgumentWrapping, BraceStyle, FormatConfig, InitializerLayout, PointerAlignment,
+    };
+    use crate::formatter::formatter::format;
+    use crate::formatter::suppression::suppress_formatting;
+    use crate::formatter::verify::{check_formatted, verify_idempotency, verify_tokens};
+    use crate::lexer::lexer::Lexer;
+    use crate::lexer::span::Span;
+    use crate::parser::parser::Parser;
+    use crate::parser::salvage::salvage;
+
+    /// Source files which together cover most of what the formatter does.
+    const CORPUS: &[&str] = &[
+        "#include <stdio.h>\n#include \"list.h\"\n\n#define MAX(a, b) ((a) > (b) ? (a) : (b))\n\
+         static const char*names[]={\"first\",\"second\",\"third\",\"fourth\",\"fifth\",\"sixth\"};\n",
+        "int main(int argc,char**argv){if(argc>1){printf(\"%s\\n\",argv[1]);}else if(argc==1)\
+         return 1;else{return 2;}for(int i=0;i<argc;i++)continue;while(0);do{}while(0);}\n",
+        "struct point{int x;int y;};typedef union{long l;double d;}number;\
+         enum color{RED=1,GREEN,BLUE=GREEN<<2};\n",
+        "void f(void){switch(x){case 1:{y();break;}case 2:default:z();}goto end;end:;}\n",
+        "// A comment before a function.\nint g(int a /* first */, int b) // trailing\n{\n\n\
+         return a*b+some_long_function_name(a,b,a+b,a-b,a*b)-another_function(b,a);\n}\n",
+        "#ifdef __cplusplus\nextern \"C\" {\n#endif\n#if 0\nnot  C  at  all\n#else\n\
+         # if defined(X)\nint x;\n# endif\n#endif\n#ifdef __cplusplus\n}\n#endif\n",
+        "void h(){int matrix[2][3]={{1,2,3},{4,5,6}};struct point p={.x=1,.y=2};\
+         x=(int)sizeof(long)+sizeof x;p.x+=q->y;a=b?c:d;}\n",
+        "/*\n * A block comment\n *   with indentation.\n */\nint y; // cfmt: skip\n\
+         int   kept  ;\n// cfmt: off\nint  z ;\n// cfmt: on\n",
+    ];
+
+    fn format_str(input: &str, config: &FormatConfig) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+        salvage(&mut unit, input);
+        suppress_formatting(&mut unit, input);
+        format(&unit, config)
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        let configs = [
+            FormatConfig::default(),
+            FormatConfig {
+                max_line_length: 40,
+                brace_style: BraceStyle::Allman,
+                pointer_alignment: PointerAlignment::Left,
+                argument_wrapping: ArgumentWrapping::BinPack,
+                initializer_layout: InitializerLayout::Columns,
+                reflow_comments: true,
+                split_string_literals: true,
+                ..FormatConfig::default()
+            },
+            FormatConfig {
+                indent_width: 2,
+                use_tabs: true,
+                max_line_length: 60,
+                argument_wrapping: ArgumentWrapping::Align,
+                align_consecutive_declarations: true,
+                align_consecutive_assignments: true,
+                align_trailing_comments: true,
+                ..FormatConfig::default()
+            },
+        ];
+
+        for config in &configs {
+            for input in CORPUS {
+                let formatted = format_str(input, config);
+                let again = format_str(&formatted, config);
+                if let Some(error) = verify_idempotency(&formatted, &again) {
+                    panic!("{}", error.render("corpus", &formatted));
+                }
+                if let Some(error) = verify_tokens(input, &formatted, config) {
+                    panic!("{}", error.render("corpus", input));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn changes_to_the_tokens_are_reported() {
+        let config = FormatConfig {
+            normalize_number_literals: true,
+            sizeof_parentheses: true,
+            ..FormatConfig::default()
+        };
+        let source =
+            "/* a */ char *s = \"a\" \"b\"; int n = 0xff + sizeof x;\n#  define  X \\\n 1\n";
+        let formatted = "char *s = \"ab\";\nint n = 0xFF + sizeof(x);\n#define X 1\n";
+        assert_eq!(verify_tokens(source, formatted, &config), None);
+
+        let changed = "char *s = \"ab\";\nint n = 0xFF - sizeof(x);\n#define X 1\n";
+        let error = verify_tokens(source, changed, &config).unwrap();
+        assert_eq!(
+            error.render("a.c", source),
+            format!(
+                "a.c:1:41: error: formatting would change `+` into `-`\n{}\n{}^\n",
+                source.lines().next().unwrap(),
+                " ".repeat(40)
+            )
+        );
+
+        let error = verify_tokens("x;", "x;;", &FormatConfig::default()).unwrap();
+        assert_eq!(error.message, "formatting would add `;` here");
+    }
+
+    #[test]
+    fn differences_are_reported_on_their_line() {
+        assert_eq!(verify_idempotency("int x;\n", "int x;\n"), None);
+
+        let error = verify_idempotency("int x;\nint  y;\n", "int x;\nint y;\n").unwrap();
+        assert_eq!(
+            error.render("a.c", "int x;\nint  y;\n"),
+            "a.c:2:5: error: formatting is not idempotent: formatting the output again turns \
+             this line into `int y;`\nint  y;\n    ^\n"
+        );
+    }
+
+    #[test]
+    fn unformatted_files_are_reported_at_the_first_changed_line() {
+        assert_eq!(check_formatted("int a;\n", "int a;\n"), None);
+
+        let error = check_formatted("int a;\nint  b;\n", "int a;\nint b;\n").unwrap();
+        assert_eq!(error.span, Span::new(7, 7));
+        assert_eq!(error.message, "the file is not formatted");
+    }
+}
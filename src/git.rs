@@ -0,0 +1,135 @@
+//! Finding the lines which changed relative to a Git revision, or in a unified diff, so that only
+//! those lines are formatted. This lets a project adopt the formatter for the code it touches,
+//! without reformatting everything at once.
+
+use crate::formatter::range::Selection;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The lines which changed in every file, by the absolute path of the file.
+pub type Changes = HashMap<PathBuf, Vec<Selection>>;
+
+/// Read the lines which a unified diff adds or changes, from the headers of its hunks. The paths
+/// in the diff are taken relative to a directory, without the `b/` prefix Git gives them. Files
+/// which the diff removes, or which do not exist, are left out.
+pub fn parse_diff(diff: &str, root: &Path) -> Changes {
+    let mut changes = Changes::new();
+    let mut file = None;
+    let mut previous = "";
+
+    for line in diff.lines() {
+        // The name of a file follows the line with its old name, which tells it apart from an
+        // added line starting with `++`.
+        let name = line
+            .strip_prefix("+++ ")
+            .filter(|_| previous.starts_with("--- "));
+        previous = line;
+
+        if let Some(name) = name {
+            // Git ends the name with a tab when it has spaces in it.
+            let name = name.split('\t').next().unwrap_or(name);
+            let name = name.strip_prefix("b/").unwrap_or(name);
+            file = match name {
+                "/dev/null" => None,
+                _ => fs::canonicalize(root.join(name)).ok(),
+            };
+            if let Some(file) = &file {
+                changes.entry(file.clone()).or_default();
+            }
+        } else if let (Some(hunk), Some(file)) = (line.strip_prefix("@@ "), &file) {
+            if let Some(selection) = hunk_lines(hunk) {
+                changes.entry(file.clone()).or_default().push(selection);
+            }
+        }
+    }
+
+    changes
+}
+
+/// The lines of the new file covered by the header of a hunk, as in `-12,3 +12,4 @@`. A hunk which
+/// only removes lines covers none.
+fn hunk_lines(hunk: &str) -> Option<Selection> {
+    let new = hunk.split(' ').find_map(|range| range.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+
+    match count {
+        0 => None,
+        _ => Some(Selection::Lines(start, start + count - 1)),
+    }
+}
+
+/// Run git in the current directory, returning what it writes to the standard output, or to the
+/// standard error if it fails.
+pub fn git(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|error| format!("could not run git: {}", error))?;
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).into_owned()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+/// The lines which changed in the working tree of the repository around the current directory,
+/// relative to a revision such as `origin/main`.
+pub fn changed_since(base: &str) -> Result<Changes, String> {
+    let root = git(&["rev-parse", "--show-toplevel"])?;
+    let diff = git(&[
+        "diff",
+        "--unified=0",
+        "--no-color",
+        "--no-ext-diff",
+        "--src-prefix=a/",
+        "--dst-prefix=b/",
+        base,
+        "--",
+    ])?;
+    Ok(parse_diff(&diff, Path::new(root.trim_end())))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::range::Selection;
+    use crate::git::parse_diff;
+    use std::fs;
+
+    #[test]
+    fn hunks_are_read_as_lines() {
+        let root = std::env::temp_dir().join(format!("cfmt-git-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/a.c"), "").unwrap();
+        fs::write(root.join("b.h"), "").unwrap();
+
+        let diff = "diff --git a/src/a.c b/src/a.c\n\
+                    --- a/src/a.c\n\
+                    +++ b/src/a.c\n\
+                    @@ -3 +3 @@ int main(void)\n\
+                    -old\n\
+                    +new\n\
+                    @@ -10,2 +10,0 @@\n\
+                    @@ -20,0 +19,3 @@\n\
+                    --- /dev/null\n\
+                    +++ b/b.h\n\
+                    @@ -0,0 +1,2 @@\n\
+                    --- a/gone.c\n\
+                    +++ /dev/null\n\
+                    @@ -1,2 +0,0 @@\n";
+        let changes = parse_diff(diff, &root);
+
+        let root = fs::canonicalize(&root).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            changes[&root.join("src/a.c")],
+            vec![Selection::Lines(3, 3), Selection::Lines(19, 21)]
+        );
+        assert_eq!(changes[&root.join("b.h")], vec![Selection::Lines(1, 2)]);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
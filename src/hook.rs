@@ -0,0 +1,99 @@
+//! The `cfmt install-hook` subcommand, which installs a Git pre-commit hook that checks the staged
+//! C files, or formats them. The hook works on the contents in the index, which are what gets
+//! committed, rather than on the files in the working tree.
+
+use crate::git::git;
+use std::fs;
+use std::path::PathBuf;
+
+/// The line which marks a hook as written by the formatter, so that it can be replaced.
+const MARKER: &str = "# Installed by `cfmt install-hook`.";
+
+/// The pre-commit hook. It either checks that the staged files are formatted, or formats them in
+/// place and stages the result. A file which also has changes that are not staged is only checked,
+/// since formatting it in place would stage those changes too.
+pub fn script(in_place: bool) -> String {
+    let action = match in_place {
+        false => "    check \"$file\"\n",
+        true => {
+            "    if git diff --quiet -- \"$file\"; then\n\
+                 \x20       \"$cfmt\" --in-place \"$file\" && git add -- \"$file\" || status=1\n\
+                 \x20   else\n\
+                 \x20       check \"$file\"\n\
+                 \x20   fi\n"
+        }
+    };
+
+    format!(
+        "#!/bin/sh\n\
+         {}\n\
+         cfmt=\"${{CFMT:-cfmt}}\"\n\
+         status=0\n\
+         \n\
+         # The staged contents are checked, under the name of the file they are staged for.\n\
+         check() {{\n\
+         \x20   git show \":$1\" | \"$cfmt\" --check --assume-filename \"$1\" || status=1\n\
+         }}\n\
+         \n\
+         set -f\n\
+         IFS='\n'\n\
+         for file in $(git -c core.quotePath=false diff --cached --name-only --diff-filter=ACMR \
+         -- '*.c' '*.h'); do\n\
+         {}\
+         done\n\
+         \n\
+         if [ \"$status\" -ne 0 ]; then\n\
+         \x20   echo \"cfmt: format the files above, or commit with --no-verify\" >&2\n\
+         fi\n\
+         exit \"$status\"\n",
+        MARKER, action
+    )
+}
+
+/// Write the pre-commit hook into the hooks directory of the repository around the current
+/// directory, returning its path. An existing hook which was not written by the formatter is only
+/// replaced when forced to.
+pub fn install(script: &str, force: bool) -> Result<PathBuf, String> {
+    let hooks = PathBuf::from(git(&["rev-parse", "--git-path", "hooks"])?.trim_end());
+    let path = hooks.join("pre-commit");
+
+    if let Ok(existing) = fs::read_to_string(&path) {
+        if !force && !existing.contains(MARKER) {
+            return Err(format!(
+                "{} already exists, use --force to replace it",
+                path.display()
+            ));
+        }
+    }
+
+    fs::create_dir_all(&hooks)
+        .and_then(|_| fs::write(&path, script))
+        .map_err(|error| format!("could not write {}: {}", path.display(), error))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+            .map_err(|error| format!("could not make {} executable: {}", path.display(), error))?;
+    }
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hook::{script, MARKER};
+
+    #[test]
+    fn hooks_check_the_index() {
+        let check = script(false);
+        assert!(check.starts_with(&format!("#!/bin/sh\n{}\n", MARKER)));
+        assert!(check.contains("git show \":$1\" | \"$cfmt\" --check --assume-filename \"$1\""));
+        assert!(check.contains("--cached --name-only --diff-filter=ACMR -- '*.c' '*.h'"));
+        assert!(!check.contains("--in-place"));
+
+        let fix = script(true);
+        assert!(fix.contains("    if git diff --quiet -- \"$file\"; then\n"));
+        assert!(fix.contains("\"$cfmt\" --in-place \"$file\" && git add -- \"$file\""));
+    }
+}
@@ -0,0 +1,244 @@
+//! Ignore files in the syntax of `.gitignore`, which keep paths such as build output and vendored
+//! code out of a recursive search for files to format.
+
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// The ignore files which are read in every directory. Rules from later files take precedence, so
+/// that `.cfmtignore` can override `.gitignore`.
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".cfmtignore"];
+
+/// A single line of an ignore file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Rule {
+    /// The directory of the ignore file, which anchored patterns are relative to.
+    base: PathBuf,
+    pattern: Vec<char>,
+    /// Whether the rule starts with `!`, which includes paths an earlier rule ignored.
+    negated: bool,
+    /// Whether the rule ends with `/`, which makes it only match directories.
+    directory_only: bool,
+    /// Whether the pattern contains a `/`, which makes it match the path relative to the base
+    /// rather than only the name of a file or directory.
+    anchored: bool,
+}
+
+/// The rules of the ignore files which apply to a directory, from the outermost to the innermost.
+#[derive(Clone, Debug, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    pub fn new() -> IgnoreRules {
+        IgnoreRules::default()
+    }
+
+    /// Add the rules of the ignore files in a directory, if it has any.
+    pub fn read(&mut self, directory: &Path) -> io::Result<()> {
+        for name in IGNORE_FILES {
+            match fs::read_to_string(directory.join(name)) {
+                Ok(text) => self.parse(directory, &text),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the rules of an ignore file in the given directory. Blank lines and comments starting
+    /// with `#` are skipped, as is trailing whitespace which is not escaped with a backslash.
+    pub fn parse(&mut self, base: &Path, text: &str) {
+        for line in text.lines() {
+            let mut line = line.trim_end_matches('\r');
+            while line.ends_with(' ') && !line.ends_with("\\ ") {
+                line = &line[..line.len() - 1];
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let negated = line.starts_with('!');
+            if negated {
+                line = &line[1..];
+            }
+            let directory_only = line.ends_with('/');
+            let line = line.trim_end_matches('/');
+            if line.is_empty() {
+                continue;
+            }
+
+            let anchored = line.contains('/');
+            self.rules.push(Rule {
+                base: base.to_path_buf(),
+                pattern: line.trim_start_matches('/').chars().collect(),
+                negated,
+                directory_only,
+                anchored,
+            });
+        }
+    }
+
+    /// The number of rules, which can be passed to `truncate` to drop the rules which were added
+    /// after it.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.rules.truncate(len);
+    }
+
+    /// Whether a path is ignored, which is decided by the last rule that matches it.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        for rule in self.rules.iter().rev() {
+            if rule.directory_only && !is_dir {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&rule.base) else {
+                continue;
+            };
+
+            let text: Vec<char> = if rule.anchored {
+                let components: Vec<_> = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect();
+                components.join("/").chars().collect()
+            } else {
+                match relative.file_name() {
+                    Some(name) => name.to_string_lossy().chars().collect(),
+                    None => continue,
+                }
+            };
+
+            if matches(&rule.pattern, &text) {
+                return !rule.negated;
+            }
+        }
+
+        false
+    }
+}
+
+/// Match a path against a glob, where `*` and `?` do not match a `/` but `**` does. A `**/` also
+/// matches no directories at all, so that `**/build` matches `build` itself.
+pub fn matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] => (0..=text.len())
+            .filter(|&i| i == 0 || text[i - 1] == '/')
+            .any(|i| matches(rest, &text[i..])),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+        ['*', rest @ ..] => {
+            let end = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+            (0..=end).any(|i| matches(rest, &text[i..]))
+        }
+        ['?', rest @ ..] => matches!(text, [c, ..] if *c != '/') && matches(rest, &text[1..]),
+        ['[', rest @ ..] => match (class(rest), text) {
+            (Some((matched, rest)), [c, text @ ..]) => {
+                *c != '/' && matched(*c) && matches(rest, text)
+            }
+            (Some(_), []) => false,
+            // An unclosed bracket is taken literally.
+            (None, [c, text @ ..]) => *c == '[' && matches(rest, text),
+            (None, []) => false,
+        },
+        ['\\', c, rest @ ..] | [c, rest @ ..] => {
+            matches!(text, [first, ..] if first == c) && matches(rest, &text[1..])
+        }
+    }
+}
+
+/// Parse a character class such as `[a-z]` or `[!0-9]` after its opening bracket, returning a
+/// predicate for the characters it matches and the rest of the pattern after the closing bracket.
+fn class(pattern: &[char]) -> Option<(impl Fn(char) -> bool + '_, &[char])> {
+    let (negated, body) = match pattern {
+        ['!' | '^', body @ ..] => (true, body),
+        body => (false, body),
+    };
+    // A `]` right after the opening bracket is part of the class.
+    let close = body.iter().skip(1).position(|&c| c == ']').map(|i| i + 1)?;
+    let members = &body[..close];
+
+    let matched = move |c: char| {
+        let mut i = 0;
+        let mut found = false;
+        while i < members.len() {
+            if i + 2 < members.len() && members[i + 1] == '-' {
+                found |= (members[i]..=members[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= members[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+
+    Some((matched, &body[close + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ignore::{matches, IgnoreRules};
+    use std::path::Path;
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let text: Vec<char> = text.chars().collect();
+        matches(&pattern, &text)
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob("*.o", "list.o"));
+        assert!(!glob("*.o", "dir/list.o"));
+        assert!(glob("list.?", "list.c"));
+        assert!(glob("[a-c]x[!0-9]", "bxy"));
+        assert!(!glob("[a-c]x[!0-9]", "bx1"));
+        assert!(glob("**/build", "build"));
+        assert!(glob("**/build", "a/b/build"));
+        assert!(glob("a/**/b", "a/b"));
+        assert!(glob("a/**/b", "a/x/y/b"));
+        assert!(glob("vendor/**", "vendor/lib/x.c"));
+        assert!(!glob("vendor/**", "vendor"));
+        assert!(glob("\\*literal", "*literal"));
+        assert!(glob("[unclosed", "[unclosed"));
+    }
+
+    #[test]
+    fn rules() {
+        let root = Path::new("/project");
+        let mut rules = IgnoreRules::new();
+        rules.parse(
+            root,
+            "# Build output\nbuild/\n*.gen.c\n!keep.gen.c\n/generated.h  \nthird_party/vendored\n",
+        );
+
+        assert!(rules.is_ignored(&root.join("build"), true));
+        assert!(!rules.is_ignored(&root.join("build"), false));
+        assert!(rules.is_ignored(&root.join("src/build"), true));
+        assert!(rules.is_ignored(&root.join("src/parser.gen.c"), false));
+        assert!(!rules.is_ignored(&root.join("src/keep.gen.c"), false));
+        assert!(rules.is_ignored(&root.join("generated.h"), false));
+        assert!(!rules.is_ignored(&root.join("src/generated.h"), false));
+        assert!(rules.is_ignored(&root.join("third_party/vendored"), true));
+        assert!(!rules.is_ignored(&root.join("src/third_party/vendored"), true));
+        assert!(!rules.is_ignored(&root.join("main.c"), false));
+
+        // Rules of nested ignore files only apply below their own directory.
+        let len = rules.len();
+        rules.parse(&root.join("src"), "!parser.gen.c\nmain.c\n");
+        assert!(!rules.is_ignored(&root.join("src/parser.gen.c"), false));
+        assert!(rules.is_ignored(&root.join("src/main.c"), false));
+        assert!(!rules.is_ignored(&root.join("main.c"), false));
+        rules.truncate(len);
+        assert!(rules.is_ignored(&root.join("src/parser.gen.c"), false));
+    }
+}
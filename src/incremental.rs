@@ -0,0 +1,401 @@
+//! Formatting a document again after every edit, as editors do while the user types, without
+//! lexing, parsing and printing all of it every time. The document is split into paragraphs: runs
+//! of items at file scope which end with a blank line, where nothing that comes before can change
+//! how the items after it are parsed or printed. An edit only reparses the paragraphs it touches,
+//! and only the code around the edit is formatted, as with a selection.
+
+use crate::diagnostics::diagnostic::{Diagnostic, Diagnostics};
+use crate::diff::{replacements, Replacement};
+use crate::formatter::config::FormatConfig;
+use crate::formatter::fallback::format_tokens;
+use crate::formatter::formatter::format;
+use crate::formatter::guard::normalize_header_guard;
+use crate::formatter::includes::sort_includes;
+use crate::formatter::range::{format_selections, Selection};
+use crate::formatter::suppression::suppress_formatting;
+use crate::formatter::verify::verify_tokens;
+use crate::lex;
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::span::Span;
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{Lexeme, Token};
+use crate::parser::ast::TranslationUnit;
+use crate::parser::parser::Parser;
+use crate::parser::salvage::salvage;
+use crate::rewrite::typedefs;
+use std::mem;
+
+/// A document which is edited and formatted around every edit.
+#[derive(Debug)]
+pub struct Incremental {
+    source: String,
+    file_path: String,
+    config: FormatConfig,
+    /// The paragraphs, which cover the whole source in order.
+    paragraphs: Vec<Paragraph>,
+}
+
+/// A run of items at file scope, which is parsed on its own. Its spans are relative to its start.
+#[derive(Debug)]
+struct Paragraph {
+    /// Where the paragraph starts in the source. It ends where the next one starts.
+    start: usize,
+    /// The syntax tree, or the errors which keep the paragraph from being formatted.
+    unit: Result<TranslationUnit, Vec<Diagnostic>>,
+    /// The names which the paragraph introduces with `typedef`.
+    typedefs: Vec<Symbol>,
+}
+
+impl Incremental {
+    /// Parse a document, with the options to format it with. An automatic line ending is resolved
+    /// against the source.
+    pub fn new(source: &str, file_path: &str, config: &FormatConfig) -> Incremental {
+        let mut document = Incremental {
+            source: source.to_string(),
+            file_path: file_path.to_string(),
+            config: FormatConfig {
+                line_ending: config.line_ending.resolve(source),
+                ..config.clone()
+            },
+            paragraphs: Vec::new(),
+        };
+        document.paragraphs = document.parse(0, source.len(), Vec::new());
+        document
+    }
+
+    /// The source of the document, with all edits and the formatting around them applied.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Replace a number of bytes at an offset with a text, and format the code around it. Returns
+    /// the replacements which formatted it, relative to the source after the edit, and which have
+    /// been applied to the document as well. If the edited code cannot be parsed, the edit is
+    /// still made, but nothing is formatted.
+    pub fn edit(
+        &mut self,
+        offset: usize,
+        length: usize,
+        text: &str,
+    ) -> Result<Vec<Replacement>, Diagnostics> {
+        let end = offset + length;
+        self.source.replace_range(offset..end, text);
+        let (first, count) = self.reparse(offset, end, text.len());
+
+        // Only the paragraphs with the edited text are formatted.
+        let edited = Span::new(offset, offset + text.len());
+        let mut result = Vec::new();
+        let mut errors = Vec::new();
+        let mut formatted = Vec::new();
+        for index in first..first + count {
+            let start = self.paragraphs[index].start;
+            let end = self.end(index);
+            if end < edited.start || edited.end < start {
+                continue;
+            }
+
+            let text = &self.source[start..end];
+            let selection = Selection::Bytes(
+                edited.start.max(start) - start,
+                Some(edited.end.min(end) - edited.start.max(start)),
+            );
+            match self.format(&self.paragraphs[index], text, selection) {
+                Ok(new) => {
+                    result.extend(replacements(text, &new).into_iter().map(|replacement| {
+                        Replacement {
+                            offset: start + replacement.offset,
+                            ..replacement
+                        }
+                    }));
+                    formatted.push((start, end, new));
+                }
+                Err(diagnostics) => {
+                    errors.extend(diagnostics.into_iter().map(|diagnostic| Diagnostic {
+                        span: Span::new(start + diagnostic.span.start, start + diagnostic.span.end),
+                        ..diagnostic
+                    }))
+                }
+            }
+        }
+
+        // The formatted paragraphs are parsed again, starting from the last one so that the
+        // offsets of the others stay the same.
+        for (start, end, new) in formatted.into_iter().rev() {
+            if new != self.source[start..end] {
+                self.source.replace_range(start..end, &new);
+                self.reparse(start, end, new.len());
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(result),
+            false => Err(errors.into_iter().collect()),
+        }
+    }
+
+    /// Where a paragraph ends in the source.
+    fn end(&self, index: usize) -> usize {
+        self.paragraphs
+            .get(index + 1)
+            .map_or(self.source.len(), |paragraph| paragraph.start)
+    }
+
+    /// Parse the paragraphs which an edit touched again, after a number of bytes from an offset
+    /// up to an end were replaced with a text of a length. Returns the index of the first
+    /// paragraph which was parsed and the number of paragraphs it became.
+    fn reparse(&mut self, offset: usize, end: usize, length: usize) -> (usize, usize) {
+        // The paragraph before the edited ones is parsed as well, as the edit may continue its last
+        // item, or keep the first edited one from starting a paragraph.
+        let first = self
+            .paragraphs
+            .partition_point(|paragraph| paragraph.start <= offset)
+            .saturating_sub(2);
+        let edited = self
+            .paragraphs
+            .partition_point(|paragraph| paragraph.start <= end)
+            .max(1)
+            - 1;
+        for paragraph in &mut self.paragraphs[edited + 1..] {
+            paragraph.start = paragraph.start + length - (end - offset);
+        }
+
+        // Whether the paragraph after the edited ones starts a paragraph depends on the code and
+        // the empty lines in front of it, so it is parsed as well. If it still does, and the
+        // edited paragraphs introduce the same types, the paragraphs after it stay as they are.
+        let mut last = (edited + 1).min(self.paragraphs.len() - 1);
+        let typedefs: Vec<Symbol> = self.paragraphs[..first]
+            .iter()
+            .flat_map(|paragraph| paragraph.typedefs.iter().cloned())
+            .collect();
+        let old: Vec<Symbol> = self.paragraphs[first..=last]
+            .iter()
+            .flat_map(|paragraph| paragraph.typedefs.iter().cloned())
+            .collect();
+
+        let start = self.paragraphs[first].start;
+        let mut paragraphs = self.parse(start, self.end(last), typedefs.clone());
+        let new: Vec<Symbol> = paragraphs
+            .iter()
+            .flat_map(|paragraph| paragraph.typedefs.iter().cloned())
+            .collect();
+        let next = self.paragraphs[last].start;
+        let settled = last == edited || paragraphs.iter().any(|paragraph| paragraph.start == next);
+        if (!settled || new != old) && last + 1 < self.paragraphs.len() {
+            last = self.paragraphs.len() - 1;
+            paragraphs = self.parse(start, self.source.len(), typedefs);
+        }
+
+        let count = paragraphs.len();
+        self.paragraphs.splice(first..=last, paragraphs);
+        (first, count)
+    }
+
+    /// Split a part of the source into paragraphs and parse them, knowing the types introduced
+    /// before it.
+    fn parse(&self, start: usize, end: usize, mut typedefs: Vec<Symbol>) -> Vec<Paragraph> {
+        let text = &self.source[start..end];
+        let lexemes = match lex(text) {
+            Ok(lexemes) => lexemes,
+            Err(error) => {
+                return vec![Paragraph {
+                    start,
+                    unit: Err(vec![Diagnostic::from(&error)]),
+                    typedefs: Vec::new(),
+                }]
+            }
+        };
+
+        let mut starts = boundaries(&lexemes);
+        starts.insert(0, 0);
+        let ends = starts[1..].iter().copied().chain([text.len()]);
+        let mut paragraphs = Vec::new();
+        for (offset, end) in starts.iter().copied().zip(ends) {
+            let paragraph = self.parse_paragraph(start + offset, &text[offset..end], &typedefs);
+            typedefs.extend(paragraph.typedefs.iter().cloned());
+            paragraphs.push(paragraph);
+        }
+        paragraphs
+    }
+
+    /// Parse a paragraph and apply the passes which rewrite its syntax tree, as for a whole file.
+    fn parse_paragraph(&self, start: usize, text: &str, typedefs: &[Symbol]) -> Paragraph {
+        let lexemes = match lex(text) {
+            Ok(lexemes) => lexemes,
+            Err(error) => {
+                return Paragraph {
+                    start,
+                    unit: Err(vec![Diagnostic::from(&error)]),
+                    typedefs: Vec::new(),
+                }
+            }
+        };
+        let mut parser = Parser::new(lexemes.into_iter()).with_typedefs(typedefs.iter().copied());
+        let mut unit = parser.parse_translation_unit();
+        if !parser.errors().is_empty() {
+            return Paragraph {
+                start,
+                unit: Err(parser.errors().iter().map(Diagnostic::from).collect()),
+                typedefs: self::typedefs(&unit.items),
+            };
+        }
+
+        salvage(&mut unit, text);
+        suppress_formatting(&mut unit, text);
+        sort_includes(&mut unit, self.config.include_sorting, &self.file_path);
+        normalize_header_guard(&mut unit, self.config.header_guards, &self.file_path);
+        Paragraph {
+            start,
+            typedefs: self::typedefs(&unit.items),
+            unit: Ok(unit),
+        }
+    }
+
+    /// The text of a paragraph with the selected part of it formatted.
+    fn format(
+        &self,
+        paragraph: &Paragraph,
+        text: &str,
+        selection: Selection,
+    ) -> Result<String, Vec<Diagnostic>> {
+        let unit = paragraph.unit.as_ref().map_err(Clone::clone)?;
+        let mut formatted = format(unit, &self.config);
+        // As with a whole file, the tokens are formatted on their own if the syntax tree got the
+        // meaning of the source wrong.
+        if verify_tokens(text, &formatted, &self.config).is_some() {
+            let lexemes = lex(text).map_err(|error| vec![Diagnostic::from(&error)])?;
+            formatted = format_tokens(&lexemes, &self.config);
+            if let Some(error) = verify_tokens(text, &formatted, &self.config) {
+                return Err(vec![error]);
+            }
+        }
+        Ok(format_selections(unit, text, &formatted, &[selection]))
+    }
+}
+
+/// The offsets at which new paragraphs start among the tokens of a part of the source. A paragraph
+/// starts at an item at file scope which follows an empty line, and which neither has comments in
+/// front of it nor opens a conditional group. The item before it must end with a semicolon, the
+/// body of a function or a directive other than a conditional one. Conditional groups, `extern`
+/// blocks and regions where formatting is turned off are never split.
+fn boundaries(lexemes: &[Lexeme]) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut depth = 0usize;
+    let mut conditionals = 0usize;
+    let mut suppressed = false;
+    // Whether the braces at file scope which are open are the body of a function, and whether
+    // the last ones which were closed were.
+    let mut function = false;
+    let mut closed_function = false;
+    let mut previous: Option<&Token> = None;
+
+    for lexeme in lexemes {
+        let ends_item = match previous {
+            Some(Token::Semicolon) => true,
+            Some(Token::Brace(Right)) => closed_function,
+            Some(Token::Directive(text)) => !is_conditional(text),
+            _ => false,
+        };
+        let starts_item = match &lexeme.token {
+            Token::SlashSlash(_) | Token::SlashStar(_) => false,
+            Token::Directive(text) => !is_conditional(text),
+            _ => true,
+        };
+        if ends_item
+            && starts_item
+            && lexeme.newlines > 1
+            && depth == 0
+            && conditionals == 0
+            && !suppressed
+        {
+            boundaries.push(lexeme.span.start);
+        }
+
+        match &lexeme.token {
+            Token::Brace(Left) if depth == 0 => {
+                function = matches!(previous, Some(Token::Parenthesis(Right)));
+                depth += 1;
+            }
+            Token::Brace(Left) | Token::Parenthesis(Left) | Token::Bracket(Left) => depth += 1,
+            Token::Brace(Right) | Token::Parenthesis(Right) | Token::Bracket(Right) => {
+                depth = depth.saturating_sub(1);
+                closed_function =
+                    depth == 0 && mem::take(&mut function) && lexeme.token == Token::Brace(Right);
+            }
+            Token::Directive(text) => {
+                let name = text.trim_start();
+                if name.starts_with("if") {
+                    conditionals += 1;
+                } else if name.starts_with("endif") {
+                    conditionals = conditionals.saturating_sub(1);
+                }
+            }
+            Token::SlashSlash(text) | Token::SlashStar(text) if depth == 0 => {
+                match text.split_whitespace().collect::<String>().as_str() {
+                    "cfmt:off" => suppressed = true,
+                    "cfmt:on" => suppressed = false,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        previous = Some(&lexeme.token);
+    }
+    boundaries
+}
+
+/// Whether a directive, without its hash, is part of a conditional group.
+fn is_conditional(text: &str) -> bool {
+    let name = text.trim_start();
+    name.starts_with("if") || name.starts_with("el") || name.starts_with("endif")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format_source;
+    use crate::formatter::config::FormatConfig;
+    use crate::incremental::Incremental;
+
+    #[test]
+    fn edits_are_formatted_around_them() {
+        let config = FormatConfig::default();
+        let source = format_source(
+            "#include <stdio.h>\n\n\
+             typedef int count;\n\n\
+             int f(void) {\n    return 0;\n}\n\n\
+             int g(void) {\n    return 1;\n}\n\n\
+             // A comment in front of h.\n\
+             int h(void) {\n    return 2;\n}\n",
+            &config,
+        )
+        .unwrap();
+        // The comment in front of `h` keeps it in the paragraph of `g`.
+        let mut document = Incremental::new(&source, "a.c", &config);
+        assert_eq!(document.paragraphs.len(), 4);
+
+        // The new statement is formatted, and nothing else.
+        let offset = source.find("return 1").unwrap();
+        let replacements = document.edit(offset, 0, "count  *c=0;").unwrap();
+        assert_eq!(replacements.len(), 4);
+        let expected = source.replace("return 1", "count *c = 0;\n    return 1");
+        assert_eq!(document.source(), expected);
+        assert_eq!(format_source(document.source(), &config).unwrap(), expected);
+        assert_eq!(document.paragraphs.len(), 4);
+
+        // Removing the empty line in front of a function joins it with the paragraph before.
+        let offset = expected.find("\n\nint g").unwrap();
+        document.edit(offset, 1, "").unwrap();
+        assert_eq!(document.paragraphs.len(), 3);
+        document.edit(offset, 0, "\n").unwrap();
+        assert_eq!(document.source(), expected);
+        assert_eq!(document.paragraphs.len(), 4);
+
+        // An edit which breaks the code is made, but reported.
+        let offset = expected.find("return 2").unwrap();
+        let errors = document.edit(offset, 0, "x = ;").unwrap_err();
+        let errors = errors.sorted();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].span.start, offset + 4);
+        document.edit(offset, 5, "").unwrap();
+        assert_eq!(document.source(), expected);
+    }
+}
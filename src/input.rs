@@ -0,0 +1,221 @@
+//! Reading the code of the files to format. With the `mmap` feature, large files such as generated
+//! amalgamations are mapped into memory on Unix rather than read into a `Vec`, and the lexer works
+//! on the mapped bytes directly. Files are only mapped for reading, and are replaced rather than
+//! overwritten when formatted in place, so a mapping never sees its own writes.
+//!
+//! A mapping does see the writes of other programs, though. If a file is truncated or rewritten in
+//! place while it is mapped, as by an editor which saves over the file, the bytes change under the
+//! lexer, or reading past the new end of the file kills the formatter with `SIGBUS`. The `mmap`
+//! feature is only meant for files which are not being edited while they are formatted, so files
+//! are never mapped when they are watched.
+//!
+//! The bytes of a file are decoded into text in the encoding of the file, and the formatted code
+//! is encoded in it again, so that comments and strings in Latin-1 are kept byte for byte.
+
+use crate::formatter::config::Encoding;
+use std::borrow::Cow;
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+/// The size from which files are mapped rather than read, as mapping a small file costs more than
+/// it saves.
+#[cfg(all(feature = "mmap", unix))]
+const MAP_THRESHOLD: u64 = 1 << 20;
+
+/// The code of a file, which dereferences to the bytes of the file however it was read.
+#[derive(Debug)]
+pub enum Contents {
+    /// The file was read into memory.
+    Read(Vec<u8>),
+    /// The file was mapped into memory.
+    #[cfg(all(feature = "mmap", unix))]
+    Mapped(mapping::Mapping),
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Contents::Read(bytes) => bytes,
+            #[cfg(all(feature = "mmap", unix))]
+            Contents::Mapped(mapping) => mapping,
+        }
+    }
+}
+
+/// Read the code of a file. Large files may be mapped into memory when the `mmap` feature is
+/// enabled, unless they are expected to change while they are formatted.
+#[cfg_attr(not(all(feature = "mmap", unix)), allow(unused_variables))]
+pub fn read(path: &Path, map: bool) -> io::Result<Contents> {
+    #[cfg(all(feature = "mmap", unix))]
+    if map && fs::metadata(path)?.len() >= MAP_THRESHOLD {
+        return mapping::Mapping::new(path).map(Contents::Mapped);
+    }
+
+    fs::read(path).map(Contents::Read)
+}
+
+/// The text of the bytes of a file in an encoding, which is borrowed unless it has to be decoded.
+/// Fails if the bytes are not UTF-8 when they must be.
+pub fn decode(bytes: &[u8], encoding: Encoding) -> io::Result<Cow<'_, str>> {
+    match encoding.resolve(bytes) {
+        Encoding::Latin1 if !bytes.is_ascii() => Ok(Cow::Owned(
+            bytes.iter().map(|&byte| char::from(byte)).collect(),
+        )),
+        _ => std::str::from_utf8(bytes)
+            .map(Cow::Borrowed)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+    }
+}
+
+/// The bytes of text in the encoding of a file, which are borrowed unless they have to be encoded.
+/// Characters which do not exist in Latin-1, which are never in formatted code read as Latin-1,
+/// become question marks.
+pub fn encode(text: &str, encoding: Encoding) -> Cow<'_, [u8]> {
+    match encoding {
+        Encoding::Latin1 if !text.is_ascii() => Cow::Owned(
+            text.chars()
+                .map(|c| u8::try_from(c).unwrap_or(b'?'))
+                .collect(),
+        ),
+        _ => Cow::Borrowed(text.as_bytes()),
+    }
+}
+
+#[cfg(all(feature = "mmap", unix))]
+mod mapping {
+    use std::ffi::{c_int, c_void};
+    use std::fs::File;
+    use std::io;
+    use std::ops::Deref;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+    use std::{ptr, slice};
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 2;
+
+    extern "C" {
+        fn mmap(
+            address: *mut c_void,
+            length: usize,
+            protection: c_int,
+            flags: c_int,
+            descriptor: c_int,
+            offset: isize,
+        ) -> *mut c_void;
+        fn munmap(address: *mut c_void, length: usize) -> c_int;
+    }
+
+    /// A file mapped into memory for reading.
+    #[derive(Debug)]
+    pub struct Mapping {
+        address: *mut c_void,
+        length: usize,
+    }
+
+    // The mapping is never written to, so it can be read from any thread.
+    unsafe impl Send for Mapping {}
+    unsafe impl Sync for Mapping {}
+
+    impl Mapping {
+        /// Map a file into memory.
+        pub fn new(path: &Path) -> io::Result<Mapping> {
+            let file = File::open(path)?;
+            let length = usize::try_from(file.metadata()?.len())
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+            // An empty file cannot be mapped, but it is read easily enough.
+            if length == 0 {
+                return Ok(Mapping {
+                    address: ptr::null_mut(),
+                    length,
+                });
+            }
+
+            // The mapping stays valid after the file is closed.
+            let address = unsafe {
+                mmap(
+                    ptr::null_mut(),
+                    length,
+                    PROT_READ,
+                    MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if address as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Mapping { address, length })
+        }
+    }
+
+    impl Deref for Mapping {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            match self.length {
+                0 => &[],
+                length => unsafe { slice::from_raw_parts(self.address as *const u8, length) },
+            }
+        }
+    }
+
+    impl Drop for Mapping {
+        fn drop(&mut self) {
+            if self.length > 0 {
+                unsafe { munmap(self.address, self.length) };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::Encoding;
+    use crate::input::{decode, encode, read, Contents};
+    use std::{env, fs, process};
+
+    #[test]
+    fn files_are_read_however_large() {
+        let directory = env::temp_dir().join(format!("cfmt-input-{}", process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let small = directory.join("small.c");
+        fs::write(&small, "int x;\n").unwrap();
+        assert_eq!(&*read(&small, true).unwrap(), b"int x;\n");
+
+        let code = "int é = 1;\n".repeat(200_000);
+        let large = directory.join("large.c");
+        fs::write(&large, &code).unwrap();
+        assert_eq!(&*read(&large, true).unwrap(), code.as_bytes());
+        let read_whole = read(&large, false).unwrap();
+        assert!(matches!(read_whole, Contents::Read(_)));
+
+        let empty = directory.join("empty.c");
+        fs::write(&empty, "").unwrap();
+        assert_eq!(&*read(&empty, true).unwrap(), b"");
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn latin1_is_kept_byte_for_byte() {
+        let bytes = b"/* Gr\xfc\xdfe */ char *s = \"\xe9t\xe9\";\n";
+        assert!(decode(bytes, Encoding::Utf8).is_err());
+
+        let text = decode(bytes, Encoding::Auto).unwrap();
+        assert_eq!(text, "/* Grüße */ char *s = \"été\";\n");
+        assert_eq!(encode(&text, Encoding::Latin1), &bytes[..]);
+
+        // Code in UTF-8 is read as it is, unless it is known to be Latin-1.
+        let utf8 = "/* é */".as_bytes();
+        assert_eq!(decode(utf8, Encoding::Auto).unwrap(), "/* é */");
+        assert_eq!(decode(utf8, Encoding::Latin1).unwrap(), "/* Ã\u{a9} */");
+        assert_eq!(encode("/* é */", Encoding::Utf8), utf8);
+    }
+}
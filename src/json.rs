@@ -0,0 +1,357 @@
+//! A minimal JSON document model for the machine readable output of the command line tool, and for
+//! the messages of the language server. Values are built in memory and written with two spaces of
+//! indentation, or read from text.
+
+use std::fmt::{Display, Formatter, Result, Write};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(usize),
+    /// A whole number which is below zero.
+    Integer(i64),
+    /// A number which is not a whole number.
+    Float(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// The fields of an object, in the order in which they are written.
+    Object(Vec<(String, Json)>),
+}
+
+/// Conversion of a value into its JSON representation.
+pub trait ToJson {
+    fn to_json(&self) -> Json;
+}
+
+impl Json {
+    /// Create an object from its fields.
+    pub fn object<const N: usize>(fields: [(&'static str, Json); N]) -> Json {
+        Json::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// The value of a field of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, indent: usize) -> Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::Integer(value) => write!(f, "{}", value),
+            Json::Float(value) => write!(f, "{}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(items) if items.is_empty() => write!(f, "[]"),
+            Json::Array(items) => {
+                writeln!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    item.write(f, indent + 2)?;
+                    writeln!(f, "{}", if i + 1 < items.len() { "," } else { "" })?;
+                }
+                write!(f, "{:1$}]", "", indent)
+            }
+            Json::Object(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Json::Object(fields) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 2)?;
+                    writeln!(f, "{}", if i + 1 < fields.len() { "," } else { "" })?;
+                }
+                write!(f, "{:1$}}}", "", indent)
+            }
+        }
+    }
+}
+
+/// Write a string literal, escaping quotes, backslashes and control characters.
+fn write_string(f: &mut Formatter<'_>, value: &str) -> Result {
+    f.write_char('"')?;
+
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+
+    f.write_char('"')
+}
+
+/// Read a value from text, failing with a message if it is not valid JSON.
+pub fn parse(text: &str) -> std::result::Result<Json, String> {
+    let mut reader = Reader {
+        chars: text.chars().peekable(),
+    };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    match reader.chars.peek() {
+        Some(c) => Err(format!("unexpected `{}` after the value", c)),
+        None => Ok(value),
+    }
+}
+
+/// The position in the text of a value.
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn value(&mut self) -> std::result::Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => {
+                self.chars.next();
+                let mut fields = Vec::new();
+                if self.eat('}') {
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let key = self.string()?;
+                    if !self.eat(':') {
+                        return Err(format!("expected `:` after \"{}\"", key));
+                    }
+                    fields.push((key, self.value()?));
+                    if self.eat('}') {
+                        return Ok(Json::Object(fields));
+                    }
+                    if !self.eat(',') {
+                        return Err("expected `,` or `}` in the object".to_string());
+                    }
+                }
+            }
+            Some('[') => {
+                self.chars.next();
+                let mut items = Vec::new();
+                if self.eat(']') {
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.value()?);
+                    if self.eat(']') {
+                        return Ok(Json::Array(items));
+                    }
+                    if !self.eat(',') {
+                        return Err("expected `,` or `]` in the array".to_string());
+                    }
+                }
+            }
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|&c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+                {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "" => Err("expected a value".to_string()),
+                    "null" => Ok(Json::Null),
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    _ => match (word.parse(), word.parse(), word.parse()) {
+                        (Ok(number), _, _) => Ok(Json::Number(number)),
+                        (_, Ok(number), _) => Ok(Json::Integer(number)),
+                        (_, _, Ok(number)) => Ok(Json::Float(number)),
+                        _ => Err(format!("invalid value `{}`", word)),
+                    },
+                }
+            }
+            None => Err("expected a value".to_string()),
+        }
+    }
+
+    fn string(&mut self) -> std::result::Result<String, String> {
+        if self.chars.next_if_eq(&'"').is_none() {
+            return Err("expected a string".to_string());
+        }
+
+        let mut result = String::new();
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(result),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => result.push('\n'),
+                    Some('r') => result.push('\r'),
+                    Some('t') => result.push('\t'),
+                    Some('b') => result.push('\u{8}'),
+                    Some('f') => result.push('\u{c}'),
+                    Some('u') => {
+                        let unit = self.code_unit()?;
+                        // Characters outside the basic plane are written as a surrogate pair.
+                        let c = if (0xD800..0xDC00).contains(&unit) {
+                            let low = match (self.chars.next(), self.chars.next()) {
+                                (Some('\\'), Some('u')) => self.code_unit()?,
+                                _ => return Err("expected a low surrogate".to_string()),
+                            };
+                            char::from_u32(0x10000 + ((unit - 0xD800) << 10) + (low - 0xDC00))
+                        } else {
+                            char::from_u32(unit)
+                        };
+                        result.push(c.ok_or("invalid escape in the string")?);
+                    }
+                    Some(c @ ('"' | '\\' | '/')) => result.push(c),
+                    _ => return Err("invalid escape in the string".to_string()),
+                },
+                Some(c) => result.push(c),
+                None => return Err("the string is not closed".to_string()),
+            }
+        }
+    }
+
+    /// The four hexadecimal digits of a `\u` escape.
+    fn code_unit(&mut self) -> std::result::Result<u32, String> {
+        let digits: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| "invalid escape in the string".to_string())
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.write(f, 0)
+    }
+}
+
+impl ToJson for bool {
+    fn to_json(&self) -> Json {
+        Json::Bool(*self)
+    }
+}
+
+impl ToJson for usize {
+    fn to_json(&self) -> Json {
+        Json::Number(*self)
+    }
+}
+
+impl ToJson for str {
+    fn to_json(&self) -> Json {
+        Json::String(self.to_string())
+    }
+}
+
+impl ToJson for String {
+    fn to_json(&self) -> Json {
+        Json::String(self.clone())
+    }
+}
+
+impl<T: ToJson + ?Sized> ToJson for Box<T> {
+    fn to_json(&self) -> Json {
+        self.as_ref().to_json()
+    }
+}
+
+impl<T: ToJson> ToJson for Option<T> {
+    fn to_json(&self) -> Json {
+        match self {
+            Some(value) => value.to_json(),
+            None => Json::Null,
+        }
+    }
+}
+
+impl<T: ToJson> ToJson for [T] {
+    fn to_json(&self) -> Json {
+        Json::Array(self.iter().map(ToJson::to_json).collect())
+    }
+}
+
+impl<T: ToJson> ToJson for Vec<T> {
+    fn to_json(&self) -> Json {
+        self.as_slice().to_json()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{parse, Json, ToJson};
+
+    #[test]
+    fn nested_values() {
+        let value = Json::object([
+            ("name", "a \"quoted\"\n\u{1}".to_json()),
+            ("items", vec![1usize, 2].to_json()),
+            ("empty", Json::Array(Vec::new())),
+            ("missing", None::<bool>.to_json()),
+        ]);
+
+        assert_eq!(
+            value.to_string(),
+            "{\n  \"name\": \"a \\\"quoted\\\"\\n\\u0001\",\n  \"items\": [\n    1,\n    2\n  ],\n  \
+             \"empty\": [],\n  \"missing\": null\n}"
+        );
+    }
+
+    #[test]
+    fn values_are_read() {
+        let text = r#" {"id": 1, "params": {"text": "a\n\u00e9\ud83d\ude00", "x": [-1, 2.5, null,
+                       true]}} "#;
+        let value = parse(text).unwrap();
+        assert_eq!(value.get("id").and_then(Json::as_usize), Some(1));
+        let params = value.get("params").unwrap();
+        assert_eq!(params.get("text").and_then(Json::as_str), Some("a\né😀"));
+        assert_eq!(
+            params.get("x"),
+            Some(&Json::Array(vec![
+                Json::Integer(-1),
+                Json::Float(2.5),
+                Json::Null,
+                Json::Bool(true)
+            ]))
+        );
+
+        assert_eq!(parse(&value.to_string()), Ok(value));
+        assert_eq!(parse("[1,]"), Err("expected a value".to_string()));
+        assert_eq!(
+            parse("{} {}"),
+            Err("unexpected `{` after the value".to_string())
+        );
+    }
+}
@@ -1,19 +1,31 @@
+use crate::diagnostics::diagnostic::{Diagnostic, Diagnostics};
 use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::span::Span;
+use crate::lexer::symbol::Symbol;
 use crate::lexer::token::Token::{
-    Ampersand, Arrow, Bang, BangEqual, Brace, Bracket, Caret, Comma, Dot, Equal, EqualEqual,
-    Greater, GreaterEqual, Identifier, Keyword, Less, LessEqual, Minus, MinusMinus, Number,
-    Parenthesis, Plus, PlusPlus, Semicolon, Slash, SlashSlash, SlashStar, Star, Str, Tilde,
+    Ampersand, AmpersandAmpersand, AmpersandEqual, Arrow, Bang, BangEqual, Brace, Bracket, Caret,
+    CaretEqual, Character, Colon, Comma, Directive, Disabled, Dot, Ellipsis, Equal, EqualEqual,
+    Greater, GreaterEqual, GreaterGreater, GreaterGreaterEqual, Identifier, Invalid, Keyword, Less,
+    LessEqual, LessLess, LessLessEqual, Minus, MinusEqual, MinusMinus, Number, Parenthesis,
+    Percent, PercentEqual, Pipe, PipeEqual, PipePipe, Plus, PlusEqual, PlusPlus, Question,
+    Semicolon, Slash, SlashEqual, SlashSlash, SlashStar, Star, StarEqual, Str, Tilde,
 };
-use crate::lexer::token::TokenKeyword::For;
-use crate::lexer::token::{Token, TokenKeyword};
+use crate::lexer::token::{Lexeme, Token};
+use std::fmt::{Display, Formatter};
 
 /// A stateful lexer which can be executed once, returning a stream of tokens in the process.
 #[derive(Debug)]
-pub struct Lexer {
-    /// The source code that will be parsed by the lexer.
-    source: Vec<char>,
-    /// The position of the next character that needs to be parsed.
-    index: usize,
+pub struct Lexer<'a> {
+    /// The source code that will be parsed by the lexer, which is borrowed rather than copied so
+    /// that large files are lexed where they are, such as in a mapping of the file.
+    source: &'a str,
+    /// The byte offset of the next character that needs to be parsed.
+    offset: usize,
+    /// Whether the previous token was an `#if 0`, so that the lines after it are disabled.
+    disabled: bool,
+    /// The byte offset up to which the source has been searched for line breaks, together with
+    /// the column at that offset, so that the columns of tokens are found without going back.
+    scanned: (usize, usize),
 }
 
 #[derive(Debug, Clone)]
@@ -30,89 +42,224 @@ pub enum LexerError {
 
     /// The lexer found a character which it does not know how to handle, and rather quits.
     UnknownCharacter,
+
+    /// A block comment was opened, but the file ended before it was closed.
+    UnterminatedComment,
+}
+
+impl Display for LexerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            LexerError::EndOfFileReached => "unexpected end of file",
+            LexerError::CharacterMismatch => "unexpected character",
+            LexerError::InvalidNumber => "invalid number",
+            LexerError::UnknownCharacter => "unknown character",
+            LexerError::UnterminatedComment => "unterminated comment",
+        };
+        write!(f, "{}", message)
+    }
 }
 
-impl Lexer {
+impl std::error::Error for LexerError {}
+
+impl<'a> Lexer<'a> {
     /// Create a new lexer for a given source file.
-    pub fn new(source: String) -> Lexer {
+    pub fn new(source: &'a str) -> Lexer<'a> {
         Lexer {
-            source: source.chars().collect(),
-            index: 0,
+            source,
+            offset: 0,
+            disabled: false,
+            scanned: (0, 0),
+        }
+    }
+
+    /// Turn the lexer into an iterator over tokens which also carry their location.
+    pub fn lexemes(self) -> Lexemes<'a> {
+        Lexemes { lexer: self }
+    }
+
+    /// Split the whole source into tokens, carrying on past the constructs which cannot be lexed,
+    /// such as a stray character or an unterminated comment. Each of them becomes an invalid token
+    /// which keeps its text, and is reported as a warning.
+    pub fn recovering_lexemes(mut self, diagnostics: &mut Diagnostics) -> Vec<Lexeme<'a>> {
+        let mut lexemes = Vec::new();
+        loop {
+            let before = self.offset;
+            let error = match self.next_lexeme() {
+                Ok(lexeme) => {
+                    lexemes.push(lexeme);
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            // The construct is lexed again up to where it failed, at least a character.
+            let failed = self.offset;
+            self.offset = before;
+            let newlines = self.trim_leading_whitespace().unwrap_or(0);
+            let start = self.offset;
+            let Some(c) = self.source[start..].chars().next() else {
+                break;
+            };
+            let column = self.column(start);
+            self.offset = failed.max(start + c.len_utf8());
+            let span = Span::new(start, self.offset);
+            diagnostics.push(Diagnostic::recovered(span, error.to_string()));
+            lexemes.push(Lexeme {
+                token: Invalid(self.source[start..self.offset].into()),
+                span,
+                newlines,
+                column,
+            });
         }
+        lexemes
     }
 
     /// Check the next character in the input stream, without advancing the lexer.
     fn peek(&self) -> Result<char, LexerError> {
-        if let Some(c) = self.source.get(self.index) {
-            Ok(*c)
-        } else {
-            Err(LexerError::EndOfFileReached)
-        }
+        self.source[self.offset..]
+            .chars()
+            .next()
+            .ok_or(LexerError::EndOfFileReached)
+    }
+
+    /// The bytes of the source which have not been lexed yet, which are scanned directly while
+    /// they are ASCII rather than decoded a character at a time.
+    fn rest(&self) -> &'a [u8] {
+        &self.source.as_bytes()[self.offset..]
     }
 
     /// Check if the source file has been completely finished.
     fn finished(&self) -> bool {
-        self.source.len() == self.index
+        self.source.len() == self.offset
     }
 
-    /// Remove all whitespace leading up to the next readable character.
-    fn trim_leading_whitespace(&mut self) -> Result<(), LexerError> {
-        while let Ok(c) = self.peek() {
-            if c.is_whitespace() {
-                self.eat(c)?;
-            } else {
+    /// Remove all whitespace leading up to the next readable character, returning the number of
+    /// line breaks that were removed.
+    fn trim_leading_whitespace(&mut self) -> Result<usize, LexerError> {
+        let mut newlines = 0;
+
+        loop {
+            let spaces = self
+                .rest()
+                .iter()
+                .take_while(|byte| byte.is_ascii_whitespace() || **byte == b'\x0b')
+                .inspect(|&&byte| newlines += usize::from(byte == b'\n'))
+                .count();
+            self.offset += spaces;
+
+            // Whitespace outside of ASCII, such as a no-break space, is rare enough to be eaten
+            // a character at a time.
+            let Ok(c) = self.peek() else {
+                break;
+            };
+            if c.is_ascii() || !c.is_whitespace() {
                 break;
             }
+            self.eat(c)?;
         }
 
-        Ok(())
+        Ok(newlines)
     }
 
     /// Attempt to remove a specific character from the input stream.
     fn eat(&mut self, c: char) -> Result<(), LexerError> {
         if self.peek()? == c {
-            self.index += 1;
+            self.offset += c.len_utf8();
             Ok(())
         } else {
             Err(LexerError::CharacterMismatch)
         }
     }
 
-    /// Eat all remaining characters on the current line. Handy for handling comments, nothing else
-    /// really.
-    fn eat_line(&mut self) -> Result<String, LexerError> {
-        self.eat_until('\n')
+    /// Eat all remaining characters on the current line, used for line comments and directives.
+    /// Escaped line breaks are included, but the final line break is left in place so that it
+    /// counts towards the whitespace in front of the next token.
+    fn eat_line(&mut self) -> Result<&'a str, LexerError> {
+        let rest = self.rest();
+        let mut end = 0;
+
+        while let Some(newline) = rest[end..].iter().position(|&byte| byte == b'\n') {
+            let backslashes = rest[..end + newline]
+                .iter()
+                .rev()
+                .take_while(|&&byte| byte == b'\\')
+                .count();
+            end += newline;
+            if backslashes % 2 == 0 {
+                return Ok(self.take(end));
+            }
+            end += 1;
+        }
+
+        Ok(self.take(rest.len()))
+    }
+
+    /// Eat a number of bytes, which end at the boundary of a character, returning their text.
+    fn take(&mut self, length: usize) -> &'a str {
+        let text = &self.source[self.offset..self.offset + length];
+        self.offset += length;
+        text
     }
 
     /// Attempt to eat a string literal.
-    fn eat_string_literal(&mut self) -> Result<String, LexerError> {
+    fn eat_string_literal(&mut self) -> Result<&'a str, LexerError> {
         self.eat('"')?;
         self.eat_until('"')
     }
 
+    /// Attempt to eat a character literal, such as `'a'` or `'\n'`.
+    fn eat_character_literal(&mut self) -> Result<&'a str, LexerError> {
+        self.eat('\'')?;
+        self.eat_until('\'')
+    }
+
+    /// Eat the body of a block comment, assuming the opening `/*` has already been eaten. The
+    /// closing `*/` is eaten as well, but not included in the result.
+    fn eat_block_comment(&mut self) -> Result<&'a str, LexerError> {
+        match self.source[self.offset..].find("*/") {
+            Some(length) => {
+                let comment = self.take(length);
+                self.offset += 2;
+                Ok(comment)
+            }
+            None => {
+                self.offset = self.source.len();
+                Err(LexerError::UnterminatedComment)
+            }
+        }
+    }
+
     /// Attempt to eat all characters until a specific character is found. Also eat that character.
     /// Note that if a character is escaped using `\` in the source code, it will be skipped.
-    fn eat_until(&mut self, goal: char) -> Result<String, LexerError> {
-        let mut result = String::new();
-        let mut espaced = false;
+    fn eat_until(&mut self, goal: char) -> Result<&'a str, LexerError> {
+        let goal = goal as u8;
+        let rest = self.rest();
+        let mut end = 0;
 
-        while let Ok(c) = self.peek() {
-            self.eat(c)?;
-
-            if !espaced && c == goal {
-                break;
+        while let Some(found) = rest[end..]
+            .iter()
+            .position(|&byte| byte == goal || byte == b'\\')
+        {
+            end += found;
+            if rest[end] == goal {
+                let result = self.take(end);
+                self.offset += 1;
+                return Ok(result);
             }
-
-            espaced = c == '\\';
-            result.push(c);
+            // The escaped character may take more than a byte, but the bytes of a character
+            // outside of ASCII are never mistaken for the goal or a backslash.
+            end = (end + 2).min(rest.len());
         }
 
-        Ok(result)
+        Ok(self.take(rest.len()))
     }
 
-    /// Attempt to eat a number literal.
-    fn eat_number_literal(&mut self) -> Result<String, LexerError> {
-        let mut result = String::new();
+    /// Attempt to eat a number literal. Anything that the preprocessor would consider a number is
+    /// accepted, which includes hexadecimal digits, exponents and suffixes such as `0x1Fu` or
+    /// `1.5e-3f`.
+    fn eat_number_literal(&mut self) -> Result<&'a str, LexerError> {
+        let start = self.offset;
         let mut period_passed = false;
 
         while let Ok(c) = self.peek() {
@@ -122,36 +269,42 @@ impl Lexer {
                 }
                 self.eat(c)?;
                 period_passed = true;
-                result.push(c);
-            } else if c.is_numeric() {
+            } else if c == '_'
+                || c.is_alphanumeric()
+                || ((c == '+' || c == '-')
+                    && self.source[start..self.offset].ends_with(['e', 'E', 'p', 'P']))
+            {
                 self.eat(c)?;
-                result.push(c);
             } else {
                 break;
             }
         }
 
-        Ok(result)
+        Ok(&self.source[start..self.offset])
     }
 
     /// Eat all characters which might be part of an identifier or a keyword.
-    fn eat_alphanumeric(&mut self) -> Result<String, LexerError> {
-        let mut result = String::new();
-
-        while let Ok(c) = self.peek() {
-            if c != '_' && !c.is_alphanumeric() {
-                break;
-            }
+    fn eat_alphanumeric(&mut self) -> Result<&'a str, LexerError> {
+        let mut length = self
+            .rest()
+            .iter()
+            .take_while(|byte| byte.is_ascii_alphanumeric() || **byte == b'_')
+            .count();
 
-            self.eat(c)?;
-            result.push(c);
+        // Letters outside of ASCII are rare enough to be eaten a character at a time.
+        if self.rest().get(length).is_some_and(|byte| !byte.is_ascii()) {
+            length += self.source[self.offset + length..]
+                .chars()
+                .take_while(|&c| c == '_' || c.is_alphanumeric())
+                .map(char::len_utf8)
+                .sum::<usize>();
         }
 
-        Ok(result)
+        Ok(self.take(length))
     }
 
     /// Find the next token in input stream.
-    fn next_token(&mut self) -> Result<Token, LexerError> {
+    fn next_token(&mut self) -> Result<Token<'a>, LexerError> {
         self.trim_leading_whitespace()?;
 
         match self.peek()? {
@@ -160,6 +313,8 @@ impl Lexer {
 
                 if let Ok(()) = self.eat('+') {
                     Ok(PlusPlus)
+                } else if let Ok(()) = self.eat('=') {
+                    Ok(PlusEqual)
                 } else {
                     Ok(Plus)
                 }
@@ -171,6 +326,8 @@ impl Lexer {
                     Ok(MinusMinus)
                 } else if let Ok(()) = self.eat('>') {
                     Ok(Arrow)
+                } else if let Ok(()) = self.eat('=') {
+                    Ok(MinusEqual)
                 } else {
                     Ok(Minus)
                 }
@@ -178,20 +335,35 @@ impl Lexer {
             '*' => {
                 self.eat('*')?;
 
-                Ok(Star)
+                if let Ok(()) = self.eat('=') {
+                    Ok(StarEqual)
+                } else {
+                    Ok(Star)
+                }
             }
             '/' => {
                 self.eat('/')?;
 
                 if let Ok(()) = self.eat('/') {
                     let comment = self.eat_line()?;
-                    Ok(SlashSlash(comment))
+                    Ok(SlashSlash(comment.into()))
                 } else if let Ok(()) = self.eat('*') {
-                    Ok(SlashStar(String::new())) // TODO: Implementation
+                    Ok(SlashStar(self.eat_block_comment()?.into()))
+                } else if let Ok(()) = self.eat('=') {
+                    Ok(SlashEqual)
                 } else {
                     Ok(Slash)
                 }
             }
+            '%' => {
+                self.eat('%')?;
+
+                if let Ok(()) = self.eat('=') {
+                    Ok(PercentEqual)
+                } else {
+                    Ok(Percent)
+                }
+            }
             '!' => {
                 self.eat('!')?;
 
@@ -207,7 +379,12 @@ impl Lexer {
             }
             '^' => {
                 self.eat('^')?;
-                Ok(Caret)
+
+                if let Ok(()) = self.eat('=') {
+                    Ok(CaretEqual)
+                } else {
+                    Ok(Caret)
+                }
             }
             '=' => {
                 self.eat('=')?;
@@ -223,6 +400,12 @@ impl Lexer {
 
                 if let Ok(()) = self.eat('=') {
                     Ok(GreaterEqual)
+                } else if let Ok(()) = self.eat('>') {
+                    if let Ok(()) = self.eat('=') {
+                        Ok(GreaterGreaterEqual)
+                    } else {
+                        Ok(GreaterGreater)
+                    }
                 } else {
                     Ok(Greater)
                 }
@@ -232,6 +415,12 @@ impl Lexer {
 
                 if let Ok(()) = self.eat('=') {
                     Ok(LessEqual)
+                } else if let Ok(()) = self.eat('<') {
+                    if let Ok(()) = self.eat('=') {
+                        Ok(LessLessEqual)
+                    } else {
+                        Ok(LessLess)
+                    }
                 } else {
                     Ok(Less)
                 }
@@ -266,37 +455,180 @@ impl Lexer {
             }
             '&' => {
                 self.eat('&')?;
-                Ok(Ampersand)
+
+                if let Ok(()) = self.eat('&') {
+                    Ok(AmpersandAmpersand)
+                } else if let Ok(()) = self.eat('=') {
+                    Ok(AmpersandEqual)
+                } else {
+                    Ok(Ampersand)
+                }
+            }
+            '|' => {
+                self.eat('|')?;
+
+                if let Ok(()) = self.eat('|') {
+                    Ok(PipePipe)
+                } else if let Ok(()) = self.eat('=') {
+                    Ok(PipeEqual)
+                } else {
+                    Ok(Pipe)
+                }
+            }
+            '?' => {
+                self.eat('?')?;
+                Ok(Question)
+            }
+            ':' => {
+                self.eat(':')?;
+                Ok(Colon)
             }
             ',' => {
                 self.eat(',')?;
                 Ok(Comma)
             }
             '.' => {
+                let rest = &self.source.as_bytes()[self.offset + 1..];
+                if let Some(b'0'..=b'9') = rest.first() {
+                    return Ok(Number(self.eat_number_literal()?.into()));
+                }
+
+                if rest.starts_with(b"..") {
+                    self.eat('.')?;
+                    self.eat('.')?;
+                    self.eat('.')?;
+                    return Ok(Ellipsis);
+                }
+
                 self.eat('.')?;
                 Ok(Dot)
             }
-            '"' => Ok(Str(self.eat_string_literal()?)),
-            '0'..='9' => Ok(Number(self.eat_number_literal()?)),
+            '#' => {
+                self.eat('#')?;
+                let line = self.eat_line()?;
+                let mut words = line.split_whitespace();
+                self.disabled = words.next() == Some("if")
+                    && words.next() == Some("0")
+                    && words
+                        .next()
+                        .is_none_or(|word| word.starts_with("//") || word.starts_with("/*"));
+                Ok(Directive(line.into()))
+            }
+            '"' => Ok(Str(self.eat_string_literal()?.into())),
+            '\'' => Ok(Character(self.eat_character_literal()?.into())),
+            '0'..='9' => Ok(Number(self.eat_number_literal()?.into())),
             'a'..='z' | 'A'..='Z' | '_' => {
-                let result = self.eat_alphanumeric()?;
-                if let Some(keyword) = TokenKeyword::from(&result) {
+                let symbol = Symbol::intern(self.eat_alphanumeric()?);
+                if let Some(keyword) = symbol.keyword() {
                     Ok(Keyword(keyword))
                 } else {
-                    Ok(Identifier(result))
+                    Ok(Identifier(symbol))
                 }
             }
             _ => Err(LexerError::UnknownCharacter),
         }
     }
-}
 
-impl Iterator for Lexer {
-    type Item = Result<Token, LexerError>;
+    /// Find the end of a branch which is never compiled, starting at the line after the directive
+    /// which opens it. This is the start of the line of the `#elif`, `#else` or `#endif` which
+    /// ends the branch, skipping over nested groups, or the end of the file.
+    fn disabled_end(&self, start: usize) -> usize {
+        let mut depth = 0;
+        let mut line_start = start;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.next_token() {
-            Ok(token) => Some(Ok(token)),
+        while line_start < self.source.len() {
+            let line_end = self.source[line_start..]
+                .find('\n')
+                .map_or(self.source.len(), |end| line_start + end);
+            let line = &self.source[line_start..line_end];
+
+            if let Some(directive) = line.trim_start().strip_prefix('#') {
+                let directive = directive.trim_start();
+                let name = directive
+                    .split(|c: char| !c.is_ascii_alphabetic())
+                    .next()
+                    .unwrap_or(directive);
+
+                match name {
+                    "if" | "ifdef" | "ifndef" => depth += 1,
+                    "elif" | "elifdef" | "elifndef" | "else" | "endif" if depth == 0 => {
+                        return line_start;
+                    }
+                    "endif" => depth -= 1,
+                    _ => {}
+                }
+            }
+
+            line_start = line_end + 1;
+        }
+
+        self.source.len()
+    }
+
+    /// Eat the lines after an `#if 0` as a single token, if there are any. The line break in
+    /// front of the directive which ends them is left in place.
+    fn eat_disabled(&mut self) -> Option<Lexeme<'a>> {
+        if !matches!(self.peek(), Ok('\n')) {
+            return None;
+        }
+
+        let start = self.offset + 1;
+        let mut end = self.disabled_end(start);
+        if end < self.source.len() {
+            end -= 1;
+        }
+        if end <= start {
+            return None;
+        }
+
+        self.eat('\n').ok()?;
+        self.offset = end;
+
+        Some(Lexeme {
+            token: Disabled(self.source[start..end].into()),
+            span: Span::new(start, end),
+            newlines: 1,
+            column: 0,
+        })
+    }
+
+    /// The column of a token which starts at an offset, which is at least the offset up to which
+    /// the source has been searched for line breaks.
+    fn column(&mut self, start: usize) -> usize {
+        let (scanned, column) = self.scanned;
+        let column = match self.source[scanned..start].rfind('\n') {
+            Some(end) => self.source[scanned + end + 1..start].chars().count(),
+            None => column + self.source[scanned..start].chars().count(),
+        };
+        self.scanned = (start, column);
+        column
+    }
+
+    /// Find the next token in the input stream, together with its location.
+    fn next_lexeme(&mut self) -> Result<Lexeme<'a>, LexerError> {
+        if std::mem::take(&mut self.disabled) {
+            if let Some(lexeme) = self.eat_disabled() {
+                return Ok(lexeme);
+            }
+        }
+
+        let newlines = self.trim_leading_whitespace()?;
+        let start = self.offset;
+        let column = self.column(start);
+        let token = self.next_token()?;
+
+        Ok(Lexeme {
+            token,
+            span: Span::new(start, self.offset),
+            newlines,
+            column,
+        })
+    }
+
+    /// Wrap the result of the lexer such that reaching the end of the file stops iteration.
+    fn wrap<T>(&self, result: Result<T, LexerError>) -> Option<Result<T, LexerError>> {
+        match result {
+            Ok(value) => Some(Ok(value)),
             Err(LexerError::EndOfFileReached) => {
                 if self.finished() {
                     None
@@ -304,23 +636,48 @@ impl Iterator for Lexer {
                     Some(Err(LexerError::EndOfFileReached))
                 }
             }
-            err => Some(err),
+            Err(err) => Some(Err(err)),
         }
     }
 }
 
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token<'a>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_lexeme().map(|lexeme| lexeme.token);
+        self.wrap(result)
+    }
+}
+
+/// An iterator over the tokens of a lexer, which also yields their location in the source file.
+#[derive(Debug)]
+pub struct Lexemes<'a> {
+    lexer: Lexer<'a>,
+}
+
+impl<'a> Iterator for Lexemes<'a> {
+    type Item = Result<Lexeme<'a>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.lexer.next_lexeme();
+        self.lexer.wrap(result)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::lexer::token::Token::Number;
-    use crate::lexer::token::TokenKeyword::Auto;
+    use crate::lexer::token::TokenKeyword::{Auto, For};
+    use std::borrow::Cow;
 
     #[test]
     fn empty_string() {
         let input = "".to_string();
         let expected = vec![];
 
-        let lexer = Lexer::new(input);
+        let lexer = Lexer::new(&input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(result, expected);
     }
@@ -328,9 +685,9 @@ mod tests {
     #[test]
     fn single_integer() {
         let input = "24".to_string();
-        let expected = vec![Number("24".to_string())];
+        let expected = vec![Number("24".into())];
 
-        let lexer = Lexer::new(input);
+        let lexer = Lexer::new(&input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(result, expected);
     }
@@ -338,9 +695,9 @@ mod tests {
     #[test]
     fn single_float() {
         let input = "4.63".to_string();
-        let expected = vec![Number("4.63".to_string())];
+        let expected = vec![Number("4.63".into())];
 
-        let lexer = Lexer::new(input);
+        let lexer = Lexer::new(&input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(result, expected);
     }
@@ -348,9 +705,9 @@ mod tests {
     #[test]
     fn single_string() {
         let input = "\"Hello, World!\"".to_string();
-        let expected = vec![Str("Hello, World!".to_string())];
+        let expected = vec![Str("Hello, World!".into())];
 
-        let lexer = Lexer::new(input);
+        let lexer = Lexer::new(&input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(result, expected);
     }
@@ -359,18 +716,18 @@ mod tests {
     fn complex_access() {
         let input = "a.b->c.d->e".to_string();
         let expected = vec![
-            Identifier("a".to_string()),
+            Identifier("a".into()),
             Dot,
-            Identifier("b".to_string()),
+            Identifier("b".into()),
             Arrow,
-            Identifier("c".to_string()),
+            Identifier("c".into()),
             Dot,
-            Identifier("d".to_string()),
+            Identifier("d".into()),
             Arrow,
-            Identifier("e".to_string()),
+            Identifier("e".into()),
         ];
 
-        let lexer = Lexer::new(input);
+        let lexer = Lexer::new(&input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(result, expected);
     }
@@ -381,21 +738,21 @@ mod tests {
         let expected = vec![
             Keyword(For),
             Parenthesis(Left),
-            Identifier("int".to_string()),
-            Identifier("i".to_string()),
+            Identifier("int".into()),
+            Identifier("i".into()),
             Equal,
-            Number("0".to_string()),
+            Number("0".into()),
             Semicolon,
-            Identifier("i".to_string()),
+            Identifier("i".into()),
             Less,
-            Identifier("n".to_string()),
+            Identifier("n".into()),
             Semicolon,
             PlusPlus,
-            Identifier("i".to_string()),
+            Identifier("i".into()),
             Parenthesis(Right),
         ];
 
-        let lexer = Lexer::new(input);
+        let lexer = Lexer::new(&input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(result, expected);
     }
@@ -405,38 +762,268 @@ mod tests {
         let input = "for foreign auto automatic".to_string();
         let expected = vec![
             Keyword(For),
-            Str("foreign".to_string()),
+            Identifier("foreign".into()),
             Keyword(Auto),
-            Str("auto".to_string()),
+            Identifier("automatic".into()),
         ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
     }
 
     #[test]
     fn hello_world() {
         let input = "int main(int argc, char** argv) { printf(\"Hello, World!\"); }";
         let expected = vec![
-            Identifier("int".to_string()),
-            Identifier("main".to_string()),
+            Identifier("int".into()),
+            Identifier("main".into()),
             Parenthesis(Left),
-            Identifier("int".to_string()),
-            Identifier("argc".to_string()),
+            Identifier("int".into()),
+            Identifier("argc".into()),
             Comma,
-            Identifier("char".to_string()),
+            Identifier("char".into()),
             Star,
             Star,
-            Identifier("argv".to_string()),
+            Identifier("argv".into()),
             Parenthesis(Right),
             Brace(Left),
-            Identifier("printf".to_string()),
+            Identifier("printf".into()),
             Parenthesis(Left),
-            Str("Hello, World!".to_string()),
+            Str("Hello, World!".into()),
             Parenthesis(Right),
             Semicolon,
             Brace(Right),
         ];
 
-        let lexer = Lexer::new(input.to_string());
+        let lexer = Lexer::new(input);
         let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
         assert_eq!(expected, result);
     }
+
+    #[test]
+    fn ellipsis() {
+        let input = "(int, ...) a.b .. .5".to_string();
+        let expected = vec![
+            Parenthesis(Left),
+            Identifier("int".into()),
+            Comma,
+            Ellipsis,
+            Parenthesis(Right),
+            Identifier("a".into()),
+            Dot,
+            Identifier("b".into()),
+            Dot,
+            Dot,
+            Number(".5".into()),
+        ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn compound_operators() {
+        let input = "a <<= b >> c && d || e % f".to_string();
+        let expected = vec![
+            Identifier("a".into()),
+            LessLessEqual,
+            Identifier("b".into()),
+            GreaterGreater,
+            Identifier("c".into()),
+            AmpersandAmpersand,
+            Identifier("d".into()),
+            PipePipe,
+            Identifier("e".into()),
+            Percent,
+            Identifier("f".into()),
+        ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn number_suffixes() {
+        let input = "0x1Fu 1.5e-3f .5".to_string();
+        let expected = vec![
+            Number("0x1Fu".into()),
+            Number("1.5e-3f".into()),
+            Number(".5".into()),
+        ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn comments_and_directives() {
+        let input = "#include <stdio.h>\n/* a * b */ x; // done".to_string();
+        let expected = vec![
+            Directive("include <stdio.h>".into()),
+            SlashStar(" a * b ".into()),
+            Identifier("x".into()),
+            Semicolon,
+            SlashSlash(" done".into()),
+        ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn escaped_backslash() {
+        let input = "'\\\\' \"a\\\\\"".to_string();
+        let expected = vec![Character("\\\\".into()), Str("a\\\\".into())];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn lexeme_spans() {
+        let input = "\"é\";\n\n  x".to_string();
+        let expected = vec![
+            Lexeme {
+                token: Str("é".into()),
+                span: Span::new(0, 4),
+                newlines: 0,
+                column: 0,
+            },
+            Lexeme {
+                token: Semicolon,
+                span: Span::new(4, 5),
+                newlines: 0,
+                column: 3,
+            },
+            Lexeme {
+                token: Identifier("x".into()),
+                span: Span::new(9, 10),
+                newlines: 2,
+                column: 2,
+            },
+        ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer
+            .lexemes()
+            .collect::<Result<Vec<Lexeme>, LexerError>>()
+            .unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(result[0].text(&input), "\"é\"");
+    }
+
+    #[test]
+    fn payloads_are_borrowed_from_the_source() {
+        let input = "/* a */ 0x10 \"b\" 'c' // d\n#e\n#if 0\nf\n#endif".to_string();
+        let lexemes = Lexer::new(&input)
+            .lexemes()
+            .collect::<Result<Vec<Lexeme>, LexerError>>()
+            .unwrap();
+
+        for lexeme in lexemes {
+            match lexeme.token {
+                SlashSlash(text) | SlashStar(text) | Number(text) | Str(text) | Character(text)
+                | Directive(text) | Disabled(text) => assert!(matches!(text, Cow::Borrowed(_))),
+                token => panic!("unexpected {}", token),
+            }
+        }
+    }
+
+    #[test]
+    fn line_break_after_line_comment() {
+        let input = "a // b\n#define c \\\n d\ne".to_string();
+        let lexemes = Lexer::new(&input)
+            .lexemes()
+            .collect::<Result<Vec<Lexeme>, LexerError>>()
+            .unwrap();
+
+        let newlines: Vec<usize> = lexemes.iter().map(|lexeme| lexeme.newlines).collect();
+        assert_eq!(newlines, vec![0, 0, 1, 1]);
+        assert_eq!(lexemes[1].span, Span::new(2, 6));
+        assert_eq!(lexemes[2].token, Directive("define c \\\n d".into()));
+    }
+
+    #[test]
+    fn disabled_branches() {
+        let input = "#if 0\n'\n#if 1\n#endif\n\n#else\nx\n#endif".to_string();
+        let expected = vec![
+            Directive("if 0".into()),
+            Disabled("'\n#if 1\n#endif\n".into()),
+            Directive("else".into()),
+            Identifier("x".into()),
+            Directive("endif".into()),
+        ];
+
+        let lexer = Lexer::new(&input);
+        let result = lexer.collect::<Result<Vec<Token>, LexerError>>().unwrap();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn characters_outside_of_ascii() {
+        let input = "\u{a0}naïve = \"é\\\"\";\u{2003}/* ü */\n\tx\n\u{3000}y".to_string();
+        let lexemes = Lexer::new(&input)
+            .lexemes()
+            .collect::<Result<Vec<Lexeme>, LexerError>>()
+            .unwrap();
+
+        let tokens: Vec<Token> = lexemes.iter().map(|lexeme| lexeme.token.clone()).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Identifier("naïve".into()),
+                Equal,
+                Str("é\\\"".into()),
+                Semicolon,
+                SlashStar(" ü ".into()),
+                Identifier("x".into()),
+                Identifier("y".into()),
+            ]
+        );
+        let columns: Vec<usize> = lexemes.iter().map(|lexeme| lexeme.column).collect();
+        assert_eq!(columns, vec![1, 7, 9, 14, 16, 1, 1]);
+        assert_eq!(lexemes[1].span, Span::new(9, 10));
+    }
+
+    #[test]
+    fn lexing_recovers_from_errors() {
+        let input = "a = `x;\n  b /* c";
+        let mut diagnostics = Diagnostics::new();
+        let lexemes = Lexer::new(input).recovering_lexemes(&mut diagnostics);
+
+        let tokens: Vec<Token> = lexemes.iter().map(|lexeme| lexeme.token.clone()).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Identifier("a".into()),
+                Equal,
+                Invalid("`".into()),
+                Identifier("x".into()),
+                Semicolon,
+                Identifier("b".into()),
+                Invalid("/* c".into()),
+            ]
+        );
+        assert_eq!(lexemes[2].span, Span::new(4, 5));
+        assert_eq!((lexemes[6].newlines, lexemes[6].column), (0, 4));
+
+        let warnings: Vec<String> = diagnostics
+            .sorted()
+            .iter()
+            .map(|warning| warning.to_string())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "warning: unknown character, so the code is kept as it was",
+                "warning: unterminated comment, so the code is kept as it was",
+            ]
+        );
+    }
 }
@@ -1,3 +1,6 @@
 pub mod direction;
+#[allow(clippy::module_inception)]
 pub mod lexer;
+pub mod span;
+pub mod symbol;
 pub mod token;
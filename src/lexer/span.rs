@@ -0,0 +1,12 @@
+/// A range of bytes in the source file, where `start` is inclusive and `end` is exclusive.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
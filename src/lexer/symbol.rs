@@ -0,0 +1,141 @@
+use crate::lexer::token::TokenKeyword;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Deref;
+use std::sync::{OnceLock, PoisonError, RwLock};
+
+/// An interned name, such as that of an identifier. Every spelling is stored once for the whole
+/// program, so symbols are small, cheap to copy and compared without looking at their text. The
+/// spellings of the keywords come first, in the order of `TokenKeyword::ALL`, so that telling a
+/// keyword apart from an identifier takes a single lookup.
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Symbol(u32);
+
+/// The spellings of the symbols, indexed by their ids, and the other way around. Spellings are
+/// never freed, since a program only ever uses so many names.
+#[derive(Default)]
+struct Interner {
+    names: Vec<&'static str>,
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+impl Interner {
+    fn insert(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(name) {
+            return symbol;
+        }
+
+        let name: &'static str = Box::leak(name.into());
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name);
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+}
+
+fn interner() -> &'static RwLock<Interner> {
+    static INTERNER: OnceLock<RwLock<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(|| {
+        let mut interner = Interner::default();
+        for keyword in TokenKeyword::ALL {
+            interner.insert(keyword.as_str());
+        }
+        RwLock::new(interner)
+    })
+}
+
+impl Symbol {
+    /// The symbol of a name, which is the same for every occurrence of that name.
+    pub fn intern(name: &str) -> Symbol {
+        let interner = interner();
+        let symbol = interner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .symbols
+            .get(name)
+            .copied();
+        symbol.unwrap_or_else(|| {
+            interner
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(name)
+        })
+    }
+
+    /// The name as it is written in source code.
+    pub fn as_str(self) -> &'static str {
+        interner()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .names[self.0 as usize]
+    }
+
+    /// The keyword which is spelled as the symbol, if any.
+    pub fn keyword(self) -> Option<TokenKeyword> {
+        TokenKeyword::ALL.get(self.0 as usize).copied()
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Symbol {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Symbol {
+        Symbol::intern(&name)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::symbol::Symbol;
+    use crate::lexer::token::TokenKeyword;
+
+    #[test]
+    fn names_are_interned_once() {
+        let name = Symbol::intern("counter");
+        assert_eq!(name, Symbol::intern(&String::from("counter")));
+        assert_ne!(name, Symbol::intern("count"));
+        assert_eq!(name.as_str(), "counter");
+        assert_eq!(name, "counter");
+        assert_eq!(name.keyword(), None);
+
+        for keyword in TokenKeyword::ALL {
+            assert_eq!(Symbol::intern(keyword.as_str()).keyword(), Some(keyword));
+        }
+    }
+}
@@ -1,4 +1,9 @@
 use crate::lexer::direction::Direction;
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::span::Span;
+use crate::lexer::symbol::Symbol;
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
 
 /// Exhaustive list of all keywords.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -15,71 +20,365 @@ pub enum TokenKeyword {
     Case,
     Const,
     Volatile,
-    External,
+    Extern,
     Static,
     Auto,
     Struct,
     Union,
+    Enum,
+    Typedef,
+    Register,
+    Inline,
+    Restrict,
+    Break,
+    Continue,
+    Default,
+    Sizeof,
+    Alignof,
+    Generic,
+    StaticAssert,
 }
 
 impl TokenKeyword {
+    /// Every keyword, in the order of their symbols.
+    pub const ALL: [TokenKeyword; 29] = [
+        TokenKeyword::If,
+        TokenKeyword::Else,
+        TokenKeyword::Return,
+        TokenKeyword::Unsigned,
+        TokenKeyword::For,
+        TokenKeyword::Do,
+        TokenKeyword::While,
+        TokenKeyword::Goto,
+        TokenKeyword::Switch,
+        TokenKeyword::Case,
+        TokenKeyword::Const,
+        TokenKeyword::Volatile,
+        TokenKeyword::Extern,
+        TokenKeyword::Static,
+        TokenKeyword::Auto,
+        TokenKeyword::Struct,
+        TokenKeyword::Union,
+        TokenKeyword::Enum,
+        TokenKeyword::Typedef,
+        TokenKeyword::Register,
+        TokenKeyword::Inline,
+        TokenKeyword::Restrict,
+        TokenKeyword::Break,
+        TokenKeyword::Continue,
+        TokenKeyword::Default,
+        TokenKeyword::Sizeof,
+        TokenKeyword::Alignof,
+        TokenKeyword::Generic,
+        TokenKeyword::StaticAssert,
+    ];
+
     /// Attempt to match a string to a keyword.
     pub fn from(keyword: &str) -> Option<TokenKeyword> {
-        // TODO: Check if this performant enough, or a trie/map is required.
         match keyword {
             "if" => Some(TokenKeyword::If),
             "else" => Some(TokenKeyword::Else),
             "return" => Some(TokenKeyword::Return),
             "unsigned" => Some(TokenKeyword::Unsigned),
             "for" => Some(TokenKeyword::For),
-            "while" => Some(TokenKeyword::While),
             "do" => Some(TokenKeyword::Do),
+            "while" => Some(TokenKeyword::While),
             "goto" => Some(TokenKeyword::Goto),
             "switch" => Some(TokenKeyword::Switch),
             "case" => Some(TokenKeyword::Case),
             "const" => Some(TokenKeyword::Const),
             "volatile" => Some(TokenKeyword::Volatile),
-            "external" => Some(TokenKeyword::External),
+            "extern" => Some(TokenKeyword::Extern),
             "static" => Some(TokenKeyword::Static),
             "auto" => Some(TokenKeyword::Auto),
             "struct" => Some(TokenKeyword::Struct),
             "union" => Some(TokenKeyword::Union),
+            "enum" => Some(TokenKeyword::Enum),
+            "typedef" => Some(TokenKeyword::Typedef),
+            "register" => Some(TokenKeyword::Register),
+            "inline" => Some(TokenKeyword::Inline),
+            "restrict" => Some(TokenKeyword::Restrict),
+            "break" => Some(TokenKeyword::Break),
+            "continue" => Some(TokenKeyword::Continue),
+            "default" => Some(TokenKeyword::Default),
+            "sizeof" => Some(TokenKeyword::Sizeof),
+            "_Alignof" => Some(TokenKeyword::Alignof),
+            "_Generic" => Some(TokenKeyword::Generic),
+            "_Static_assert" => Some(TokenKeyword::StaticAssert),
             &_ => None,
         }
     }
+
+    /// The keyword as it is written in source code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenKeyword::If => "if",
+            TokenKeyword::Else => "else",
+            TokenKeyword::Return => "return",
+            TokenKeyword::Unsigned => "unsigned",
+            TokenKeyword::For => "for",
+            TokenKeyword::Do => "do",
+            TokenKeyword::While => "while",
+            TokenKeyword::Goto => "goto",
+            TokenKeyword::Switch => "switch",
+            TokenKeyword::Case => "case",
+            TokenKeyword::Const => "const",
+            TokenKeyword::Volatile => "volatile",
+            TokenKeyword::Extern => "extern",
+            TokenKeyword::Static => "static",
+            TokenKeyword::Auto => "auto",
+            TokenKeyword::Struct => "struct",
+            TokenKeyword::Union => "union",
+            TokenKeyword::Enum => "enum",
+            TokenKeyword::Typedef => "typedef",
+            TokenKeyword::Register => "register",
+            TokenKeyword::Inline => "inline",
+            TokenKeyword::Restrict => "restrict",
+            TokenKeyword::Break => "break",
+            TokenKeyword::Continue => "continue",
+            TokenKeyword::Default => "default",
+            TokenKeyword::Sizeof => "sizeof",
+            TokenKeyword::Alignof => "_Alignof",
+            TokenKeyword::Generic => "_Generic",
+            TokenKeyword::StaticAssert => "_Static_assert",
+        }
+    }
 }
 
-/// All token types used by cfmt.
+/// All token types used by cfmt. The text of literals, comments and directives is borrowed from
+/// the source file where possible, so lexing allocates little more than the list of tokens.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub enum Token {
+pub enum Token<'a> {
     Plus,
     PlusPlus,
+    PlusEqual,
     Minus,
     MinusMinus,
+    MinusEqual,
     Star,
+    StarEqual,
     Slash,
-    SlashSlash(String),
-    SlashStar(String),
+    SlashEqual,
+    SlashSlash(Cow<'a, str>),
+    SlashStar(Cow<'a, str>),
+    Percent,
+    PercentEqual,
     Bang,
     BangEqual,
     Tilde,
     Caret,
+    CaretEqual,
     Equal,
     EqualEqual,
     Greater,
     GreaterEqual,
+    GreaterGreater,
+    GreaterGreaterEqual,
     Less,
     LessEqual,
+    LessLess,
+    LessLessEqual,
     Brace(Direction),
     Parenthesis(Direction),
     Bracket(Direction),
     Semicolon,
     Ampersand,
+    AmpersandAmpersand,
+    AmpersandEqual,
+    Pipe,
+    PipePipe,
+    PipeEqual,
+    Question,
+    Colon,
     Comma,
     Dot,
+    Ellipsis,
     Arrow,
-    Identifier(String),
-    Number(String),
-    Str(String),
+    Identifier(Symbol),
+    Number(Cow<'a, str>),
+    Str(Cow<'a, str>),
+    Character(Cow<'a, str>),
     Keyword(TokenKeyword),
+    /// A preprocessor line, without the leading `#` but including any escaped newlines.
+    Directive(Cow<'a, str>),
+    /// The lines of a branch which is never compiled, such as the one of `#if 0`, exactly as they
+    /// were written. They need not even consist of valid tokens.
+    Disabled(Cow<'a, str>),
+    /// Text which could not be lexed, such as a stray character or an unterminated comment,
+    /// exactly as it was written. Only a lexer which recovers from errors produces these.
+    Invalid(Cow<'a, str>),
+}
+
+impl Token<'_> {
+    /// Whether the token ends an operand, so that an operator after it is a binary one.
+    pub fn ends_operand(&self) -> bool {
+        matches!(
+            self,
+            Token::Identifier(_)
+                | Token::Number(_)
+                | Token::Str(_)
+                | Token::Character(_)
+                | Token::Parenthesis(Right)
+                | Token::Bracket(Right)
+                | Token::PlusPlus
+                | Token::MinusMinus
+        )
+    }
+
+    /// Whether the token is an operator which is written directly in front of its operand, such
+    /// as the `&` of `&x`, given the token before it. This is the case at the start of a sequence
+    /// of tokens, such as an argument of a macro, and after anything but an operand.
+    pub fn is_prefix_after(&self, previous: Option<&Token>) -> bool {
+        matches!(
+            self,
+            Token::Ampersand
+                | Token::Star
+                | Token::Minus
+                | Token::Plus
+                | Token::Bang
+                | Token::Tilde
+        ) && !previous.is_some_and(Token::ends_operand)
+    }
+
+    /// The token with its text copied out of the source file, so that it can be kept after the
+    /// source is gone, such as in a syntax tree.
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::SlashSlash(text) => Token::SlashSlash(Cow::Owned(text.into_owned())),
+            Token::SlashStar(text) => Token::SlashStar(Cow::Owned(text.into_owned())),
+            Token::Number(text) => Token::Number(Cow::Owned(text.into_owned())),
+            Token::Str(text) => Token::Str(Cow::Owned(text.into_owned())),
+            Token::Character(text) => Token::Character(Cow::Owned(text.into_owned())),
+            Token::Directive(text) => Token::Directive(Cow::Owned(text.into_owned())),
+            Token::Disabled(text) => Token::Disabled(Cow::Owned(text.into_owned())),
+            Token::Invalid(text) => Token::Invalid(Cow::Owned(text.into_owned())),
+            Token::Plus => Token::Plus,
+            Token::PlusPlus => Token::PlusPlus,
+            Token::PlusEqual => Token::PlusEqual,
+            Token::Minus => Token::Minus,
+            Token::MinusMinus => Token::MinusMinus,
+            Token::MinusEqual => Token::MinusEqual,
+            Token::Star => Token::Star,
+            Token::StarEqual => Token::StarEqual,
+            Token::Slash => Token::Slash,
+            Token::SlashEqual => Token::SlashEqual,
+            Token::Percent => Token::Percent,
+            Token::PercentEqual => Token::PercentEqual,
+            Token::Bang => Token::Bang,
+            Token::BangEqual => Token::BangEqual,
+            Token::Tilde => Token::Tilde,
+            Token::Caret => Token::Caret,
+            Token::CaretEqual => Token::CaretEqual,
+            Token::Equal => Token::Equal,
+            Token::EqualEqual => Token::EqualEqual,
+            Token::Greater => Token::Greater,
+            Token::GreaterEqual => Token::GreaterEqual,
+            Token::GreaterGreater => Token::GreaterGreater,
+            Token::GreaterGreaterEqual => Token::GreaterGreaterEqual,
+            Token::Less => Token::Less,
+            Token::LessEqual => Token::LessEqual,
+            Token::LessLess => Token::LessLess,
+            Token::LessLessEqual => Token::LessLessEqual,
+            Token::Brace(direction) => Token::Brace(direction),
+            Token::Parenthesis(direction) => Token::Parenthesis(direction),
+            Token::Bracket(direction) => Token::Bracket(direction),
+            Token::Semicolon => Token::Semicolon,
+            Token::Ampersand => Token::Ampersand,
+            Token::AmpersandAmpersand => Token::AmpersandAmpersand,
+            Token::AmpersandEqual => Token::AmpersandEqual,
+            Token::Pipe => Token::Pipe,
+            Token::PipePipe => Token::PipePipe,
+            Token::PipeEqual => Token::PipeEqual,
+            Token::Question => Token::Question,
+            Token::Colon => Token::Colon,
+            Token::Comma => Token::Comma,
+            Token::Dot => Token::Dot,
+            Token::Ellipsis => Token::Ellipsis,
+            Token::Arrow => Token::Arrow,
+            Token::Identifier(name) => Token::Identifier(name),
+            Token::Keyword(keyword) => Token::Keyword(keyword),
+        }
+    }
+}
+
+impl Display for Token<'_> {
+    /// Write the token as it appeared in the source code.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Plus => write!(f, "+"),
+            Token::PlusPlus => write!(f, "++"),
+            Token::PlusEqual => write!(f, "+="),
+            Token::Minus => write!(f, "-"),
+            Token::MinusMinus => write!(f, "--"),
+            Token::MinusEqual => write!(f, "-="),
+            Token::Star => write!(f, "*"),
+            Token::StarEqual => write!(f, "*="),
+            Token::Slash => write!(f, "/"),
+            Token::SlashEqual => write!(f, "/="),
+            Token::Percent => write!(f, "%"),
+            Token::PercentEqual => write!(f, "%="),
+            Token::Bang => write!(f, "!"),
+            Token::BangEqual => write!(f, "!="),
+            Token::Tilde => write!(f, "~"),
+            Token::Caret => write!(f, "^"),
+            Token::CaretEqual => write!(f, "^="),
+            Token::Equal => write!(f, "="),
+            Token::EqualEqual => write!(f, "=="),
+            Token::Greater => write!(f, ">"),
+            Token::GreaterEqual => write!(f, ">="),
+            Token::GreaterGreater => write!(f, ">>"),
+            Token::GreaterGreaterEqual => write!(f, ">>="),
+            Token::Less => write!(f, "<"),
+            Token::LessEqual => write!(f, "<="),
+            Token::LessLess => write!(f, "<<"),
+            Token::LessLessEqual => write!(f, "<<="),
+            Token::Brace(Left) => write!(f, "{{"),
+            Token::Brace(Right) => write!(f, "}}"),
+            Token::Parenthesis(Left) => write!(f, "("),
+            Token::Parenthesis(Right) => write!(f, ")"),
+            Token::Bracket(Left) => write!(f, "["),
+            Token::Bracket(Right) => write!(f, "]"),
+            Token::Semicolon => write!(f, ";"),
+            Token::Ampersand => write!(f, "&"),
+            Token::AmpersandAmpersand => write!(f, "&&"),
+            Token::AmpersandEqual => write!(f, "&="),
+            Token::Pipe => write!(f, "|"),
+            Token::PipePipe => write!(f, "||"),
+            Token::PipeEqual => write!(f, "|="),
+            Token::Question => write!(f, "?"),
+            Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
+            Token::Dot => write!(f, "."),
+            Token::Ellipsis => write!(f, "..."),
+            Token::Arrow => write!(f, "->"),
+            Token::SlashSlash(text) => write!(f, "//{}", text),
+            Token::SlashStar(text) => write!(f, "/*{}*/", text),
+            Token::Identifier(name) => write!(f, "{}", name),
+            Token::Number(text) => write!(f, "{}", text),
+            Token::Str(text) => write!(f, "\"{}\"", text),
+            Token::Character(text) => write!(f, "'{}'", text),
+            Token::Keyword(keyword) => write!(f, "{}", keyword.as_str()),
+            Token::Directive(text) => write!(f, "#{}", text),
+            Token::Disabled(text) => write!(f, "{}", text),
+            Token::Invalid(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// A token together with its location in the source file and the whitespace leading up to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lexeme<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+    /// The number of line breaks in between this token and the previous one.
+    pub newlines: usize,
+    /// The number of characters in front of the token on its line.
+    pub column: usize,
+}
+
+impl Lexeme<'_> {
+    /// The text of the token as it was written in the source file it was lexed from.
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.span.start..self.span.end]
+    }
 }
@@ -0,0 +1,402 @@
+//! An opinionated C source code formatter. Besides the `cfmt` command, the formatter can be called
+//! from other programs, such as build tools, code generators and test harnesses, through
+//! `format_source`, which formats the code of a single file held in memory.
+
+pub mod bindings;
+pub mod cache;
+pub mod diagnostics;
+pub mod diff;
+pub mod error;
+pub mod files;
+pub mod formatter;
+pub mod git;
+pub mod hook;
+pub mod ignore;
+pub mod incremental;
+pub mod input;
+pub mod json;
+pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod parser;
+pub mod rewrite;
+pub mod settings;
+pub mod statistics;
+pub mod watch;
+
+pub use crate::diagnostics::diagnostic::Diagnostics;
+pub use crate::error::Error;
+pub use crate::formatter::config::FormatConfig as Config;
+pub use crate::settings::builder::ConfigBuilder;
+
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::formatter::fallback::format_tokens;
+use crate::formatter::formatter::{format, format_statements};
+use crate::formatter::guard::normalize_header_guard;
+use crate::formatter::includes::sort_includes;
+use crate::formatter::passthrough::format_with_passthrough;
+use crate::formatter::pragma::keep_pragmas;
+use crate::formatter::range::{format_selections, Selection};
+use crate::formatter::suppression::suppress_formatting;
+use crate::formatter::verify::{verify_idempotency, verify_tokens};
+use crate::lexer::lexer::Lexer;
+use crate::lexer::span::Span;
+use crate::lexer::token::{Lexeme, Token};
+use crate::parser::ast::TranslationUnit;
+use crate::parser::parser::{Parser, ParserError};
+use crate::parser::salvage::salvage;
+use crate::statistics::{Phase, Statistics};
+
+/// Format the code of a source file. Code which cannot be lexed, parsed or formatted is kept as it
+/// was written, as the command does, so this only fails with the diagnostics of a bug in the
+/// formatter. The passes which depend on the name of the file, such as normalizing header guards,
+/// are left out, and an automatic line ending is resolved against the source.
+pub fn format_source(source: &str, config: &Config) -> Result<String, Diagnostics> {
+    format_file_contents(source, "", config, None)
+}
+
+/// Format the code of the source file at a path, or only the selected parts of it, as the command
+/// does. The path is only used by the passes which depend on the name of the file. The code which
+/// cannot be lexed, parsed or formatted is kept as it was written, so this only fails on a bug in
+/// the formatter.
+pub fn format_file_contents(
+    source: &str,
+    file_path: &str,
+    config: &Config,
+    selections: Option<&[Selection]>,
+) -> Result<String, Diagnostics> {
+    let mut statistics = Statistics::default();
+    match format_file(
+        source,
+        file_path,
+        config,
+        selections,
+        Mode::default(),
+        &mut statistics,
+    ) {
+        Ok(formatted) => Ok(formatted.code),
+        Err(failure) => Err(Diagnostics::from_iter([failure.into_diagnostic()])),
+    }
+}
+
+/// How `format_file` formats a file, besides its options.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Mode {
+    /// Whether only the tokens are formatted, without parsing them.
+    pub fallback: bool,
+    /// Whether the formatted code is formatted once more, to check that it stays the same.
+    pub verify: bool,
+}
+
+/// A file formatted by `format_file`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Formatted {
+    /// The formatted code, in which the code which could not be lexed, parsed or formatted is kept
+    /// as it was written.
+    pub code: String,
+    /// The warnings about the code which was kept as it was.
+    pub warnings: Diagnostics,
+}
+
+/// Why `format_file` could not format a file. Both are bugs in the formatter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Failure {
+    /// The formatted code does not have the same meaning as the source, even with the code around
+    /// the change kept as it was. The error refers to the source.
+    Changed(Diagnostic),
+    /// Formatting the formatted code once more changes it. The error refers to the formatted code,
+    /// which is given with it.
+    Unstable(Diagnostic, String),
+}
+
+impl Failure {
+    pub fn into_diagnostic(self) -> Diagnostic {
+        match self {
+            Failure::Changed(error) | Failure::Unstable(error, _) => error,
+        }
+    }
+}
+
+/// Format the code of the source file at a path, or only the selected parts of it, timing every
+/// phase. This is what the command, the language server and the bindings all come down to.
+///
+/// Code which cannot be lexed, parsed or formatted is kept as it was, with a warning, and the rest
+/// of the file is formatted all the same. The code is only formatted if it keeps the meaning of
+/// the source. If the syntax tree got it wrong, the tokens are formatted on their own instead, and
+/// failing that, the code which would change is kept as it was.
+pub fn format_file(
+    source: &str,
+    file_path: &str,
+    config: &Config,
+    selections: Option<&[Selection]>,
+    mode: Mode,
+    statistics: &mut Statistics,
+) -> Result<Formatted, Failure> {
+    let config = &resolved(source, config);
+    let mut warnings = Diagnostics::new();
+    let lexemes = statistics.time(Phase::Lex, || {
+        Lexer::new(source).recovering_lexemes(&mut warnings)
+    });
+    let (mut translation_unit, errors) = statistics.time(Phase::Parse, || {
+        parse(lexemes.clone(), source, file_path, config)
+    });
+
+    let mut fallback = mode.fallback;
+    let mut formatted = statistics.time(Phase::Print, || match fallback {
+        true => format_tokens(&lexemes, config),
+        false => format(&translation_unit, config),
+    });
+    if let Some(error) = verify_tokens(source, &formatted, config) {
+        let tokens = statistics.time(Phase::Print, || format_tokens(&lexemes, config));
+        if !fallback && verify_tokens(source, &tokens, config).is_none() {
+            fallback = true;
+            formatted = tokens;
+        } else if fallback {
+            return Err(Failure::Changed(error));
+        } else {
+            formatted = statistics
+                .time(Phase::Print, || {
+                    let unit = &mut translation_unit;
+                    format_with_passthrough(unit, source, config, &mut warnings)
+                })
+                .map_err(|_| Failure::Changed(error))?;
+        }
+    }
+
+    // The code which could not be parsed is kept as it was, unless only the tokens are formatted.
+    let warning = match fallback {
+        true => Diagnostic::tokens_only,
+        false => Diagnostic::recovered,
+    };
+    report_parser_errors(&errors, warning, &mut warnings);
+
+    if mode.verify {
+        // The code which was kept as it was is kept again, without being reported twice.
+        let mut unreported = Diagnostics::new();
+        let lexemes = Lexer::new(&formatted).recovering_lexemes(&mut unreported);
+        let again = if fallback {
+            format_tokens(&lexemes, config)
+        } else {
+            let (mut again, _) = parse(lexemes, &formatted, file_path, config);
+            format_with_passthrough(&mut again, &formatted, config, &mut unreported)
+                .map_err(|error| Failure::Unstable(error, formatted.clone()))?
+        };
+        if let Some(error) = verify_idempotency(&formatted, &again) {
+            return Err(Failure::Unstable(error, formatted));
+        }
+    }
+
+    // Only the selected parts of the file take the changes, after the whole of it has been
+    // checked.
+    if let Some(selections) = selections {
+        formatted = format_selections(&translation_unit, source, &formatted, selections);
+    }
+    Ok(Formatted {
+        code: formatted,
+        warnings,
+    })
+}
+
+/// Read the syntax tree of the code of a source file, with the passes which rewrite it before it
+/// is formatted applied, so that it can be changed in code and printed again.
+pub fn syntax_tree(
+    source: &str,
+    file_path: &str,
+    config: &Config,
+) -> Result<TranslationUnit, Diagnostics> {
+    let lexemes =
+        lex(source).map_err(|error| Diagnostics::from_iter([Diagnostic::from(&error)]))?;
+    let (translation_unit, errors) = parse(lexemes, source, file_path, config);
+    match errors.is_empty() {
+        true => Ok(translation_unit),
+        false => Err(errors.iter().map(Diagnostic::from).collect()),
+    }
+}
+
+/// Format a single expression, such as `a+b*c`, without a line break after it. This suits code
+/// generators which write C code a piece at a time.
+pub fn format_expression(source: &str, config: &Config) -> Result<String, Diagnostics> {
+    let config = &resolved(source, config);
+    let lexemes =
+        lex(source).map_err(|error| Diagnostics::from_iter([Diagnostic::from(&error)]))?;
+    let (expression, nodes) = Parser::new(lexemes.into_iter())
+        .parse_whole_expression()
+        .map_err(|error| Diagnostics::from_iter([Diagnostic::from(&error)]))?;
+    verified(
+        source,
+        formatter::formatter::format_expression(&nodes[expression], &nodes, config),
+        config,
+    )
+}
+
+/// Format a sequence of statements and declarations, such as part of a function body, as if they
+/// were at the outermost level of indentation.
+pub fn format_statement(source: &str, config: &Config) -> Result<String, Diagnostics> {
+    let config = &resolved(source, config);
+    let lexemes =
+        lex(source).map_err(|error| Diagnostics::from_iter([Diagnostic::from(&error)]))?;
+    let mut parser = Parser::new(lexemes.into_iter());
+    let (block, nodes) = parser.parse_statements();
+    if !parser.errors().is_empty() {
+        return Err(parser.errors().iter().map(Diagnostic::from).collect());
+    }
+    verified(source, format_statements(&block, &nodes, config), config)
+}
+
+/// The options with an automatic line ending resolved against the source.
+fn resolved(source: &str, config: &Config) -> Config {
+    Config {
+        line_ending: config.line_ending.resolve(source),
+        ..config.clone()
+    }
+}
+
+/// The formatted code, as long as it still has the same meaning as the source.
+fn verified(source: &str, formatted: String, config: &Config) -> Result<String, Diagnostics> {
+    match verify_tokens(source, &formatted, config) {
+        Some(error) => Err(Diagnostics::from_iter([error])),
+        None => Ok(formatted),
+    }
+}
+
+/// Split a source file into tokens, failing at the end of the last token which could be lexed.
+pub fn lex(contents: &str) -> Result<Vec<Lexeme<'_>>, Error> {
+    let mut lexemes: Vec<Lexeme> = Vec::new();
+    for lexeme in Lexer::new(contents).lexemes() {
+        match lexeme {
+            Ok(lexeme) => lexemes.push(lexeme),
+            Err(error) => {
+                let end = lexemes.last().map_or(0, |lexeme| lexeme.span.end);
+                return Err(Error::Lex(error, Span::new(end, end)));
+            }
+        }
+    }
+    Ok(lexemes)
+}
+
+/// Parse a source file and apply the passes which rewrite the syntax tree before it is formatted.
+/// The parser recovers from errors, so they are returned to be reported at the very end.
+pub fn parse(
+    lexemes: Vec<Lexeme>,
+    contents: &str,
+    file_path: &str,
+    config: &Config,
+) -> (TranslationUnit, Vec<ParserError>) {
+    let mut parser = Parser::new(lexemes.into_iter());
+    let mut translation_unit = parser.parse_translation_unit();
+
+    // Regions which could not be parsed are kept as they were, rather than giving up on the file.
+    salvage(&mut translation_unit, contents);
+    suppress_formatting(&mut translation_unit, contents);
+    keep_pragmas(&mut translation_unit, contents);
+
+    sort_includes(&mut translation_unit, config.include_sorting, file_path);
+    normalize_header_guard(&mut translation_unit, config.header_guards, file_path);
+
+    (translation_unit, parser.errors().to_vec())
+}
+
+/// Parse a source file as `parse` does, carrying on past the errors. The regions which could not be
+/// parsed are kept as they were written, and reported as warnings, unless they start with code
+/// which could not be lexed, which was reported already.
+pub fn parse_recovering(
+    lexemes: Vec<Lexeme>,
+    contents: &str,
+    file_path: &str,
+    config: &Config,
+    diagnostics: &mut Diagnostics,
+) -> TranslationUnit {
+    let (translation_unit, errors) = parse(lexemes, contents, file_path, config);
+    report_parser_errors(&errors, Diagnostic::recovered, diagnostics);
+    translation_unit
+}
+
+/// Report the errors of the parser as warnings of the given kind, leaving out those at code which
+/// could not be lexed, which were reported already.
+fn report_parser_errors(
+    errors: &[ParserError],
+    warning: fn(Span, String) -> Diagnostic,
+    diagnostics: &mut Diagnostics,
+) {
+    for error in errors {
+        if !matches!(error, ParserError::UnexpectedToken(Token::Invalid(_), _)) {
+            diagnostics.push(warning(error.span(), error.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::diagnostic::Severity;
+    use crate::formatter::config::LineEnding;
+    use crate::statistics::Statistics;
+    use crate::{format_expression, format_file, format_source, format_statement, Config, Mode};
+
+    #[test]
+    fn sources_are_formatted_in_memory() {
+        let config = Config::default();
+        assert_eq!(
+            format_source("int  main(){return 0;}", &config).unwrap(),
+            "int main() {\n    return 0;\n}\n"
+        );
+
+        let config = Config {
+            line_ending: LineEnding::Auto,
+            ..Config::default()
+        };
+        assert_eq!(
+            format_source("int a;\r\nint b;\r\n", &config).unwrap(),
+            "int a;\r\nint b;\r\n"
+        );
+
+        // One construct which does not parse is kept as it was, rather than failing the file.
+        assert_eq!(
+            format_source("int f(void) { x = ; }\nint  y;", &config).unwrap(),
+            "int f(void) {\n    x = ;\n}\n\nint y;\n"
+        );
+    }
+
+    #[test]
+    fn fragments_are_formatted() {
+        let config = Config::default();
+        assert_eq!(
+            format_expression("a+b *f( c,d )", &config).unwrap(),
+            "a + b * f(c, d)"
+        );
+        assert_eq!(
+            format_statement("int x=1;// one\nif(x){x++;}", &config).unwrap(),
+            "int x = 1; // one\nif (x) {\n    x++;\n}\n"
+        );
+
+        assert!(format_expression("a + b;", &config).is_err());
+        let errors = format_statement("x = ;\ny = ;", &config).unwrap_err();
+        assert_eq!(errors.count(Severity::Error), 2);
+    }
+
+    #[test]
+    fn files_are_formatted_past_their_errors() {
+        let config = Config::default();
+        let mut statistics = Statistics::default();
+        let source = "int f(void) { x = ; }\nint  y;\n";
+        let mode = Mode {
+            fallback: false,
+            verify: true,
+        };
+        let formatted = format_file(source, "f.c", &config, None, mode, &mut statistics).unwrap();
+        assert_eq!(formatted.code, "int f(void) {\n    x = ;\n}\n\nint y;\n");
+        assert_eq!(formatted.warnings.count(Severity::Warning), 1);
+
+        // Only the tokens are formatted, which still works on code which does not parse.
+        let mode = Mode {
+            fallback: true,
+            verify: true,
+        };
+        let formatted = format_file(source, "f.c", &config, None, mode, &mut statistics).unwrap();
+        assert_eq!(formatted.code, "int f(void) {\n    x =;\n}\nint y;\n");
+        let warning = &formatted.warnings.sorted()[0].message;
+        assert_eq!(
+            warning,
+            "unexpected `;`, so only the tokens of the code are formatted"
+        );
+    }
+}
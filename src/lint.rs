@@ -0,0 +1,187 @@
+//! Style checks which report where a file breaks the style, rather than rewriting it, so that a
+//! project can adopt the formatter one rule at a time. Every problem is a warning named after the
+//! rule which found it, and rules can be turned off in the `[lint]` table of a configuration file,
+//! as in `line-too-long = false`.
+
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::formatter::config::FormatConfig;
+use crate::lexer::direction::Direction::Left;
+use crate::lexer::lexer::Lexer;
+use crate::lexer::span::Span;
+use crate::lexer::token::{Lexeme, Token};
+
+/// The names of all rules.
+pub const RULES: &[&str] = &[
+    "brace-placement",
+    "line-too-long",
+    "tab-indent",
+    "trailing-whitespace",
+    "final-newline",
+];
+
+/// Check a file against its formatted code, returning a warning for every problem found by the
+/// rules which are turned on, in source order.
+pub fn lint(source: &str, formatted: &str, config: &FormatConfig) -> Vec<Diagnostic> {
+    let tokens = lexemes(source);
+    let mut warnings = brace_placement(&tokens, &lexemes(formatted));
+
+    // Lines which start inside a token, such as a block comment, are left as they are.
+    let inside = |offset: usize| {
+        tokens
+            .iter()
+            .any(|lexeme| lexeme.span.start < offset && offset < lexeme.span.end)
+    };
+
+    let mut start = 0;
+    for line in source.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let code = text.trim_end_matches([' ', '\t']);
+
+        let width = config.column_after(0, text);
+        if width > config.max_line_length {
+            let offset = text
+                .char_indices()
+                .map(|(offset, _)| offset)
+                .find(|&offset| config.column_after(0, &text[..offset]) >= config.max_line_length)
+                .unwrap_or(text.len());
+            warnings.push(Diagnostic::warning(
+                "line-too-long",
+                Span::new(start + offset, start + offset),
+                format!(
+                    "the line is {} columns wide, more than {}",
+                    width, config.max_line_length
+                ),
+            ));
+        }
+
+        let indentation = &code[..code.len() - code.trim_start_matches([' ', '\t']).len()];
+        let wrong = match config.use_tabs {
+            false => indentation.contains('\t').then_some("tabs"),
+            true => indentation
+                .starts_with(&" ".repeat(config.indent_width.max(1)))
+                .then_some("spaces"),
+        };
+        if let Some(wrong) = wrong.filter(|_| !inside(start)) {
+            warnings.push(Diagnostic::warning(
+                "tab-indent",
+                Span::new(start, start + indentation.len()),
+                format!("the line is indented with {}", wrong),
+            ));
+        }
+
+        if config.trim_trailing_whitespace && code.len() < text.len() && !inside(start + code.len())
+        {
+            warnings.push(Diagnostic::warning(
+                "trailing-whitespace",
+                Span::new(start + code.len(), start + text.len()),
+                "the line ends with whitespace".to_string(),
+            ));
+        }
+
+        start += line.len();
+    }
+
+    if config.insert_final_newline && !source.is_empty() && !source.ends_with('\n') {
+        warnings.push(Diagnostic::warning(
+            "final-newline",
+            Span::new(source.len(), source.len()),
+            "the file does not end with a line break".to_string(),
+        ));
+    }
+
+    warnings.retain(|warning| {
+        warning.rule.is_some_and(|rule| {
+            !config
+                .disabled_rules
+                .iter()
+                .any(|disabled| disabled == rule)
+        })
+    });
+    warnings.sort_by_key(|warning| warning.span.start);
+    warnings
+}
+
+/// Report the opening braces which formatting moves onto a line of their own, or onto the end of
+/// the line before. The braces of both files are in the same order, as formatting does not change
+/// the tokens.
+fn brace_placement(source: &[Lexeme], formatted: &[Lexeme]) -> Vec<Diagnostic> {
+    let braces = |lexemes: &[Lexeme]| {
+        lexemes
+            .iter()
+            .filter(|lexeme| lexeme.token == Token::Brace(Left))
+            .map(|lexeme| (lexeme.span, lexeme.newlines > 0))
+            .collect::<Vec<_>>()
+    };
+
+    braces(source)
+        .into_iter()
+        .zip(braces(formatted))
+        .filter(|((_, before), (_, after))| before != after)
+        .map(|((span, _), (_, own_line))| {
+            let message = match own_line {
+                true => "the brace belongs on a line of its own",
+                false => "the brace belongs at the end of the line before",
+            };
+            Diagnostic::warning("brace-placement", span, message.to_string())
+        })
+        .collect()
+}
+
+/// The tokens of a file, up to the first one which cannot be lexed.
+fn lexemes(source: &str) -> Vec<Lexeme<'_>> {
+    Lexer::new(source).lexemes().map_while(Result::ok).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::FormatConfig;
+    use crate::lint::lint;
+
+    fn rules(source: &str, formatted: &str, config: &FormatConfig) -> Vec<(&'static str, usize)> {
+        lint(source, formatted, config)
+            .into_iter()
+            .map(|warning| (warning.rule.unwrap(), warning.span.start))
+            .collect()
+    }
+
+    #[test]
+    fn problems_are_named_after_their_rule() {
+        let config = FormatConfig {
+            max_line_length: 15,
+            ..FormatConfig::default()
+        };
+        let source = "int f()\n{\n\treturn 1;  \n}\nint a_long_name;";
+        let formatted = "int f() {\n    return 1;\n}\nint a_long_name;\n";
+        assert_eq!(
+            rules(source, formatted, &config),
+            vec![
+                ("brace-placement", 8),
+                ("tab-indent", 10),
+                ("trailing-whitespace", 20),
+                ("line-too-long", 40),
+                ("final-newline", 41),
+            ]
+        );
+
+        // Wide characters take up two columns.
+        let source = "// 一二三四五六七\n";
+        assert_eq!(rules(source, source, &config), vec![("line-too-long", 21)]);
+
+        // Tabs inside a block comment are not indentation.
+        let source = "/* a\n\tb */\nint a;\n";
+        assert_eq!(rules(source, source, &FormatConfig::default()), vec![]);
+    }
+
+    #[test]
+    fn rules_can_be_turned_off() {
+        let config = FormatConfig {
+            use_tabs: true,
+            disabled_rules: vec!["final-newline".to_string()],
+            ..FormatConfig::default()
+        };
+        assert_eq!(
+            rules("int f() {\n    return 1;\n}", "", &config),
+            vec![("tab-indent", 10)]
+        );
+    }
+}
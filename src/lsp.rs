@@ -0,0 +1,547 @@
+//! The language server of `cfmt lsp`, so that editors which speak the Language Server Protocol,
+//! such as VS Code and Neovim, format C files without an extension of their own. Documents are
+//! formatted with the options found for their paths, as the command finds them, rather than with
+//! the indentation settings of the editor.
+
+use crate::diff::{replacements, Replacement};
+use crate::format_file_contents;
+use crate::formatter::config::FormatConfig;
+use crate::formatter::range::Selection;
+use crate::json::{parse, Json};
+use crate::settings::modeline::apply_modeline;
+use crate::settings::resolve::{Resolver, Source};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+/// The codes of the errors the server responds with.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+/// The number of bytes from which a formatted document is sent as a single edit, rather than as
+/// the smallest edits which turn it into its formatted code.
+const LARGE_DOCUMENT: usize = 1 << 20;
+
+/// The documents which are open in the editor, by their URIs, and whether the client asked the
+/// server to shut down.
+#[derive(Debug, Default)]
+pub struct Server {
+    documents: HashMap<String, String>,
+    shut_down: bool,
+    exited: bool,
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    /// Whether the client told the server to exit.
+    pub fn exited(&self) -> bool {
+        self.exited
+    }
+
+    /// Handle a message from the client, returning the response if it is a request.
+    pub fn handle(&mut self, message: &Json) -> Option<Json> {
+        let id = message.get("id").cloned();
+        let params = message.get("params").unwrap_or(&Json::Null);
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            return id.map(|id| error(id, INVALID_REQUEST, "the message has no method"));
+        };
+
+        let result = match method {
+            "initialize" => Ok(capabilities()),
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Json::Null)
+            }
+            "exit" => {
+                self.exited = true;
+                return None;
+            }
+            "textDocument/didOpen" => {
+                let document = params.get("textDocument");
+                let text = document.and_then(|document| document.get("text"));
+                if let (Some(uri), Some(text)) = (uri(params), text.and_then(Json::as_str)) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                return None;
+            }
+            "textDocument/didChange" => {
+                // The whole text is sent with every change, as the capabilities ask for.
+                let changes = match params.get("contentChanges") {
+                    Some(Json::Array(changes)) => changes.as_slice(),
+                    _ => &[],
+                };
+                let text = changes.last().and_then(|change| change.get("text"));
+                if let (Some(uri), Some(text)) = (uri(params), text.and_then(Json::as_str)) {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                return None;
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = uri(params) {
+                    self.documents.remove(uri);
+                }
+                return None;
+            }
+            "textDocument/formatting" => self.format(params, Scope::Document),
+            "textDocument/rangeFormatting" => self.format(params, Scope::Range),
+            "textDocument/onTypeFormatting" => self.format(params, Scope::Typed),
+            _ if id.is_none() => return None,
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+        };
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => Json::object([
+                ("jsonrpc", Json::String("2.0".to_string())),
+                ("id", id),
+                ("result", result),
+            ]),
+            Err((code, message)) => error(id, code, &message),
+        })
+    }
+
+    /// The edits which format a document, or only the part of it in the parameters.
+    fn format(&self, params: &Json, scope: Scope) -> Result<Json, (i64, String)> {
+        let uri = uri(params).ok_or((INVALID_PARAMS, "no document is given".to_string()))?;
+        let text = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("{} is not open", uri)))?;
+
+        let selection = match scope {
+            Scope::Range => {
+                let range = params.get("range");
+                let line = |end: &str| -> Option<(usize, usize)> {
+                    let position = range?.get(end)?;
+                    Some((
+                        position.get("line")?.as_usize()?,
+                        position.get("character")?.as_usize()?,
+                    ))
+                };
+                let (Some((first, _)), Some((last, character))) = (line("start"), line("end"))
+                else {
+                    return Err((INVALID_PARAMS, "no range is given".to_string()));
+                };
+                // A range which ends at the start of a line does not cover that line.
+                let last = match character == 0 && last > first {
+                    true => last - 1,
+                    false => last,
+                };
+                Some([Selection::Lines(first + 1, last + 1)])
+            }
+            Scope::Typed => {
+                let position = params.get("position");
+                let at = |key: &str| position?.get(key)?.as_usize();
+                let typed = params.get("ch").and_then(Json::as_str);
+                let (Some(line), Some(character), Some(typed)) =
+                    (at("line"), at("character"), typed)
+                else {
+                    return Err((INVALID_PARAMS, "no position is given".to_string()));
+                };
+                // The position is right after the character which was typed.
+                let offset = offset(text, line, character).saturating_sub(typed.len());
+                Some([Selection::Typed(offset)])
+            }
+            Scope::Document => None,
+        };
+
+        let path = path_of(uri);
+        let file_path = path
+            .as_ref()
+            .map_or(uri.to_string(), |path| path.to_string_lossy().into_owned());
+        let config =
+            config_for(&path, &file_path, text).map_err(|error| (REQUEST_FAILED, error))?;
+        let selections = selection.as_ref().map(|selection| selection.as_slice());
+        let formatted = format_file_contents(text, &file_path, &config, selections)
+            .map_err(|diagnostics| (REQUEST_FAILED, diagnostics.render(&file_path, text)))?;
+
+        let replacements = match text.len() < LARGE_DOCUMENT {
+            true => replacements(text, &formatted),
+            false if text == &formatted => Vec::new(),
+            false => vec![Replacement {
+                offset: 0,
+                length: text.len(),
+                text: formatted,
+            }],
+        };
+        let edits = replacements
+            .iter()
+            .map(|replacement| {
+                let end = replacement.offset + replacement.length;
+                Json::object([
+                    (
+                        "range",
+                        Json::object([
+                            ("start", position(text, replacement.offset)),
+                            ("end", position(text, end)),
+                        ]),
+                    ),
+                    ("newText", Json::String(replacement.text.clone())),
+                ])
+            })
+            .collect();
+        Ok(Json::Array(edits))
+    }
+}
+
+/// The part of a document which a request formats.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Scope {
+    Document,
+    Range,
+    /// The code around a character which was just typed.
+    Typed,
+}
+
+/// What the server can do, in response to `initialize`.
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                // The whole text of a document is sent when it changes.
+                ("textDocumentSync", Json::Number(1)),
+                ("documentFormattingProvider", Json::Bool(true)),
+                ("documentRangeFormattingProvider", Json::Bool(true)),
+                // A block is formatted when it is closed, and a statement when it is ended.
+                (
+                    "documentOnTypeFormattingProvider",
+                    Json::object([
+                        ("firstTriggerCharacter", Json::String("}".to_string())),
+                        (
+                            "moreTriggerCharacter",
+                            Json::Array(vec![Json::String(";".to_string())]),
+                        ),
+                    ]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", Json::String("cfmt".to_string())),
+                (
+                    "version",
+                    Json::String(env!("CARGO_PKG_VERSION").to_string()),
+                ),
+            ]),
+        ),
+    ])
+}
+
+fn error(id: Json, code: i64, message: &str) -> Json {
+    Json::object([
+        ("jsonrpc", Json::String("2.0".to_string())),
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", Json::Integer(code)),
+                ("message", Json::String(message.to_string())),
+            ]),
+        ),
+    ])
+}
+
+/// The URI of the document in the parameters of a message.
+fn uri(params: &Json) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+/// The path of a document with a `file:` URI, with its escaped characters decoded.
+fn path_of(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+    // On Windows, the path starts with a slash in front of the drive, as in `/c:/src/a.c`.
+    match cfg!(windows) {
+        true => Some(PathBuf::from(path.trim_start_matches('/'))),
+        false => Some(PathBuf::from(path)),
+    }
+}
+
+/// The options for a document, found from its path as the command finds them. Documents which
+/// are not files get the defaults.
+fn config_for(path: &Option<PathBuf>, file_path: &str, text: &str) -> Result<FormatConfig, String> {
+    // The configuration files are read again every time, so that changes to them are seen.
+    let mut config = match path {
+        Some(path) => Resolver::new(Source::Discovered)
+            .config_for(path)
+            .map_err(|error| error.to_string())?,
+        None => FormatConfig::default(),
+    };
+    apply_modeline(text, &mut config)
+        .map_err(|error| format!("{}:{}: error: {}", file_path, error.line, error.message))?;
+    Ok(config)
+}
+
+/// The position of a byte offset in a document, where the character counts UTF-16 code units as
+/// the protocol does.
+fn position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Json::object([
+        ("line", Json::Number(before.matches('\n').count())),
+        (
+            "character",
+            Json::Number(before[line_start..].encode_utf16().count()),
+        ),
+    ])
+}
+
+/// The byte offset of a position in a document, the inverse of `position`. Positions past the end
+/// of a line are taken to be at its end.
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => text
+            .match_indices('\n')
+            .nth(line - 1)
+            .map_or(text.len(), |(index, _)| index + 1),
+    };
+    let mut units = 0;
+    for (index, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// Read a message, which is preceded by a header with its length. Returns `None` at the end of the
+/// input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "a message has no Content-Length",
+        )
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Write a message, preceded by a header with its length.
+pub fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// Serve a client until it tells the server to exit, or closes the input. Returns whether it shut
+/// the server down before that, as it should.
+pub fn serve(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<bool> {
+    let mut server = Server::new();
+    while let Some(text) = read_message(input)? {
+        let response = match parse(&text) {
+            Ok(message) => server.handle(&message),
+            Err(message) => Some(error(Json::Null, PARSE_ERROR, &message)),
+        };
+        if let Some(response) = response {
+            write_message(output, &response)?;
+        }
+        if server.exited() {
+            break;
+        }
+    }
+    Ok(server.shut_down)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{parse, Json};
+    use crate::lsp::{read_message, serve, Server, LARGE_DOCUMENT};
+    use std::fs;
+
+    #[test]
+    fn documents_are_formatted_over_stdio() {
+        let root = std::env::temp_dir().join(format!("cfmt-lsp-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(".cfmt.toml"), "indent_width = 2\n").unwrap();
+        let uri = format!("file://{}/a%20b.c", root.display());
+
+        let messages = [
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}"#.to_string(),
+            format!(
+                r#"{{"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {{"textDocument":
+                   {{"uri": "{}", "text": "int a;\nchar *s = \"é\",  *t;\nint f(){{return 0;}}\n"}}}}}}"#,
+                uri
+            ),
+            format!(
+                r#"{{"jsonrpc": "2.0", "id": 2, "method": "textDocument/rangeFormatting", "params":
+                   {{"textDocument": {{"uri": "{}"}}, "range": {{"start": {{"line": 1,
+                   "character": 0}}, "end": {{"line": 2, "character": 0}}}}}}}}"#,
+                uri
+            ),
+            format!(
+                r#"{{"jsonrpc": "2.0", "id": 3, "method": "textDocument/formatting", "params":
+                   {{"textDocument": {{"uri": "{}"}}}}}}"#,
+                uri
+            ),
+            format!(
+                r#"{{"jsonrpc": "2.0", "id": 4, "method": "textDocument/onTypeFormatting",
+                   "params": {{"textDocument": {{"uri": "{}"}}, "position": {{"line": 2,
+                   "character": 18}}, "ch": "}}", "options": {{"tabSize": 8}}}}}}"#,
+                uri
+            ),
+            r#"{"jsonrpc": "2.0", "id": 5, "method": "shutdown"}"#.to_string(),
+            r#"{"jsonrpc": "2.0", "method": "exit"}"#.to_string(),
+        ];
+        let input: String = messages
+            .iter()
+            .map(|message| format!("Content-Length: {}\r\n\r\n{}", message.len(), message))
+            .collect();
+
+        let mut output = Vec::new();
+        assert!(serve(&mut input.as_bytes(), &mut output).unwrap());
+
+        let mut output = output.as_slice();
+        let mut responses = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            responses.push(parse(&message).unwrap());
+        }
+        assert_eq!(responses.len(), 5);
+
+        let capabilities = responses[0].get("result").unwrap().get("capabilities");
+        let formatting = capabilities.unwrap().get("documentFormattingProvider");
+        assert_eq!(formatting, Some(&Json::Bool(true)));
+
+        // Only the second line is formatted, and the positions count the `é` as a single unit
+        // rather than two bytes.
+        let edit = |text: &str, line, start, end| {
+            let position =
+                |character| format!(r#"{{"line": {}, "character": {}}}"#, line, character);
+            parse(&format!(
+                r#"{{"range": {{"start": {}, "end": {}}}, "newText": "{}"}}"#,
+                position(start),
+                position(end),
+                text
+            ))
+            .unwrap()
+        };
+        assert_eq!(
+            responses[1].get("result"),
+            Some(&Json::Array(vec![edit("", 1, 15, 16)]))
+        );
+        let Some(Json::Array(edits)) = responses[2].get("result") else {
+            panic!("formatting responds with edits");
+        };
+        // The options of the configuration file apply.
+        assert_eq!(edits.len(), 5);
+        assert_eq!(edits[3], edit("\\n  ", 2, 8, 8));
+        // Closing the body of the function formats the function, along with the blank line in front
+        // of it, and nothing else.
+        let mut typed = vec![edit("\\n", 2, 0, 0)];
+        typed.extend_from_slice(&edits[2..]);
+        assert_eq!(responses[3].get("result"), Some(&Json::Array(typed)));
+        assert_eq!(responses[4].get("result"), Some(&Json::Null));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn errors_and_large_documents() {
+        let mut server = Server::new();
+        let request = |method: &str, params: &str| {
+            parse(&format!(
+                r#"{{"jsonrpc": "2.0", "id": 1, "method": "{}", "params": {}}}"#,
+                method, params
+            ))
+            .unwrap()
+        };
+
+        // Error codes are written as whole numbers.
+        let response = server.handle(&request("textDocument/hover", "{}")).unwrap();
+        assert!(response.to_string().contains("\"code\": -32601,"));
+
+        let open = |uri: &str, text: &str| {
+            Json::object([
+                ("jsonrpc", Json::String("2.0".to_string())),
+                ("method", Json::String("textDocument/didOpen".to_string())),
+                (
+                    "params",
+                    Json::object([(
+                        "textDocument",
+                        Json::object([
+                            ("uri", Json::String(uri.to_string())),
+                            ("text", Json::String(text.to_string())),
+                        ]),
+                    )]),
+                ),
+            ])
+        };
+        let mut format = |uri: &str, text: &str| {
+            assert_eq!(server.handle(&open(uri, text)), None);
+            let params = format!(r#"{{"textDocument": {{"uri": "{}"}}}}"#, uri);
+            let response = server.handle(&request("textDocument/formatting", &params));
+            match response
+                .as_ref()
+                .and_then(|response| response.get("result"))
+            {
+                Some(Json::Array(edits)) => edits.clone(),
+                _ => panic!("formatting responds with edits"),
+            }
+        };
+
+        // A large document is replaced as a whole.
+        let text = "int  x;\n".repeat(LARGE_DOCUMENT / 8);
+        let edits = format("untitled:large.c", &text);
+        assert_eq!(edits.len(), 1);
+        let new_text = edits[0].get("newText").and_then(Json::as_str);
+        assert_eq!(new_text, Some(text.replace("  ", " ").as_str()));
+
+        // Code which does not parse is kept as it was, rather than failing the request, and the
+        // rest of the document is formatted.
+        let edits = format("untitled:broken.c", "int f(void) { x = ; }\nint  y;\n");
+        assert!(!edits.is_empty());
+        let new_text = edits
+            .iter()
+            .filter_map(|edit| edit.get("newText")?.as_str());
+        assert!(new_text.clone().all(|text| !text.contains("x = ;")));
+    }
+}
@@ -1,29 +1,1189 @@
-extern crate core;
+use cfmt::cache::{default_path, key, Cache};
+use cfmt::diagnostics::diagnostic::{Diagnostic, Diagnostics, Severity};
+use cfmt::diagnostics::location::LineIndex;
+use cfmt::diagnostics::report::{sarif, Report};
+use cfmt::diff::{changed_lines, replacements, unified_diff};
+use cfmt::files::{collect_files, read_file_list, write_atomically, DEFAULT_EXTENSIONS};
+use cfmt::formatter::config::{Encoding, FormatConfig, Style};
+use cfmt::formatter::cursor::map_cursor;
+use cfmt::formatter::infer::infer_style;
+use cfmt::formatter::range::Selection;
+use cfmt::formatter::verify::check_formatted;
+use cfmt::git::{changed_since, parse_diff, Changes};
+use cfmt::hook::{install, script};
+use cfmt::input;
+use cfmt::json::{Json, ToJson};
+use cfmt::lexer::lexer::Lexer;
+use cfmt::lexer::span::Span;
+use cfmt::lint::lint;
+use cfmt::lsp::serve;
+use cfmt::settings::file::ConfigError;
+use cfmt::settings::modeline::apply_modeline;
+use cfmt::settings::resolve::{is_option_flag, Resolver, Source};
+use cfmt::statistics::{Phase, Statistics};
+use cfmt::watch::{Watcher, POLL_INTERVAL};
+use cfmt::{format_file, lex, parse_recovering, Failure, Mode};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{IsTerminal, Read, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex, PoisonError};
+use std::time::Instant;
+use std::{env, fs, io, process, thread};
 
-use crate::lexer::lexer::Lexer;
-use crate::parser::parser::Parser;
-use std::{env, fs};
+const HELP_MESSAGE: &str =
+    "usage: cfmt [--emit=tree|ast|tokens] [--style=kr|linux|gnu|llvm|google|infer] [--verify] \
+     [--fallback] [-i | --in-place | --check | --lint | -l | --dry-run | --watch] [--diff] [--verbose] \
+     [--color=auto|always|never] [--lines=<first>:<last>]... [--offset=<byte>] [--length=<bytes>] \
+     [--on-type=<byte>] [--cursor=<byte>] [--diff-base=<revision>|-] \
+     [--output-replacements=json] [--assume-filename=<path>] [-j <jobs> | --jobs=<jobs>] \
+     [--cache | --cache-path=<path>] [--message-format=human|json|sarif] [--extensions=c,h] [--config-path=<path>] \
+     [--profile=<name>] [--files-from=<file>|- [-0 | --null]] [--<option> <value>]... [<path>... | -]
+       cfmt install-hook [--in-place] [--print | --force]
+       cfmt lsp
 
-mod lexer;
-mod parser;
+Options are taken from, in increasing order of precedence: the defaults, the .editorconfig files \
+which apply to every file, the .cfmt.toml or .clang-format file found upwards from every file or \
+the one given with --config-path, or instead of either the style given with --style, the options \
+in a comment at the start of a file, such as /* cfmt: max_line_length=120 */, and finally the \
+options given on the command line, such as --indent-width 2 or --brace-style=allman.
 
-const HELP_MESSAGE: &str = "usage: cfmt <file path>";
+A configuration file can define profiles, such as [profile.tests], whose options apply to the \
+files matched by the globs in their paths array, as in paths = [\"tests/**\"]. The profile given \
+with --profile applies to every file instead.
+
+Code which cannot be lexed, parsed or formatted without changing its meaning is kept as it was \
+written, with a warning, and the rest of the file is formatted all the same.
+
+Files are read as UTF-8, or as Latin-1 if they are not valid UTF-8, and written back in the same \
+encoding, so that comments and strings keep their bytes. The encoding can be set with the charset \
+of an .editorconfig file or with --encoding=utf8|latin1|auto.
+
+The exit code is 0 on success, 1 if files are not formatted with --check or --dry-run or break a \
+rule with --lint, 2 for invalid options and for files which cannot be read or written, and 3 if \
+the formatter failed on a file. Every file is formatted even if others fail, and the code is that \
+of the worst failure.";
+
+/// The name under which the standard input is reported.
+const STDIN_NAME: &str = "<stdin>";
+
+/// What formatting a file writes to the standard output and the standard error. It is collected
+/// rather than written right away, so that the files which are formatted at the same time are
+/// reported in order. Once all files before it have been written out, the code of a file is
+/// written to the standard output as soon as it is done instead. The code is only done once all
+/// of it has been formatted and checked, so it is not written as it is formatted.
+#[derive(Default)]
+struct Output<'a> {
+    /// The code which is written out is in the encoding of its file, so it is kept as bytes.
+    stdout: Vec<u8>,
+    /// The position of the file among the files, and the number of files which have been written
+    /// out so far.
+    turn: Option<(usize, &'a AtomicUsize)>,
+    stderr: String,
+    /// The problems which are written out in a machine readable format once all files are done.
+    reports: Vec<Report>,
+    statistics: Statistics,
+}
+
+impl<'a> Output<'a> {
+    /// The output of the file at a position among the files, which have been written out up to
+    /// the given number.
+    fn ordered(index: usize, written: &'a AtomicUsize) -> Output<'a> {
+        Output {
+            turn: Some((index, written)),
+            ..Output::default()
+        }
+    }
+
+    fn write(&self) {
+        // The output is written to a pipe which may have been closed, as by `head`, which is not
+        // worth reporting.
+        let _ = io::stdout().write_all(&self.stdout);
+        eprint!("{}", self.stderr);
+    }
+
+    /// Add text or bytes to what is written to the standard output, writing it right away if the
+    /// files before this one have been written out and nothing of this one is held back.
+    fn print(&mut self, text: impl AsRef<[u8]>) {
+        match self.turn {
+            Some((index, written))
+                if self.stdout.is_empty() && written.load(Ordering::Acquire) == index =>
+            {
+                let _ = io::stdout().lock().write_all(text.as_ref());
+            }
+            _ => self.stdout.extend_from_slice(text.as_ref()),
+        }
+    }
+
+    /// Report a problem with a file, in the format asked for.
+    fn report(&mut self, options: &Options, path: &str, source: &str, diagnostic: Diagnostic) {
+        match options.message_format {
+            MessageFormat::Human => self.stderr += &diagnostic.render(path, source),
+            _ => self.reports.push(Report::new(path, source, diagnostic)),
+        }
+    }
+}
+
+/// How formatting ends, from the best to the worst, with the exit code of the formatter. When
+/// many files are formatted, the worst outcome of any of them decides the exit code.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Outcome {
+    Success = 0,
+    /// A file is not formatted with `--check` or `--dry-run`, or breaks a rule with `--lint`.
+    NotFormatted = 1,
+    /// The options are invalid, a file cannot be read or written, or a configuration file cannot
+    /// be read or parsed.
+    InvalidInput = 2,
+    /// The formatter failed on a file, because it would have changed the meaning of the code, or
+    /// because it crashed.
+    InternalError = 3,
+}
+
+impl Outcome {
+    fn exit(self) -> ! {
+        process::exit(self as i32)
+    }
+}
+
+/// How problems with the files are reported.
+enum MessageFormat {
+    /// As text on the standard error, in the style of a compiler.
+    Human,
+    /// As a JSON array on the standard output, once all files are done.
+    Json,
+    /// As a SARIF log on the standard output, once all files are done.
+    Sarif,
+}
+
+/// What gets written to the standard output.
+enum Emit {
+    /// The formatted source code.
+    Formatted,
+    /// The compact S-expression rendering of the syntax tree.
+    Tree,
+    /// The syntax tree as JSON, including spans and comments.
+    Ast,
+    /// The tokens produced by the lexer, one per line.
+    Tokens,
+}
+
+/// The options given on the command line, which apply to every file.
+struct Options {
+    emit: Emit,
+    // Debug builds always check that formatting is idempotent, to catch mistakes early.
+    verify: bool,
+    // Whether the tokens are formatted on their own, without parsing them.
+    fallback: bool,
+    // Whether the style is guessed from each file itself.
+    infer: bool,
+    // Whether files are overwritten with the formatted code, rather than writing it out.
+    in_place: bool,
+    // Whether files are only checked to be formatted already, without writing anything.
+    check: bool,
+    // Whether the problems with the style of files are reported as warnings, without writing
+    // anything.
+    lint: bool,
+    // Whether only the paths of the files which are not formatted are written, one per line.
+    dry_run: bool,
+    // Whether a diff from the source to the formatted code is written instead of the code.
+    diff: bool,
+    // Whether the diff is colored.
+    color: bool,
+    // The parts of the file which are formatted, or nothing to format all of it.
+    selections: Vec<Selection>,
+    // The lines which changed in every file since the revision given with --diff-base, which are
+    // the only ones formatted.
+    changes: Option<Changes>,
+    // The offset of the cursor of an editor, whose place in the formatted code is written in front
+    // of it.
+    cursor: Option<usize>,
+    // The files which are known to be formatted, to skip them when checking or rewriting files.
+    cache: Option<Cache>,
+    // Whether the changes are written as a list of replacements, rather than the formatted code.
+    replacements: bool,
+    message_format: MessageFormat,
+    // Whether statistics about the run are written to the standard error once all files are done.
+    verbose: bool,
+    // Whether large files may be mapped into memory rather than read, which is not done for the
+    // files which are watched, as they are being edited.
+    map: bool,
+}
+
+impl Options {
+    /// The parts of a file, given as an absolute path, which are formatted, or nothing to format
+    /// all of it.
+    fn selections_for(&self, path: &Path) -> Option<&[Selection]> {
+        match &self.changes {
+            Some(changes) => Some(changes.get(path).map_or(&[], Vec::as_slice)),
+            None if self.selections.is_empty() => None,
+            None => Some(&self.selections),
+        }
+    }
+}
+
+/// What the command line gives besides the options which apply to every file: where the files
+/// and their options are found, and how they are formatted.
+struct Arguments {
+    paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+    source: Source,
+    profile: Option<String>,
+    overrides: Vec<(String, String)>,
+    // The path under which the standard input is formatted and reported.
+    assume_filename: Option<String>,
+    jobs: usize,
+    diff_base: Option<String>,
+    watch: bool,
+    // The file with the list of paths to format, and whether they are separated by NUL characters.
+    files_from: Option<String>,
+    nul: bool,
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    if env::args().nth(1).as_deref() == Some("install-hook") {
+        install_hook(env::args().skip(2));
+        return;
+    }
+    if env::args().nth(1).as_deref() == Some("lsp") {
+        serve_lsp();
+    }
 
-    let file_path = args.get(1).expect(HELP_MESSAGE);
-    let contents = fs::read_to_string(file_path).expect("Could not read file.");
-    let lexer = Lexer::new(contents);
+    let (mut options, mut arguments) = parse_arguments();
+    check_combinations(&options);
 
-    // Error handling for the lexer.
-    let tokens = lexer.map(|x| match x {
-        Ok(token) => token,
-        _ => {
-            panic!("An error occurred during lexing.")
+    // The paths listed in a file, or on the standard input, are formatted together with those given
+    // on the command line. An empty list leaves nothing to format.
+    if let Some(list) = &arguments.files_from {
+        arguments.paths.extend(read_paths(list, &arguments));
+        if arguments.paths.is_empty() {
+            return;
+        }
+    }
+
+    let mut resolver = Resolver::new(arguments.source.clone());
+    if let Some(profile) = &arguments.profile {
+        resolver.set_profile(profile);
+    }
+    for (flag, value) in &arguments.overrides {
+        if let Err(error) = resolver.add_override(flag, value) {
+            eprintln!("{}\n{}", error, HELP_MESSAGE);
+            Outcome::InvalidInput.exit();
+        }
+    }
+
+    // Only the lines which changed since a revision are formatted, or those which a unified diff on
+    // the standard input changes. Without a path, the changed files are formatted.
+    if let Some(base) = &arguments.diff_base {
+        options.changes = Some(read_changes(base, &options, &arguments));
+        if arguments.paths.is_empty() {
+            arguments.paths.push(PathBuf::from("."));
         }
+    }
+
+    // Watching formats the files in place whenever they change, in the current directory if no
+    // path is given.
+    if arguments.watch {
+        if options.check
+            || options.diff
+            || options.lint
+            || options.dry_run
+            || options.cursor.is_some()
+            || options.replacements
+            || !options.selections.is_empty()
+            || options.changes.is_some()
+            || arguments.paths.iter().any(|path| path == "-")
+        {
+            eprintln!(
+                "--watch only formats files in place, and cannot be combined with --check, \
+                 --diff, --lint, --dry-run, --cursor, --output-replacements, --lines, --offset, --length, \
+                 --on-type, --diff-base or the standard input\n{}",
+                HELP_MESSAGE
+            );
+            Outcome::InvalidInput.exit();
+        }
+        options.in_place = true;
+        options.map = false;
+        if arguments.paths.is_empty() {
+            arguments.paths.push(PathBuf::from("."));
+        }
+    }
+
+    // Without a path, or with `-`, the source is read from the standard input.
+    if arguments.paths.is_empty() || arguments.paths == [Path::new("-")] {
+        let name = arguments.assume_filename.as_deref().unwrap_or(STDIN_NAME);
+        format_stdin(name, &options, resolver);
+    }
+    if arguments.assume_filename.is_some() {
+        eprintln!(
+            "--assume-filename only applies to the standard input\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+    if arguments.paths.iter().any(|path| path == "-") {
+        eprintln!(
+            "the standard input cannot be formatted together with files\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+
+    if arguments.watch {
+        watch_files(arguments.paths, arguments.extensions, &options, resolver);
+    }
+    let mut files = match collect_files(&arguments.paths, &arguments.extensions) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: could not search the directories: {}", error);
+            Outcome::InvalidInput.exit();
+        }
+    };
+
+    if let Some(changes) = &options.changes {
+        files.retain(|file| fs::canonicalize(file).is_ok_and(|path| changes.contains_key(&path)));
+    }
+
+    if (!options.selections.is_empty() || options.cursor.is_some() || options.replacements)
+        && files.len() > 1
+    {
+        eprintln!(
+            "--lines, --offset, --length, --on-type, --cursor and --output-replacements only \
+             apply to a single file\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+
+    format_files(&files, arguments.jobs, &options, resolver);
+}
+
+/// Read the options and the other arguments from the command line, exiting if any is invalid.
+fn parse_arguments() -> (Options, Arguments) {
+    let mut options = Options {
+        emit: Emit::Formatted,
+        verify: cfg!(debug_assertions),
+        fallback: false,
+        infer: false,
+        in_place: false,
+        check: false,
+        lint: false,
+        dry_run: false,
+        diff: false,
+        color: io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        selections: Vec::new(),
+        changes: None,
+        cursor: None,
+        cache: None,
+        replacements: false,
+        message_format: MessageFormat::Human,
+        verbose: false,
+        map: true,
+    };
+    let mut arguments = Arguments {
+        paths: Vec::new(),
+        extensions: DEFAULT_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+        source: Source::Discovered,
+        profile: None,
+        overrides: Vec::new(),
+        assume_filename: None,
+        jobs: thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+        diff_base: None,
+        watch: false,
+        files_from: None,
+        nul: false,
+    };
+    let mut offset = None;
+    let mut length = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        // Options with a value can be given as `--name=value` or as `--name value`.
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |flag: &str| {
+            inline.clone().or_else(|| args.next()).unwrap_or_else(|| {
+                eprintln!("{} needs a value\n{}", flag, HELP_MESSAGE);
+                Outcome::InvalidInput.exit();
+            })
+        };
+
+        match arg.as_str() {
+            "--emit=tree" => options.emit = Emit::Tree,
+            "--emit=ast" => options.emit = Emit::Ast,
+            "--emit=tokens" => options.emit = Emit::Tokens,
+            "--verify" => options.verify = true,
+            "--fallback" => options.fallback = true,
+            "-i" | "--in-place" => options.in_place = true,
+            "--check" => options.check = true,
+            "--lint" => options.lint = true,
+            "-l" | "--dry-run" => options.dry_run = true,
+            "-v" | "--verbose" => options.verbose = true,
+            "--watch" => arguments.watch = true,
+            "-0" | "--null" => arguments.nul = true,
+            "--diff" => options.diff = true,
+            "--output-replacements=json" => options.replacements = true,
+            "--message-format=human" => options.message_format = MessageFormat::Human,
+            "--message-format=json" => options.message_format = MessageFormat::Json,
+            "--message-format=sarif" => options.message_format = MessageFormat::Sarif,
+            "--color=auto" => {
+                options.color = io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none()
+            }
+            "--color=always" => options.color = true,
+            "--color=never" => options.color = false,
+            "--style=infer" => options.infer = true,
+            _ if arg.starts_with("--style=") => match Style::from_name(&arg["--style=".len()..]) {
+                Some(style) => arguments.source = Source::Style(style),
+                None => {
+                    eprintln!(
+                        "unknown style `{}`\n{}",
+                        &arg["--style=".len()..],
+                        HELP_MESSAGE
+                    );
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            _ if arg.starts_with("--extensions=") => {
+                arguments.extensions = arg["--extensions=".len()..]
+                    .split(',')
+                    .map(|extension| extension.trim_start_matches('.').to_string())
+                    .filter(|extension| !extension.is_empty())
+                    .collect();
+            }
+            _ if flag == "--lines" => match Selection::lines(&value(flag)) {
+                Some(selection) => options.selections.push(selection),
+                None => {
+                    eprintln!("--lines needs a range such as 10:20\n{}", HELP_MESSAGE);
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            _ if flag == "--offset" || flag == "--length" => match value(flag).parse() {
+                Ok(bytes) if flag == "--offset" => offset = Some(bytes),
+                Ok(bytes) => length = Some(bytes),
+                Err(_) => {
+                    eprintln!("{} needs a number of bytes\n{}", flag, HELP_MESSAGE);
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            // The offset of a character an editor just typed, around which the code is formatted.
+            _ if flag == "--on-type" => match value(flag).parse() {
+                Ok(offset) => options.selections.push(Selection::Typed(offset)),
+                Err(_) => {
+                    eprintln!("--on-type needs a byte offset\n{}", HELP_MESSAGE);
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            _ if flag == "--cursor" => match value(flag).parse() {
+                Ok(offset) => options.cursor = Some(offset),
+                Err(_) => {
+                    eprintln!("--cursor needs a byte offset\n{}", HELP_MESSAGE);
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            _ if flag == "--jobs" || flag == "-j" => match value(flag).parse() {
+                Ok(count) if count > 0 => arguments.jobs = count,
+                _ => {
+                    eprintln!("{} needs a number of one or more\n{}", flag, HELP_MESSAGE);
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            "--cache" => match default_path() {
+                Some(path) => options.cache = Some(Cache::load(&path)),
+                None => {
+                    eprintln!("there is no cache directory, use --cache-path instead");
+                    Outcome::InvalidInput.exit();
+                }
+            },
+            _ if flag == "--cache-path" => {
+                options.cache = Some(Cache::load(Path::new(&value(flag))))
+            }
+            _ if flag == "--diff-base" => arguments.diff_base = Some(value(flag)),
+            _ if flag == "--files-from" => arguments.files_from = Some(value(flag)),
+            _ if flag == "--assume-filename" => arguments.assume_filename = Some(value(flag)),
+            _ if flag == "--config-path" => arguments.source = Source::File(value(flag).into()),
+            _ if flag == "--profile" => arguments.profile = Some(value(flag)),
+            _ if is_option_flag(flag) => arguments.overrides.push((flag.to_string(), value(flag))),
+            _ if arg.starts_with('-') && arg != "-" => {
+                eprintln!("{}", HELP_MESSAGE);
+                Outcome::InvalidInput.exit();
+            }
+            _ => arguments.paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if offset.is_some() || length.is_some() {
+        let selection = Selection::Bytes(offset.unwrap_or(0), length);
+        options.selections.push(selection);
+    }
+    (options, arguments)
+}
+
+/// Exit if options are given together which cannot be combined.
+fn check_combinations(options: &Options) {
+    if (options.cursor.is_some() || options.replacements)
+        && (options.in_place || options.check || options.diff)
+    {
+        eprintln!(
+            "--cursor and --output-replacements only apply when the formatted code is written \
+             out\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+    if options.in_place && options.check {
+        eprintln!(
+            "--in-place and --check cannot be combined\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+    if options.lint
+        && (options.in_place
+            || options.check
+            || options.diff
+            || options.cursor.is_some()
+            || options.replacements)
+    {
+        eprintln!(
+            "--lint cannot be combined with --in-place, --check, --diff, --cursor or \
+             --output-replacements\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+    if options.dry_run
+        && (options.in_place
+            || options.check
+            || options.lint
+            || options.diff
+            || options.cursor.is_some()
+            || options.replacements)
+    {
+        eprintln!(
+            "--dry-run cannot be combined with --in-place, --check, --lint, --diff, --cursor or \
+             --output-replacements\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+}
+
+/// Read the list of paths given with `--files-from`, from a file or the standard input, exiting if
+/// it cannot be read.
+fn read_paths(list: &str, arguments: &Arguments) -> Vec<PathBuf> {
+    if list == "-"
+        && (arguments.diff_base.as_deref() == Some("-")
+            || arguments.paths.iter().any(|path| path == "-"))
+    {
+        eprintln!(
+            "the standard input cannot be read for --files-from and something else\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+
+    let mut contents = Vec::new();
+    let read = match list {
+        "-" => io::stdin().read_to_end(&mut contents).map(|_| contents),
+        _ => fs::read(list),
+    };
+    match read {
+        Ok(contents) => read_file_list(&contents, arguments.nul),
+        Err(error) => {
+            eprintln!(
+                "error: could not read the list of files {}: {}",
+                list, error
+            );
+            Outcome::InvalidInput.exit();
+        }
+    }
+}
+
+/// Find the lines which changed since a revision, or which the unified diff on the standard input
+/// changes with `-`, exiting if they cannot be found.
+fn read_changes(base: &str, options: &Options, arguments: &Arguments) -> Changes {
+    if !options.selections.is_empty() || arguments.paths.iter().any(|path| path == "-") {
+        eprintln!(
+            "--diff-base cannot be combined with --lines, --offset, --length, --on-type or the \
+             standard input\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+
+    let changes = match base {
+        "-" => {
+            let mut diff = String::new();
+            io::stdin()
+                .read_to_string(&mut diff)
+                .map(|_| parse_diff(&diff, &env::current_dir().unwrap_or_default()))
+                .map_err(|error| error.to_string())
+        }
+        _ => changed_since(base),
+    };
+    changes.unwrap_or_else(|error| {
+        eprintln!("error: could not find the lines which changed: {}", error);
+        Outcome::InvalidInput.exit();
+    })
+}
+
+/// Format the code on the standard input, as if it was read from the file with the given name.
+fn format_stdin(name: &str, options: &Options, mut resolver: Resolver) -> ! {
+    if options.in_place {
+        eprintln!(
+            "the standard input cannot be formatted in place\n{}",
+            HELP_MESSAGE
+        );
+        Outcome::InvalidInput.exit();
+    }
+
+    // The options are found as if the standard input was read from the file it is assumed to be,
+    // and that file also names it in diagnostics and tells whether it is a header.
+    let path = env::current_dir().unwrap_or_default().join(name);
+    let mut bytes = Vec::new();
+    let decoded = io::stdin()
+        .read_to_end(&mut bytes)
+        .and_then(|_| decode(&bytes, &path, &mut resolver));
+    let (contents, encoding) = match decoded {
+        Ok(decoded) => decoded,
+        Err(error) => {
+            eprintln!("error: could not read the standard input: {}", error);
+            Outcome::InvalidInput.exit();
+        }
+    };
+    let config = match config_for(name, &path, &contents, encoding, options, &mut resolver) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("{}", error);
+            Outcome::InvalidInput.exit();
+        }
+    };
+    let written = AtomicUsize::new(0);
+    let mut output = Output::ordered(0, &written);
+    let selections = options.selections_for(&path);
+    let outcome = catch_crash(name, &mut output, |output| {
+        format_contents(name, &contents, &config, options, selections, output)
     });
+    output.write();
+    write_reports(options, &output.reports);
+    if options.verbose {
+        let mut statistics = Statistics::default();
+        statistics.add(name, &output.statistics);
+        eprint!("{}", statistics.render());
+    }
+    save_cache(options);
+    outcome.exit();
+}
+
+/// Format the files with a number of jobs, and write out what they report in the order of the
+/// files.
+fn format_files(files: &[PathBuf], jobs: usize, options: &Options, resolver: Resolver) -> ! {
+    // Every file is formatted, even if an earlier one failed, so that all problems are reported.
+    // The jobs take the next file until there are none left, and what they write is passed back to
+    // be written out in the order of the files.
+    let resolver = Mutex::new(resolver);
+    let next = AtomicUsize::new(0);
+    let written = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut outcome = Outcome::Success;
+    let mut reports = Vec::new();
+    let mut statistics = Statistics::default();
+
+    thread::scope(|scope| {
+        let (resolver, next, written) = (&resolver, &next, &written);
+        let work = move |sender: mpsc::Sender<_>| loop {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(file) = files.get(index) else {
+                break;
+            };
+            let mut output = Output::ordered(index, written);
+            let outcome = format_path(file, options, resolver, &mut output);
+            if sender.send((index, output, outcome)).is_err() {
+                break;
+            }
+        };
+
+        let mut started = 0;
+        for _ in 0..jobs.min(files.len()) {
+            let sender = sender.clone();
+            // The parser recurses into nested code, so the jobs get as much stack as the main
+            // thread has.
+            let job = thread::Builder::new().stack_size(8 << 20);
+            match job.spawn_scoped(scope, move || work(sender)) {
+                Ok(_) => started += 1,
+                Err(error) => {
+                    eprintln!("warning: could not start a job: {}", error);
+                    break;
+                }
+            }
+        }
+        // Without any jobs, the files are formatted on this thread instead.
+        if started == 0 {
+            work(sender.clone());
+        }
+        drop(sender);
+
+        // Only the files which finish before the ones in front of them are held back here.
+        let mut finished = HashMap::new();
+        for (index, output, file_outcome) in receiver {
+            finished.insert(index, (output, file_outcome));
+            let mut count = written.load(Ordering::Acquire);
+            while let Some((output, file_outcome)) = finished.remove(&count) {
+                output.write();
+                reports.extend(output.reports);
+                statistics.add(&files[count].to_string_lossy(), &output.statistics);
+                outcome = outcome.max(file_outcome);
+                count += 1;
+                written.store(count, Ordering::Release);
+            }
+        }
+    });
+
+    write_reports(options, &reports);
+    if options.verbose {
+        eprint!("{}", statistics.render());
+    }
+    save_cache(options);
+    outcome.exit();
+}
+
+/// Install a pre-commit hook which checks the staged files, or formats them with `--in-place`, or
+/// only write it out with `--print`.
+fn install_hook(args: impl Iterator<Item = String>) {
+    let (mut in_place, mut print, mut force) = (false, false, false);
+    for arg in args {
+        match arg.as_str() {
+            "-i" | "--in-place" => in_place = true,
+            "--print" => print = true,
+            "--force" => force = true,
+            _ => {
+                eprintln!("{}", HELP_MESSAGE);
+                Outcome::InvalidInput.exit();
+            }
+        }
+    }
+
+    let script = script(in_place);
+    if print {
+        print!("{}", script);
+        return;
+    }
+    match install(&script, force) {
+        Ok(path) => eprintln!("installed the pre-commit hook in {}", path.display()),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            Outcome::InvalidInput.exit();
+        }
+    }
+}
+
+/// Run the language server on the standard input and output, until the client tells it to exit.
+fn serve_lsp() -> ! {
+    if env::args().nth(2).is_some() {
+        eprintln!("{}", HELP_MESSAGE);
+        Outcome::InvalidInput.exit();
+    }
+    match serve(&mut io::stdin().lock(), &mut io::stdout().lock()) {
+        Ok(true) => Outcome::Success.exit(),
+        // The client exited without shutting the server down first.
+        Ok(false) => Outcome::NotFormatted.exit(),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            Outcome::InternalError.exit();
+        }
+    }
+}
+
+/// Format the files in some paths in place whenever they change, until the formatter is stopped.
+/// Problems are reported as they are found, and do not stop the watching.
+fn watch_files(
+    paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+    options: &Options,
+    resolver: Resolver,
+) -> ! {
+    let mut watcher = match Watcher::new(paths, extensions) {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            eprintln!("error: could not search the directories: {}", error);
+            Outcome::InvalidInput.exit();
+        }
+    };
+    let resolver = Mutex::new(resolver);
+
+    loop {
+        thread::sleep(POLL_INTERVAL);
+        // Files can disappear while the directories are searched, which is tried again later.
+        let Ok(files) = watcher.poll(Instant::now()) else {
+            continue;
+        };
+
+        for file in files {
+            let mut output = Output::default();
+            format_path(&file, options, &resolver, &mut output);
+            output.write();
+            write_reports(options, &output.reports);
+            if output.statistics.changed > 0 {
+                eprintln!("{}: formatted", file.display());
+                watcher.written(&file);
+            }
+        }
+    }
+}
+
+/// Write out the problems with all files, if they are reported in a machine readable format.
+fn write_reports(options: &Options, reports: &[Report]) {
+    match options.message_format {
+        MessageFormat::Human => {}
+        MessageFormat::Json => {
+            let reports = reports.iter().map(ToJson::to_json).collect();
+            println!("{}", Json::Array(reports));
+        }
+        MessageFormat::Sarif => println!("{}", sarif(reports)),
+    }
+}
+
+/// Write back the files which turned out to be formatted. A cache which cannot be written only
+/// makes the next run slower, so that is not an error.
+fn save_cache(options: &Options) {
+    if let Some(Err(error)) = options.cache.as_ref().map(Cache::save) {
+        eprintln!("warning: could not write the cache: {}", error);
+    }
+}
+
+/// Read and format a file from the list of files to format.
+fn format_path(
+    file: &Path,
+    options: &Options,
+    resolver: &Mutex<Resolver>,
+    output: &mut Output,
+) -> Outcome {
+    let file_path = file.to_string_lossy();
+    let path = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+
+    // A job which crashed while resolving options leaves them as they were, so the lock is taken
+    // even then.
+    let bytes = input::read(file, options.map);
+    let mut resolver = resolver.lock().unwrap_or_else(PoisonError::into_inner);
+    let decoded = match &bytes {
+        Ok(bytes) => decode(bytes, &path, &mut resolver),
+        Err(error) => Err(io::Error::new(error.kind(), error.to_string())),
+    };
+    let (contents, encoding) = match decoded {
+        Ok(decoded) => decoded,
+        Err(error) => {
+            output.stderr += &format!("{}: error: could not read the file: {}\n", file_path, error);
+            return Outcome::InvalidInput;
+        }
+    };
+    let config = config_for(
+        &file_path,
+        &path,
+        &contents,
+        encoding,
+        options,
+        &mut resolver,
+    );
+    drop(resolver);
+    let config = match config {
+        Ok(config) => config,
+        Err(error) => {
+            output.stderr += &format!("{}\n", error);
+            return Outcome::InvalidInput;
+        }
+    };
+
+    let selections = options.selections_for(&path);
+    catch_crash(&file_path, output, |output| {
+        format_contents(&file_path, &contents, &config, options, selections, output)
+    })
+}
+
+/// Run the formatting of a single file, turning a crash of the formatter into an internal error
+/// of that file, so that the other files are still formatted.
+fn catch_crash(
+    file_path: &str,
+    output: &mut Output,
+    run: impl FnOnce(&mut Output) -> Outcome,
+) -> Outcome {
+    match panic::catch_unwind(AssertUnwindSafe(|| run(output))) {
+        Ok(outcome) => outcome,
+        Err(_) => {
+            output.stderr += &format!(
+                "{}: internal error: the formatter crashed on this file\n",
+                file_path
+            );
+            Outcome::InternalError
+        }
+    }
+}
+
+/// The options for a file, given both as it is reported and as an absolute path, which are guessed
+/// from its contents with `--style=infer`, and overridden by those in a comment at its start.
+/// Automatic line endings are resolved against the contents, and the encoding is the one the file
+/// was read in. Fails if a configuration file which applies to the file, or the comment, is
+/// invalid.
+fn config_for(
+    file_path: &str,
+    path: &Path,
+    contents: &str,
+    encoding: Encoding,
+    options: &Options,
+    resolver: &mut Resolver,
+) -> Result<FormatConfig, ConfigError> {
+    let mut config = if options.infer {
+        // A file which cannot be lexed is reported once it is formatted.
+        let lexemes = lex(contents).unwrap_or_default();
+        infer_style(&lexemes, contents)
+    } else {
+        resolver.config_for(path)?
+    };
+
+    // The options in a comment at the start of the file apply below those on the command line.
+    apply_modeline(contents, &mut config).map_err(|error| ConfigError {
+        path: PathBuf::from(file_path),
+        line: Some(error.line),
+        message: error.message,
+    })?;
+    resolver.apply_overrides(&mut config);
+
+    config.line_ending = config.line_ending.resolve(contents);
+    config.encoding = encoding;
+    Ok(config)
+}
+
+/// The text of a file in its encoding, as set for its path, together with that encoding, which is
+/// resolved against the bytes. Fails if the file is not in that encoding. Options which are
+/// invalid are reported once the file is formatted, so the encoding is detected then.
+fn decode<'a>(
+    bytes: &'a [u8],
+    path: &Path,
+    resolver: &mut Resolver,
+) -> io::Result<(Cow<'a, str>, Encoding)> {
+    let encoding = resolver
+        .config_for(path)
+        .map_or(Encoding::Auto, |config| config.encoding)
+        .resolve(bytes);
+    Ok((input::decode(bytes, encoding)?, encoding))
+}
+
+/// Format a single file, or only the selected parts of it, or write out its syntax tree or tokens.
+/// Problems are reported on the standard error, and the outcome tells whether there were any.
+fn format_contents(
+    file_path: &str,
+    contents: &str,
+    config: &FormatConfig,
+    options: &Options,
+    selections: Option<&[Selection]>,
+    output: &mut Output,
+) -> Outcome {
+    output.statistics.files = 1;
+
+    // A file which is known to be formatted is skipped, unless its code is to be written out.
+    let cache = options.cache.as_ref().filter(|_| {
+        matches!(options.emit, Emit::Formatted)
+            && selections.is_none()
+            && (options.check || options.dry_run || options.in_place || options.diff)
+    });
+    let known = |code: &str| key(file_path, code, config, options.fallback);
+    if cache.is_some_and(|cache| cache.contains(known(contents))) {
+        return Outcome::Success;
+    }
+
+    let mode = Mode {
+        fallback: options.fallback,
+        verify: options.verify,
+    };
+    let mut diagnostics = Diagnostics::new();
+    let formatted = match options.emit {
+        Emit::Tokens => return print_tokens(file_path, contents, options, output),
+        Emit::Tree | Emit::Ast => {
+            let statistics = &mut output.statistics;
+            let lexemes = statistics.time(Phase::Lex, || {
+                Lexer::new(contents).recovering_lexemes(&mut diagnostics)
+            });
+            let translation_unit = statistics.time(Phase::Parse, || {
+                parse_recovering(lexemes, contents, file_path, config, &mut diagnostics)
+            });
+            match options.emit {
+                Emit::Tree => output.print(translation_unit.to_string()),
+                _ => output.print(format!("{}\n", translation_unit.to_json())),
+            }
+            return report(
+                file_path,
+                contents,
+                options,
+                diagnostics,
+                Outcome::Success,
+                output,
+            );
+        }
+        Emit::Formatted => {
+            let statistics = &mut output.statistics;
+            match format_file(contents, file_path, config, selections, mode, statistics) {
+                Ok(formatted) => {
+                    diagnostics = formatted.warnings;
+                    formatted.code
+                }
+                Err(Failure::Changed(error)) => {
+                    output.report(options, file_path, contents, error);
+                    return Outcome::InternalError;
+                }
+                Err(Failure::Unstable(error, formatted)) => {
+                    output.report(options, file_path, &formatted, error);
+                    return Outcome::InternalError;
+                }
+            }
+        }
+    };
+
+    // Diffing the lines of a large file takes longer than formatting it, so the changed lines are
+    // only counted when the statistics are written out.
+    if formatted != contents {
+        output.statistics.changed = 1;
+        if options.verbose {
+            output.statistics.lines = changed_lines(contents, &formatted);
+        }
+    }
+
+    if options.diff {
+        let diff = unified_diff(file_path, contents, &formatted, options.color);
+        output.print(input::encode(&diff, config.encoding));
+    }
+
+    let mut outcome = Outcome::Success;
+    if options.lint {
+        for warning in lint(contents, &formatted, config) {
+            diagnostics.push(warning);
+        }
+    } else if options.dry_run {
+        if formatted != contents {
+            output.print(format!("{}\n", file_path));
+            outcome = Outcome::NotFormatted;
+        }
+    } else if options.check {
+        if let Some(error) = check_formatted(contents, &formatted) {
+            output.report(options, file_path, contents, error);
+            outcome = Outcome::NotFormatted;
+        }
+    } else if !options.in_place {
+        if !options.diff {
+            // The place of the cursor is written on a line of its own, as editors expect.
+            if let Some(cursor) = options.cursor {
+                let cursor = map_cursor(contents, &formatted, cursor);
+                output.print(format!("{{ \"cursor\": {} }}\n", cursor));
+            }
+            if options.replacements {
+                let replacements = replacements(contents, &formatted);
+                let list = replacements.iter().map(ToJson::to_json).collect();
+                output.print(format!("{}\n", Json::Array(list)));
+            } else {
+                output.print(input::encode(&formatted, config.encoding));
+            }
+        }
+    } else if formatted != contents {
+        let code = input::encode(&formatted, config.encoding);
+        if let Err(error) = write_atomically(Path::new(file_path), &code) {
+            output.stderr += &format!(
+                "{}: error: could not write the file: {}\n",
+                file_path, error
+            );
+            return Outcome::InvalidInput;
+        }
+    }
+
+    // The code which was kept as it was is only reported, and the file is not cached, so that it
+    // is reported again.
+    if diagnostics.is_empty() && (formatted == contents || options.in_place) {
+        if let Some(cache) = cache {
+            cache.insert(known(&formatted));
+        }
+    }
+    report(file_path, contents, options, diagnostics, outcome, output)
+}
+
+/// Report the warnings and errors found in a file, returning the outcome of the file with them.
+/// The rules of `--lint` fail the file.
+fn report(
+    file_path: &str,
+    contents: &str,
+    options: &Options,
+    diagnostics: Diagnostics,
+    outcome: Outcome,
+    output: &mut Output,
+) -> Outcome {
+    if diagnostics.is_empty() {
+        return outcome;
+    }
+    match options.message_format {
+        MessageFormat::Human => output.stderr += &diagnostics.render(file_path, contents),
+        _ => {
+            for diagnostic in diagnostics.sorted() {
+                output.report(options, file_path, contents, diagnostic.clone());
+            }
+        }
+    }
+
+    let broken = diagnostics
+        .sorted()
+        .iter()
+        .any(|warning| warning.rule.is_some());
+    match diagnostics.count(Severity::Error) {
+        0 if broken => outcome.max(Outcome::NotFormatted),
+        0 => outcome,
+        _ => Outcome::InvalidInput,
+    }
+}
+
+/// Write every token on its own line, together with its location, its byte span and the number of
+/// line breaks in front of it. Lexing stops at the first error, which is reported where the code
+/// which could not be lexed starts, past the whitespace after the last token, as it is when
+/// formatting. Fails if the whole file could not be lexed.
+fn print_tokens(
+    file_path: &str,
+    contents: &str,
+    options: &Options,
+    output: &mut Output,
+) -> Outcome {
+    let lines = LineIndex::new(contents);
+    let mut end = 0;
+
+    for lexeme in Lexer::new(contents).lexemes() {
+        let lexeme = match lexeme {
+            Ok(lexeme) => lexeme,
+            Err(error) => {
+                let rest = &contents[end..];
+                let start = end + rest.len() - rest.trim_start().len();
+                if let MessageFormat::Human = options.message_format {
+                    let location = lines.location(start);
+                    output.stderr += &format!(
+                        "{}:{}:{}: error: {}\n",
+                        file_path, location.line, location.column, error
+                    );
+                } else {
+                    let diagnostic = Diagnostic::error(Span::new(start, start), error.to_string());
+                    output.report(options, file_path, contents, diagnostic);
+                }
+                return Outcome::InvalidInput;
+            }
+        };
+
+        let location = lines.location(lexeme.span.start);
+        let span = format!("{}..{}", lexeme.span.start, lexeme.span.end);
+        output.print(format!(
+            "{:>4}:{:<4} {:<12} {:?}",
+            location.line, location.column, span, lexeme.token
+        ));
+        if lexeme.newlines > 0 {
+            output.print(format!("  newlines={}", lexeme.newlines));
+        }
+        output.print("\n");
+
+        end = lexeme.span.end;
+    }
 
-    let parser = Parser::new();
-    let parse_tree = parser.parse(tokens);
+    Outcome::Success
 }
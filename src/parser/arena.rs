@@ -0,0 +1,116 @@
+//! Typed arenas backing the syntax tree. Rather than owning their children through a `Box` each,
+//! recursive nodes refer to them by an `Id` into an arena, so that the nodes of a translation unit
+//! end up next to each other in a handful of large allocations. The arena also records where in
+//! the source each value came from.
+
+use std::fmt::{Debug, Formatter, Result};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::{Index, IndexMut};
+
+use crate::lexer::span::Span;
+
+/// The position of a value in an `Arena<T>`.
+pub struct Id<T> {
+    index: u32,
+    marker: PhantomData<fn() -> T>,
+}
+
+// The implementations are written out by hand, since deriving them would require `T` to implement
+// the same traits.
+impl<T> Clone for Id<T> {
+    fn clone(&self) -> Id<T> {
+        *self
+    }
+}
+
+impl<T> Copy for Id<T> {}
+
+impl<T> PartialEq for Id<T> {
+    fn eq(&self, other: &Id<T>) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Id<T> {}
+
+impl<T> Hash for Id<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<T> Debug for Id<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "#{}", self.index)
+    }
+}
+
+/// An append-only collection of values of a single type, addressed by their `Id`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Arena<T> {
+    values: Vec<T>,
+    spans: Vec<Span>,
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena {
+            values: Vec::new(),
+            spans: Vec::new(),
+        }
+    }
+}
+
+impl<T> Arena<T> {
+    /// Move a value into the arena together with its source range, returning its id.
+    pub fn alloc(&mut self, value: T, span: Span) -> Id<T> {
+        let index = u32::try_from(self.values.len()).expect("arena is full");
+        self.values.push(value);
+        self.spans.push(span);
+        Id {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// The source range of the value with the given id.
+    pub fn span(&self, id: Id<T>) -> Span {
+        self.spans[id.index as usize]
+    }
+}
+
+impl<T> Index<Id<T>> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, id: Id<T>) -> &T {
+        &self.values[id.index as usize]
+    }
+}
+
+impl<T> IndexMut<Id<T>> for Arena<T> {
+    fn index_mut(&mut self, id: Id<T>) -> &mut T {
+        &mut self.values[id.index as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::span::Span;
+    use crate::parser::arena::Arena;
+
+    #[test]
+    fn alloc_and_index() {
+        let mut arena = Arena::default();
+        let first = arena.alloc("first", Span::new(0, 5));
+        let second = arena.alloc("second", Span::new(6, 12));
+
+        assert_ne!(first, second);
+        assert_eq!(arena[first], "first");
+        assert_eq!(arena.span(second), Span::new(6, 12));
+
+        arena[second] = "changed";
+        assert_eq!(arena[second], "changed");
+        assert_eq!(format!("{:?}", second), "#1");
+    }
+}
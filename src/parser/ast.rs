@@ -0,0 +1,797 @@
+use crate::lexer::span::Span;
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{Token, TokenKeyword};
+use crate::parser::arena::{Arena, Id};
+use std::ops::{Index, IndexMut};
+
+pub type ExpressionId = Id<Expression>;
+pub type StatementId = Id<Statement>;
+
+/// The root of the abstract syntax tree, covering an entire source file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TranslationUnit {
+    /// Everything declared at file scope, in source order.
+    pub items: Vec<Annotated<ExternalDeclaration>>,
+    /// Comments after the last item of the file.
+    pub comments: Vec<Comment>,
+    /// The expressions and statements referred to by id from the items.
+    pub nodes: Nodes,
+}
+
+/// The arenas holding the expressions and nested statements of a translation unit, together with
+/// their source ranges. Every expression is stored here and referred to by id, as are statements
+/// nested in another statement, while those directly inside a block are stored inline.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Nodes {
+    pub expressions: Arena<Expression>,
+    pub statements: Arena<Statement>,
+}
+
+/// Whether a comment was written as `// ...` or as `/* ... */`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CommentStyle {
+    Line,
+    Block,
+}
+
+/// A comment, without its delimiters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Comment {
+    pub text: String,
+    pub style: CommentStyle,
+    /// Whether the comment started on a line of its own, rather than after other code.
+    pub own_line: bool,
+    /// The number of empty lines in front of the comment.
+    pub blank_lines: usize,
+    /// The number of characters in front of the comment on its first line, so that the lines
+    /// after it can be indented again relative to where it ends up.
+    pub column: usize,
+}
+
+/// A node together with the comments surrounding it. Leading comments precede the node, while
+/// trailing comments either follow it on the same line or were found in between its tokens.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Annotated<T> {
+    pub node: T,
+    /// The source range of the node itself, excluding its comments.
+    pub span: Span,
+    pub leading: Vec<Comment>,
+    pub trailing: Vec<Comment>,
+    /// The number of empty lines in between the leading comments and the node itself, or in front
+    /// of the node if there are no leading comments.
+    pub blank_lines: usize,
+}
+
+/// The statements in between a pair of braces.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Block {
+    /// The source range from the opening brace up to and including the closing brace.
+    pub span: Span,
+    /// Comments in front of the opening brace, or after it on the same line, such as those in
+    /// between the `)` of a function or control statement and its `{`.
+    pub opening: Vec<Comment>,
+    pub statements: Vec<Annotated<Statement>>,
+    /// Comments after the last statement, right before the closing brace.
+    pub comments: Vec<Comment>,
+    /// The number of empty lines in front of the closing brace.
+    pub closing_blank_lines: usize,
+}
+
+/// A single item at file scope.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExternalDeclaration {
+    /// A preprocessor line, such as `#include <stdio.h>`, without the leading `#`.
+    Directive(String),
+
+    /// Any declaration terminated by a semicolon, including struct definitions and prototypes.
+    Declaration(Declaration),
+
+    /// A function with a body.
+    FunctionDefinition(FunctionDefinition),
+
+    /// A region guarded by preprocessor conditionals.
+    Conditional(ConditionalGroup<ExternalDeclaration>),
+
+    /// A macro invocation standing on its own, such as `MODULE_LICENSE("GPL");`.
+    Macro { call: MacroCall, semicolon: bool },
+
+    /// A top level assembly block.
+    Asm(Asm),
+
+    /// A compile time assertion.
+    StaticAssert(StaticAssert),
+
+    /// A stray semicolon.
+    Empty,
+
+    /// The lines which open or close an `extern "C"` block for C++ compilers in a header shared
+    /// with C, as in `#ifdef __cplusplus`, `extern "C" {`, `#endif`. The braces are not balanced
+    /// within the conditional group, so the declarations in between are kept apart from it.
+    LinkageWrapper {
+        /// The directive which checks for C++, without the hash.
+        condition: String,
+        /// The language of the `extern` which opens the block, or nothing for the closing brace.
+        language: Option<String>,
+        /// The closing directive, without the hash.
+        end: String,
+    },
+
+    /// A region of the source file which could not be parsed.
+    Invalid(Span),
+
+    /// The original text of a region which could not be parsed, to be reproduced byte for byte.
+    Verbatim(String),
+}
+
+/// A region guarded by `#if`, `#ifdef` or `#ifndef`, together with its `#elif` and `#else`
+/// branches. Every branch contains a sequence of complete items, which are either external
+/// declarations, statements or record members.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConditionalGroup<T> {
+    pub branches: Vec<ConditionalBranch<T>>,
+    /// The closing directive, without the hash, such as `endif /* DEBUG */`.
+    pub end: String,
+}
+
+/// A single branch of a conditional group.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConditionalBranch<T> {
+    /// The directive which opens the branch, without the hash, such as `ifdef DEBUG` or `else`.
+    pub directive: String,
+    /// The original text of a branch which is never compiled, such as the one of `#if 0`, to be
+    /// reproduced byte for byte. Such a branch has no items.
+    pub disabled: Option<String>,
+    pub items: Vec<Annotated<T>>,
+    /// Comments after the last item of the branch.
+    pub comments: Vec<Comment>,
+    /// The number of empty lines in front of the directive which ends the branch.
+    pub closing_blank_lines: usize,
+}
+
+/// An invocation of a function-like macro which does not fit the grammar of C. The arguments are
+/// kept as tokens, since they may contain anything from types to partial statements.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MacroCall {
+    pub name: Symbol,
+    pub arguments: Vec<Vec<Token<'static>>>,
+}
+
+/// An inline assembly block, such as `__asm__ volatile ("nop" : : : "memory");`. The sections in
+/// between the colons are kept as tokens, starting with the template.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Asm {
+    /// The spelling of the keyword, which is one of `asm`, `__asm` and `__asm__`.
+    pub keyword: Symbol,
+    /// Qualifiers such as `volatile` or `goto`, in their original spelling.
+    pub qualifiers: Vec<String>,
+    pub sections: Vec<Vec<Token<'static>>>,
+}
+
+/// A `_Static_assert` declaration, with an optional message.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StaticAssert {
+    pub condition: ExpressionId,
+    pub message: Option<ExpressionId>,
+}
+
+/// A function declarator followed by a compound statement.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionDefinition {
+    pub specifiers: Vec<Specifier>,
+    pub declarator: Declarator,
+    pub body: Block,
+}
+
+/// A list of specifiers followed by zero or more declarators, such as `static int a = 1, *b;`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Declaration {
+    pub specifiers: Vec<Specifier>,
+    pub declarators: Vec<InitDeclarator>,
+}
+
+/// A declarator with an optional initial value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InitDeclarator {
+    pub span: Span,
+    pub declarator: Declarator,
+    pub initializer: Option<Initializer>,
+}
+
+/// The initial value of a declared entity.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Initializer {
+    Expression(ExpressionId),
+    List(InitializerList),
+}
+
+/// A brace enclosed list of initializers, such as `{ 1, .y = 2, [3] = { 4 } }`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InitializerList {
+    pub span: Span,
+    pub items: Vec<Annotated<InitializerItem>>,
+    /// Whether the last item is followed by a comma.
+    pub trailing_comma: bool,
+    /// Comments after the last item, right before the closing brace.
+    pub comments: Vec<Comment>,
+}
+
+/// A single entry in an initializer list, optionally preceded by designators.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InitializerItem {
+    pub designators: Vec<Designator>,
+    pub value: Initializer,
+}
+
+/// Selects the member or element which is initialized by an item of an initializer list.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Designator {
+    /// A member of a record, as in `.name = value`.
+    Member(Symbol),
+
+    /// An element of an array, as in `[index] = value`.
+    Index(ExpressionId),
+}
+
+/// Everything that may precede the declarators of a declaration, in source order.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Specifier {
+    /// Storage classes, qualifiers and function specifiers such as `static`, `const` or `inline`.
+    Keyword(TokenKeyword),
+
+    /// A built-in type such as `int`, or a name introduced by `typedef`.
+    Type(Symbol),
+
+    /// A `struct` or `union`, possibly with a definition of its members.
+    Record(Record),
+
+    /// An `enum`, possibly with a definition of its enumerators.
+    Enum(Enum),
+
+    /// An attribute in between the other specifiers, as in `__attribute__((unused)) static int`.
+    Attribute(Attribute),
+}
+
+/// The two spellings of attributes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AttributeStyle {
+    /// The standard `[[...]]` syntax from C23.
+    Standard,
+
+    /// The GNU `__attribute__((...))` syntax.
+    Gnu,
+}
+
+/// An attribute specifier. Attributes are not interpreted, so only the tokens in between the
+/// delimiters are kept.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Attribute {
+    pub span: Span,
+    pub style: AttributeStyle,
+    pub tokens: Vec<Token<'static>>,
+}
+
+/// Whether a record is a `struct` or a `union`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum RecordKind {
+    Struct,
+    Union,
+}
+
+/// A `struct` or `union` specifier. The members are absent if only the tag is referenced.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Record {
+    pub span: Span,
+    pub kind: RecordKind,
+    /// Attributes in between the keyword and the tag.
+    pub attributes: Vec<Attribute>,
+    pub name: Option<Symbol>,
+    pub members: Option<Vec<Annotated<Member>>>,
+    /// Comments after the last member, right before the closing brace.
+    pub comments: Vec<Comment>,
+}
+
+/// A single line inside a record definition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Member {
+    /// One or more fields sharing the same specifiers, such as `int x, y : 4;`.
+    Field {
+        specifiers: Vec<Specifier>,
+        declarators: Vec<MemberDeclarator>,
+    },
+
+    /// A preprocessor line in between the fields.
+    Directive(String),
+
+    /// Fields guarded by preprocessor conditionals.
+    Conditional(ConditionalGroup<Member>),
+
+    /// A macro invocation in between the fields, such as `DECLARE_BITMAP(flags, 8);`.
+    Macro { call: MacroCall, semicolon: bool },
+}
+
+/// A field inside a record, optionally with a bit width.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemberDeclarator {
+    pub span: Span,
+    pub declarator: Option<Declarator>,
+    pub width: Option<ExpressionId>,
+}
+
+/// An `enum` specifier. The enumerators are absent if only the tag is referenced.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enum {
+    pub span: Span,
+    /// Attributes in between the keyword and the tag.
+    pub attributes: Vec<Attribute>,
+    pub name: Option<Symbol>,
+    pub enumerators: Option<Vec<Annotated<Enumerator>>>,
+    /// Comments after the last enumerator, right before the closing brace.
+    pub comments: Vec<Comment>,
+}
+
+/// A single constant inside an enum definition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Enumerator {
+    pub name: Symbol,
+    pub value: Option<ExpressionId>,
+}
+
+/// The part of a declaration which names an entity and decorates its type with pointers, arrays
+/// and function parameters.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Declarator {
+    /// The source range of the declarator, which is empty for an abstract declarator without any
+    /// tokens, such as the one of `int` in `sizeof(int)`.
+    pub span: Span,
+    /// Pointers are listed from left to right, so `* const *` yields two entries.
+    pub pointers: Vec<Pointer>,
+    pub direct: DirectDeclarator,
+    /// Attributes following the declarator, as in `void f(void) __attribute__((noreturn))`.
+    pub attributes: Vec<Attribute>,
+}
+
+/// A single `*`, together with the qualifiers which follow it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pointer {
+    pub qualifiers: Vec<TokenKeyword>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DirectDeclarator {
+    /// The name being declared.
+    Identifier(Symbol),
+
+    /// No name at all, as in type names and unnamed parameters.
+    Abstract,
+
+    /// A declarator wrapped in parentheses, such as the `(*callback)` in a function pointer.
+    Parenthesized(Box<Declarator>),
+
+    /// An array with an optional size.
+    Array(Box<DirectDeclarator>, Option<ExpressionId>),
+
+    /// A function with its parameter list, and whether that list ends in `...`.
+    Function(Box<DirectDeclarator>, Vec<Annotated<Parameter>>, bool),
+}
+
+/// A type without a name, as used by casts and `sizeof`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TypeName {
+    pub span: Span,
+    pub specifiers: Vec<Specifier>,
+    pub declarator: Declarator,
+}
+
+/// A single entry in the parameter list of a function declarator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Parameter {
+    pub specifiers: Vec<Specifier>,
+    pub declarator: Declarator,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Statement {
+    /// A block of statements in between braces.
+    Compound(Block),
+
+    /// A declaration inside a block.
+    Declaration(Declaration),
+
+    /// A compile time assertion inside a block.
+    StaticAssert(StaticAssert),
+
+    /// Statements guarded by preprocessor conditionals.
+    Conditional(ConditionalGroup<Statement>),
+
+    /// A macro invocation which is not an expression, optionally followed by a semicolon.
+    Macro {
+        call: MacroCall,
+        semicolon: bool,
+    },
+
+    /// An inline assembly block.
+    Asm(Asm),
+
+    /// A macro invocation acting like a loop header, as in `LIST_FOREACH(item, &list, entries) {}`.
+    MacroLoop(MacroCall, StatementId),
+
+    /// A statement preceded by attributes, as in `[[fallthrough]];`.
+    Attributed(Vec<Attribute>, StatementId),
+
+    /// The statement after a label, with the markers which follow the label on its line, as in
+    /// `case 1: /* fallthrough */`, so that they stay on that line.
+    Marked(Vec<Comment>, StatementId),
+
+    /// An expression followed by a semicolon, or just a semicolon.
+    Expression(Option<ExpressionId>),
+
+    If {
+        condition: ExpressionId,
+        then: StatementId,
+        otherwise: Option<StatementId>,
+    },
+
+    Switch {
+        condition: ExpressionId,
+        body: StatementId,
+    },
+
+    While {
+        condition: ExpressionId,
+        body: StatementId,
+    },
+
+    DoWhile {
+        body: StatementId,
+        condition: ExpressionId,
+    },
+
+    For {
+        initializer: Option<StatementId>,
+        condition: Option<ExpressionId>,
+        step: Option<ExpressionId>,
+        body: StatementId,
+    },
+
+    Goto(Symbol),
+
+    Continue,
+
+    Break,
+
+    Return(Option<ExpressionId>),
+
+    /// A statement preceded by a `label:`.
+    Labeled(Symbol, StatementId),
+
+    /// A statement preceded by a `case value:`.
+    Case(ExpressionId, StatementId),
+
+    /// A statement preceded by a `default:`.
+    Default(StatementId),
+
+    /// A preprocessor line inside a function body.
+    Directive(String),
+
+    /// A region of the source file which could not be parsed.
+    Invalid(Span),
+
+    /// The original text of a region which could not be parsed, to be reproduced byte for byte.
+    Verbatim(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Expression {
+    Identifier(Symbol),
+
+    Number(String),
+
+    /// One or more adjacent string literals, which the compiler concatenates.
+    Str(Vec<String>),
+
+    Character(String),
+
+    /// A prefix or postfix operator applied to a single operand.
+    Unary(UnaryOperator, ExpressionId),
+
+    Binary(BinaryOperator, ExpressionId, ExpressionId),
+
+    Assignment(AssignmentOperator, ExpressionId, ExpressionId),
+
+    /// The ternary `condition ? then : otherwise`.
+    Conditional(ExpressionId, ExpressionId, ExpressionId),
+
+    /// Two or more expressions separated by the comma operator, evaluated from left to right.
+    Comma(Vec<ExpressionId>),
+
+    Call(ExpressionId, Vec<ExpressionId>),
+
+    Index(ExpressionId, ExpressionId),
+
+    /// Member access, either through `.` or through `->` if the flag is set.
+    Member(ExpressionId, Symbol, bool),
+
+    /// Parentheses are kept, as they are part of the source which must be reproduced.
+    Parenthesized(ExpressionId),
+
+    /// An explicit conversion such as `(int) x`.
+    Cast(TypeName, ExpressionId),
+
+    /// An unnamed object such as `(struct point) { 1, 2 }`.
+    CompoundLiteral(TypeName, InitializerList),
+
+    /// The size of the type of an expression, as in `sizeof x`.
+    Sizeof(ExpressionId),
+
+    /// The size of a type, as in `sizeof(int)`.
+    SizeofType(TypeName),
+
+    /// The alignment of a type, as in `_Alignof(int)`.
+    Alignof(TypeName),
+
+    /// A type passed to a macro which looks like a function, as in `va_arg(args, char *)`.
+    Type(TypeName),
+
+    /// A `_Generic` selection between several expressions based on the type of the first one.
+    Generic(ExpressionId, Vec<GenericAssociation>),
+}
+
+/// A single branch of a `_Generic` selection. The type is absent for the `default` branch.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GenericAssociation {
+    pub type_name: Option<TypeName>,
+    pub value: ExpressionId,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UnaryOperator {
+    Plus,
+    Minus,
+    Not,
+    Complement,
+    Dereference,
+    AddressOf,
+    PreIncrement,
+    PreDecrement,
+    PostIncrement,
+    PostDecrement,
+}
+
+impl UnaryOperator {
+    /// The operator as it is written in source code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOperator::Plus => "+",
+            UnaryOperator::Minus => "-",
+            UnaryOperator::Not => "!",
+            UnaryOperator::Complement => "~",
+            UnaryOperator::Dereference => "*",
+            UnaryOperator::AddressOf => "&",
+            UnaryOperator::PreIncrement | UnaryOperator::PostIncrement => "++",
+            UnaryOperator::PreDecrement | UnaryOperator::PostDecrement => "--",
+        }
+    }
+
+    /// Whether the operator is written after its operand.
+    pub fn is_postfix(&self) -> bool {
+        matches!(
+            self,
+            UnaryOperator::PostIncrement | UnaryOperator::PostDecrement
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BinaryOperator {
+    Multiply,
+    Divide,
+    Modulo,
+    Add,
+    Subtract,
+    ShiftLeft,
+    ShiftRight,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    BitwiseAnd,
+    BitwiseXor,
+    BitwiseOr,
+    LogicalAnd,
+    LogicalOr,
+}
+
+impl BinaryOperator {
+    /// The operator as it is written in source code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::ShiftLeft => "<<",
+            BinaryOperator::ShiftRight => ">>",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::BitwiseAnd => "&",
+            BinaryOperator::BitwiseXor => "^",
+            BinaryOperator::BitwiseOr => "|",
+            BinaryOperator::LogicalAnd => "&&",
+            BinaryOperator::LogicalOr => "||",
+        }
+    }
+
+    /// How tightly the operator binds, where higher numbers bind more tightly.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo => 10,
+            BinaryOperator::Add | BinaryOperator::Subtract => 9,
+            BinaryOperator::ShiftLeft | BinaryOperator::ShiftRight => 8,
+            BinaryOperator::Less
+            | BinaryOperator::Greater
+            | BinaryOperator::LessEqual
+            | BinaryOperator::GreaterEqual => 7,
+            BinaryOperator::Equal | BinaryOperator::NotEqual => 6,
+            BinaryOperator::BitwiseAnd => 5,
+            BinaryOperator::BitwiseXor => 4,
+            BinaryOperator::BitwiseOr => 3,
+            BinaryOperator::LogicalAnd => 2,
+            BinaryOperator::LogicalOr => 1,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AssignmentOperator {
+    Assign,
+    Multiply,
+    Divide,
+    Modulo,
+    Add,
+    Subtract,
+    ShiftLeft,
+    ShiftRight,
+    BitwiseAnd,
+    BitwiseXor,
+    BitwiseOr,
+}
+
+impl AssignmentOperator {
+    /// The operator as it is written in source code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignmentOperator::Assign => "=",
+            AssignmentOperator::Multiply => "*=",
+            AssignmentOperator::Divide => "/=",
+            AssignmentOperator::Modulo => "%=",
+            AssignmentOperator::Add => "+=",
+            AssignmentOperator::Subtract => "-=",
+            AssignmentOperator::ShiftLeft => "<<=",
+            AssignmentOperator::ShiftRight => ">>=",
+            AssignmentOperator::BitwiseAnd => "&=",
+            AssignmentOperator::BitwiseXor => "^=",
+            AssignmentOperator::BitwiseOr => "|=",
+        }
+    }
+}
+
+impl Nodes {
+    /// Move an expression into the arena, together with its source range.
+    pub fn expression(&mut self, expression: Expression, span: Span) -> ExpressionId {
+        self.expressions.alloc(expression, span)
+    }
+
+    /// Move a statement into the arena, together with its source range.
+    pub fn statement(&mut self, statement: Statement, span: Span) -> StatementId {
+        self.statements.alloc(statement, span)
+    }
+}
+
+impl Index<ExpressionId> for Nodes {
+    type Output = Expression;
+
+    fn index(&self, id: ExpressionId) -> &Expression {
+        &self.expressions[id]
+    }
+}
+
+impl IndexMut<ExpressionId> for Nodes {
+    fn index_mut(&mut self, id: ExpressionId) -> &mut Expression {
+        &mut self.expressions[id]
+    }
+}
+
+impl Index<StatementId> for Nodes {
+    type Output = Statement;
+
+    fn index(&self, id: StatementId) -> &Statement {
+        &self.statements[id]
+    }
+}
+
+impl IndexMut<StatementId> for Nodes {
+    fn index_mut(&mut self, id: StatementId) -> &mut Statement {
+        &mut self.statements[id]
+    }
+}
+
+impl Declarator {
+    /// The name being declared, if any.
+    pub fn name(&self) -> Option<Symbol> {
+        self.direct.name()
+    }
+}
+
+impl DirectDeclarator {
+    /// The name being declared, if any.
+    pub fn name(&self) -> Option<Symbol> {
+        match self {
+            DirectDeclarator::Identifier(name) => Some(*name),
+            DirectDeclarator::Abstract => None,
+            DirectDeclarator::Parenthesized(inner) => inner.name(),
+            DirectDeclarator::Array(inner, _) | DirectDeclarator::Function(inner, _, _) => {
+                inner.name()
+            }
+        }
+    }
+}
+
+/// The starts of the comments which tell a tool to ignore the code they are on, or the code after
+/// them, such as `// NOLINT` for clang-tidy.
+const SUPPRESSIONS: &[&str] = &[
+    "NOLINT",
+    "cppcheck-suppress",
+    "coverity[",
+    "LCOV_EXCL_",
+    "NOSONAR",
+    "lint -",
+    "lint !",
+    "lint +",
+];
+
+impl Comment {
+    /// Whether the comment is a marker for a compiler or another tool, such as `/* fallthrough */`,
+    /// `// NOLINT` or `/* cppcheck-suppress nullPointer */`, which only means something on the
+    /// line it is written on, or right in front of the code it is about.
+    pub fn is_marker(&self) -> bool {
+        let text = self.text.trim_start_matches(['*', '!', '/']).trim_start();
+        SUPPRESSIONS.iter().any(|start| text.starts_with(start))
+            || text.contains("NOLINT")
+            || is_fallthrough(text)
+    }
+}
+
+/// Whether a comment says that a case falls through to the next one, in one of the forms which
+/// GCC accepts for `-Wimplicit-fallthrough`, such as `FALLTHRU`, `Falls through.` or
+/// `Intentional fall-through`.
+fn is_fallthrough(text: &str) -> bool {
+    let text = text
+        .trim_start_matches(['.', '!', '@', '$', '-'])
+        .to_ascii_lowercase();
+    let text = ["else, ", "else ", "intentionally ", "intentional "]
+        .iter()
+        .find_map(|start| text.strip_prefix(start))
+        .unwrap_or(&text);
+
+    let Some(rest) = text
+        .strip_prefix("falls")
+        .or_else(|| text.strip_prefix("fall"))
+    else {
+        return false;
+    };
+    let rest = rest.strip_prefix([' ', '-']).unwrap_or(rest);
+    match rest
+        .strip_prefix("through")
+        .or_else(|| rest.strip_prefix("thru"))
+    {
+        Some(rest) => !rest.starts_with(|c: char| c.is_alphanumeric()),
+        None => false,
+    }
+}
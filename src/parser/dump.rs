@@ -0,0 +1,673 @@
+//! A compact, S-expression like rendering of the syntax tree. Every node is written on a single
+//! line, except for the items of a translation unit which are separated by newlines. This is
+//! mostly useful for debugging the parser and for writing concise tests.
+
+use crate::lexer::direction::Direction::Left;
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::Token;
+use crate::lexer::token::Token::{Comma, Parenthesis};
+use crate::parser::ast::{
+    Annotated, Asm, Attribute, AttributeStyle, Block, Comment, ConditionalBranch, ConditionalGroup,
+    Declaration, Declarator, Designator, DirectDeclarator, Enum, Enumerator, Expression,
+    ExpressionId, ExternalDeclaration, FunctionDefinition, GenericAssociation, InitDeclarator,
+    Initializer, InitializerItem, InitializerList, MacroCall, Member, MemberDeclarator, Nodes,
+    Parameter, Record, RecordKind, Specifier, Statement, StatementId, StaticAssert,
+    TranslationUnit, TypeName,
+};
+use std::fmt::{Display, Formatter, Result};
+
+/// A node which can be rendered, given the arenas holding its descendants.
+trait Dump {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result;
+
+    /// Pair the node with the arenas, so that it can be used with `write!`.
+    fn with<'a>(&'a self, nodes: &'a Nodes) -> Tree<'a, Self> {
+        Tree { nodes, node: self }
+    }
+}
+
+/// A node together with the arenas holding its descendants.
+struct Tree<'a, T: ?Sized> {
+    nodes: &'a Nodes,
+    node: &'a T,
+}
+
+impl<T: Dump + ?Sized> Display for Tree<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.node.dump(self.nodes, f)
+    }
+}
+
+impl Dump for String {
+    fn dump(&self, _nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Dump for Symbol {
+    fn dump(&self, _nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl Dump for ExpressionId {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        nodes[*self].dump(nodes, f)
+    }
+}
+
+impl Dump for StatementId {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        nodes[*self].dump(nodes, f)
+    }
+}
+
+/// Write a list of nodes, each one preceded by a space.
+fn write_all<T: Dump>(f: &mut Formatter<'_>, nodes: &Nodes, items: &[T]) -> Result {
+    for item in items {
+        write!(f, " {}", item.with(nodes))?;
+    }
+
+    Ok(())
+}
+
+/// Write an optional node preceded by a space, or an underscore if it is absent.
+fn write_optional<T: Dump>(f: &mut Formatter<'_>, nodes: &Nodes, item: &Option<T>) -> Result {
+    match item {
+        Some(item) => write!(f, " {}", item.with(nodes)),
+        None => write!(f, " _"),
+    }
+}
+
+/// Write a list of specifiers in between square brackets.
+fn write_specifiers(f: &mut Formatter<'_>, nodes: &Nodes, specifiers: &[Specifier]) -> Result {
+    write!(f, "[")?;
+
+    for (i, specifier) in specifiers.iter().enumerate() {
+        if i > 0 {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", specifier.with(nodes))?;
+    }
+
+    write!(f, "]")
+}
+
+/// Write a sequence of uninterpreted tokens, separated by spaces except around parentheses, before
+/// commas and after prefix operators.
+fn write_tokens(f: &mut Formatter<'_>, tokens: &[Token]) -> Result {
+    for (i, token) in tokens.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|i| &tokens[i]);
+        let before = i.checked_sub(2).map(|i| &tokens[i]);
+        let glued = matches!(token, Parenthesis(_) | Comma)
+            || matches!(previous, Some(Parenthesis(Left)))
+            || previous.is_some_and(|previous| previous.is_prefix_after(before));
+        if i > 0 && !glued {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", token)?;
+    }
+
+    Ok(())
+}
+
+/// Write a macro invocation, followed by a semicolon if it has one.
+fn write_macro(f: &mut Formatter<'_>, nodes: &Nodes, call: &MacroCall, semicolon: bool) -> Result {
+    write!(f, "(macro {}", call.with(nodes))?;
+    if semicolon {
+        write!(f, " ;")?;
+    }
+    write!(f, ")")
+}
+
+impl Display for TranslationUnit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for item in &self.items {
+            writeln!(f, "{}", item.with(&self.nodes))?;
+        }
+
+        for comment in &self.comments {
+            writeln!(f, "{}", comment.with(&self.nodes))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Dump for Comment {
+    fn dump(&self, _nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(comment {:?})", self.text)
+    }
+}
+
+impl<T: Dump> Dump for Annotated<T> {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        for comment in &self.leading {
+            write!(f, "{} ", comment.with(nodes))?;
+        }
+
+        write!(f, "{}", self.node.with(nodes))?;
+        write_all(f, nodes, &self.trailing)
+    }
+}
+
+impl Dump for Block {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(block")?;
+        write_all(f, nodes, &self.opening)?;
+        write_all(f, nodes, &self.statements)?;
+        write_all(f, nodes, &self.comments)?;
+        write!(f, ")")
+    }
+}
+
+impl Dump for ExternalDeclaration {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ExternalDeclaration::Directive(line) => write!(f, "(directive {:?})", line),
+            ExternalDeclaration::Declaration(declaration) => {
+                write!(f, "{}", declaration.with(nodes))
+            }
+            ExternalDeclaration::FunctionDefinition(function) => {
+                write!(f, "{}", function.with(nodes))
+            }
+            ExternalDeclaration::StaticAssert(assertion) => write!(f, "{}", assertion.with(nodes)),
+            ExternalDeclaration::Conditional(group) => write!(f, "{}", group.with(nodes)),
+            ExternalDeclaration::Macro { call, semicolon } => {
+                write_macro(f, nodes, call, *semicolon)
+            }
+            ExternalDeclaration::Asm(asm) => write!(f, "{}", asm.with(nodes)),
+            ExternalDeclaration::Empty => write!(f, "(empty)"),
+            ExternalDeclaration::LinkageWrapper { language, .. } => match language {
+                Some(language) => write!(f, "(extern_open {:?})", language),
+                None => write!(f, "(extern_close)"),
+            },
+            ExternalDeclaration::Invalid(span) => {
+                write!(f, "(invalid {}..{})", span.start, span.end)
+            }
+            ExternalDeclaration::Verbatim(text) => write!(f, "(verbatim {:?})", text),
+        }
+    }
+}
+
+impl Dump for FunctionDefinition {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(definition ")?;
+        write_specifiers(f, nodes, &self.specifiers)?;
+        write!(
+            f,
+            " {} {})",
+            self.declarator.with(nodes),
+            self.body.with(nodes)
+        )
+    }
+}
+
+impl Dump for Declaration {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(declaration ")?;
+        write_specifiers(f, nodes, &self.specifiers)?;
+        write_all(f, nodes, &self.declarators)?;
+        write!(f, ")")
+    }
+}
+
+impl Dump for InitDeclarator {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match &self.initializer {
+            Some(initializer) => write!(
+                f,
+                "(= {} {})",
+                self.declarator.with(nodes),
+                initializer.with(nodes)
+            ),
+            None => write!(f, "{}", self.declarator.with(nodes)),
+        }
+    }
+}
+
+impl<T: Dump> Dump for ConditionalGroup<T> {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(conditional")?;
+        write_all(f, nodes, &self.branches)?;
+        write!(f, " {:?})", self.end)
+    }
+}
+
+impl<T: Dump> Dump for ConditionalBranch<T> {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(branch {:?}", self.directive)?;
+        if let Some(text) = &self.disabled {
+            write!(f, " (disabled {:?})", text)?;
+        }
+        write_all(f, nodes, &self.items)?;
+        write_all(f, nodes, &self.comments)?;
+        write!(f, ")")
+    }
+}
+
+impl Dump for MacroCall {
+    fn dump(&self, _nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}(", self.name)?;
+        for (i, argument) in self.arguments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_tokens(f, argument)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Dump for Asm {
+    fn dump(&self, _nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(asm [{}]", self.qualifiers.join(" "))?;
+        for section in &self.sections {
+            write!(f, " (")?;
+            write_tokens(f, section)?;
+            write!(f, ")")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Dump for StaticAssert {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(static_assert {}", self.condition.with(nodes))?;
+        if let Some(message) = &self.message {
+            write!(f, " {}", message.with(nodes))?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Dump for GenericAssociation {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match &self.type_name {
+            Some(type_name) => write!(f, "({} {})", type_name.with(nodes), self.value.with(nodes)),
+            None => write!(f, "(default {})", self.value.with(nodes)),
+        }
+    }
+}
+
+impl Dump for Initializer {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Initializer::Expression(expression) => write!(f, "{}", expression.with(nodes)),
+            Initializer::List(list) => write!(f, "{}", list.with(nodes)),
+        }
+    }
+}
+
+impl Dump for InitializerList {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(list")?;
+        write_all(f, nodes, &self.items)?;
+        if self.trailing_comma {
+            write!(f, " ,")?;
+        }
+        write_all(f, nodes, &self.comments)?;
+        write!(f, ")")
+    }
+}
+
+impl Dump for InitializerItem {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        if self.designators.is_empty() {
+            return write!(f, "{}", self.value.with(nodes));
+        }
+
+        write!(f, "(= ")?;
+        for designator in &self.designators {
+            match designator {
+                Designator::Member(name) => write!(f, ".{}", name)?,
+                Designator::Index(index) => write!(f, "[{}]", index.with(nodes))?,
+            }
+        }
+        write!(f, " {})", self.value.with(nodes))
+    }
+}
+
+impl Dump for Specifier {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Specifier::Keyword(keyword) => write!(f, "{}", keyword.as_str()),
+            Specifier::Type(name) => write!(f, "{}", name),
+            Specifier::Record(record) => write!(f, "{}", record.with(nodes)),
+            Specifier::Enum(enumeration) => write!(f, "{}", enumeration.with(nodes)),
+            Specifier::Attribute(attribute) => write!(f, "{}", attribute.with(nodes)),
+        }
+    }
+}
+
+impl Dump for Attribute {
+    fn dump(&self, _nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self.style {
+            AttributeStyle::Standard => write!(f, "(attribute ")?,
+            AttributeStyle::Gnu => write!(f, "(gnu_attribute ")?,
+        }
+
+        write_tokens(f, &self.tokens)?;
+        write!(f, ")")
+    }
+}
+
+impl Dump for Record {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self.kind {
+            RecordKind::Struct => write!(f, "(struct")?,
+            RecordKind::Union => write!(f, "(union")?,
+        }
+
+        write_all(f, nodes, &self.attributes)?;
+        write_optional(f, nodes, &self.name)?;
+
+        if let Some(members) = &self.members {
+            write_all(f, nodes, members)?;
+        }
+        write_all(f, nodes, &self.comments)?;
+
+        write!(f, ")")
+    }
+}
+
+impl Dump for Member {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Member::Field {
+                specifiers,
+                declarators,
+            } => {
+                write!(f, "(field ")?;
+                write_specifiers(f, nodes, specifiers)?;
+                write_all(f, nodes, declarators)?;
+                write!(f, ")")
+            }
+            Member::Directive(line) => write!(f, "(directive {:?})", line),
+            Member::Conditional(group) => write!(f, "{}", group.with(nodes)),
+            Member::Macro { call, semicolon } => write_macro(f, nodes, call, *semicolon),
+        }
+    }
+}
+
+impl Dump for MemberDeclarator {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match (&self.declarator, &self.width) {
+            (Some(declarator), None) => write!(f, "{}", declarator.with(nodes)),
+            (declarator, Some(width)) => {
+                write!(f, "(:")?;
+                write_optional(f, nodes, declarator)?;
+                write!(f, " {})", width.with(nodes))
+            }
+            (None, None) => write!(f, "_"),
+        }
+    }
+}
+
+impl Dump for Enum {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(enum")?;
+        write_all(f, nodes, &self.attributes)?;
+        write_optional(f, nodes, &self.name)?;
+
+        if let Some(enumerators) = &self.enumerators {
+            write_all(f, nodes, enumerators)?;
+        }
+        write_all(f, nodes, &self.comments)?;
+
+        write!(f, ")")
+    }
+}
+
+impl Dump for Enumerator {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match &self.value {
+            Some(value) => write!(f, "(= {} {})", self.name, value.with(nodes)),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl Dump for Declarator {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        for pointer in &self.pointers {
+            write!(f, "(*")?;
+            for qualifier in &pointer.qualifiers {
+                write!(f, " {}", qualifier.as_str())?;
+            }
+            write!(f, " ")?;
+        }
+
+        if self.attributes.is_empty() {
+            write!(f, "{}", self.direct.with(nodes))?;
+        } else {
+            write!(f, "(attributed {}", self.direct.with(nodes))?;
+            write_all(f, nodes, &self.attributes)?;
+            write!(f, ")")?;
+        }
+
+        for _ in &self.pointers {
+            write!(f, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Dump for DirectDeclarator {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            DirectDeclarator::Identifier(name) => write!(f, "{}", name),
+            DirectDeclarator::Abstract => write!(f, "_"),
+            DirectDeclarator::Parenthesized(inner) => write!(f, "{}", inner.with(nodes)),
+            DirectDeclarator::Array(inner, size) => {
+                write!(f, "(array {}", inner.with(nodes))?;
+                if let Some(size) = size {
+                    write!(f, " {}", size.with(nodes))?;
+                }
+                write!(f, ")")
+            }
+            DirectDeclarator::Function(inner, parameters, variadic) => {
+                write!(f, "(function {}", inner.with(nodes))?;
+                write_all(f, nodes, parameters)?;
+                if *variadic {
+                    write!(f, " ...")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl Dump for TypeName {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(type ")?;
+        write_specifiers(f, nodes, &self.specifiers)?;
+        write!(f, " {})", self.declarator.with(nodes))
+    }
+}
+
+impl Dump for Parameter {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        write!(f, "(parameter ")?;
+        write_specifiers(f, nodes, &self.specifiers)?;
+        write!(f, " {})", self.declarator.with(nodes))
+    }
+}
+
+impl Dump for Statement {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Statement::Compound(block) => write!(f, "{}", block.with(nodes)),
+            Statement::Declaration(declaration) => write!(f, "{}", declaration.with(nodes)),
+            Statement::StaticAssert(assertion) => write!(f, "{}", assertion.with(nodes)),
+            Statement::Conditional(group) => write!(f, "{}", group.with(nodes)),
+            Statement::Macro { call, semicolon } => write_macro(f, nodes, call, *semicolon),
+            Statement::Asm(asm) => write!(f, "{}", asm.with(nodes)),
+            Statement::MacroLoop(call, body) => {
+                write!(f, "(macro_loop {} {})", call.with(nodes), body.with(nodes))
+            }
+            Statement::Attributed(attributes, statement) => {
+                write!(f, "(attributed")?;
+                write_all(f, nodes, attributes)?;
+                write!(f, " {})", statement.with(nodes))
+            }
+            Statement::Marked(comments, statement) => {
+                write!(f, "(marked")?;
+                write_all(f, nodes, comments)?;
+                write!(f, " {})", statement.with(nodes))
+            }
+            Statement::Expression(Some(expression)) => {
+                write!(f, "(expression {})", expression.with(nodes))
+            }
+            Statement::Expression(None) => write!(f, "(empty)"),
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                write!(f, "(if {} {}", condition.with(nodes), then.with(nodes))?;
+                if let Some(otherwise) = otherwise {
+                    write!(f, " {}", otherwise.with(nodes))?;
+                }
+                write!(f, ")")
+            }
+            Statement::Switch { condition, body } => {
+                write!(f, "(switch {} {})", condition.with(nodes), body.with(nodes))
+            }
+            Statement::While { condition, body } => {
+                write!(f, "(while {} {})", condition.with(nodes), body.with(nodes))
+            }
+            Statement::DoWhile { body, condition } => {
+                write!(f, "(do {} {})", body.with(nodes), condition.with(nodes))
+            }
+            Statement::For {
+                initializer,
+                condition,
+                step,
+                body,
+            } => {
+                write!(f, "(for")?;
+                write_optional(f, nodes, initializer)?;
+                write_optional(f, nodes, condition)?;
+                write_optional(f, nodes, step)?;
+                write!(f, " {})", body.with(nodes))
+            }
+            Statement::Goto(label) => write!(f, "(goto {})", label),
+            Statement::Continue => write!(f, "(continue)"),
+            Statement::Break => write!(f, "(break)"),
+            Statement::Return(value) => {
+                write!(f, "(return")?;
+                if let Some(value) = value {
+                    write!(f, " {}", value.with(nodes))?;
+                }
+                write!(f, ")")
+            }
+            Statement::Labeled(label, statement) => {
+                write!(f, "(label {} {})", label, statement.with(nodes))
+            }
+            Statement::Case(value, statement) => {
+                write!(f, "(case {} {})", value.with(nodes), statement.with(nodes))
+            }
+            Statement::Default(statement) => write!(f, "(default {})", statement.with(nodes)),
+            Statement::Directive(line) => write!(f, "(directive {:?})", line),
+            Statement::Invalid(span) => write!(f, "(invalid {}..{})", span.start, span.end),
+            Statement::Verbatim(text) => write!(f, "(verbatim {:?})", text),
+        }
+    }
+}
+
+impl Dump for Expression {
+    fn dump(&self, nodes: &Nodes, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Expression::Identifier(name) => write!(f, "{}", name),
+            Expression::Number(number) => write!(f, "{}", number),
+            Expression::Str(parts) => {
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "\"{}\"", part)?;
+                }
+                Ok(())
+            }
+            Expression::Character(character) => write!(f, "'{}'", character),
+            Expression::Unary(operator, operand) if operator.is_postfix() => {
+                write!(f, "(post{} {})", operator.as_str(), operand.with(nodes))
+            }
+            Expression::Unary(operator, operand) => {
+                write!(f, "({} {})", operator.as_str(), operand.with(nodes))
+            }
+            Expression::Binary(operator, left, right) => {
+                write!(
+                    f,
+                    "({} {} {})",
+                    operator.as_str(),
+                    left.with(nodes),
+                    right.with(nodes)
+                )
+            }
+            Expression::Assignment(operator, target, value) => {
+                write!(
+                    f,
+                    "({} {} {})",
+                    operator.as_str(),
+                    target.with(nodes),
+                    value.with(nodes)
+                )
+            }
+            Expression::Conditional(condition, then, otherwise) => {
+                write!(
+                    f,
+                    "(? {} {} {})",
+                    condition.with(nodes),
+                    then.with(nodes),
+                    otherwise.with(nodes)
+                )
+            }
+            Expression::Comma(expressions) => {
+                write!(f, "(,")?;
+                write_all(f, nodes, expressions)?;
+                write!(f, ")")
+            }
+            Expression::Call(function, arguments) => {
+                write!(f, "(call {}", function.with(nodes))?;
+                write_all(f, nodes, arguments)?;
+                write!(f, ")")
+            }
+            Expression::Index(array, index) => {
+                write!(f, "(index {} {})", array.with(nodes), index.with(nodes))
+            }
+            Expression::Member(object, member, false) => {
+                write!(f, "(. {} {})", object.with(nodes), member)
+            }
+            Expression::Member(object, member, true) => {
+                write!(f, "(-> {} {})", object.with(nodes), member)
+            }
+            Expression::Parenthesized(inner) => write!(f, "(paren {})", inner.with(nodes)),
+            Expression::Cast(type_name, operand) => write!(
+                f,
+                "(cast {} {})",
+                type_name.with(nodes),
+                operand.with(nodes)
+            ),
+            Expression::CompoundLiteral(type_name, list) => {
+                write!(
+                    f,
+                    "(compound {} {})",
+                    type_name.with(nodes),
+                    list.with(nodes)
+                )
+            }
+            Expression::Sizeof(operand) => write!(f, "(sizeof {})", operand.with(nodes)),
+            Expression::SizeofType(type_name) => write!(f, "(sizeof {})", type_name.with(nodes)),
+            Expression::Alignof(type_name) => write!(f, "(alignof {})", type_name.with(nodes)),
+            Expression::Type(type_name) => write!(f, "{}", type_name.with(nodes)),
+            Expression::Generic(controlling, associations) => {
+                write!(f, "(generic {}", controlling.with(nodes))?;
+                write_all(f, nodes, associations)?;
+                write!(f, ")")
+            }
+        }
+    }
+}
@@ -0,0 +1,867 @@
+//! The JSON representation of the syntax tree, as written by `--emit=ast`. Every variant of an enum
+//! becomes an object with a `kind` field naming it. Nodes carry their byte span in the source file
+//! in a `span` field, and annotated nodes also the comments surrounding them.
+
+use crate::json::{Json, ToJson};
+use crate::lexer::span::Span;
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::{Token, TokenKeyword};
+use crate::parser::ast::{
+    Annotated, Asm, Attribute, AttributeStyle, Block, Comment, CommentStyle, ConditionalBranch,
+    ConditionalGroup, Declaration, Declarator, Designator, DirectDeclarator, Enum, Enumerator,
+    Expression, ExpressionId, ExternalDeclaration, FunctionDefinition, GenericAssociation,
+    InitDeclarator, Initializer, InitializerItem, InitializerList, MacroCall, Member,
+    MemberDeclarator, Nodes, Parameter, Pointer, Record, RecordKind, Specifier, Statement,
+    StatementId, StaticAssert, TranslationUnit, TypeName,
+};
+
+/// Conversion of a node into JSON, given the arenas holding its descendants. Nodes referred to by
+/// id are written in place, so that the output is a single nested tree.
+trait ToJsonIn {
+    fn to_json_in(&self, nodes: &Nodes) -> Json;
+}
+
+impl<T: ToJsonIn> ToJsonIn for Box<T> {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        self.as_ref().to_json_in(nodes)
+    }
+}
+
+impl<T: ToJsonIn> ToJsonIn for Option<T> {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Some(value) => value.to_json_in(nodes),
+            None => Json::Null,
+        }
+    }
+}
+
+impl<T: ToJsonIn> ToJsonIn for Vec<T> {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::Array(self.iter().map(|item| item.to_json_in(nodes)).collect())
+    }
+}
+
+impl ToJsonIn for ExpressionId {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        spanned(
+            nodes.expressions.span(*self),
+            nodes[*self].to_json_in(nodes),
+        )
+    }
+}
+
+impl ToJsonIn for StatementId {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        spanned(nodes.statements.span(*self), nodes[*self].to_json_in(nodes))
+    }
+}
+
+/// Add the span of a node stored in an arena in front of the fields of its object.
+fn spanned(span: Span, node: Json) -> Json {
+    match node {
+        Json::Object(mut fields) => {
+            fields.insert(0, ("span".to_string(), span.to_json()));
+            Json::Object(fields)
+        }
+        node => node,
+    }
+}
+
+/// Create an object for a variant without any fields.
+fn kind(kind: &'static str) -> Json {
+    Json::object([("kind", kind.to_json())])
+}
+
+/// The source spelling of uninterpreted tokens.
+fn tokens(tokens: &[Token]) -> Json {
+    Json::Array(
+        tokens
+            .iter()
+            .map(|token| token.to_string().to_json())
+            .collect(),
+    )
+}
+
+impl ToJson for Span {
+    fn to_json(&self) -> Json {
+        Json::object([("start", self.start.to_json()), ("end", self.end.to_json())])
+    }
+}
+
+impl ToJson for Symbol {
+    fn to_json(&self) -> Json {
+        self.as_str().to_json()
+    }
+}
+
+impl ToJson for TokenKeyword {
+    fn to_json(&self) -> Json {
+        self.as_str().to_json()
+    }
+}
+
+impl ToJson for TranslationUnit {
+    fn to_json(&self) -> Json {
+        Json::object([
+            ("items", self.items.to_json_in(&self.nodes)),
+            ("comments", self.comments.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Comment {
+    fn to_json(&self) -> Json {
+        let style = match self.style {
+            CommentStyle::Line => "line",
+            CommentStyle::Block => "block",
+        };
+
+        Json::object([
+            ("text", self.text.to_json()),
+            ("style", style.to_json()),
+            ("own_line", self.own_line.to_json()),
+            ("blank_lines", self.blank_lines.to_json()),
+            ("column", self.column.to_json()),
+        ])
+    }
+}
+
+impl<T: ToJsonIn> ToJsonIn for Annotated<T> {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("leading", self.leading.to_json()),
+            ("node", self.node.to_json_in(nodes)),
+            ("trailing", self.trailing.to_json()),
+            ("blank_lines", self.blank_lines.to_json()),
+        ])
+    }
+}
+
+impl ToJsonIn for Block {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("opening", self.opening.to_json()),
+            ("statements", self.statements.to_json_in(nodes)),
+            ("comments", self.comments.to_json()),
+            ("closing_blank_lines", self.closing_blank_lines.to_json()),
+        ])
+    }
+}
+
+impl ToJsonIn for ExternalDeclaration {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            ExternalDeclaration::Directive(line) => {
+                Json::object([("kind", "Directive".to_json()), ("line", line.to_json())])
+            }
+            ExternalDeclaration::Declaration(declaration) => Json::object([
+                ("kind", "Declaration".to_json()),
+                ("declaration", declaration.to_json_in(nodes)),
+            ]),
+            ExternalDeclaration::FunctionDefinition(definition) => Json::object([
+                ("kind", "FunctionDefinition".to_json()),
+                ("definition", definition.to_json_in(nodes)),
+            ]),
+            ExternalDeclaration::Conditional(group) => Json::object([
+                ("kind", "Conditional".to_json()),
+                ("group", group.to_json_in(nodes)),
+            ]),
+            ExternalDeclaration::Macro { call, semicolon } => Json::object([
+                ("kind", "Macro".to_json()),
+                ("call", call.to_json()),
+                ("semicolon", semicolon.to_json()),
+            ]),
+            ExternalDeclaration::Asm(asm) => {
+                Json::object([("kind", "Asm".to_json()), ("asm", asm.to_json())])
+            }
+            ExternalDeclaration::StaticAssert(assertion) => Json::object([
+                ("kind", "StaticAssert".to_json()),
+                ("assertion", assertion.to_json_in(nodes)),
+            ]),
+            ExternalDeclaration::Empty => kind("Empty"),
+            ExternalDeclaration::LinkageWrapper {
+                condition,
+                language,
+                end,
+            } => Json::object([
+                ("kind", "LinkageWrapper".to_json()),
+                ("condition", condition.to_json()),
+                ("language", language.to_json()),
+                ("end", end.to_json()),
+            ]),
+            ExternalDeclaration::Invalid(span) => {
+                Json::object([("kind", "Invalid".to_json()), ("span", span.to_json())])
+            }
+            ExternalDeclaration::Verbatim(text) => {
+                Json::object([("kind", "Verbatim".to_json()), ("text", text.to_json())])
+            }
+        }
+    }
+}
+
+impl<T: ToJsonIn> ToJsonIn for ConditionalGroup<T> {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("branches", self.branches.to_json_in(nodes)),
+            ("end", self.end.to_json()),
+        ])
+    }
+}
+
+impl<T: ToJsonIn> ToJsonIn for ConditionalBranch<T> {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("directive", self.directive.to_json()),
+            ("disabled", self.disabled.to_json()),
+            ("items", self.items.to_json_in(nodes)),
+            ("comments", self.comments.to_json()),
+            ("closing_blank_lines", self.closing_blank_lines.to_json()),
+        ])
+    }
+}
+
+impl ToJson for MacroCall {
+    fn to_json(&self) -> Json {
+        let arguments = self.arguments.iter().map(|argument| tokens(argument));
+
+        Json::object([
+            ("name", self.name.to_json()),
+            ("arguments", Json::Array(arguments.collect())),
+        ])
+    }
+}
+
+impl ToJson for Asm {
+    fn to_json(&self) -> Json {
+        let sections = self.sections.iter().map(|section| tokens(section));
+
+        Json::object([
+            ("keyword", self.keyword.to_json()),
+            ("qualifiers", self.qualifiers.to_json()),
+            ("sections", Json::Array(sections.collect())),
+        ])
+    }
+}
+
+impl ToJsonIn for StaticAssert {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("condition", self.condition.to_json_in(nodes)),
+            ("message", self.message.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for FunctionDefinition {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("specifiers", self.specifiers.to_json_in(nodes)),
+            ("declarator", self.declarator.to_json_in(nodes)),
+            ("body", self.body.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Declaration {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("specifiers", self.specifiers.to_json_in(nodes)),
+            ("declarators", self.declarators.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for InitDeclarator {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("declarator", self.declarator.to_json_in(nodes)),
+            ("initializer", self.initializer.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Initializer {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Initializer::Expression(expression) => Json::object([
+                ("kind", "Expression".to_json()),
+                ("expression", expression.to_json_in(nodes)),
+            ]),
+            Initializer::List(list) => {
+                Json::object([("kind", "List".to_json()), ("list", list.to_json_in(nodes))])
+            }
+        }
+    }
+}
+
+impl ToJsonIn for InitializerList {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("items", self.items.to_json_in(nodes)),
+            ("trailing_comma", self.trailing_comma.to_json()),
+            ("comments", self.comments.to_json()),
+        ])
+    }
+}
+
+impl ToJsonIn for InitializerItem {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("designators", self.designators.to_json_in(nodes)),
+            ("value", self.value.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Designator {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Designator::Member(name) => {
+                Json::object([("kind", "Member".to_json()), ("name", name.to_json())])
+            }
+            Designator::Index(index) => Json::object([
+                ("kind", "Index".to_json()),
+                ("index", index.to_json_in(nodes)),
+            ]),
+        }
+    }
+}
+
+impl ToJsonIn for Specifier {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Specifier::Keyword(keyword) => Json::object([
+                ("kind", "Keyword".to_json()),
+                ("keyword", keyword.to_json()),
+            ]),
+            Specifier::Type(name) => {
+                Json::object([("kind", "Type".to_json()), ("name", name.to_json())])
+            }
+            Specifier::Record(record) => Json::object([
+                ("kind", "Record".to_json()),
+                ("record", record.to_json_in(nodes)),
+            ]),
+            Specifier::Enum(enumeration) => Json::object([
+                ("kind", "Enum".to_json()),
+                ("enum", enumeration.to_json_in(nodes)),
+            ]),
+            Specifier::Attribute(attribute) => Json::object([
+                ("kind", "Attribute".to_json()),
+                ("attribute", attribute.to_json()),
+            ]),
+        }
+    }
+}
+
+impl ToJson for Attribute {
+    fn to_json(&self) -> Json {
+        let style = match self.style {
+            AttributeStyle::Standard => "standard",
+            AttributeStyle::Gnu => "gnu",
+        };
+
+        Json::object([
+            ("span", self.span.to_json()),
+            ("style", style.to_json()),
+            ("tokens", tokens(&self.tokens)),
+        ])
+    }
+}
+
+impl ToJsonIn for Record {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        let kind = match self.kind {
+            RecordKind::Struct => "struct",
+            RecordKind::Union => "union",
+        };
+
+        Json::object([
+            ("span", self.span.to_json()),
+            ("kind", kind.to_json()),
+            ("attributes", self.attributes.to_json()),
+            ("name", self.name.to_json()),
+            ("members", self.members.to_json_in(nodes)),
+            ("comments", self.comments.to_json()),
+        ])
+    }
+}
+
+impl ToJsonIn for Member {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Member::Field {
+                specifiers,
+                declarators,
+            } => Json::object([
+                ("kind", "Field".to_json()),
+                ("specifiers", specifiers.to_json_in(nodes)),
+                ("declarators", declarators.to_json_in(nodes)),
+            ]),
+            Member::Directive(line) => {
+                Json::object([("kind", "Directive".to_json()), ("line", line.to_json())])
+            }
+            Member::Conditional(group) => Json::object([
+                ("kind", "Conditional".to_json()),
+                ("group", group.to_json_in(nodes)),
+            ]),
+            Member::Macro { call, semicolon } => Json::object([
+                ("kind", "Macro".to_json()),
+                ("call", call.to_json()),
+                ("semicolon", semicolon.to_json()),
+            ]),
+        }
+    }
+}
+
+impl ToJsonIn for MemberDeclarator {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("declarator", self.declarator.to_json_in(nodes)),
+            ("width", self.width.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Enum {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("attributes", self.attributes.to_json()),
+            ("name", self.name.to_json()),
+            ("enumerators", self.enumerators.to_json_in(nodes)),
+            ("comments", self.comments.to_json()),
+        ])
+    }
+}
+
+impl ToJsonIn for Enumerator {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("name", self.name.to_json()),
+            ("value", self.value.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Declarator {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("pointers", self.pointers.to_json()),
+            ("direct", self.direct.to_json_in(nodes)),
+            ("attributes", self.attributes.to_json()),
+        ])
+    }
+}
+
+impl ToJson for Pointer {
+    fn to_json(&self) -> Json {
+        Json::object([("qualifiers", self.qualifiers.to_json())])
+    }
+}
+
+impl ToJsonIn for DirectDeclarator {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            DirectDeclarator::Identifier(name) => {
+                Json::object([("kind", "Identifier".to_json()), ("name", name.to_json())])
+            }
+            DirectDeclarator::Abstract => kind("Abstract"),
+            DirectDeclarator::Parenthesized(inner) => Json::object([
+                ("kind", "Parenthesized".to_json()),
+                ("inner", inner.to_json_in(nodes)),
+            ]),
+            DirectDeclarator::Array(inner, size) => Json::object([
+                ("kind", "Array".to_json()),
+                ("inner", inner.to_json_in(nodes)),
+                ("size", size.to_json_in(nodes)),
+            ]),
+            DirectDeclarator::Function(inner, parameters, variadic) => Json::object([
+                ("kind", "Function".to_json()),
+                ("inner", inner.to_json_in(nodes)),
+                ("parameters", parameters.to_json_in(nodes)),
+                ("variadic", variadic.to_json()),
+            ]),
+        }
+    }
+}
+
+impl ToJsonIn for TypeName {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("span", self.span.to_json()),
+            ("specifiers", self.specifiers.to_json_in(nodes)),
+            ("declarator", self.declarator.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Parameter {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("specifiers", self.specifiers.to_json_in(nodes)),
+            ("declarator", self.declarator.to_json_in(nodes)),
+        ])
+    }
+}
+
+impl ToJsonIn for Statement {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Statement::Compound(block) => Json::object([
+                ("kind", "Compound".to_json()),
+                ("block", block.to_json_in(nodes)),
+            ]),
+            Statement::Declaration(declaration) => Json::object([
+                ("kind", "Declaration".to_json()),
+                ("declaration", declaration.to_json_in(nodes)),
+            ]),
+            Statement::StaticAssert(assertion) => Json::object([
+                ("kind", "StaticAssert".to_json()),
+                ("assertion", assertion.to_json_in(nodes)),
+            ]),
+            Statement::Conditional(group) => Json::object([
+                ("kind", "Conditional".to_json()),
+                ("group", group.to_json_in(nodes)),
+            ]),
+            Statement::Macro { call, semicolon } => Json::object([
+                ("kind", "Macro".to_json()),
+                ("call", call.to_json()),
+                ("semicolon", semicolon.to_json()),
+            ]),
+            Statement::Asm(asm) => {
+                Json::object([("kind", "Asm".to_json()), ("asm", asm.to_json())])
+            }
+            Statement::MacroLoop(call, body) => Json::object([
+                ("kind", "MacroLoop".to_json()),
+                ("call", call.to_json()),
+                ("body", body.to_json_in(nodes)),
+            ]),
+            Statement::Attributed(attributes, statement) => Json::object([
+                ("kind", "Attributed".to_json()),
+                ("attributes", attributes.to_json()),
+                ("statement", statement.to_json_in(nodes)),
+            ]),
+            Statement::Marked(comments, statement) => Json::object([
+                ("kind", "Marked".to_json()),
+                ("comments", comments.to_json()),
+                ("statement", statement.to_json_in(nodes)),
+            ]),
+            Statement::Expression(expression) => Json::object([
+                ("kind", "Expression".to_json()),
+                ("expression", expression.to_json_in(nodes)),
+            ]),
+            Statement::If {
+                condition,
+                then,
+                otherwise,
+            } => Json::object([
+                ("kind", "If".to_json()),
+                ("condition", condition.to_json_in(nodes)),
+                ("then", then.to_json_in(nodes)),
+                ("otherwise", otherwise.to_json_in(nodes)),
+            ]),
+            Statement::Switch { condition, body } => Json::object([
+                ("kind", "Switch".to_json()),
+                ("condition", condition.to_json_in(nodes)),
+                ("body", body.to_json_in(nodes)),
+            ]),
+            Statement::While { condition, body } => Json::object([
+                ("kind", "While".to_json()),
+                ("condition", condition.to_json_in(nodes)),
+                ("body", body.to_json_in(nodes)),
+            ]),
+            Statement::DoWhile { body, condition } => Json::object([
+                ("kind", "DoWhile".to_json()),
+                ("body", body.to_json_in(nodes)),
+                ("condition", condition.to_json_in(nodes)),
+            ]),
+            Statement::For {
+                initializer,
+                condition,
+                step,
+                body,
+            } => Json::object([
+                ("kind", "For".to_json()),
+                ("initializer", initializer.to_json_in(nodes)),
+                ("condition", condition.to_json_in(nodes)),
+                ("step", step.to_json_in(nodes)),
+                ("body", body.to_json_in(nodes)),
+            ]),
+            Statement::Goto(label) => {
+                Json::object([("kind", "Goto".to_json()), ("label", label.to_json())])
+            }
+            Statement::Continue => kind("Continue"),
+            Statement::Break => kind("Break"),
+            Statement::Return(value) => Json::object([
+                ("kind", "Return".to_json()),
+                ("value", value.to_json_in(nodes)),
+            ]),
+            Statement::Labeled(label, statement) => Json::object([
+                ("kind", "Labeled".to_json()),
+                ("label", label.to_json()),
+                ("statement", statement.to_json_in(nodes)),
+            ]),
+            Statement::Case(value, statement) => Json::object([
+                ("kind", "Case".to_json()),
+                ("value", value.to_json_in(nodes)),
+                ("statement", statement.to_json_in(nodes)),
+            ]),
+            Statement::Default(statement) => Json::object([
+                ("kind", "Default".to_json()),
+                ("statement", statement.to_json_in(nodes)),
+            ]),
+            Statement::Directive(line) => {
+                Json::object([("kind", "Directive".to_json()), ("line", line.to_json())])
+            }
+            Statement::Invalid(span) => {
+                Json::object([("kind", "Invalid".to_json()), ("span", span.to_json())])
+            }
+            Statement::Verbatim(text) => {
+                Json::object([("kind", "Verbatim".to_json()), ("text", text.to_json())])
+            }
+        }
+    }
+}
+
+impl ToJsonIn for Expression {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        match self {
+            Expression::Identifier(name) => {
+                Json::object([("kind", "Identifier".to_json()), ("name", name.to_json())])
+            }
+            Expression::Number(value) => {
+                Json::object([("kind", "Number".to_json()), ("value", value.to_json())])
+            }
+            Expression::Str(pieces) => {
+                Json::object([("kind", "Str".to_json()), ("pieces", pieces.to_json())])
+            }
+            Expression::Character(value) => {
+                Json::object([("kind", "Character".to_json()), ("value", value.to_json())])
+            }
+            Expression::Unary(operator, operand) => Json::object([
+                ("kind", "Unary".to_json()),
+                ("operator", operator.as_str().to_json()),
+                ("postfix", operator.is_postfix().to_json()),
+                ("operand", operand.to_json_in(nodes)),
+            ]),
+            Expression::Binary(operator, left, right) => Json::object([
+                ("kind", "Binary".to_json()),
+                ("operator", operator.as_str().to_json()),
+                ("left", left.to_json_in(nodes)),
+                ("right", right.to_json_in(nodes)),
+            ]),
+            Expression::Assignment(operator, target, value) => Json::object([
+                ("kind", "Assignment".to_json()),
+                ("operator", operator.as_str().to_json()),
+                ("target", target.to_json_in(nodes)),
+                ("value", value.to_json_in(nodes)),
+            ]),
+            Expression::Conditional(condition, then, otherwise) => Json::object([
+                ("kind", "Conditional".to_json()),
+                ("condition", condition.to_json_in(nodes)),
+                ("then", then.to_json_in(nodes)),
+                ("otherwise", otherwise.to_json_in(nodes)),
+            ]),
+            Expression::Comma(expressions) => Json::object([
+                ("kind", "Comma".to_json()),
+                ("expressions", expressions.to_json_in(nodes)),
+            ]),
+            Expression::Call(function, arguments) => Json::object([
+                ("kind", "Call".to_json()),
+                ("function", function.to_json_in(nodes)),
+                ("arguments", arguments.to_json_in(nodes)),
+            ]),
+            Expression::Index(array, index) => Json::object([
+                ("kind", "Index".to_json()),
+                ("array", array.to_json_in(nodes)),
+                ("index", index.to_json_in(nodes)),
+            ]),
+            Expression::Member(object, member, arrow) => Json::object([
+                ("kind", "Member".to_json()),
+                ("object", object.to_json_in(nodes)),
+                ("member", member.to_json()),
+                ("arrow", arrow.to_json()),
+            ]),
+            Expression::Parenthesized(inner) => Json::object([
+                ("kind", "Parenthesized".to_json()),
+                ("inner", inner.to_json_in(nodes)),
+            ]),
+            Expression::Cast(type_name, operand) => Json::object([
+                ("kind", "Cast".to_json()),
+                ("type", type_name.to_json_in(nodes)),
+                ("operand", operand.to_json_in(nodes)),
+            ]),
+            Expression::CompoundLiteral(type_name, list) => Json::object([
+                ("kind", "CompoundLiteral".to_json()),
+                ("type", type_name.to_json_in(nodes)),
+                ("list", list.to_json_in(nodes)),
+            ]),
+            Expression::Sizeof(operand) => Json::object([
+                ("kind", "Sizeof".to_json()),
+                ("operand", operand.to_json_in(nodes)),
+            ]),
+            Expression::SizeofType(type_name) => Json::object([
+                ("kind", "SizeofType".to_json()),
+                ("type", type_name.to_json_in(nodes)),
+            ]),
+            Expression::Alignof(type_name) => Json::object([
+                ("kind", "Alignof".to_json()),
+                ("type", type_name.to_json_in(nodes)),
+            ]),
+            Expression::Type(type_name) => Json::object([
+                ("kind", "Type".to_json()),
+                ("type", type_name.to_json_in(nodes)),
+            ]),
+            Expression::Generic(controlling, associations) => Json::object([
+                ("kind", "Generic".to_json()),
+                ("controlling", controlling.to_json_in(nodes)),
+                ("associations", associations.to_json_in(nodes)),
+            ]),
+        }
+    }
+}
+
+impl ToJsonIn for GenericAssociation {
+    fn to_json_in(&self, nodes: &Nodes) -> Json {
+        Json::object([
+            ("type", self.type_name.to_json_in(nodes)),
+            ("value", self.value.to_json_in(nodes)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::{Json, ToJson};
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+
+    /// Collect the kind of every object with a span, together with the source it spans.
+    fn spanned<'s>(json: &Json, source: &'s str, found: &mut Vec<(String, &'s str)>) {
+        match json {
+            Json::Object(fields) => {
+                if let (Some(span), Some(kind)) = (json.get("span"), json.get("kind")) {
+                    let start = span.get("start").and_then(Json::as_usize).unwrap();
+                    let end = span.get("end").and_then(Json::as_usize).unwrap();
+                    let kind = kind.as_str().unwrap().to_string();
+                    found.push((kind, &source[start..end]));
+                }
+                for (_, value) in fields {
+                    spanned(value, source, found);
+                }
+            }
+            Json::Array(values) => {
+                for value in values {
+                    spanned(value, source, found);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn spans_and_kinds() {
+        let tokens = Lexer::new("int x = 1; // one")
+            .lexemes()
+            .map(|lexeme| lexeme.unwrap());
+        let unit = Parser::new(tokens).parse_translation_unit();
+
+        let expected = r#"{
+  "items": [
+    {
+      "span": {
+        "start": 0,
+        "end": 10
+      },
+      "leading": [],
+      "node": {
+        "kind": "Declaration",
+        "declaration": {
+          "specifiers": [
+            {
+              "kind": "Type",
+              "name": "int"
+            }
+          ],
+          "declarators": [
+            {
+              "span": {
+                "start": 4,
+                "end": 9
+              },
+              "declarator": {
+                "span": {
+                  "start": 4,
+                  "end": 5
+                },
+                "pointers": [],
+                "direct": {
+                  "kind": "Identifier",
+                  "name": "x"
+                },
+                "attributes": []
+              },
+              "initializer": {
+                "kind": "Expression",
+                "expression": {
+                  "span": {
+                    "start": 8,
+                    "end": 9
+                  },
+                  "kind": "Number",
+                  "value": "1"
+                }
+              }
+            }
+          ]
+        }
+      },
+      "trailing": [
+        {
+          "text": " one",
+          "style": "line",
+          "own_line": false,
+          "blank_lines": 0,
+          "column": 11
+        }
+      ],
+      "blank_lines": 0
+    }
+  ],
+  "comments": []
+}"#;
+
+        assert_eq!(unit.to_json().to_string(), expected);
+    }
+
+    #[test]
+    fn nested_nodes_have_spans() {
+        let source = "int f(int n) {\n    if (n > 0)\n        return g(n - 1, (long)n);\n}\n";
+        let tokens = Lexer::new(source).lexemes().map(|lexeme| lexeme.unwrap());
+        let unit = Parser::new(tokens).parse_translation_unit();
+
+        let mut found = Vec::new();
+        spanned(&unit.to_json(), source, &mut found);
+        let found: Vec<(&str, &str)> = found
+            .iter()
+            .map(|(kind, text)| (kind.as_str(), *text))
+            .collect();
+        for expected in [
+            ("Binary", "n > 0"),
+            ("Return", "return g(n - 1, (long)n);"),
+            ("Call", "g(n - 1, (long)n)"),
+            ("Binary", "n - 1"),
+            ("Cast", "(long)n"),
+            ("Identifier", "n"),
+        ] {
+            assert!(found.contains(&expected), "{:?} in {:?}", expected, found);
+        }
+    }
+}
@@ -1,2 +1,9 @@
-mod parse_tree;
+pub mod arena;
+pub mod ast;
+mod dump;
+pub mod json;
+#[allow(clippy::module_inception)]
 pub mod parser;
+pub mod salvage;
+pub mod visit;
+pub mod visit_mut;
@@ -1,18 +1,2405 @@
+use crate::lexer::direction::Direction::{Left, Right};
+use crate::lexer::span::Span;
+use crate::lexer::symbol::Symbol;
 use crate::lexer::token::Token;
-use crate::parser::parse_tree::ParseTree;
+use crate::lexer::token::Token::{
+    Ampersand, AmpersandAmpersand, AmpersandEqual, Arrow, Bang, BangEqual, Brace, Bracket, Caret,
+    CaretEqual, Character, Colon, Comma, Directive, Disabled, Dot, Ellipsis, Equal, EqualEqual,
+    Greater, GreaterEqual, GreaterGreater, GreaterGreaterEqual, Identifier, Keyword, Less,
+    LessEqual, LessLess, LessLessEqual, Minus, MinusEqual, MinusMinus, Number, Parenthesis,
+    Percent, PercentEqual, Pipe, PipeEqual, PipePipe, Plus, PlusEqual, PlusPlus, Question,
+    Semicolon, Slash, SlashEqual, Star, StarEqual, Str, Tilde,
+};
+use crate::lexer::token::{Lexeme, TokenKeyword};
+use crate::parser::ast::{
+    Annotated, Asm, AssignmentOperator, Attribute, AttributeStyle, BinaryOperator, Block, Comment,
+    CommentStyle, ConditionalBranch, ConditionalGroup, Declaration, Declarator, Designator,
+    DirectDeclarator, Enum, Enumerator, Expression, ExpressionId, ExternalDeclaration,
+    FunctionDefinition, GenericAssociation, InitDeclarator, Initializer, InitializerItem,
+    InitializerList, MacroCall, Member, MemberDeclarator, Nodes, Parameter, Pointer, Record,
+    RecordKind, Specifier, Statement, StatementId, StaticAssert, TranslationUnit, TypeName,
+    UnaryOperator,
+};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 
-pub struct Parser;
+/// Names of the built-in types which the lexer reports as plain identifiers.
+const BUILTIN_TYPES: [&str; 12] = [
+    "void",
+    "char",
+    "short",
+    "int",
+    "long",
+    "float",
+    "double",
+    "signed",
+    "_Bool",
+    "bool",
+    "_Complex",
+    "_Imaginary",
+];
 
-impl Parser {
-    pub fn new() -> Parser {
-        Parser
+/// A stateful recursive descent parser which consumes a stream of tokens and turns it into an
+/// abstract syntax tree.
+pub struct Parser<'a> {
+    /// The tokens which will be parsed, with comments already filtered out.
+    tokens: Vec<Lexeme<'a>>,
+    /// The position of the next token that needs to be parsed.
+    index: usize,
+    /// All comments in the source file, which are attached to nodes as parsing progresses.
+    comments: Vec<Lexeme<'a>>,
+    /// The position of the next comment which has not been attached to a node yet.
+    comment_index: usize,
+    /// The byte offset right after the last token that was parsed.
+    previous_end: usize,
+    /// All names introduced by `typedef` so far, used to tell declarations and expressions apart.
+    typedefs: HashSet<Symbol>,
+    /// The errors from which the parser recovered so far.
+    errors: Vec<ParserError>,
+    /// The nested expressions and statements parsed so far.
+    nodes: Nodes,
+}
+
+#[derive(Debug, Clone)]
+pub enum ParserError {
+    /// The token stream ended while a construct was still open. The span is empty and points to
+    /// the end of the last token.
+    UnexpectedEndOfFile(Span),
+
+    /// The parser found a token which does not fit the grammar at that point.
+    UnexpectedToken(Token<'static>, Span),
+}
+
+impl ParserError {
+    /// The span of the token at which the error was found.
+    pub fn span(&self) -> Span {
+        match self {
+            ParserError::UnexpectedEndOfFile(span) | ParserError::UnexpectedToken(_, span) => *span,
+        }
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserError::UnexpectedEndOfFile(_) => write!(f, "unexpected end of file"),
+            ParserError::UnexpectedToken(token, _) => write!(f, "unexpected `{}`", token),
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+impl<'a> Parser<'a> {
+    /// Create a new parser for a given stream of tokens.
+    pub fn new(iter: impl Iterator<Item = Lexeme<'a>>) -> Parser<'a> {
+        let (comments, tokens) = iter
+            .partition(|lexeme| matches!(lexeme.token, Token::SlashSlash(_) | Token::SlashStar(_)));
+
+        Parser {
+            tokens,
+            index: 0,
+            comments,
+            comment_index: 0,
+            previous_end: 0,
+            typedefs: HashSet::new(),
+            errors: Vec::new(),
+            nodes: Nodes::default(),
+        }
+    }
+
+    /// Allocate the nested expressions and statements after those of other nodes, so that the
+    /// nodes which are parsed can be added to an existing syntax tree.
+    pub fn with_nodes(mut self, nodes: Nodes) -> Parser<'a> {
+        self.nodes = nodes;
+        self
+    }
+
+    /// Parse as if the names had already been introduced by `typedef`, so that code taken out of
+    /// a file is parsed as it would be within the file.
+    pub fn with_typedefs(mut self, names: impl IntoIterator<Item = Symbol>) -> Parser<'a> {
+        self.typedefs.extend(names);
+        self
+    }
+
+    /// All errors encountered while parsing. The parser recovers from each of them by skipping
+    /// the offending statement or declaration, which is then marked as invalid in the tree.
+    pub fn errors(&self) -> &[ParserError] {
+        &self.errors
+    }
+
+    /// Create an error describing that the next token was not expected.
+    fn unexpected(&self) -> ParserError {
+        match self.tokens.get(self.index) {
+            Some(lexeme) => {
+                ParserError::UnexpectedToken(lexeme.token.clone().into_owned(), lexeme.span)
+            }
+            None => {
+                ParserError::UnexpectedEndOfFile(Span::new(self.previous_end, self.previous_end))
+            }
+        }
+    }
+
+    /// Check the next token in the stream, without advancing the parser.
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.peek_nth(0)
+    }
+
+    /// Check the token `n` positions ahead of the next one, without advancing the parser.
+    fn peek_nth(&self, n: usize) -> Option<&Token<'a>> {
+        self.tokens.get(self.index + n).map(|lexeme| &lexeme.token)
+    }
+
+    /// Check whether the next token equals the given one.
+    fn at(&self, token: &Token) -> bool {
+        self.peek() == Some(token)
+    }
+
+    /// Take the next token from the stream.
+    fn advance(&mut self) -> Result<Token<'a>, ParserError> {
+        let Some(lexeme) = self.tokens.get(self.index) else {
+            return Err(self.unexpected());
+        };
+        self.index += 1;
+        self.previous_end = lexeme.span.end;
+        Ok(lexeme.token.clone())
+    }
+
+    /// Attempt to remove a specific token from the stream.
+    fn eat(&mut self, token: Token) -> Result<(), ParserError> {
+        match self.peek() {
+            Some(next) if *next == token => {
+                self.advance()?;
+                Ok(())
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Remove a specific token from the stream if it is next, and report whether it was.
+    fn eat_if(&mut self, token: Token) -> bool {
+        self.eat(token).is_ok()
+    }
+
+    /// Attempt to remove an identifier from the stream, returning its name.
+    fn eat_identifier(&mut self) -> Result<Symbol, ParserError> {
+        match self.peek() {
+            Some(&Identifier(name)) => {
+                self.advance()?;
+                Ok(name)
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Turn a comment token into a comment node.
+    fn comment(lexeme: &Lexeme) -> Comment {
+        let (text, style) = match &lexeme.token {
+            Token::SlashSlash(text) => (text.to_string(), CommentStyle::Line),
+            Token::SlashStar(text) => (text.to_string(), CommentStyle::Block),
+            _ => unreachable!("only comments are stored as comments"),
+        };
+
+        Comment {
+            text,
+            style,
+            own_line: lexeme.newlines > 0 || lexeme.span.start == 0,
+            blank_lines: lexeme.newlines.saturating_sub(1),
+            column: lexeme.column,
+        }
+    }
+
+    /// The number of empty lines in front of the next token.
+    fn blank_lines(&self) -> usize {
+        match self.tokens.get(self.index) {
+            Some(lexeme) => lexeme.newlines.saturating_sub(1),
+            None => 0,
+        }
+    }
+
+    /// Take all comments which have not been attached yet, and which start before `offset`.
+    fn take_comments_before(&mut self, offset: usize) -> Vec<Comment> {
+        let mut result = Vec::new();
+
+        while let Some(lexeme) = self.comments.get(self.comment_index) {
+            if lexeme.span.start >= offset {
+                break;
+            }
+
+            result.push(Parser::comment(lexeme));
+            self.comment_index += 1;
+        }
+
+        result
+    }
+
+    /// Take all comments in front of the next token. If there are no tokens left, all remaining
+    /// comments are taken.
+    fn take_leading_comments(&mut self) -> Vec<Comment> {
+        let offset = match self.tokens.get(self.index) {
+            Some(lexeme) => lexeme.span.start,
+            None => usize::MAX,
+        };
+
+        self.take_comments_before(offset)
+    }
+
+    /// Take all comments which were skipped while parsing the last node, as well as those which
+    /// follow it on the same line.
+    fn take_trailing_comments(&mut self) -> Vec<Comment> {
+        let mut result = self.take_comments_before(self.previous_end);
+        let next = match self.tokens.get(self.index) {
+            Some(lexeme) => lexeme.span.start,
+            None => usize::MAX,
+        };
+
+        while let Some(lexeme) = self.comments.get(self.comment_index) {
+            if lexeme.newlines > 0 || lexeme.span.start > next {
+                break;
+            }
+
+            result.push(Parser::comment(lexeme));
+            self.comment_index += 1;
+        }
+
+        result
+    }
+
+    /// The offset at which the next token starts, or the end of the last token if there is none.
+    fn next_start(&self) -> usize {
+        match self.tokens.get(self.index) {
+            Some(lexeme) => lexeme.span.start,
+            None => self.previous_end,
+        }
+    }
+
+    /// The source range from `start` up to the end of the last token, which is empty if no tokens
+    /// were consumed since.
+    fn span_from(&self, start: usize) -> Span {
+        Span::new(start, self.previous_end.max(start))
+    }
+
+    /// Move an expression which started at `start` into the arena.
+    fn expression(&mut self, start: usize, expression: Expression) -> ExpressionId {
+        let span = self.span_from(start);
+        self.nodes.expression(expression, span)
+    }
+
+    /// Move a statement which started at `start` into the arena.
+    fn statement(&mut self, start: usize, statement: Statement) -> StatementId {
+        let span = self.span_from(start);
+        self.nodes.statement(statement, span)
+    }
+
+    /// Parse a single node using the given function, and attach the surrounding comments to it.
+    fn annotated<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser<'a>) -> Result<T, ParserError>,
+    ) -> Result<Annotated<T>, ParserError> {
+        let leading = self.take_leading_comments();
+        let blank_lines = self.blank_lines();
+        let start = self.next_start();
+        let node = parse(self)?;
+        let span = Span::new(start, self.previous_end);
+        let trailing = self.take_trailing_comments();
+
+        Ok(Annotated {
+            node,
+            span,
+            leading,
+            trailing,
+            blank_lines,
+        })
+    }
+
+    /// Parse a single node like `annotated` does, but recover from errors instead of returning
+    /// them. After an error, all tokens up to the next synchronization point are skipped and
+    /// turned into a node using `invalid`.
+    fn recovering<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Parser<'a>) -> Result<T, ParserError>,
+        invalid: impl FnOnce(Span) -> T,
+    ) -> Annotated<T> {
+        let leading = self.take_leading_comments();
+        let blank_lines = self.blank_lines();
+        let start = self.index;
+
+        let node = match parse(self) {
+            Ok(node) => node,
+            Err(error) => {
+                self.errors.push(error);
+                let failed_at = self.index;
+                self.index = start;
+                self.synchronize(failed_at);
+
+                // Comments in the skipped region belong to it, and are not attached separately.
+                let offset = self.tokens[start].span.start;
+                self.take_comments_before(self.previous_end);
+                invalid(Span::new(offset, self.previous_end))
+            }
+        };
+
+        let span = Span::new(self.tokens[start].span.start, self.previous_end);
+        let trailing = self.take_trailing_comments();
+
+        Annotated {
+            node,
+            span,
+            leading,
+            trailing,
+            blank_lines,
+        }
+    }
+
+    /// Skip tokens up to and including the next semicolon, or up to and including the closing
+    /// brace of a block which was opened along the way. The braces of a declaration, such as
+    /// those of `struct { int x } s;`, do not end it, so it is skipped up to its semicolon. Only
+    /// synchronization points at or after the index `failed_at` count, so the offending token is
+    /// never parsed twice. A closing brace belonging to an enclosing block is not consumed. At
+    /// least one token is always skipped, to guarantee progress.
+    fn synchronize(&mut self, failed_at: usize) {
+        let start = self.index;
+        let mut braces = 0;
+        // Whether the outermost braces which are open are those of a block, such as a function
+        // body, rather than those of a declaration.
+        let mut block = false;
+
+        while let Some(token) = self.peek() {
+            let past_error = self.index >= failed_at;
+
+            match token {
+                Brace(Right) if braces == 0 => break,
+                Brace(Right) if braces == 1 && past_error && block => {
+                    self.index += 1;
+                    break;
+                }
+                Brace(Right) => braces -= 1,
+                Brace(Left) => {
+                    if braces == 0 {
+                        let previous = self.tokens[start..self.index].last();
+                        block = !previous.is_some_and(|previous| {
+                            matches!(
+                                previous.token,
+                                Identifier(_)
+                                    | Equal
+                                    | Keyword(
+                                        TokenKeyword::Struct
+                                            | TokenKeyword::Union
+                                            | TokenKeyword::Enum
+                                    )
+                            )
+                        });
+                    }
+                    braces += 1
+                }
+                Semicolon if braces == 0 && past_error => {
+                    self.index += 1;
+                    break;
+                }
+                _ => {}
+            }
+
+            self.index += 1;
+        }
+
+        if self.index == start && self.index < self.tokens.len() {
+            self.index += 1;
+        }
+
+        if self.index > 0 {
+            self.previous_end = self.tokens[self.index - 1].span.end;
+        }
+    }
+
+    /// Consume an entire source file. Errors do not stop the parser, but are collected instead and
+    /// can be retrieved using `errors`.
+    pub fn parse_translation_unit(&mut self) -> TranslationUnit {
+        let mut items = Vec::new();
+
+        while self.peek().is_some() {
+            items.push(self.recovering(
+                Parser::parse_external_declaration,
+                ExternalDeclaration::Invalid,
+            ));
+        }
+
+        let comments = self.take_leading_comments();
+        TranslationUnit {
+            items,
+            comments,
+            nodes: std::mem::take(&mut self.nodes),
+        }
+    }
+
+    /// Consume a sequence of statements and declarations, such as part of a function body, rather
+    /// than an entire source file. Errors are collected as for a translation unit.
+    pub fn parse_statements(&mut self) -> (Block, Nodes) {
+        let start = self.next_start();
+        let mut statements = Vec::new();
+        while self.peek().is_some() {
+            statements.push(self.recovering(Parser::parse_statement, Statement::Invalid));
+        }
+
+        let block = Block {
+            span: self.span_from(start),
+            opening: Vec::new(),
+            statements,
+            comments: self.take_leading_comments(),
+            closing_blank_lines: 0,
+        };
+        (block, std::mem::take(&mut self.nodes))
+    }
+
+    /// Consume a single expression, which must make up the whole stream of tokens.
+    pub fn parse_whole_expression(&mut self) -> Result<(ExpressionId, Nodes), ParserError> {
+        let expression = self.parse_expression()?;
+        match self.peek() {
+            Some(_) => Err(self.unexpected()),
+            None => Ok((expression, std::mem::take(&mut self.nodes))),
+        }
+    }
+
+    /// Consume either a directive, a stray semicolon, a declaration up to and including its
+    /// semicolon, or a function definition up to and including its closing brace.
+    fn parse_external_declaration(&mut self) -> Result<ExternalDeclaration, ParserError> {
+        if let Some(wrapper) = self.parse_linkage_wrapper()? {
+            return Ok(wrapper);
+        }
+
+        match self.peek() {
+            Some(Directive(line)) if Parser::opens_conditional(line) => {
+                return Ok(ExternalDeclaration::Conditional(
+                    self.parse_conditional_group(|parser| {
+                        Ok(parser.recovering(
+                            Parser::parse_external_declaration,
+                            ExternalDeclaration::Invalid,
+                        ))
+                    })?,
+                ));
+            }
+            Some(Directive(line)) => {
+                let line = line.to_string();
+                self.advance()?;
+                return Ok(ExternalDeclaration::Directive(line));
+            }
+            Some(Semicolon) => {
+                self.advance()?;
+                return Ok(ExternalDeclaration::Empty);
+            }
+            Some(Identifier(name)) if Parser::is_asm_keyword(name) => {
+                return Ok(ExternalDeclaration::Asm(self.parse_asm()?));
+            }
+            Some(Keyword(TokenKeyword::StaticAssert)) => {
+                return Ok(ExternalDeclaration::StaticAssert(
+                    self.parse_static_assert()?,
+                ));
+            }
+            _ => {}
+        }
+
+        if let Some(end) = self.call_like_end() {
+            if Parser::ends_macro(self.peek_nth(end)) || self.at_pragma() {
+                let call = self.parse_macro_call()?;
+                let semicolon = self.eat_if(Semicolon);
+                return Ok(ExternalDeclaration::Macro { call, semicolon });
+            }
+        }
+
+        let specifiers = self.parse_specifiers()?;
+
+        if self.eat_if(Semicolon) {
+            return Ok(ExternalDeclaration::Declaration(Declaration {
+                specifiers,
+                declarators: Vec::new(),
+            }));
+        }
+
+        let declarator = self.parse_declarator()?;
+
+        if self.at(&Brace(Left)) {
+            let body = self.parse_block()?;
+            return Ok(ExternalDeclaration::FunctionDefinition(
+                FunctionDefinition {
+                    specifiers,
+                    declarator,
+                    body,
+                },
+            ));
+        }
+
+        let declaration = self.parse_declaration_rest(specifiers, declarator)?;
+        Ok(ExternalDeclaration::Declaration(declaration))
+    }
+
+    /// Extract the name of a directive, such as `ifdef` from `ifdef DEBUG`.
+    fn directive_name(line: &str) -> &str {
+        let line = line.trim_start();
+        let end = line
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(line.len());
+        &line[..end]
+    }
+
+    /// Consume the lines which open or close an `extern "C"` block for C++ compilers, if they
+    /// come next: `#ifdef __cplusplus`, followed by either `extern "C" {` or `}`, and `#endif`.
+    fn parse_linkage_wrapper(&mut self) -> Result<Option<ExternalDeclaration>, ParserError> {
+        let Some(Directive(condition)) = self.peek().cloned() else {
+            return Ok(None);
+        };
+        let words = condition.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !matches!(
+            words.as_str(),
+            "ifdef __cplusplus" | "if defined(__cplusplus)" | "if defined __cplusplus"
+        ) {
+            return Ok(None);
+        }
+
+        let (language, length) = match (self.peek_nth(1), self.peek_nth(2), self.peek_nth(3)) {
+            (Some(Keyword(TokenKeyword::Extern)), Some(Str(language)), Some(Brace(Left))) => {
+                (Some(language.to_string()), 4)
+            }
+            (Some(Brace(Right)), _, _) => (None, 2),
+            _ => return Ok(None),
+        };
+        let end = match self.peek_nth(length) {
+            Some(Directive(end)) if Parser::directive_name(end) == "endif" => end.to_string(),
+            _ => return Ok(None),
+        };
+
+        for _ in 0..=length {
+            self.advance()?;
+        }
+        Ok(Some(ExternalDeclaration::LinkageWrapper {
+            condition: condition.into_owned(),
+            language,
+            end,
+        }))
+    }
+
+    /// Check whether a directive opens a conditional group.
+    fn opens_conditional(line: &str) -> bool {
+        matches!(Parser::directive_name(line), "if" | "ifdef" | "ifndef")
+    }
+
+    /// Check whether the next token is a directive which continues or closes a conditional group.
+    fn at_conditional_branch(&self) -> bool {
+        match self.peek() {
+            Some(Directive(line)) => matches!(
+                Parser::directive_name(line),
+                "elif" | "elifdef" | "elifndef" | "else" | "endif"
+            ),
+            _ => false,
+        }
+    }
+
+    /// Consume a conditional group, from its opening directive up to and including `#endif`,
+    /// parsing the items in every branch using `parse_item`. A group must be balanced with respect
+    /// to braces, so that it can be formatted independently of its surroundings.
+    fn parse_conditional_group<T>(
+        &mut self,
+        parse_item: impl Fn(&mut Parser<'a>) -> Result<Annotated<T>, ParserError>,
+    ) -> Result<ConditionalGroup<T>, ParserError> {
+        let mut branches = Vec::new();
+
+        loop {
+            let Some(Directive(directive)) = self.peek().cloned() else {
+                return Err(self.unexpected());
+            };
+
+            self.advance()?;
+
+            if Parser::directive_name(&directive) == "endif" {
+                return Ok(ConditionalGroup {
+                    branches,
+                    end: directive.into_owned(),
+                });
+            }
+
+            let disabled = match self.peek() {
+                Some(Disabled(text)) => {
+                    let text = text.to_string();
+                    self.advance()?;
+                    Some(text)
+                }
+                _ => None,
+            };
+
+            let mut items = Vec::new();
+            while !self.at_conditional_branch() {
+                if matches!(self.peek(), None | Some(Brace(Right))) {
+                    return Err(self.unexpected());
+                }
+
+                items.push(parse_item(self)?);
+            }
+
+            let comments = self.take_leading_comments();
+            let closing_blank_lines = self.blank_lines();
+            branches.push(ConditionalBranch {
+                directive: directive.into_owned(),
+                disabled,
+                items,
+                comments,
+                closing_blank_lines,
+            });
+        }
     }
 
-    pub fn parse(&self, iter: impl Iterator<Item = Token>) -> ParseTree {
-        for token in iter {
-            // TODO: do stuff
+    /// If the upcoming tokens look like `name(...)`, where `name` is not a type, return the
+    /// position of the token following the closing parenthesis relative to the next token.
+    fn call_like_end(&self) -> Option<usize> {
+        match (self.peek(), self.peek_nth(1)) {
+            (Some(&Identifier(name)), Some(Parenthesis(Left))) if !self.is_type_name(name) => {}
+            _ => return None,
         }
 
-        ParseTree
+        let mut depth = 0;
+        let mut offset = 1;
+
+        loop {
+            match self.peek_nth(offset)? {
+                Parenthesis(Left) => depth += 1,
+                Parenthesis(Right) if depth == 1 => return Some(offset + 1),
+                Parenthesis(Right) => depth -= 1,
+                _ => {}
+            }
+
+            offset += 1;
+        }
+    }
+
+    /// Check whether a token cannot continue a call or a declarator, so that a preceding
+    /// `name(...)` must be a macro invocation.
+    fn ends_macro(token: Option<&Token>) -> bool {
+        matches!(
+            token,
+            None | Some(Semicolon | Identifier(_) | Keyword(_) | Directive(_) | Brace(Right))
+        )
+    }
+
+    /// Consume a macro invocation, splitting its arguments on the commas which are not nested
+    /// inside parentheses, brackets or braces.
+    fn parse_macro_call(&mut self) -> Result<MacroCall, ParserError> {
+        let name = self.eat_identifier()?;
+        self.eat(Parenthesis(Left))?;
+
+        let mut arguments = Vec::new();
+        let mut argument = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            match self.advance()? {
+                Parenthesis(Right) if depth == 0 => break,
+                Comma if depth == 0 => arguments.push(std::mem::take(&mut argument)),
+                token => {
+                    match token {
+                        Parenthesis(Left) | Bracket(Left) | Brace(Left) => depth += 1,
+                        Parenthesis(Right) | Bracket(Right) | Brace(Right) => depth -= 1,
+                        _ => {}
+                    }
+                    argument.push(token.into_owned());
+                }
+            }
+        }
+
+        if !argument.is_empty() || !arguments.is_empty() {
+            arguments.push(argument);
+        }
+
+        Ok(MacroCall { name, arguments })
+    }
+
+    /// Check whether the next token is the `_Pragma` operator, which stands on its own like the
+    /// directive it stands for, rather than starting a call or a loop.
+    fn at_pragma(&self) -> bool {
+        matches!(self.peek(), Some(Identifier(name)) if *name == "_Pragma")
+    }
+
+    /// Check whether an identifier is one of the spellings of the `asm` keyword.
+    fn is_asm_keyword(name: &str) -> bool {
+        matches!(name, "asm" | "__asm" | "__asm__")
+    }
+
+    /// Consume an inline assembly block, including its semicolon.
+    fn parse_asm(&mut self) -> Result<Asm, ParserError> {
+        let keyword = self.eat_identifier()?;
+        let mut qualifiers = Vec::new();
+
+        while !self.at(&Parenthesis(Left)) {
+            let qualifier = match self.peek() {
+                Some(
+                    token @ Keyword(
+                        TokenKeyword::Volatile | TokenKeyword::Inline | TokenKeyword::Goto,
+                    ),
+                ) => token.to_string(),
+                Some(Identifier(name))
+                    if name.starts_with("__volatile") || name.starts_with("__inline") =>
+                {
+                    name.to_string()
+                }
+                _ => return Err(self.unexpected()),
+            };
+
+            qualifiers.push(qualifier);
+            self.advance()?;
+        }
+
+        self.eat(Parenthesis(Left))?;
+
+        let mut sections = vec![Vec::new()];
+        let mut depth = 0;
+
+        loop {
+            match self.advance()? {
+                Parenthesis(Right) if depth == 0 => break,
+                Colon if depth == 0 => sections.push(Vec::new()),
+                token => {
+                    match token {
+                        Parenthesis(Left) | Bracket(Left) => depth += 1,
+                        Parenthesis(Right) | Bracket(Right) => depth -= 1,
+                        _ => {}
+                    }
+                    sections.last_mut().unwrap().push(token.into_owned());
+                }
+            }
+        }
+
+        self.eat(Semicolon)?;
+
+        Ok(Asm {
+            keyword,
+            qualifiers,
+            sections,
+        })
+    }
+
+    /// Consume a `_Static_assert` declaration, including its semicolon.
+    fn parse_static_assert(&mut self) -> Result<StaticAssert, ParserError> {
+        self.eat(Keyword(TokenKeyword::StaticAssert))?;
+        self.eat(Parenthesis(Left))?;
+        let condition = self.parse_conditional()?;
+        let message = if self.eat_if(Comma) {
+            Some(self.parse_primary()?)
+        } else {
+            None
+        };
+        self.eat(Parenthesis(Right))?;
+        self.eat(Semicolon)?;
+
+        Ok(StaticAssert { condition, message })
+    }
+
+    /// Consume a complete declaration, including its semicolon.
+    fn parse_declaration(&mut self) -> Result<Declaration, ParserError> {
+        let specifiers = self.parse_specifiers()?;
+
+        if self.eat_if(Semicolon) {
+            return Ok(Declaration {
+                specifiers,
+                declarators: Vec::new(),
+            });
+        }
+
+        let declarator = self.parse_declarator()?;
+        self.parse_declaration_rest(specifiers, declarator)
+    }
+
+    /// Consume the remainder of a declaration once its specifiers and first declarator have been
+    /// parsed.
+    fn parse_declaration_rest(
+        &mut self,
+        specifiers: Vec<Specifier>,
+        first: Declarator,
+    ) -> Result<Declaration, ParserError> {
+        let is_typedef = specifiers.contains(&Specifier::Keyword(TokenKeyword::Typedef));
+        let mut declarators = Vec::new();
+        let mut declarator = first;
+
+        loop {
+            let start = declarator.span.start;
+            if is_typedef {
+                if let Some(name) = declarator.name() {
+                    self.typedefs.insert(name);
+                }
+            }
+
+            let initializer = if self.eat_if(Equal) {
+                Some(self.parse_initializer()?)
+            } else {
+                None
+            };
+
+            declarators.push(InitDeclarator {
+                span: self.span_from(start),
+                declarator,
+                initializer,
+            });
+
+            if !self.eat_if(Comma) {
+                break;
+            }
+
+            declarator = self.parse_declarator()?;
+        }
+
+        self.eat(Semicolon)?;
+
+        Ok(Declaration {
+            specifiers,
+            declarators,
+        })
+    }
+
+    /// Consume the initial value of a declaration, which is either an expression or a brace
+    /// enclosed list.
+    fn parse_initializer(&mut self) -> Result<Initializer, ParserError> {
+        let start = self.next_start();
+        if !self.eat_if(Brace(Left)) {
+            return Ok(Initializer::Expression(self.parse_assignment()?));
+        }
+
+        let mut items = Vec::new();
+        let mut trailing_comma = false;
+
+        while !self.at(&Brace(Right)) {
+            let item = self.annotated(|parser| {
+                let mut designators = Vec::new();
+
+                loop {
+                    if parser.eat_if(Dot) {
+                        designators.push(Designator::Member(parser.eat_identifier()?));
+                    } else if parser.eat_if(Bracket(Left)) {
+                        designators.push(Designator::Index(parser.parse_conditional()?));
+                        parser.eat(Bracket(Right))?;
+                    } else {
+                        break;
+                    }
+                }
+
+                if !designators.is_empty() {
+                    parser.eat(Equal)?;
+                }
+
+                let value = parser.parse_initializer()?;
+
+                trailing_comma = parser.eat_if(Comma);
+                if !trailing_comma && !parser.at(&Brace(Right)) {
+                    return Err(parser.unexpected());
+                }
+
+                Ok(InitializerItem { designators, value })
+            })?;
+
+            items.push(item);
+        }
+        let comments = self.take_leading_comments();
+        self.eat(Brace(Right))?;
+
+        Ok(Initializer::List(InitializerList {
+            span: self.span_from(start),
+            items,
+            trailing_comma,
+            comments,
+        }))
+    }
+
+    /// Check whether an identifier names a type.
+    fn is_type_name(&self, name: Symbol) -> bool {
+        self.typedefs.contains(&name) || BUILTIN_TYPES.contains(&name.as_str())
+    }
+
+    /// Check whether a keyword may appear in the specifiers of a declaration.
+    fn is_specifier_keyword(keyword: TokenKeyword) -> bool {
+        use TokenKeyword::*;
+        matches!(
+            keyword,
+            Typedef
+                | Extern
+                | Static
+                | Auto
+                | Register
+                | Const
+                | Volatile
+                | Restrict
+                | Inline
+                | Unsigned
+                | Struct
+                | Union
+                | Enum
+        )
+    }
+
+    /// Consume the specifiers of a declaration. Identifiers are accepted as type names if they are
+    /// built-in types, or if no type has been seen yet. The latter accounts for types declared in
+    /// headers which the parser never gets to see.
+    fn parse_specifiers(&mut self) -> Result<Vec<Specifier>, ParserError> {
+        let mut specifiers = Vec::new();
+        let mut seen_type = false;
+
+        loop {
+            let start = self.next_start();
+            match self.peek() {
+                Some(Keyword(TokenKeyword::Struct)) => {
+                    self.advance()?;
+                    let record = self.parse_record(start, RecordKind::Struct)?;
+                    specifiers.push(Specifier::Record(record));
+                    seen_type = true;
+                }
+                Some(Keyword(TokenKeyword::Union)) => {
+                    self.advance()?;
+                    let record = self.parse_record(start, RecordKind::Union)?;
+                    specifiers.push(Specifier::Record(record));
+                    seen_type = true;
+                }
+                Some(Keyword(TokenKeyword::Enum)) => {
+                    self.advance()?;
+                    specifiers.push(Specifier::Enum(self.parse_enum(start)?));
+                    seen_type = true;
+                }
+                _ if self.starts_attribute() => {
+                    specifiers.push(Specifier::Attribute(self.parse_attribute()?));
+                }
+                Some(Keyword(keyword)) if Parser::is_specifier_keyword(*keyword) => {
+                    let keyword = *keyword;
+                    self.advance()?;
+                    specifiers.push(Specifier::Keyword(keyword));
+                    seen_type |= keyword == TokenKeyword::Unsigned;
+                }
+                Some(Identifier(name)) if BUILTIN_TYPES.contains(&name.as_str()) => {
+                    specifiers.push(Specifier::Type(*name));
+                    self.advance()?;
+                    seen_type = true;
+                }
+                Some(Identifier(name)) if !seen_type => {
+                    specifiers.push(Specifier::Type(*name));
+                    self.advance()?;
+                    seen_type = true;
+                }
+                _ if specifiers.is_empty() => return Err(self.unexpected()),
+                _ => return Ok(specifiers),
+            }
+        }
+    }
+
+    /// Consume a `struct` or `union` specifier, assuming the keyword starting at `start` has
+    /// already been eaten.
+    fn parse_record(&mut self, start: usize, kind: RecordKind) -> Result<Record, ParserError> {
+        let attributes = self.parse_attributes()?;
+        let name = match self.peek() {
+            Some(Identifier(_)) => Some(self.eat_identifier()?),
+            _ => None,
+        };
+
+        if !self.eat_if(Brace(Left)) {
+            return Ok(Record {
+                span: self.span_from(start),
+                kind,
+                attributes,
+                name,
+                members: None,
+                comments: Vec::new(),
+            });
+        }
+
+        let mut members = Vec::new();
+
+        while !self.at(&Brace(Right)) {
+            members.push(self.annotated(Parser::parse_member)?);
+        }
+        let comments = self.take_leading_comments();
+        self.eat(Brace(Right))?;
+
+        Ok(Record {
+            span: self.span_from(start),
+            kind,
+            attributes,
+            name,
+            members: Some(members),
+            comments,
+        })
+    }
+
+    /// Consume a single line inside a record definition.
+    fn parse_member(&mut self) -> Result<Member, ParserError> {
+        if let Some(Directive(line)) = self.peek() {
+            if Parser::opens_conditional(line) {
+                return Ok(Member::Conditional(self.parse_conditional_group(
+                    |parser| parser.annotated(Parser::parse_member),
+                )?));
+            }
+
+            let line = line.to_string();
+            self.advance()?;
+            return Ok(Member::Directive(line));
+        }
+
+        if let Some(end) = self.call_like_end() {
+            if Parser::ends_macro(self.peek_nth(end)) || self.at_pragma() {
+                let call = self.parse_macro_call()?;
+                let semicolon = self.eat_if(Semicolon);
+                return Ok(Member::Macro { call, semicolon });
+            }
+        }
+
+        let specifiers = self.parse_specifiers()?;
+        let mut declarators = Vec::new();
+
+        while !self.eat_if(Semicolon) {
+            if !declarators.is_empty() {
+                self.eat(Comma)?;
+            }
+
+            let start = self.next_start();
+            let declarator = if self.at(&Colon) {
+                None
+            } else {
+                Some(self.parse_declarator()?)
+            };
+
+            let width = if self.eat_if(Colon) {
+                Some(self.parse_conditional()?)
+            } else {
+                None
+            };
+
+            declarators.push(MemberDeclarator {
+                span: self.span_from(start),
+                declarator,
+                width,
+            });
+        }
+
+        Ok(Member::Field {
+            specifiers,
+            declarators,
+        })
+    }
+
+    /// Consume an `enum` specifier, assuming the keyword starting at `start` has already been
+    /// eaten.
+    fn parse_enum(&mut self, start: usize) -> Result<Enum, ParserError> {
+        let attributes = self.parse_attributes()?;
+        let name = match self.peek() {
+            Some(Identifier(_)) => Some(self.eat_identifier()?),
+            _ => None,
+        };
+
+        if !self.eat_if(Brace(Left)) {
+            return Ok(Enum {
+                span: self.span_from(start),
+                attributes,
+                name,
+                enumerators: None,
+                comments: Vec::new(),
+            });
+        }
+
+        let mut enumerators = Vec::new();
+
+        while !self.at(&Brace(Right)) {
+            let enumerator = self.annotated(|parser| {
+                let name = parser.eat_identifier()?;
+                let value = if parser.eat_if(Equal) {
+                    Some(parser.parse_conditional()?)
+                } else {
+                    None
+                };
+
+                if !parser.at(&Brace(Right)) {
+                    parser.eat(Comma)?;
+                }
+
+                Ok(Enumerator { name, value })
+            })?;
+
+            enumerators.push(enumerator);
+        }
+        let comments = self.take_leading_comments();
+        self.eat(Brace(Right))?;
+
+        Ok(Enum {
+            span: self.span_from(start),
+            attributes,
+            name,
+            enumerators: Some(enumerators),
+            comments,
+        })
+    }
+
+    /// Consume a declarator, which may be abstract, meaning it does not contain a name.
+    fn parse_declarator(&mut self) -> Result<Declarator, ParserError> {
+        let start = self.next_start();
+        let mut pointers = Vec::new();
+
+        while self.eat_if(Star) {
+            let mut qualifiers = Vec::new();
+
+            while let Some(Keyword(
+                keyword @ (TokenKeyword::Const | TokenKeyword::Volatile | TokenKeyword::Restrict),
+            )) = self.peek()
+            {
+                qualifiers.push(*keyword);
+                self.advance()?;
+            }
+
+            pointers.push(Pointer { qualifiers });
+        }
+
+        let mut direct = match self.peek() {
+            Some(Identifier(_)) => DirectDeclarator::Identifier(self.eat_identifier()?),
+            Some(Parenthesis(Left)) if self.starts_nested_declarator() => {
+                self.eat(Parenthesis(Left))?;
+                let inner = self.parse_declarator()?;
+                self.eat(Parenthesis(Right))?;
+                DirectDeclarator::Parenthesized(Box::new(inner))
+            }
+            _ => DirectDeclarator::Abstract,
+        };
+
+        loop {
+            if self.starts_attribute() {
+                let attributes = self.parse_attributes()?;
+                return Ok(Declarator {
+                    span: self.span_from(start),
+                    pointers,
+                    direct,
+                    attributes,
+                });
+            } else if self.eat_if(Bracket(Left)) {
+                let size = if self.at(&Bracket(Right)) {
+                    None
+                } else {
+                    Some(self.parse_assignment()?)
+                };
+                self.eat(Bracket(Right))?;
+                direct = DirectDeclarator::Array(Box::new(direct), size);
+            } else if self.eat_if(Parenthesis(Left)) {
+                let (parameters, variadic) = self.parse_parameters()?;
+                direct = DirectDeclarator::Function(Box::new(direct), parameters, variadic);
+            } else {
+                return Ok(Declarator {
+                    span: self.span_from(start),
+                    pointers,
+                    direct,
+                    attributes: Vec::new(),
+                });
+            }
+        }
+    }
+
+    /// Check whether the upcoming tokens start either a standard or a GNU attribute.
+    fn starts_attribute(&self) -> bool {
+        match self.peek() {
+            Some(Bracket(Left)) => self.peek_nth(1) == Some(&Bracket(Left)),
+            Some(Identifier(name)) => name == "__attribute__" || name == "__attribute",
+            _ => false,
+        }
+    }
+
+    /// Consume any number of consecutive attributes.
+    fn parse_attributes(&mut self) -> Result<Vec<Attribute>, ParserError> {
+        let mut attributes = Vec::new();
+
+        while self.starts_attribute() {
+            attributes.push(self.parse_attribute()?);
+        }
+
+        Ok(attributes)
+    }
+
+    /// Consume a single `[[...]]` or `__attribute__((...))`.
+    fn parse_attribute(&mut self) -> Result<Attribute, ParserError> {
+        let start = self.next_start();
+        let (style, open, close) = if self.at(&Bracket(Left)) {
+            (AttributeStyle::Standard, Bracket(Left), Bracket(Right))
+        } else {
+            self.advance()?;
+            (AttributeStyle::Gnu, Parenthesis(Left), Parenthesis(Right))
+        };
+
+        self.eat(open.clone())?;
+        self.eat(open.clone())?;
+
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            match self.advance()? {
+                token if token == close && depth == 0 => break,
+                token => {
+                    if token == open {
+                        depth += 1;
+                    } else if token == close {
+                        depth -= 1;
+                    }
+                    tokens.push(token.into_owned());
+                }
+            }
+        }
+
+        self.eat(close)?;
+        Ok(Attribute {
+            span: self.span_from(start),
+            style,
+            tokens,
+        })
+    }
+
+    /// Check whether an opening parenthesis at the start of a direct declarator wraps a nested
+    /// declarator, as opposed to starting the parameter list of an abstract function declarator.
+    fn starts_nested_declarator(&self) -> bool {
+        match self.peek_nth(1) {
+            Some(Star) | Some(Parenthesis(Left)) | Some(Bracket(Left)) => true,
+            Some(&Identifier(name)) => !self.is_type_name(name),
+            _ => false,
+        }
+    }
+
+    /// Consume a parameter list, assuming the opening parenthesis has already been eaten. Also
+    /// reports whether the list ends in `...`.
+    fn parse_parameters(&mut self) -> Result<(Vec<Annotated<Parameter>>, bool), ParserError> {
+        let mut parameters: Vec<Annotated<Parameter>> = Vec::new();
+        let mut variadic = false;
+
+        while !self.at(&Parenthesis(Right)) {
+            if let Some(last) = parameters.last_mut() {
+                // Comments after the comma on its line are about the parameter in front of it.
+                self.eat(Comma)?;
+                last.trailing.extend(self.take_trailing_comments());
+            }
+
+            if self.eat_if(Ellipsis) {
+                variadic = true;
+                break;
+            }
+
+            parameters.push(self.annotated(|parser| {
+                let specifiers = parser.parse_specifiers()?;
+                let declarator = parser.parse_declarator()?;
+                Ok(Parameter {
+                    specifiers,
+                    declarator,
+                })
+            })?);
+        }
+
+        // Comments in front of the closing parenthesis follow the last parameter.
+        if let Some(last) = parameters.last_mut() {
+            last.trailing.extend(self.take_leading_comments());
+        }
+        self.eat(Parenthesis(Right))?;
+
+        Ok((parameters, variadic))
+    }
+
+    /// Check whether the upcoming tokens start a declaration rather than a statement.
+    fn starts_declaration(&self) -> bool {
+        match self.peek() {
+            Some(Keyword(keyword)) => Parser::is_specifier_keyword(*keyword),
+            Some(&Identifier(name)) if self.is_type_name(name) => true,
+            Some(Identifier(_)) => {
+                // Types from headers are unknown to the parser, so fall back to the shape of the
+                // statement: `name name`, or `name *name` followed by something which can only
+                // occur in a declaration, can never be a useful expression.
+                let mut offset = 1;
+                while self.peek_nth(offset) == Some(&Star) {
+                    offset += 1;
+                }
+
+                match (self.peek_nth(offset), self.peek_nth(offset + 1)) {
+                    (Some(Identifier(_)), _) if offset == 1 => true,
+                    (Some(Identifier(_)), Some(Equal | Semicolon | Comma | Bracket(Left))) => true,
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Consume the statements in between a pair of braces.
+    fn parse_block(&mut self) -> Result<Block, ParserError> {
+        let mut opening = self.take_leading_comments();
+        let start = self.next_start();
+        self.eat(Brace(Left))?;
+        opening.extend(self.take_trailing_comments());
+
+        let mut statements = Vec::new();
+        while !self.at(&Brace(Right)) {
+            if self.peek().is_none() {
+                return Err(self.unexpected());
+            }
+
+            statements.push(self.recovering(Parser::parse_statement, Statement::Invalid));
+        }
+
+        let comments = self.take_leading_comments();
+        let closing_blank_lines = self.blank_lines();
+        self.eat(Brace(Right))?;
+
+        Ok(Block {
+            span: self.span_from(start),
+            opening,
+            statements,
+            comments,
+            closing_blank_lines,
+        })
+    }
+
+    /// Consume a single statement or declaration.
+    fn parse_statement(&mut self) -> Result<Statement, ParserError> {
+        if self.starts_attribute() {
+            let attributes = self.parse_attributes()?;
+            return Ok(Statement::Attributed(
+                attributes,
+                self.parse_nested_statement()?,
+            ));
+        }
+
+        let Some(token) = self.peek().cloned() else {
+            return Err(self.unexpected());
+        };
+
+        match token {
+            Directive(line) if Parser::opens_conditional(&line) => {
+                Ok(Statement::Conditional(self.parse_conditional_group(
+                    |parser| Ok(parser.recovering(Parser::parse_statement, Statement::Invalid)),
+                )?))
+            }
+            Directive(line) => {
+                self.advance()?;
+                Ok(Statement::Directive(line.into_owned()))
+            }
+            Brace(Left) => Ok(Statement::Compound(self.parse_block()?)),
+            Keyword(TokenKeyword::StaticAssert) => {
+                Ok(Statement::StaticAssert(self.parse_static_assert()?))
+            }
+            Identifier(name) if Parser::is_asm_keyword(&name) => {
+                Ok(Statement::Asm(self.parse_asm()?))
+            }
+            Semicolon => {
+                self.advance()?;
+                Ok(Statement::Expression(None))
+            }
+            Keyword(TokenKeyword::If) => {
+                self.advance()?;
+                let condition = self.parse_condition()?;
+                let then = self.parse_nested_statement()?;
+                let otherwise = if self.eat_if(Keyword(TokenKeyword::Else)) {
+                    Some(self.parse_nested_statement()?)
+                } else {
+                    None
+                };
+
+                Ok(Statement::If {
+                    condition,
+                    then,
+                    otherwise,
+                })
+            }
+            Keyword(TokenKeyword::Switch) => {
+                self.advance()?;
+                let condition = self.parse_condition()?;
+                let body = self.parse_nested_statement()?;
+                Ok(Statement::Switch { condition, body })
+            }
+            Keyword(TokenKeyword::While) => {
+                self.advance()?;
+                let condition = self.parse_condition()?;
+                let body = self.parse_nested_statement()?;
+                Ok(Statement::While { condition, body })
+            }
+            Keyword(TokenKeyword::Do) => {
+                self.advance()?;
+                let body = self.parse_nested_statement()?;
+                self.eat(Keyword(TokenKeyword::While))?;
+                let condition = self.parse_condition()?;
+                self.eat(Semicolon)?;
+                Ok(Statement::DoWhile { body, condition })
+            }
+            Keyword(TokenKeyword::For) => {
+                self.advance()?;
+                self.parse_for()
+            }
+            Keyword(TokenKeyword::Goto) => {
+                self.advance()?;
+                let label = self.eat_identifier()?;
+                self.eat(Semicolon)?;
+                Ok(Statement::Goto(label))
+            }
+            Keyword(TokenKeyword::Continue) => {
+                self.advance()?;
+                self.eat(Semicolon)?;
+                Ok(Statement::Continue)
+            }
+            Keyword(TokenKeyword::Break) => {
+                self.advance()?;
+                self.eat(Semicolon)?;
+                Ok(Statement::Break)
+            }
+            Keyword(TokenKeyword::Return) => {
+                self.advance()?;
+                let value = if self.at(&Semicolon) {
+                    None
+                } else {
+                    Some(self.parse_expression()?)
+                };
+                self.eat(Semicolon)?;
+                Ok(Statement::Return(value))
+            }
+            Keyword(TokenKeyword::Case) => {
+                self.advance()?;
+                let value = self.parse_conditional()?;
+                self.eat(Colon)?;
+                Ok(Statement::Case(value, self.parse_after_label()?))
+            }
+            Keyword(TokenKeyword::Default) => {
+                self.advance()?;
+                self.eat(Colon)?;
+                Ok(Statement::Default(self.parse_after_label()?))
+            }
+            Identifier(label) if self.peek_nth(1) == Some(&Colon) => {
+                self.advance()?;
+                self.advance()?;
+                // A label at the end of a block, which is allowed since C23, labels an empty
+                // statement.
+                let statement = if self.peek() == Some(&Brace(Right)) {
+                    let start = self.next_start();
+                    self.statement(start, Statement::Expression(None))
+                } else {
+                    self.parse_after_label()?
+                };
+                Ok(Statement::Labeled(label, statement))
+            }
+            _ if self.starts_declaration() => Ok(Statement::Declaration(self.parse_declaration()?)),
+            _ => match self.call_like_end() {
+                Some(end) => self.parse_call_like_statement(end),
+                None => self.parse_expression_statement(),
+            },
+        }
+    }
+
+    /// Consume a statement nested in another one, and move it into the arena.
+    fn parse_nested_statement(&mut self) -> Result<StatementId, ParserError> {
+        let start = self.next_start();
+        let statement = self.parse_statement()?;
+        Ok(self.statement(start, statement))
+    }
+
+    /// Consume the statement after a label, together with the markers which follow the label on
+    /// its line, as in `case 1: /* fallthrough */`. They are kept with the label, rather than
+    /// moved after the statement like other comments in between its tokens.
+    fn parse_after_label(&mut self) -> Result<StatementId, ParserError> {
+        let next = self.next_start();
+        let mut markers = Vec::new();
+        while let Some(lexeme) = self.comments.get(self.comment_index) {
+            let comment = Parser::comment(lexeme);
+            if lexeme.newlines > 0
+                || lexeme.span.start < self.previous_end
+                || lexeme.span.start > next
+                || !comment.is_marker()
+            {
+                break;
+            }
+            markers.push(comment);
+            self.comment_index += 1;
+        }
+
+        let statement = self.parse_nested_statement()?;
+        let span = self.nodes.statements.span(statement);
+        Ok(match markers.is_empty() {
+            true => statement,
+            false => self
+                .nodes
+                .statement(Statement::Marked(markers, statement), span),
+        })
+    }
+
+    /// Consume an expression followed by a semicolon.
+    fn parse_expression_statement(&mut self) -> Result<Statement, ParserError> {
+        let expression = self.parse_expression()?;
+        self.eat(Semicolon)?;
+        Ok(Statement::Expression(Some(expression)))
+    }
+
+    /// Consume a statement starting with `name(...)`, where the token after the closing
+    /// parenthesis is `end` positions ahead. This is either an ordinary expression statement, or
+    /// a macro invocation which does not fit the grammar of C. The latter is recognized by the
+    /// token following it, or by arguments which are not expressions.
+    fn parse_call_like_statement(&mut self, end: usize) -> Result<Statement, ParserError> {
+        use TokenKeyword::*;
+
+        if self.at_pragma() {
+            let call = self.parse_macro_call()?;
+            let semicolon = self.eat_if(Semicolon);
+            return Ok(Statement::Macro { call, semicolon });
+        }
+
+        // A statement after the call is its body if it is a block or starts on the same line, as
+        // in `FOREACH(x) if (x) ...`. On a line of its own, it follows a call without a semicolon.
+        let same_line = self
+            .tokens
+            .get(self.index + end)
+            .is_some_and(|lexeme| lexeme.newlines == 0);
+        match self.peek_nth(end) {
+            Some(Keyword(If | Switch | While | Do | For | Goto | Continue | Break | Return))
+                if !same_line =>
+            {
+                Ok(Statement::Macro {
+                    call: self.parse_macro_call()?,
+                    semicolon: false,
+                })
+            }
+            Some(
+                Brace(Left)
+                | Keyword(If | Switch | While | Do | For | Goto | Continue | Break | Return),
+            ) => {
+                let call = self.parse_macro_call()?;
+                Ok(Statement::MacroLoop(call, self.parse_nested_statement()?))
+            }
+            Some(Semicolon) => {
+                let checkpoint = (self.index, self.comment_index, self.previous_end);
+
+                match self.parse_expression_statement() {
+                    Ok(statement) => Ok(statement),
+                    Err(_) => {
+                        (self.index, self.comment_index, self.previous_end) = checkpoint;
+                        let call = self.parse_macro_call()?;
+                        self.eat(Semicolon)?;
+                        Ok(Statement::Macro {
+                            call,
+                            semicolon: true,
+                        })
+                    }
+                }
+            }
+            token if Parser::ends_macro(token) => Ok(Statement::Macro {
+                call: self.parse_macro_call()?,
+                semicolon: false,
+            }),
+            _ => self.parse_expression_statement(),
+        }
+    }
+
+    /// Consume an expression in between parentheses, as used by `if`, `while` and `switch`.
+    fn parse_condition(&mut self) -> Result<ExpressionId, ParserError> {
+        self.eat(Parenthesis(Left))?;
+        let condition = self.parse_expression()?;
+        self.eat(Parenthesis(Right))?;
+        Ok(condition)
+    }
+
+    /// Consume the remainder of a `for` statement, assuming the keyword has already been eaten.
+    fn parse_for(&mut self) -> Result<Statement, ParserError> {
+        self.eat(Parenthesis(Left))?;
+
+        let start = self.next_start();
+        let initializer = if self.eat_if(Semicolon) {
+            None
+        } else if self.starts_declaration() {
+            let declaration = self.parse_declaration()?;
+            Some(self.statement(start, Statement::Declaration(declaration)))
+        } else {
+            let expression = self.parse_expression()?;
+            self.eat(Semicolon)?;
+            Some(self.statement(start, Statement::Expression(Some(expression))))
+        };
+
+        let condition = if self.at(&Semicolon) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.eat(Semicolon)?;
+
+        let step = if self.at(&Parenthesis(Right)) {
+            None
+        } else {
+            Some(self.parse_expression()?)
+        };
+        self.eat(Parenthesis(Right))?;
+
+        let body = self.parse_nested_statement()?;
+
+        Ok(Statement::For {
+            initializer,
+            condition,
+            step,
+            body,
+        })
+    }
+
+    /// Consume a full expression, which may consist of several assignment expressions separated
+    /// by the comma operator.
+    fn parse_expression(&mut self) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+        let first = self.parse_assignment()?;
+
+        if !self.at(&Comma) {
+            return Ok(first);
+        }
+
+        let mut expressions = vec![first];
+        while self.eat_if(Comma) {
+            expressions.push(self.parse_assignment()?);
+        }
+
+        Ok(self.expression(start, Expression::Comma(expressions)))
+    }
+
+    /// Consume an assignment expression, which is right associative.
+    fn parse_assignment(&mut self) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+        let target = self.parse_conditional()?;
+
+        let operator = match self.peek() {
+            Some(Equal) => AssignmentOperator::Assign,
+            Some(StarEqual) => AssignmentOperator::Multiply,
+            Some(SlashEqual) => AssignmentOperator::Divide,
+            Some(PercentEqual) => AssignmentOperator::Modulo,
+            Some(PlusEqual) => AssignmentOperator::Add,
+            Some(MinusEqual) => AssignmentOperator::Subtract,
+            Some(LessLessEqual) => AssignmentOperator::ShiftLeft,
+            Some(GreaterGreaterEqual) => AssignmentOperator::ShiftRight,
+            Some(AmpersandEqual) => AssignmentOperator::BitwiseAnd,
+            Some(CaretEqual) => AssignmentOperator::BitwiseXor,
+            Some(PipeEqual) => AssignmentOperator::BitwiseOr,
+            _ => return Ok(target),
+        };
+
+        self.advance()?;
+        let value = self.parse_assignment()?;
+        Ok(self.expression(start, Expression::Assignment(operator, target, value)))
+    }
+
+    /// Consume a ternary expression, or anything which binds more tightly.
+    fn parse_conditional(&mut self) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+        let condition = self.parse_binary(1)?;
+
+        if !self.eat_if(Question) {
+            return Ok(condition);
+        }
+
+        let then = self.parse_expression()?;
+        self.eat(Colon)?;
+        let otherwise = self.parse_conditional()?;
+        Ok(self.expression(start, Expression::Conditional(condition, then, otherwise)))
+    }
+
+    /// The binary operator which corresponds with the next token, if any.
+    fn peek_binary_operator(&self) -> Option<BinaryOperator> {
+        match self.peek()? {
+            Star => Some(BinaryOperator::Multiply),
+            Slash => Some(BinaryOperator::Divide),
+            Percent => Some(BinaryOperator::Modulo),
+            Plus => Some(BinaryOperator::Add),
+            Minus => Some(BinaryOperator::Subtract),
+            LessLess => Some(BinaryOperator::ShiftLeft),
+            GreaterGreater => Some(BinaryOperator::ShiftRight),
+            Less => Some(BinaryOperator::Less),
+            Greater => Some(BinaryOperator::Greater),
+            LessEqual => Some(BinaryOperator::LessEqual),
+            GreaterEqual => Some(BinaryOperator::GreaterEqual),
+            EqualEqual => Some(BinaryOperator::Equal),
+            BangEqual => Some(BinaryOperator::NotEqual),
+            Ampersand => Some(BinaryOperator::BitwiseAnd),
+            Caret => Some(BinaryOperator::BitwiseXor),
+            Pipe => Some(BinaryOperator::BitwiseOr),
+            AmpersandAmpersand => Some(BinaryOperator::LogicalAnd),
+            PipePipe => Some(BinaryOperator::LogicalOr),
+            _ => None,
+        }
+    }
+
+    /// Consume a chain of left associative binary operators using precedence climbing, only
+    /// accepting operators which bind at least as tightly as `minimum`.
+    fn parse_binary(&mut self, minimum: u8) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+        let mut left = self.parse_unary()?;
+
+        while let Some(operator) = self.peek_binary_operator() {
+            if operator.precedence() < minimum {
+                break;
+            }
+
+            self.advance()?;
+            let right = self.parse_binary(operator.precedence() + 1)?;
+            left = self.expression(start, Expression::Binary(operator, left, right));
+        }
+
+        Ok(left)
+    }
+
+    /// Check whether the token `n` positions ahead starts a type name.
+    fn starts_type_name(&self, n: usize) -> bool {
+        match self.peek_nth(n) {
+            Some(Keyword(keyword)) => Parser::is_specifier_keyword(*keyword),
+            Some(&Identifier(name)) => self.is_type_name(name),
+            _ => false,
+        }
+    }
+
+    /// Check whether the upcoming opening parenthesis starts a cast or a compound literal. Known
+    /// types are recognized directly, while unknown identifiers count as types if they are
+    /// followed by pointers, or if the parenthesized name is directly followed by an operand,
+    /// which could never happen in a valid expression.
+    fn starts_cast(&self) -> bool {
+        if self.peek() != Some(&Parenthesis(Left)) {
+            return false;
+        }
+
+        if self.starts_type_name(1) {
+            return true;
+        }
+
+        if !matches!(self.peek_nth(1), Some(Identifier(_))) {
+            return false;
+        }
+
+        let mut offset = 2;
+        while self.peek_nth(offset) == Some(&Star) {
+            offset += 1;
+        }
+
+        match (self.peek_nth(offset), self.peek_nth(offset + 1)) {
+            (Some(Parenthesis(Right)), _) if offset > 2 => true,
+            (
+                Some(Parenthesis(Right)),
+                Some(
+                    Identifier(_)
+                    | Number(_)
+                    | Character(_)
+                    | Str(_)
+                    | Keyword(TokenKeyword::Sizeof),
+                ),
+            ) => true,
+            _ => false,
+        }
+    }
+
+    /// Consume a type name in between parentheses.
+    fn parse_parenthesized_type_name(&mut self) -> Result<TypeName, ParserError> {
+        self.eat(Parenthesis(Left))?;
+        let type_name = self.parse_type_name()?;
+        self.eat(Parenthesis(Right))?;
+        Ok(type_name)
+    }
+
+    /// Consume the specifiers and abstract declarator of a type name.
+    fn parse_type_name(&mut self) -> Result<TypeName, ParserError> {
+        let start = self.next_start();
+        let specifiers = self.parse_specifiers()?;
+        let declarator = self.parse_declarator()?;
+
+        Ok(TypeName {
+            span: self.span_from(start),
+            specifiers,
+            declarator,
+        })
+    }
+
+    /// Consume an expression with any number of prefix operators.
+    fn parse_unary(&mut self) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+
+        if self.starts_cast() {
+            let type_name = self.parse_parenthesized_type_name()?;
+
+            if self.at(&Brace(Left)) {
+                let Initializer::List(list) = self.parse_initializer()? else {
+                    unreachable!("an initializer starting with a brace is a list");
+                };
+                let literal = self.expression(start, Expression::CompoundLiteral(type_name, list));
+                return self.parse_postfix_operators(start, literal);
+            }
+
+            let operand = self.parse_unary()?;
+            return Ok(self.expression(start, Expression::Cast(type_name, operand)));
+        }
+
+        match self.peek() {
+            Some(Keyword(TokenKeyword::Sizeof)) => {
+                self.advance()?;
+
+                if self.at(&Parenthesis(Left)) && self.starts_type_name(1) {
+                    let type_name = self.parse_parenthesized_type_name()?;
+                    return Ok(self.expression(start, Expression::SizeofType(type_name)));
+                }
+
+                let operand = self.parse_unary()?;
+                return Ok(self.expression(start, Expression::Sizeof(operand)));
+            }
+            Some(Keyword(TokenKeyword::Alignof)) => {
+                self.advance()?;
+                let type_name = self.parse_parenthesized_type_name()?;
+                return Ok(self.expression(start, Expression::Alignof(type_name)));
+            }
+            _ => {}
+        }
+
+        let operator = match self.peek() {
+            Some(Plus) => UnaryOperator::Plus,
+            Some(Minus) => UnaryOperator::Minus,
+            Some(Bang) => UnaryOperator::Not,
+            Some(Tilde) => UnaryOperator::Complement,
+            Some(Star) => UnaryOperator::Dereference,
+            Some(Ampersand) => UnaryOperator::AddressOf,
+            Some(PlusPlus) => UnaryOperator::PreIncrement,
+            Some(MinusMinus) => UnaryOperator::PreDecrement,
+            _ => return self.parse_postfix(),
+        };
+
+        self.advance()?;
+        let operand = self.parse_unary()?;
+        Ok(self.expression(start, Expression::Unary(operator, operand)))
+    }
+
+    /// Consume a primary expression followed by any number of postfix operators.
+    fn parse_postfix(&mut self) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+        let expression = self.parse_primary()?;
+        self.parse_postfix_operators(start, expression)
+    }
+
+    /// Consume any number of postfix operators applied to an expression which was already parsed,
+    /// starting at `start`.
+    fn parse_postfix_operators(
+        &mut self,
+        start: usize,
+        mut expression: ExpressionId,
+    ) -> Result<ExpressionId, ParserError> {
+        loop {
+            let postfix = match self.peek() {
+                Some(Parenthesis(Left)) => {
+                    self.advance()?;
+                    let mut arguments = Vec::new();
+                    while !self.eat_if(Parenthesis(Right)) {
+                        if !arguments.is_empty() {
+                            self.eat(Comma)?;
+                        }
+
+                        // Macros such as `va_arg` accept types, which never start an expression.
+                        let argument = if self.starts_type_name(0) {
+                            let start = self.next_start();
+                            let type_name = self.parse_type_name()?;
+                            self.expression(start, Expression::Type(type_name))
+                        } else {
+                            self.parse_assignment()?
+                        };
+                        arguments.push(argument);
+                    }
+                    Expression::Call(expression, arguments)
+                }
+                Some(Bracket(Left)) => {
+                    self.advance()?;
+                    let index = self.parse_expression()?;
+                    self.eat(Bracket(Right))?;
+                    Expression::Index(expression, index)
+                }
+                Some(Dot) => {
+                    self.advance()?;
+                    let member = self.eat_identifier()?;
+                    Expression::Member(expression, member, false)
+                }
+                Some(Arrow) => {
+                    self.advance()?;
+                    let member = self.eat_identifier()?;
+                    Expression::Member(expression, member, true)
+                }
+                Some(PlusPlus) => {
+                    self.advance()?;
+                    Expression::Unary(UnaryOperator::PostIncrement, expression)
+                }
+                Some(MinusMinus) => {
+                    self.advance()?;
+                    Expression::Unary(UnaryOperator::PostDecrement, expression)
+                }
+                _ => return Ok(expression),
+            };
+            expression = self.expression(start, postfix);
+        }
+    }
+
+    /// Consume a `_Generic` selection.
+    fn parse_generic(&mut self) -> Result<ExpressionId, ParserError> {
+        let start = self.next_start();
+        self.eat(Keyword(TokenKeyword::Generic))?;
+        self.eat(Parenthesis(Left))?;
+        let controlling = self.parse_assignment()?;
+        let mut associations = Vec::new();
+
+        while self.eat_if(Comma) {
+            let type_name = if self.eat_if(Keyword(TokenKeyword::Default)) {
+                None
+            } else {
+                Some(self.parse_type_name()?)
+            };
+
+            self.eat(Colon)?;
+            let value = self.parse_assignment()?;
+            associations.push(GenericAssociation { type_name, value });
+        }
+
+        self.eat(Parenthesis(Right))?;
+        Ok(self.expression(start, Expression::Generic(controlling, associations)))
+    }
+
+    /// Consume a literal, an identifier or a parenthesized expression.
+    fn parse_primary(&mut self) -> Result<ExpressionId, ParserError> {
+        if self.at(&Keyword(TokenKeyword::Generic)) {
+            return self.parse_generic();
+        }
+
+        if !matches!(
+            self.peek(),
+            Some(Identifier(_) | Number(_) | Character(_) | Str(_) | Parenthesis(Left))
+        ) {
+            return Err(self.unexpected());
+        }
+
+        let start = self.next_start();
+        let expression = match self.advance()? {
+            Identifier(name) => Expression::Identifier(name),
+            Number(number) => Expression::Number(number.into_owned()),
+            Character(character) => Expression::Character(character.into_owned()),
+            Str(first) => {
+                let mut parts = vec![first.into_owned()];
+                while let Some(Str(part)) = self.peek() {
+                    parts.push(part.to_string());
+                    self.advance()?;
+                }
+                Expression::Str(parts)
+            }
+            Parenthesis(Left) => {
+                let inner = self.parse_expression()?;
+                self.eat(Parenthesis(Right))?;
+                Expression::Parenthesized(inner)
+            }
+            _ => unreachable!("the token was checked in advance"),
+        };
+
+        Ok(self.expression(start, expression))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lexer::Lexer;
+
+    fn parse(input: &str) -> String {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse_translation_unit().to_string();
+        assert!(parser.errors().is_empty());
+        result
+    }
+
+    #[test]
+    fn empty_file() {
+        assert_eq!(parse(""), "");
+    }
+
+    #[test]
+    fn declarations_and_directives() {
+        let expected = "(directive \"include <stdio.h>\")\n\
+                        (declaration [static int] (* p) (= (array q 3) 0))\n\
+                        (empty)\n";
+        assert_eq!(
+            parse("#include <stdio.h>\nstatic int *p, q[3] = 0;;"),
+            expected
+        );
+    }
+
+    #[test]
+    fn function_definition() {
+        let expected = "(definition [int] (function main \
+                        (parameter [int] argc) (parameter [char] (* (* argv)))) \
+                        (block (return 0)))\n";
+        assert_eq!(
+            parse("int main(int argc, char **argv) { return 0; }"),
+            expected
+        );
+    }
+
+    #[test]
+    fn function_pointer() {
+        let expected = "(declaration [void] (function (* callback) (parameter [int] _)))\n";
+        assert_eq!(parse("void (*callback)(int);"), expected);
+    }
+
+    #[test]
+    fn records_and_enums() {
+        let expected =
+            "(declaration [(struct point (field [int] x y) (field [unsigned] (: flag 1)))])\n\
+                        (declaration [typedef (enum _ A (= B 2))] color)\n";
+        assert_eq!(
+            parse(
+                "struct point { int x, y; unsigned flag : 1; };\ntypedef enum { A, B = 2, } color;"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn precedence() {
+        let expected = "(definition [void] (function f) (block \
+                        (expression (= a (+ b (* c d)))) \
+                        (expression (|| (&& a b) (== c (- d))))))\n";
+        assert_eq!(
+            parse("void f() { a = b + c * d; a && b || c == -d; }"),
+            expected
+        );
+    }
+
+    #[test]
+    fn postfix_and_ternary() {
+        let expected = "(definition [void] (function f) (block \
+                        (expression (= x (? (-> p next) (call g (index a i) (post++ j)) 0)))))\n";
+        assert_eq!(
+            parse("void f() { x = p->next ? g(a[i], j++) : 0; }"),
+            expected
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let expected = "(definition [void] (function f) (block \
+                        (for (declaration [int] (= i 0)) (< i n) (post++ i) \
+                        (if (paren (== i 2)) (continue) (break))) \
+                        (while x (block)) \
+                        (do (expression (post-- x)) x) \
+                        (switch x (block (case 1 (return)) (default (goto end)))) \
+                        (label end (empty))))\n";
+        assert_eq!(
+            parse(
+                "void f() {
+                    for (int i = 0; i < n; i++) if ((i == 2)) continue; else break;
+                    while (x) {}
+                    do x--; while (x);
+                    switch (x) { case 1: return; default: goto end; }
+                    end: ;
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn typedef_names() {
+        let expected = "(declaration [typedef int] length)\n\
+                        (definition [void] (function f) (block \
+                        (declaration [length] (* p)) \
+                        (declaration [FILE] (= (* file) 0)) \
+                        (expression (+ (* a b) c))))\n";
+        assert_eq!(
+            parse("typedef int length;\nvoid f() { length *p; FILE *file = 0; a * b + c; }"),
+            expected
+        );
+    }
+
+    #[test]
+    fn recovery() {
+        let input = "int main() { int x = (; return 0; }\nint y = ;\nint z;\nvoid f() { if (x) { a b c; } }";
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse_translation_unit().to_string();
+
+        let expected = "(definition [int] (function main) (block (invalid 13..23) (return 0)))\n\
+                        (invalid 36..45)\n\
+                        (declaration [int] z)\n\
+                        (definition [void] (function f) (block (if x (block (invalid 73..79)))))\n";
+        assert_eq!(result, expected);
+        assert!(matches!(
+            parser.errors(),
+            [
+                ParserError::UnexpectedToken(Semicolon, Span { start: 22, end: 23 }),
+                ParserError::UnexpectedToken(Semicolon, _),
+                ParserError::UnexpectedToken(Identifier(_), _),
+            ]
+        ));
+    }
+
+    #[test]
+    fn unexpected_end_of_file() {
+        let tokens = Lexer::new("int main() { return 0;")
+            .lexemes()
+            .map(|lexeme| lexeme.unwrap());
+        let mut parser = Parser::new(tokens);
+        let result = parser.parse_translation_unit().to_string();
+
+        assert_eq!(result, "(invalid 0..22)\n");
+        assert!(matches!(
+            parser.errors(),
+            [ParserError::UnexpectedEndOfFile(_)]
+        ));
+    }
+
+    #[test]
+    fn comments() {
+        let expected = "(comment \" Leading.\") (declaration [int] x) (comment \" Trailing.\")\n\
+                        (definition [void] (function f) (block \
+                        (expression (call g a b)) (comment \" Inner. \") \
+                        (comment \" Dangling.\")))\n\
+                        (declaration [(enum _ A (comment \" First.\") B)])\n\
+                        (comment \" End.\")\n";
+        assert_eq!(
+            parse(
+                "// Leading.\nint x; // Trailing.\n\
+                 void f() {\n  g(a, /* Inner. */ b);\n  // Dangling.\n}\n\
+                 enum { A, // First.\n B };\n// End.\n"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn markers_stay_with_their_label() {
+        let expected = "(definition [void] (function f) (block (switch x (block \
+                        (case 1 (marked (comment \" FALLTHRU \") (case 2 (expression g)))) \
+                        (comment \" Two. \") \
+                        (case 3 (expression h)) (comment \" fall through \")))))\n";
+        assert_eq!(
+            parse(
+                "void f() { switch (x) {\n\
+                 case 1: /* FALLTHRU */\ncase 2: /* Two. */\ng;\n\
+                 case 3:\nh; /* fall through */\n} }"
+            ),
+            expected
+        );
+
+        let marker = |text: &str| {
+            let comment = Parser::comment(&Lexer::new(text).lexemes().next().unwrap().unwrap());
+            comment.is_marker()
+        };
+        for text in [
+            "/* fallthrough */",
+            "// Falls through.",
+            "/* intentional fall-through - see below */",
+            "/* FALLTHRU */",
+            "// NOLINT(bugprone-branch-clone)",
+            "// NOLINTNEXTLINE",
+            "/* cppcheck-suppress nullPointer */",
+            "/* coverity[dead_error_line] */",
+            "//lint -e123",
+        ] {
+            assert!(marker(text), "{}", text);
+        }
+        for text in [
+            "/* falling through the floor */",
+            "// fall throughout",
+            "// lint",
+        ] {
+            assert!(!marker(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn initializer_lists() {
+        let expected = "(declaration [int] (= (array a) (list 1 2 ,)))\n\
+                        (declaration [struct_t] (= s (list (= .x 1) (= [2].y (list 3)) (list))))\n";
+        assert_eq!(
+            parse("int a[] = { 1, 2, };\nstruct_t s = { .x = 1, [2].y = { 3 }, {} };"),
+            expected
+        );
+    }
+
+    #[test]
+    fn casts_and_sizeof() {
+        let expected = "(definition [void] (function f) (block \
+                        (expression (= x (cast (type [unsigned char] _) y))) \
+                        (expression (= p (cast (type [size_t] (* _)) (call malloc (sizeof (type [int] _)))))) \
+                        (expression (= n (+ (cast (type [uint8_t] _) n) (sizeof (* p))))) \
+                        (expression (= q (. (compound (type [(struct point)] _) (list 1 2)) x))) \
+                        (expression (= a (alignof (type [long] _)))) \
+                        (expression (call (paren f) x))))\n";
+        assert_eq!(
+            parse(
+                "void f() {
+                    x = (unsigned char) y;
+                    p = (size_t *) malloc(sizeof(int));
+                    n = (uint8_t) n + sizeof *p;
+                    q = (struct point) { 1, 2 }.x;
+                    a = _Alignof(long);
+                    (f)(x);
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn static_assert_and_generic() {
+        let expected = "(static_assert (== (sizeof (type [int] _)) 4) \"int\")\n\
+                        (definition [void] (function f) (block \
+                        (static_assert 1) \
+                        (expression (= y (generic x ((type [int] _) 1) ((type [char] (* _)) 2) (default 3))))))\n";
+        assert_eq!(
+            parse(
+                "_Static_assert(sizeof(int) == 4, \"int\");
+                void f() {
+                    _Static_assert(1);
+                    y = _Generic(x, int: 1, char *: 2, default: 3);
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn attributes() {
+        let expected = "(declaration [(attribute nodiscard) int] (function f (parameter [void] _)))\n\
+                        (declaration [void] (attributed (function g) (gnu_attribute format(printf, 1, 2))))\n\
+                        (declaration [(struct (gnu_attribute packed) s (field [int] (attributed a (attribute deprecated))))])\n\
+                        (definition [void] (function h) (block \
+                        (attributed (attribute fallthrough) (empty))))\n";
+        assert_eq!(
+            parse(
+                "[[nodiscard]] int f(void);
+                void g() __attribute__((format(printf, 1, 2)));
+                struct __attribute__((packed)) s { int a [[deprecated]]; };
+                void h() { [[fallthrough]]; }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn conditional_groups() {
+        let expected = "(conditional (branch \"ifdef A\" \
+                        (declaration [(struct s (conditional (branch \"if B\" (field [int] x)) \"endif\"))])) \
+                        (branch \"else\" (definition [void] (function f) (block \
+                        (conditional (branch \"ifndef C\" (return)) (branch \"else\") \"endif\")))) \
+                        \"endif\")\n";
+        assert_eq!(
+            parse(
+                "#ifdef A
+                struct s {
+                #if B
+                    int x;
+                #endif
+                };
+                #else
+                void f() {
+                #ifndef C
+                    return;
+                #else
+                #endif
+                }
+                #endif"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn extern_c_wrappers() {
+        assert_eq!(
+            parse(
+                "#ifdef __cplusplus
+                extern \"C\" {
+                #endif
+                int f(void);
+                #if defined(__cplusplus)
+                }
+                #endif"
+            ),
+            "(extern_open \"C\")\n\
+             (declaration [int] (function f (parameter [void] _)))\n\
+             (extern_close)\n"
+        );
+    }
+
+    #[test]
+    fn macro_invocations() {
+        let expected = "(macro MODULE_LICENSE(\"GPL\") ;)\n\
+                        (macro EXPORT_SYMBOL(f))\n\
+                        (declaration [(struct s (macro DECLARE_BITMAP(bits, 8) ;))])\n\
+                        (definition [void] (function f) (block \
+                        (macro_loop LIST_FOREACH(item, &list) (block (expression (call g item)))) \
+                        (macro CHECK(err, goto out) ;) \
+                        (macro_loop FOREACH(x) (return x)) \
+                        (macro TRACE(x)) \
+                        (return 0) \
+                        (expression (call free p))))\n";
+        assert_eq!(
+            parse(
+                "MODULE_LICENSE(\"GPL\");
+                EXPORT_SYMBOL(f)
+                struct s { DECLARE_BITMAP(bits, 8); };
+                void f() {
+                    LIST_FOREACH(item, &list) { g(item); }
+                    CHECK(err, goto out);
+                    FOREACH(x) return x;
+                    TRACE(x)
+                    return 0;
+                    free(p);
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn inline_assembly() {
+        let expected = "(asm [] (\".globl f\"))\n\
+                        (definition [void] (function f) (block \
+                        (asm [__volatile__] (\"mov %1, %0\") (\"=r\"(d)) (\"r\"(s)) (\"memory\")) \
+                        (asm [volatile] (\"nop\") () () (\"memory\"))))\n";
+        assert_eq!(
+            parse(
+                "asm(\".globl f\");
+                void f() {
+                    __asm__ __volatile__(\"mov %1, %0\" : \"=r\"(d) : \"r\"(s) : \"memory\");
+                    asm volatile(\"nop\" ::: \"memory\");
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn variadic_functions() {
+        let expected =
+            "(declaration [int] (function printf (parameter [const char] (* format)) ...))\n\
+                        (definition [void] (function log (parameter [int] n) ...) (block \
+                        (declaration [va_list] args) \
+                        (expression (= x (call va_arg args (type [char] (* _)))))))\n";
+        assert_eq!(
+            parse(
+                "int printf(const char *format, ...);
+                void log(int n, ...) {
+                    va_list args;
+                    x = va_arg(args, char *);
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn comma_operator() {
+        let expected = "(definition [void] (function f) (block \
+                        (for (expression (, (= i 0) (= j n))) (< i j) (, (post++ i) (post-- j)) (empty)) \
+                        (expression (call g (paren (, a b)) c))))\n";
+        assert_eq!(
+            parse(
+                "void f() {
+                    for (i = 0, j = n; i < j; i++, j--);
+                    g((a, b), c);
+                }"
+            ),
+            expected
+        );
+    }
+
+    #[test]
+    fn recovery_skips_past_the_error() {
+        let tokens = Lexer::new("void f() { for (a = 0; ; b = 1 c) {} }")
+            .lexemes()
+            .map(|lexeme| lexeme.unwrap());
+        let mut parser = Parser::new(tokens);
+        parser.parse_translation_unit();
+        assert_eq!(parser.errors().len(), 1);
     }
 }
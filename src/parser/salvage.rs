@@ -0,0 +1,78 @@
+//! Salvaging of the regions which could not be parsed. The parser turns such a region into an
+//! invalid node which only knows its span. Here, those nodes are replaced with the original text
+//! they cover, so that printing the tree reproduces them byte for byte while everything around
+//! them is still formatted.
+
+use crate::parser::ast::{ExternalDeclaration, Nodes, Statement, TranslationUnit};
+use crate::parser::visit_mut::{walk_external_declaration_mut, walk_statement_mut, VisitMut};
+
+struct Salvage<'a> {
+    source: &'a str,
+}
+
+impl VisitMut for Salvage<'_> {
+    fn visit_external_declaration_mut(
+        &mut self,
+        nodes: &mut Nodes,
+        node: &mut ExternalDeclaration,
+    ) {
+        if let ExternalDeclaration::Invalid(span) = node {
+            *node = ExternalDeclaration::Verbatim(self.source[span.start..span.end].to_string());
+        }
+        walk_external_declaration_mut(self, nodes, node);
+    }
+
+    fn visit_statement_mut(&mut self, nodes: &mut Nodes, node: &mut Statement) {
+        if let Statement::Invalid(span) = node {
+            *node = Statement::Verbatim(self.source[span.start..span.end].to_string());
+        }
+        walk_statement_mut(self, nodes, node);
+    }
+}
+
+/// Replace every invalid node in a translation unit with the text of `source` it covers.
+pub fn salvage(unit: &mut TranslationUnit, source: &str) {
+    Salvage { source }.visit_translation_unit_mut(unit);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::parser::Parser;
+    use crate::parser::salvage::salvage;
+
+    #[test]
+    fn invalid_regions_become_verbatim() {
+        let input = "int x = = 1;\nvoid f() {\n    a = b +* /* c */ ;\n    return;\n}\n";
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut parser = Parser::new(tokens);
+        let mut unit = parser.parse_translation_unit();
+
+        salvage(&mut unit, input);
+
+        assert_eq!(parser.errors().len(), 2);
+        assert_eq!(
+            unit.to_string(),
+            "(verbatim \"int x = = 1;\")\n\
+             (definition [void] (function f) (block (verbatim \"a = b +* /* c */ ;\") (return)))\n"
+        );
+    }
+
+    #[test]
+    fn invalid_declarations_are_kept_up_to_their_semicolon() {
+        let input = "struct { int x } broken;\nint  y;\nvoid f() { struct { int x } s; }\n";
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        let mut parser = Parser::new(tokens);
+        let mut unit = parser.parse_translation_unit();
+
+        salvage(&mut unit, input);
+
+        assert_eq!(parser.errors().len(), 2);
+        assert_eq!(
+            unit.to_string(),
+            "(verbatim \"struct { int x } broken;\")\n\
+             (declaration [int] y)\n\
+             (definition [void] (function f) (block (verbatim \"struct { int x } s;\")))\n"
+        );
+    }
+}
@@ -0,0 +1,656 @@
+//! Read-only traversal of the syntax tree. `Visit` has a method for every kind of node, which by
+//! default calls the matching `walk_*` function to visit the children of that node. Implementors
+//! override the methods for the nodes they are interested in, and call the `walk_*` function
+//! themselves if they want to continue into the children.
+
+use crate::parser::ast::{
+    Annotated, Asm, Attribute, Block, Comment, ConditionalGroup, Declaration, Declarator,
+    Designator, DirectDeclarator, Enum, Enumerator, Expression, ExternalDeclaration,
+    FunctionDefinition, GenericAssociation, InitDeclarator, Initializer, InitializerList,
+    MacroCall, Member, MemberDeclarator, Nodes, Parameter, Record, Specifier, Statement,
+    StaticAssert, TranslationUnit, TypeName,
+};
+
+/// A read-only traversal of the syntax tree. The lifetime allows visitors to hold on to the nodes
+/// they have seen. Every method receives the arenas of the translation unit, which are needed to
+/// follow the ids of nested statements and expressions.
+pub trait Visit<'ast> {
+    fn visit_translation_unit(&mut self, node: &'ast TranslationUnit) {
+        walk_translation_unit(self, node);
+    }
+
+    fn visit_external_declaration(&mut self, nodes: &'ast Nodes, node: &'ast ExternalDeclaration) {
+        walk_external_declaration(self, nodes, node);
+    }
+
+    fn visit_function_definition(&mut self, nodes: &'ast Nodes, node: &'ast FunctionDefinition) {
+        walk_function_definition(self, nodes, node);
+    }
+
+    fn visit_declaration(&mut self, nodes: &'ast Nodes, node: &'ast Declaration) {
+        walk_declaration(self, nodes, node);
+    }
+
+    fn visit_init_declarator(&mut self, nodes: &'ast Nodes, node: &'ast InitDeclarator) {
+        walk_init_declarator(self, nodes, node);
+    }
+
+    fn visit_initializer(&mut self, nodes: &'ast Nodes, node: &'ast Initializer) {
+        walk_initializer(self, nodes, node);
+    }
+
+    fn visit_initializer_list(&mut self, nodes: &'ast Nodes, node: &'ast InitializerList) {
+        walk_initializer_list(self, nodes, node);
+    }
+
+    fn visit_designator(&mut self, nodes: &'ast Nodes, node: &'ast Designator) {
+        walk_designator(self, nodes, node);
+    }
+
+    fn visit_specifier(&mut self, nodes: &'ast Nodes, node: &'ast Specifier) {
+        walk_specifier(self, nodes, node);
+    }
+
+    fn visit_attribute(&mut self, _nodes: &'ast Nodes, _node: &'ast Attribute) {}
+
+    fn visit_record(&mut self, nodes: &'ast Nodes, node: &'ast Record) {
+        walk_record(self, nodes, node);
+    }
+
+    fn visit_member(&mut self, nodes: &'ast Nodes, node: &'ast Member) {
+        walk_member(self, nodes, node);
+    }
+
+    fn visit_member_declarator(&mut self, nodes: &'ast Nodes, node: &'ast MemberDeclarator) {
+        walk_member_declarator(self, nodes, node);
+    }
+
+    fn visit_enum(&mut self, nodes: &'ast Nodes, node: &'ast Enum) {
+        walk_enum(self, nodes, node);
+    }
+
+    fn visit_enumerator(&mut self, nodes: &'ast Nodes, node: &'ast Enumerator) {
+        walk_enumerator(self, nodes, node);
+    }
+
+    fn visit_declarator(&mut self, nodes: &'ast Nodes, node: &'ast Declarator) {
+        walk_declarator(self, nodes, node);
+    }
+
+    fn visit_direct_declarator(&mut self, nodes: &'ast Nodes, node: &'ast DirectDeclarator) {
+        walk_direct_declarator(self, nodes, node);
+    }
+
+    fn visit_parameter(&mut self, nodes: &'ast Nodes, node: &'ast Parameter) {
+        walk_parameter(self, nodes, node);
+    }
+
+    fn visit_type_name(&mut self, nodes: &'ast Nodes, node: &'ast TypeName) {
+        walk_type_name(self, nodes, node);
+    }
+
+    fn visit_block(&mut self, nodes: &'ast Nodes, node: &'ast Block) {
+        walk_block(self, nodes, node);
+    }
+
+    fn visit_statement(&mut self, nodes: &'ast Nodes, node: &'ast Statement) {
+        walk_statement(self, nodes, node);
+    }
+
+    fn visit_expression(&mut self, nodes: &'ast Nodes, node: &'ast Expression) {
+        walk_expression(self, nodes, node);
+    }
+
+    fn visit_generic_association(&mut self, nodes: &'ast Nodes, node: &'ast GenericAssociation) {
+        walk_generic_association(self, nodes, node);
+    }
+
+    fn visit_static_assert(&mut self, nodes: &'ast Nodes, node: &'ast StaticAssert) {
+        walk_static_assert(self, nodes, node);
+    }
+
+    fn visit_asm(&mut self, _nodes: &'ast Nodes, _node: &'ast Asm) {}
+
+    fn visit_macro_call(&mut self, _nodes: &'ast Nodes, _node: &'ast MacroCall) {}
+
+    fn visit_comment(&mut self, _nodes: &'ast Nodes, _node: &'ast Comment) {}
+}
+
+/// Visit the comments around a node, and the node itself using `visit`.
+fn walk_annotated<'ast, V, T>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Annotated<T>,
+    visit: impl FnOnce(&mut V, &'ast Nodes, &'ast T),
+) where
+    V: Visit<'ast> + ?Sized,
+{
+    for comment in &node.leading {
+        visitor.visit_comment(nodes, comment);
+    }
+    visit(visitor, nodes, &node.node);
+    for comment in &node.trailing {
+        visitor.visit_comment(nodes, comment);
+    }
+}
+
+/// Visit the items and comments in every branch of a conditional group using `visit`.
+fn walk_conditional_group<'ast, V, T>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast ConditionalGroup<T>,
+    visit: impl Fn(&mut V, &'ast Nodes, &'ast T),
+) where
+    V: Visit<'ast> + ?Sized,
+{
+    for branch in &node.branches {
+        for item in &branch.items {
+            walk_annotated(visitor, nodes, item, &visit);
+        }
+        for comment in &branch.comments {
+            visitor.visit_comment(nodes, comment);
+        }
+    }
+}
+
+pub fn walk_translation_unit<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    node: &'ast TranslationUnit,
+) {
+    let nodes = &node.nodes;
+    for item in &node.items {
+        walk_annotated(visitor, nodes, item, V::visit_external_declaration);
+    }
+    for comment in &node.comments {
+        visitor.visit_comment(nodes, comment);
+    }
+}
+
+pub fn walk_external_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast ExternalDeclaration,
+) {
+    match node {
+        ExternalDeclaration::Declaration(declaration) => {
+            visitor.visit_declaration(nodes, declaration)
+        }
+        ExternalDeclaration::FunctionDefinition(definition) => {
+            visitor.visit_function_definition(nodes, definition)
+        }
+        ExternalDeclaration::Conditional(group) => {
+            walk_conditional_group(visitor, nodes, group, V::visit_external_declaration)
+        }
+        ExternalDeclaration::Macro { call, .. } => visitor.visit_macro_call(nodes, call),
+        ExternalDeclaration::Asm(asm) => visitor.visit_asm(nodes, asm),
+        ExternalDeclaration::StaticAssert(assertion) => {
+            visitor.visit_static_assert(nodes, assertion)
+        }
+        ExternalDeclaration::Directive(_)
+        | ExternalDeclaration::Empty
+        | ExternalDeclaration::LinkageWrapper { .. }
+        | ExternalDeclaration::Invalid(_)
+        | ExternalDeclaration::Verbatim(_) => {}
+    }
+}
+
+pub fn walk_function_definition<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast FunctionDefinition,
+) {
+    for specifier in &node.specifiers {
+        visitor.visit_specifier(nodes, specifier);
+    }
+    visitor.visit_declarator(nodes, &node.declarator);
+    visitor.visit_block(nodes, &node.body);
+}
+
+pub fn walk_declaration<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Declaration,
+) {
+    for specifier in &node.specifiers {
+        visitor.visit_specifier(nodes, specifier);
+    }
+    for declarator in &node.declarators {
+        visitor.visit_init_declarator(nodes, declarator);
+    }
+}
+
+pub fn walk_init_declarator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast InitDeclarator,
+) {
+    visitor.visit_declarator(nodes, &node.declarator);
+    if let Some(initializer) = &node.initializer {
+        visitor.visit_initializer(nodes, initializer);
+    }
+}
+
+pub fn walk_initializer<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Initializer,
+) {
+    match node {
+        Initializer::Expression(expression) => visitor.visit_expression(nodes, &nodes[*expression]),
+        Initializer::List(list) => visitor.visit_initializer_list(nodes, list),
+    }
+}
+
+pub fn walk_initializer_list<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast InitializerList,
+) {
+    for item in &node.items {
+        walk_annotated(visitor, nodes, item, |visitor, nodes, item| {
+            for designator in &item.designators {
+                visitor.visit_designator(nodes, designator);
+            }
+            visitor.visit_initializer(nodes, &item.value);
+        });
+    }
+    for comment in &node.comments {
+        visitor.visit_comment(nodes, comment);
+    }
+}
+
+pub fn walk_designator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Designator,
+) {
+    match node {
+        Designator::Member(_) => {}
+        Designator::Index(index) => visitor.visit_expression(nodes, &nodes[*index]),
+    }
+}
+
+pub fn walk_specifier<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Specifier,
+) {
+    match node {
+        Specifier::Keyword(_) | Specifier::Type(_) => {}
+        Specifier::Record(record) => visitor.visit_record(nodes, record),
+        Specifier::Enum(enumeration) => visitor.visit_enum(nodes, enumeration),
+        Specifier::Attribute(attribute) => visitor.visit_attribute(nodes, attribute),
+    }
+}
+
+pub fn walk_record<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Record,
+) {
+    for attribute in &node.attributes {
+        visitor.visit_attribute(nodes, attribute);
+    }
+    for member in node.members.iter().flatten() {
+        walk_annotated(visitor, nodes, member, V::visit_member);
+    }
+    for comment in &node.comments {
+        visitor.visit_comment(nodes, comment);
+    }
+}
+
+pub fn walk_member<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Member,
+) {
+    match node {
+        Member::Field {
+            specifiers,
+            declarators,
+        } => {
+            for specifier in specifiers {
+                visitor.visit_specifier(nodes, specifier);
+            }
+            for declarator in declarators {
+                visitor.visit_member_declarator(nodes, declarator);
+            }
+        }
+        Member::Directive(_) => {}
+        Member::Conditional(group) => {
+            walk_conditional_group(visitor, nodes, group, V::visit_member)
+        }
+        Member::Macro { call, .. } => visitor.visit_macro_call(nodes, call),
+    }
+}
+
+pub fn walk_member_declarator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast MemberDeclarator,
+) {
+    if let Some(declarator) = &node.declarator {
+        visitor.visit_declarator(nodes, declarator);
+    }
+    if let Some(width) = &node.width {
+        visitor.visit_expression(nodes, &nodes[*width]);
+    }
+}
+
+pub fn walk_enum<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Enum,
+) {
+    for attribute in &node.attributes {
+        visitor.visit_attribute(nodes, attribute);
+    }
+    for enumerator in node.enumerators.iter().flatten() {
+        walk_annotated(visitor, nodes, enumerator, V::visit_enumerator);
+    }
+    for comment in &node.comments {
+        visitor.visit_comment(nodes, comment);
+    }
+}
+
+pub fn walk_enumerator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Enumerator,
+) {
+    if let Some(value) = &node.value {
+        visitor.visit_expression(nodes, &nodes[*value]);
+    }
+}
+
+pub fn walk_declarator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Declarator,
+) {
+    visitor.visit_direct_declarator(nodes, &node.direct);
+    for attribute in &node.attributes {
+        visitor.visit_attribute(nodes, attribute);
+    }
+}
+
+pub fn walk_direct_declarator<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast DirectDeclarator,
+) {
+    match node {
+        DirectDeclarator::Identifier(_) | DirectDeclarator::Abstract => {}
+        DirectDeclarator::Parenthesized(inner) => visitor.visit_declarator(nodes, inner),
+        DirectDeclarator::Array(inner, size) => {
+            visitor.visit_direct_declarator(nodes, inner);
+            if let Some(size) = size {
+                visitor.visit_expression(nodes, &nodes[*size]);
+            }
+        }
+        DirectDeclarator::Function(inner, parameters, _) => {
+            visitor.visit_direct_declarator(nodes, inner);
+            for parameter in parameters {
+                walk_annotated(visitor, nodes, parameter, V::visit_parameter);
+            }
+        }
+    }
+}
+
+pub fn walk_parameter<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Parameter,
+) {
+    for specifier in &node.specifiers {
+        visitor.visit_specifier(nodes, specifier);
+    }
+    visitor.visit_declarator(nodes, &node.declarator);
+}
+
+pub fn walk_type_name<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast TypeName,
+) {
+    for specifier in &node.specifiers {
+        visitor.visit_specifier(nodes, specifier);
+    }
+    visitor.visit_declarator(nodes, &node.declarator);
+}
+
+pub fn walk_block<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Block,
+) {
+    for comment in &node.opening {
+        visitor.visit_comment(nodes, comment);
+    }
+    for statement in &node.statements {
+        walk_annotated(visitor, nodes, statement, V::visit_statement);
+    }
+    for comment in &node.comments {
+        visitor.visit_comment(nodes, comment);
+    }
+}
+
+pub fn walk_statement<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Statement,
+) {
+    match node {
+        Statement::Compound(block) => visitor.visit_block(nodes, block),
+        Statement::Declaration(declaration) => visitor.visit_declaration(nodes, declaration),
+        Statement::StaticAssert(assertion) => visitor.visit_static_assert(nodes, assertion),
+        Statement::Conditional(group) => {
+            walk_conditional_group(visitor, nodes, group, V::visit_statement)
+        }
+        Statement::Macro { call, .. } => visitor.visit_macro_call(nodes, call),
+        Statement::Asm(asm) => visitor.visit_asm(nodes, asm),
+        Statement::MacroLoop(call, body) => {
+            visitor.visit_macro_call(nodes, call);
+            visitor.visit_statement(nodes, &nodes[*body]);
+        }
+        Statement::Attributed(attributes, statement) => {
+            for attribute in attributes {
+                visitor.visit_attribute(nodes, attribute);
+            }
+            visitor.visit_statement(nodes, &nodes[*statement]);
+        }
+        Statement::Marked(comments, statement) => {
+            for comment in comments {
+                visitor.visit_comment(nodes, comment);
+            }
+            visitor.visit_statement(nodes, &nodes[*statement]);
+        }
+        Statement::Expression(expression) | Statement::Return(expression) => {
+            if let Some(expression) = expression {
+                visitor.visit_expression(nodes, &nodes[*expression]);
+            }
+        }
+        Statement::If {
+            condition,
+            then,
+            otherwise,
+        } => {
+            visitor.visit_expression(nodes, &nodes[*condition]);
+            visitor.visit_statement(nodes, &nodes[*then]);
+            if let Some(otherwise) = otherwise {
+                visitor.visit_statement(nodes, &nodes[*otherwise]);
+            }
+        }
+        Statement::Switch { condition, body } | Statement::While { condition, body } => {
+            visitor.visit_expression(nodes, &nodes[*condition]);
+            visitor.visit_statement(nodes, &nodes[*body]);
+        }
+        Statement::DoWhile { body, condition } => {
+            visitor.visit_statement(nodes, &nodes[*body]);
+            visitor.visit_expression(nodes, &nodes[*condition]);
+        }
+        Statement::For {
+            initializer,
+            condition,
+            step,
+            body,
+        } => {
+            if let Some(initializer) = initializer {
+                visitor.visit_statement(nodes, &nodes[*initializer]);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expression(nodes, &nodes[*condition]);
+            }
+            if let Some(step) = step {
+                visitor.visit_expression(nodes, &nodes[*step]);
+            }
+            visitor.visit_statement(nodes, &nodes[*body]);
+        }
+        Statement::Labeled(_, statement) | Statement::Default(statement) => {
+            visitor.visit_statement(nodes, &nodes[*statement])
+        }
+        Statement::Case(value, statement) => {
+            visitor.visit_expression(nodes, &nodes[*value]);
+            visitor.visit_statement(nodes, &nodes[*statement]);
+        }
+        Statement::Goto(_)
+        | Statement::Continue
+        | Statement::Break
+        | Statement::Directive(_)
+        | Statement::Invalid(_)
+        | Statement::Verbatim(_) => {}
+    }
+}
+
+pub fn walk_expression<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast Expression,
+) {
+    match node {
+        Expression::Identifier(_)
+        | Expression::Number(_)
+        | Expression::Str(_)
+        | Expression::Character(_) => {}
+        Expression::Unary(_, operand)
+        | Expression::Member(operand, _, _)
+        | Expression::Parenthesized(operand)
+        | Expression::Sizeof(operand) => visitor.visit_expression(nodes, &nodes[*operand]),
+        Expression::Binary(_, left, right)
+        | Expression::Assignment(_, left, right)
+        | Expression::Index(left, right) => {
+            visitor.visit_expression(nodes, &nodes[*left]);
+            visitor.visit_expression(nodes, &nodes[*right]);
+        }
+        Expression::Conditional(condition, then, otherwise) => {
+            visitor.visit_expression(nodes, &nodes[*condition]);
+            visitor.visit_expression(nodes, &nodes[*then]);
+            visitor.visit_expression(nodes, &nodes[*otherwise]);
+        }
+        Expression::Comma(expressions) => {
+            for expression in expressions {
+                visitor.visit_expression(nodes, &nodes[*expression]);
+            }
+        }
+        Expression::Call(function, arguments) => {
+            visitor.visit_expression(nodes, &nodes[*function]);
+            for argument in arguments {
+                visitor.visit_expression(nodes, &nodes[*argument]);
+            }
+        }
+        Expression::Cast(type_name, operand) => {
+            visitor.visit_type_name(nodes, type_name);
+            visitor.visit_expression(nodes, &nodes[*operand]);
+        }
+        Expression::CompoundLiteral(type_name, list) => {
+            visitor.visit_type_name(nodes, type_name);
+            visitor.visit_initializer_list(nodes, list);
+        }
+        Expression::SizeofType(type_name)
+        | Expression::Alignof(type_name)
+        | Expression::Type(type_name) => visitor.visit_type_name(nodes, type_name),
+        Expression::Generic(controlling, associations) => {
+            visitor.visit_expression(nodes, &nodes[*controlling]);
+            for association in associations {
+                visitor.visit_generic_association(nodes, association);
+            }
+        }
+    }
+}
+
+pub fn walk_generic_association<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast GenericAssociation,
+) {
+    if let Some(type_name) = &node.type_name {
+        visitor.visit_type_name(nodes, type_name);
+    }
+    visitor.visit_expression(nodes, &nodes[node.value]);
+}
+
+pub fn walk_static_assert<'ast, V: Visit<'ast> + ?Sized>(
+    visitor: &mut V,
+    nodes: &'ast Nodes,
+    node: &'ast StaticAssert,
+) {
+    visitor.visit_expression(nodes, &nodes[node.condition]);
+    if let Some(message) = &node.message {
+        visitor.visit_expression(nodes, &nodes[*message]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::ast::{Expression, Nodes, Statement, TranslationUnit};
+    use crate::parser::parser::Parser;
+    use crate::parser::visit::{walk_expression, walk_statement, Visit};
+
+    fn parse(input: &str) -> TranslationUnit {
+        let tokens = Lexer::new(input).lexemes().map(|lexeme| lexeme.unwrap());
+        Parser::new(tokens).parse_translation_unit()
+    }
+
+    #[derive(Default)]
+    struct Collector<'ast> {
+        identifiers: Vec<&'ast str>,
+        depth: usize,
+        deepest: usize,
+    }
+
+    impl<'ast> Visit<'ast> for Collector<'ast> {
+        fn visit_statement(&mut self, nodes: &'ast Nodes, node: &'ast Statement) {
+            self.depth += 1;
+            self.deepest = self.deepest.max(self.depth);
+            walk_statement(self, nodes, node);
+            self.depth -= 1;
+        }
+
+        fn visit_expression(&mut self, nodes: &'ast Nodes, node: &'ast Expression) {
+            if let Expression::Identifier(name) = node {
+                self.identifiers.push(name);
+            }
+            walk_expression(self, nodes, node);
+        }
+    }
+
+    #[test]
+    fn collects_identifiers_in_order() {
+        let unit = parse(
+            "int a[N] = { b };
+            void f() {
+                if (c) { while (d) e(f, g + h); }
+            }",
+        );
+
+        let mut collector = Collector::default();
+        collector.visit_translation_unit(&unit);
+
+        assert_eq!(
+            collector.identifiers,
+            ["N", "b", "c", "d", "e", "f", "g", "h"]
+        );
+        assert_eq!(collector.deepest, 4);
+    }
+}
@@ -0,0 +1,655 @@
+//! Traversal of the syntax tree with mutable access to its nodes, mirroring `visit`.
+
+use crate::parser::ast::{
+    Annotated, Asm, Attribute, Block, Comment, ConditionalGroup, Declaration, Declarator,
+    Designator, DirectDeclarator, Enum, Enumerator, Expression, ExpressionId, ExternalDeclaration,
+    FunctionDefinition, GenericAssociation, InitDeclarator, Initializer, InitializerList,
+    MacroCall, Member, MemberDeclarator, Nodes, Parameter, Record, Specifier, Statement,
+    StatementId, StaticAssert, TranslationUnit, TypeName,
+};
+use std::mem;
+
+/// A traversal of the syntax tree which may modify the nodes in place.
+pub trait VisitMut {
+    fn visit_translation_unit_mut(&mut self, node: &mut TranslationUnit) {
+        walk_translation_unit_mut(self, node);
+    }
+
+    fn visit_external_declaration_mut(
+        &mut self,
+        nodes: &mut Nodes,
+        node: &mut ExternalDeclaration,
+    ) {
+        walk_external_declaration_mut(self, nodes, node);
+    }
+
+    fn visit_function_definition_mut(&mut self, nodes: &mut Nodes, node: &mut FunctionDefinition) {
+        walk_function_definition_mut(self, nodes, node);
+    }
+
+    fn visit_declaration_mut(&mut self, nodes: &mut Nodes, node: &mut Declaration) {
+        walk_declaration_mut(self, nodes, node);
+    }
+
+    fn visit_init_declarator_mut(&mut self, nodes: &mut Nodes, node: &mut InitDeclarator) {
+        walk_init_declarator_mut(self, nodes, node);
+    }
+
+    fn visit_initializer_mut(&mut self, nodes: &mut Nodes, node: &mut Initializer) {
+        walk_initializer_mut(self, nodes, node);
+    }
+
+    fn visit_initializer_list_mut(&mut self, nodes: &mut Nodes, node: &mut InitializerList) {
+        walk_initializer_list_mut(self, nodes, node);
+    }
+
+    fn visit_designator_mut(&mut self, nodes: &mut Nodes, node: &mut Designator) {
+        walk_designator_mut(self, nodes, node);
+    }
+
+    fn visit_specifier_mut(&mut self, nodes: &mut Nodes, node: &mut Specifier) {
+        walk_specifier_mut(self, nodes, node);
+    }
+
+    fn visit_attribute_mut(&mut self, _nodes: &mut Nodes, _node: &mut Attribute) {}
+
+    fn visit_record_mut(&mut self, nodes: &mut Nodes, node: &mut Record) {
+        walk_record_mut(self, nodes, node);
+    }
+
+    fn visit_member_mut(&mut self, nodes: &mut Nodes, node: &mut Member) {
+        walk_member_mut(self, nodes, node);
+    }
+
+    fn visit_member_declarator_mut(&mut self, nodes: &mut Nodes, node: &mut MemberDeclarator) {
+        walk_member_declarator_mut(self, nodes, node);
+    }
+
+    fn visit_enum_mut(&mut self, nodes: &mut Nodes, node: &mut Enum) {
+        walk_enum_mut(self, nodes, node);
+    }
+
+    fn visit_enumerator_mut(&mut self, nodes: &mut Nodes, node: &mut Enumerator) {
+        walk_enumerator_mut(self, nodes, node);
+    }
+
+    fn visit_declarator_mut(&mut self, nodes: &mut Nodes, node: &mut Declarator) {
+        walk_declarator_mut(self, nodes, node);
+    }
+
+    fn visit_direct_declarator_mut(&mut self, nodes: &mut Nodes, node: &mut DirectDeclarator) {
+        walk_direct_declarator_mut(self, nodes, node);
+    }
+
+    fn visit_parameter_mut(&mut self, nodes: &mut Nodes, node: &mut Parameter) {
+        walk_parameter_mut(self, nodes, node);
+    }
+
+    fn visit_type_name_mut(&mut self, nodes: &mut Nodes, node: &mut TypeName) {
+        walk_type_name_mut(self, nodes, node);
+    }
+
+    fn visit_block_mut(&mut self, nodes: &mut Nodes, node: &mut Block) {
+        walk_block_mut(self, nodes, node);
+    }
+
+    fn visit_statement_mut(&mut self, nodes: &mut Nodes, node: &mut Statement) {
+        walk_statement_mut(self, nodes, node);
+    }
+
+    fn visit_expression_mut(&mut self, nodes: &mut Nodes, node: &mut Expression) {
+        walk_expression_mut(self, nodes, node);
+    }
+
+    fn visit_generic_association_mut(&mut self, nodes: &mut Nodes, node: &mut GenericAssociation) {
+        walk_generic_association_mut(self, nodes, node);
+    }
+
+    fn visit_static_assert_mut(&mut self, nodes: &mut Nodes, node: &mut StaticAssert) {
+        walk_static_assert_mut(self, nodes, node);
+    }
+
+    fn visit_asm_mut(&mut self, _nodes: &mut Nodes, _node: &mut Asm) {}
+
+    fn visit_macro_call_mut(&mut self, _nodes: &mut Nodes, _node: &mut MacroCall) {}
+
+    fn visit_comment_mut(&mut self, _nodes: &mut Nodes, _node: &mut Comment) {}
+}
+
+/// Visit the comments around a node, and the node itself using `visit`.
+fn walk_annotated_mut<V, T>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Annotated<T>,
+    visit: impl FnOnce(&mut V, &mut Nodes, &mut T),
+) where
+    V: VisitMut + ?Sized,
+{
+    for comment in &mut node.leading {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+    visit(visitor, nodes, &mut node.node);
+    for comment in &mut node.trailing {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+}
+
+/// Visit the items and comments in every branch of a conditional group using `visit`.
+fn walk_conditional_group_mut<V, T>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut ConditionalGroup<T>,
+    visit: impl Fn(&mut V, &mut Nodes, &mut T),
+) where
+    V: VisitMut + ?Sized,
+{
+    for branch in &mut node.branches {
+        for item in &mut branch.items {
+            walk_annotated_mut(visitor, nodes, item, &visit);
+        }
+        for comment in &mut branch.comments {
+            visitor.visit_comment_mut(nodes, comment);
+        }
+    }
+}
+
+/// Visit a statement in the arena. It is moved out of the arena for the duration of the visit, so
+/// that the visitor has mutable access to both the statement and the arena.
+fn visit_statement_id_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    id: StatementId,
+) {
+    let mut statement = mem::replace(&mut nodes[id], Statement::Break);
+    visitor.visit_statement_mut(nodes, &mut statement);
+    nodes[id] = statement;
+}
+
+/// Visit an expression in the arena, in the same way as `visit_statement_id_mut`.
+fn visit_expression_id_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    id: ExpressionId,
+) {
+    let mut expression = mem::replace(&mut nodes[id], Expression::Comma(Vec::new()));
+    visitor.visit_expression_mut(nodes, &mut expression);
+    nodes[id] = expression;
+}
+
+pub fn walk_translation_unit_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    node: &mut TranslationUnit,
+) {
+    let nodes = &mut node.nodes;
+    for item in &mut node.items {
+        walk_annotated_mut(visitor, nodes, item, V::visit_external_declaration_mut);
+    }
+    for comment in &mut node.comments {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+}
+
+pub fn walk_external_declaration_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut ExternalDeclaration,
+) {
+    match node {
+        ExternalDeclaration::Declaration(declaration) => {
+            visitor.visit_declaration_mut(nodes, declaration)
+        }
+        ExternalDeclaration::FunctionDefinition(definition) => {
+            visitor.visit_function_definition_mut(nodes, definition)
+        }
+        ExternalDeclaration::Conditional(group) => {
+            walk_conditional_group_mut(visitor, nodes, group, V::visit_external_declaration_mut)
+        }
+        ExternalDeclaration::Macro { call, .. } => visitor.visit_macro_call_mut(nodes, call),
+        ExternalDeclaration::Asm(asm) => visitor.visit_asm_mut(nodes, asm),
+        ExternalDeclaration::StaticAssert(assertion) => {
+            visitor.visit_static_assert_mut(nodes, assertion)
+        }
+        ExternalDeclaration::Directive(_)
+        | ExternalDeclaration::Empty
+        | ExternalDeclaration::LinkageWrapper { .. }
+        | ExternalDeclaration::Invalid(_)
+        | ExternalDeclaration::Verbatim(_) => {}
+    }
+}
+
+pub fn walk_function_definition_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut FunctionDefinition,
+) {
+    for specifier in &mut node.specifiers {
+        visitor.visit_specifier_mut(nodes, specifier);
+    }
+    visitor.visit_declarator_mut(nodes, &mut node.declarator);
+    visitor.visit_block_mut(nodes, &mut node.body);
+}
+
+pub fn walk_declaration_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Declaration,
+) {
+    for specifier in &mut node.specifiers {
+        visitor.visit_specifier_mut(nodes, specifier);
+    }
+    for declarator in &mut node.declarators {
+        visitor.visit_init_declarator_mut(nodes, declarator);
+    }
+}
+
+pub fn walk_init_declarator_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut InitDeclarator,
+) {
+    visitor.visit_declarator_mut(nodes, &mut node.declarator);
+    if let Some(initializer) = &mut node.initializer {
+        visitor.visit_initializer_mut(nodes, initializer);
+    }
+}
+
+pub fn walk_initializer_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Initializer,
+) {
+    match node {
+        Initializer::Expression(expression) => visit_expression_id_mut(visitor, nodes, *expression),
+        Initializer::List(list) => visitor.visit_initializer_list_mut(nodes, list),
+    }
+}
+
+pub fn walk_initializer_list_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut InitializerList,
+) {
+    for item in &mut node.items {
+        walk_annotated_mut(visitor, nodes, item, |visitor, nodes, item| {
+            for designator in &mut item.designators {
+                visitor.visit_designator_mut(nodes, designator);
+            }
+            visitor.visit_initializer_mut(nodes, &mut item.value);
+        });
+    }
+    for comment in &mut node.comments {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+}
+
+pub fn walk_designator_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Designator,
+) {
+    match node {
+        Designator::Member(_) => {}
+        Designator::Index(index) => visit_expression_id_mut(visitor, nodes, *index),
+    }
+}
+
+pub fn walk_specifier_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Specifier,
+) {
+    match node {
+        Specifier::Keyword(_) | Specifier::Type(_) => {}
+        Specifier::Record(record) => visitor.visit_record_mut(nodes, record),
+        Specifier::Enum(enumeration) => visitor.visit_enum_mut(nodes, enumeration),
+        Specifier::Attribute(attribute) => visitor.visit_attribute_mut(nodes, attribute),
+    }
+}
+
+pub fn walk_record_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Record,
+) {
+    for attribute in &mut node.attributes {
+        visitor.visit_attribute_mut(nodes, attribute);
+    }
+    for member in node.members.iter_mut().flatten() {
+        walk_annotated_mut(visitor, nodes, member, V::visit_member_mut);
+    }
+    for comment in &mut node.comments {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+}
+
+pub fn walk_member_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Member,
+) {
+    match node {
+        Member::Field {
+            specifiers,
+            declarators,
+        } => {
+            for specifier in specifiers {
+                visitor.visit_specifier_mut(nodes, specifier);
+            }
+            for declarator in declarators {
+                visitor.visit_member_declarator_mut(nodes, declarator);
+            }
+        }
+        Member::Directive(_) => {}
+        Member::Conditional(group) => {
+            walk_conditional_group_mut(visitor, nodes, group, V::visit_member_mut)
+        }
+        Member::Macro { call, .. } => visitor.visit_macro_call_mut(nodes, call),
+    }
+}
+
+pub fn walk_member_declarator_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut MemberDeclarator,
+) {
+    if let Some(declarator) = &mut node.declarator {
+        visitor.visit_declarator_mut(nodes, declarator);
+    }
+    if let Some(width) = &mut node.width {
+        visit_expression_id_mut(visitor, nodes, *width);
+    }
+}
+
+pub fn walk_enum_mut<V: VisitMut + ?Sized>(visitor: &mut V, nodes: &mut Nodes, node: &mut Enum) {
+    for attribute in &mut node.attributes {
+        visitor.visit_attribute_mut(nodes, attribute);
+    }
+    for enumerator in node.enumerators.iter_mut().flatten() {
+        walk_annotated_mut(visitor, nodes, enumerator, V::visit_enumerator_mut);
+    }
+    for comment in &mut node.comments {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+}
+
+pub fn walk_enumerator_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Enumerator,
+) {
+    if let Some(value) = &mut node.value {
+        visit_expression_id_mut(visitor, nodes, *value);
+    }
+}
+
+pub fn walk_declarator_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Declarator,
+) {
+    visitor.visit_direct_declarator_mut(nodes, &mut node.direct);
+    for attribute in &mut node.attributes {
+        visitor.visit_attribute_mut(nodes, attribute);
+    }
+}
+
+pub fn walk_direct_declarator_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut DirectDeclarator,
+) {
+    match node {
+        DirectDeclarator::Identifier(_) | DirectDeclarator::Abstract => {}
+        DirectDeclarator::Parenthesized(inner) => visitor.visit_declarator_mut(nodes, inner),
+        DirectDeclarator::Array(inner, size) => {
+            visitor.visit_direct_declarator_mut(nodes, inner);
+            if let Some(size) = size {
+                visit_expression_id_mut(visitor, nodes, *size);
+            }
+        }
+        DirectDeclarator::Function(inner, parameters, _) => {
+            visitor.visit_direct_declarator_mut(nodes, inner);
+            for parameter in parameters {
+                walk_annotated_mut(visitor, nodes, parameter, V::visit_parameter_mut);
+            }
+        }
+    }
+}
+
+pub fn walk_parameter_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Parameter,
+) {
+    for specifier in &mut node.specifiers {
+        visitor.visit_specifier_mut(nodes, specifier);
+    }
+    visitor.visit_declarator_mut(nodes, &mut node.declarator);
+}
+
+pub fn walk_type_name_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut TypeName,
+) {
+    for specifier in &mut node.specifiers {
+        visitor.visit_specifier_mut(nodes, specifier);
+    }
+    visitor.visit_declarator_mut(nodes, &mut node.declarator);
+}
+
+pub fn walk_block_mut<V: VisitMut + ?Sized>(visitor: &mut V, nodes: &mut Nodes, node: &mut Block) {
+    for comment in &mut node.opening {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+    for statement in &mut node.statements {
+        walk_annotated_mut(visitor, nodes, statement, V::visit_statement_mut);
+    }
+    for comment in &mut node.comments {
+        visitor.visit_comment_mut(nodes, comment);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Statement,
+) {
+    match node {
+        Statement::Compound(block) => visitor.visit_block_mut(nodes, block),
+        Statement::Declaration(declaration) => visitor.visit_declaration_mut(nodes, declaration),
+        Statement::StaticAssert(assertion) => visitor.visit_static_assert_mut(nodes, assertion),
+        Statement::Conditional(group) => {
+            walk_conditional_group_mut(visitor, nodes, group, V::visit_statement_mut)
+        }
+        Statement::Macro { call, .. } => visitor.visit_macro_call_mut(nodes, call),
+        Statement::Asm(asm) => visitor.visit_asm_mut(nodes, asm),
+        Statement::MacroLoop(call, body) => {
+            visitor.visit_macro_call_mut(nodes, call);
+            visit_statement_id_mut(visitor, nodes, *body);
+        }
+        Statement::Attributed(attributes, statement) => {
+            for attribute in attributes {
+                visitor.visit_attribute_mut(nodes, attribute);
+            }
+            visit_statement_id_mut(visitor, nodes, *statement);
+        }
+        Statement::Marked(comments, statement) => {
+            for comment in comments {
+                visitor.visit_comment_mut(nodes, comment);
+            }
+            visit_statement_id_mut(visitor, nodes, *statement);
+        }
+        Statement::Expression(expression) | Statement::Return(expression) => {
+            if let Some(expression) = expression {
+                visit_expression_id_mut(visitor, nodes, *expression);
+            }
+        }
+        Statement::If {
+            condition,
+            then,
+            otherwise,
+        } => {
+            visit_expression_id_mut(visitor, nodes, *condition);
+            visit_statement_id_mut(visitor, nodes, *then);
+            if let Some(otherwise) = otherwise {
+                visit_statement_id_mut(visitor, nodes, *otherwise);
+            }
+        }
+        Statement::Switch { condition, body } | Statement::While { condition, body } => {
+            visit_expression_id_mut(visitor, nodes, *condition);
+            visit_statement_id_mut(visitor, nodes, *body);
+        }
+        Statement::DoWhile { body, condition } => {
+            visit_statement_id_mut(visitor, nodes, *body);
+            visit_expression_id_mut(visitor, nodes, *condition);
+        }
+        Statement::For {
+            initializer,
+            condition,
+            step,
+            body,
+        } => {
+            if let Some(initializer) = initializer {
+                visit_statement_id_mut(visitor, nodes, *initializer);
+            }
+            if let Some(condition) = condition {
+                visit_expression_id_mut(visitor, nodes, *condition);
+            }
+            if let Some(step) = step {
+                visit_expression_id_mut(visitor, nodes, *step);
+            }
+            visit_statement_id_mut(visitor, nodes, *body);
+        }
+        Statement::Labeled(_, statement) | Statement::Default(statement) => {
+            visit_statement_id_mut(visitor, nodes, *statement)
+        }
+        Statement::Case(value, statement) => {
+            visit_expression_id_mut(visitor, nodes, *value);
+            visit_statement_id_mut(visitor, nodes, *statement);
+        }
+        Statement::Goto(_)
+        | Statement::Continue
+        | Statement::Break
+        | Statement::Directive(_)
+        | Statement::Invalid(_)
+        | Statement::Verbatim(_) => {}
+    }
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut Expression,
+) {
+    match node {
+        Expression::Identifier(_)
+        | Expression::Number(_)
+        | Expression::Str(_)
+        | Expression::Character(_) => {}
+        Expression::Unary(_, operand)
+        | Expression::Member(operand, _, _)
+        | Expression::Parenthesized(operand)
+        | Expression::Sizeof(operand) => visit_expression_id_mut(visitor, nodes, *operand),
+        Expression::Binary(_, left, right)
+        | Expression::Assignment(_, left, right)
+        | Expression::Index(left, right) => {
+            visit_expression_id_mut(visitor, nodes, *left);
+            visit_expression_id_mut(visitor, nodes, *right);
+        }
+        Expression::Conditional(condition, then, otherwise) => {
+            visit_expression_id_mut(visitor, nodes, *condition);
+            visit_expression_id_mut(visitor, nodes, *then);
+            visit_expression_id_mut(visitor, nodes, *otherwise);
+        }
+        Expression::Comma(expressions) => {
+            for expression in expressions {
+                visit_expression_id_mut(visitor, nodes, *expression);
+            }
+        }
+        Expression::Call(function, arguments) => {
+            visit_expression_id_mut(visitor, nodes, *function);
+            for argument in arguments {
+                visit_expression_id_mut(visitor, nodes, *argument);
+            }
+        }
+        Expression::Cast(type_name, operand) => {
+            visitor.visit_type_name_mut(nodes, type_name);
+            visit_expression_id_mut(visitor, nodes, *operand);
+        }
+        Expression::CompoundLiteral(type_name, list) => {
+            visitor.visit_type_name_mut(nodes, type_name);
+            visitor.visit_initializer_list_mut(nodes, list);
+        }
+        Expression::SizeofType(type_name)
+        | Expression::Alignof(type_name)
+        | Expression::Type(type_name) => visitor.visit_type_name_mut(nodes, type_name),
+        Expression::Generic(controlling, associations) => {
+            visit_expression_id_mut(visitor, nodes, *controlling);
+            for association in associations {
+                visitor.visit_generic_association_mut(nodes, association);
+            }
+        }
+    }
+}
+
+pub fn walk_generic_association_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut GenericAssociation,
+) {
+    if let Some(type_name) = &mut node.type_name {
+        visitor.visit_type_name_mut(nodes, type_name);
+    }
+    visit_expression_id_mut(visitor, nodes, node.value);
+}
+
+pub fn walk_static_assert_mut<V: VisitMut + ?Sized>(
+    visitor: &mut V,
+    nodes: &mut Nodes,
+    node: &mut StaticAssert,
+) {
+    visit_expression_id_mut(visitor, nodes, node.condition);
+    if let Some(message) = &mut node.message {
+        visit_expression_id_mut(visitor, nodes, *message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lexer::lexer::Lexer;
+    use crate::parser::ast::{Comment, Expression, Nodes};
+    use crate::parser::parser::Parser;
+    use crate::parser::visit_mut::{walk_expression_mut, VisitMut};
+
+    struct Renamer;
+
+    impl VisitMut for Renamer {
+        fn visit_expression_mut(&mut self, nodes: &mut Nodes, node: &mut Expression) {
+            if let Expression::Identifier(name) = node {
+                *name = name.to_ascii_uppercase().into();
+            }
+            walk_expression_mut(self, nodes, node);
+        }
+
+        fn visit_comment_mut(&mut self, _nodes: &mut Nodes, node: &mut Comment) {
+            node.text = node.text.trim().to_string();
+        }
+    }
+
+    #[test]
+    fn modifies_nodes_in_place() {
+        let tokens = Lexer::new("int x = y; // note \nvoid f() { if (a) g(z); }")
+            .lexemes()
+            .map(|lexeme| lexeme.unwrap());
+        let mut unit = Parser::new(tokens).parse_translation_unit();
+
+        Renamer.visit_translation_unit_mut(&mut unit);
+
+        assert_eq!(
+            unit.to_string(),
+            "(declaration [int] (= x Y)) (comment \"note\")\n\
+             (definition [void] (function f) (block (if A (expression (call G Z)))))\n"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Changing a syntax tree in code before it is printed again, for refactoring tools which build on
+//! the parser of the formatter. A tree is read with `syntax_tree`, its items can be changed directly
+//! or with the traversals of `parser::visit_mut`, and new code is parsed into it with the methods
+//! below. Printing the tree formats all of it, as if it had been written that way.
+
+use crate::diagnostics::diagnostic::{Diagnostic, Diagnostics};
+use crate::formatter::config::FormatConfig;
+use crate::formatter::formatter::{format, format_to};
+use crate::formatter::includes::header;
+use crate::lex;
+use crate::lexer::symbol::Symbol;
+use crate::lexer::token::TokenKeyword;
+use crate::parser::ast::{Annotated, Declaration, ExternalDeclaration, Specifier, TranslationUnit};
+use crate::parser::parser::Parser;
+use std::io::{self, Write};
+use std::mem;
+
+impl TranslationUnit {
+    /// Parse the items of a piece of code, such as `static int count;`, so that they can be added
+    /// to the translation unit. The names of its types are known while parsing.
+    pub fn parse_items(
+        &mut self,
+        code: &str,
+    ) -> Result<Vec<Annotated<ExternalDeclaration>>, Diagnostics> {
+        let lexemes =
+            lex(code).map_err(|error| Diagnostics::from_iter([Diagnostic::from(&error)]))?;
+        let mut parser = Parser::new(lexemes.into_iter())
+            .with_nodes(mem::take(&mut self.nodes))
+            .with_typedefs(typedefs(&self.items));
+        let unit = parser.parse_translation_unit();
+        self.nodes = unit.nodes;
+
+        match parser.errors().is_empty() {
+            true => Ok(unit.items),
+            false => Err(parser.errors().iter().map(Diagnostic::from).collect()),
+        }
+    }
+
+    /// Insert the items of a piece of code in front of the item at an index, or after the last
+    /// item if the index is the number of items.
+    pub fn insert(&mut self, index: usize, code: &str) -> Result<(), Diagnostics> {
+        let items = self.parse_items(code)?;
+        self.items.splice(index..index, items);
+        Ok(())
+    }
+
+    /// Make the `#include` lines of a header include another one instead, including those in
+    /// conditional groups. Headers are named with their delimiters, as in `<stdio.h>` or
+    /// `"list.h"`. Returns the number of includes which were changed.
+    pub fn rename_include(&mut self, from: &str, to: &str) -> usize {
+        rename_include(&mut self.items, from, to)
+    }
+
+    /// Print the translation unit through the formatter.
+    pub fn print(&self, config: &FormatConfig) -> String {
+        format(self, config)
+    }
+
+    /// Print the translation unit through the formatter to a sink, such as a file, as it goes.
+    pub fn print_to(&self, config: &FormatConfig, sink: &mut impl Write) -> io::Result<()> {
+        format_to(self, config, sink)
+    }
+}
+
+fn rename_include(items: &mut [Annotated<ExternalDeclaration>], from: &str, to: &str) -> usize {
+    let mut count = 0;
+    for item in items {
+        if let ExternalDeclaration::Conditional(group) = &mut item.node {
+            for branch in &mut group.branches {
+                count += rename_include(&mut branch.items, from, to);
+            }
+        } else if header(&item.node) == Some(from) {
+            if let ExternalDeclaration::Directive(line) = &mut item.node {
+                *line = line.replacen(from, to, 1);
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// The names introduced by `typedef` at file scope.
+pub(crate) fn typedefs(items: &[Annotated<ExternalDeclaration>]) -> Vec<Symbol> {
+    let mut names = Vec::new();
+    for item in items {
+        match &item.node {
+            ExternalDeclaration::Declaration(Declaration {
+                specifiers,
+                declarators,
+            }) if specifiers.contains(&Specifier::Keyword(TokenKeyword::Typedef)) => {
+                names.extend(
+                    declarators
+                        .iter()
+                        .filter_map(|declarator| declarator.declarator.name()),
+                );
+            }
+            ExternalDeclaration::Conditional(group) => {
+                for branch in &group.branches {
+                    names.extend(typedefs(&branch.items));
+                }
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::FormatConfig;
+    use crate::parser::ast::{Expression, ExternalDeclaration, Initializer};
+    use crate::syntax_tree;
+
+    #[test]
+    fn trees_are_changed_and_printed() {
+        let config = FormatConfig::default();
+        let source = "#include <stdio.h>\n\
+                      #ifdef DEBUG\n\
+                      #include \"log.h\"\n\
+                      #endif\n\
+                      typedef int count_t;\n\
+                      int main(void) { return 0; }\n";
+        let mut unit = syntax_tree(source, "main.c", &config).unwrap();
+
+        assert_eq!(unit.rename_include("\"log.h\"", "<log.h>"), 1);
+        assert_eq!(unit.rename_include("<stdlib.h>", "<log.h>"), 0);
+        // The new function is parsed knowing that `count_t` is a type.
+        unit.insert(3, "count_t count(long n){return (count_t)-n;}")
+            .unwrap();
+        assert_eq!(
+            unit.print(&config),
+            "#include <stdio.h>\n\
+             #ifdef DEBUG\n\
+             #include <log.h>\n\
+             #endif\n\
+             typedef int count_t;\n\
+             \n\
+             count_t count(long n) {\n    return (count_t)-n;\n}\n\
+             \n\
+             int main(void) {\n    return 0;\n}\n"
+        );
+
+        // The expressions of new items are kept apart from those which were already there.
+        let mut items = unit.parse_items("int x = a + b;").unwrap();
+        let ExternalDeclaration::Declaration(declaration) = items.remove(0).node else {
+            panic!("a declaration is parsed");
+        };
+        let Some(Initializer::Expression(sum)) = &declaration.declarators[0].initializer else {
+            panic!("the initializer is an expression");
+        };
+        let Expression::Binary(_, left, _) = &unit.nodes[*sum] else {
+            panic!("the initializer is a sum");
+        };
+        assert_eq!(unit.nodes[*left], Expression::Identifier("a".into()));
+
+        assert!(unit.insert(0, "int = ;").is_err());
+    }
+}
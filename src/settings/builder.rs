@@ -0,0 +1,191 @@
+//! Building options in code, for programs which call the formatter as a library. Options are
+//! named and checked the same way as in configuration files, so that a program and the command
+//! share the options of a project, and the text of a configuration file can be built on. With the
+//! `serde` feature, options are serialized and deserialized as a map by the same names.
+
+use crate::formatter::config::{FormatConfig, Style};
+use crate::settings::file::{apply_entries, parse_config};
+use crate::settings::json::parse_json;
+#[cfg(feature = "serde")]
+use crate::settings::options::option_values;
+use crate::settings::options::set_option;
+use crate::settings::toml::Value;
+
+/// Options under construction. The first option which is invalid is remembered, and building
+/// fails with it.
+#[derive(Clone, Debug, Default)]
+pub struct ConfigBuilder {
+    config: FormatConfig,
+    error: Option<String>,
+}
+
+impl FormatConfig {
+    /// Start building options from the defaults.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+}
+
+impl ConfigBuilder {
+    pub fn new() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Apply a style to the options set so far. Options on which the style has no opinion keep
+    /// their value.
+    pub fn style(mut self, style: Style) -> ConfigBuilder {
+        self.config = style.apply(self.config);
+        self
+    }
+
+    /// Set an option by its name, as in `option("brace_style", "allman")`.
+    pub fn option(mut self, name: &str, value: impl Into<Value>) -> ConfigBuilder {
+        if self.error.is_none() {
+            if let Err(error) = set_option(&mut self.config, name, &value.into()) {
+                self.error = Some(error);
+            }
+        }
+        self
+    }
+
+    /// Apply the text of a configuration file to the options set so far.
+    pub fn config_file(mut self, text: &str) -> ConfigBuilder {
+        if self.error.is_none() {
+            match parse_config(text, self.config.clone()) {
+                Ok(config) => self.config = config,
+                Err(error) => self.error = Some(format!("line {}: {}", error.line, error.message)),
+            }
+        }
+        self
+    }
+
+    /// Apply options written as a JSON object, named as in configuration files, to the options
+    /// set so far.
+    pub fn json(mut self, text: &str) -> ConfigBuilder {
+        if self.error.is_none() {
+            let config =
+                parse_json(text).and_then(|entries| apply_entries(&entries, self.config.clone()));
+            match config {
+                Ok(config) => self.config = config,
+                Err(error) => self.error = Some(format!("line {}: {}", error.line, error.message)),
+            }
+        }
+        self
+    }
+
+    /// The options, or the first option which was invalid.
+    pub fn build(self) -> Result<FormatConfig, String> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.config),
+        }
+    }
+}
+
+/// The options as a map from their names to their values, as `option_values` gives them.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FormatConfig {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(option_values(self))
+    }
+}
+
+/// Options from a map of their names to their values, applied to the defaults as those of a
+/// configuration file are. The first option which is invalid fails.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FormatConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<FormatConfig, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = FormatConfig;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "a map of options")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<FormatConfig, A::Error> {
+                let mut builder = ConfigBuilder::new();
+                while let Some((name, value)) = map.next_entry::<String, Value>()? {
+                    builder = builder.option(&name, value);
+                }
+                builder.build().map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{BraceStyle, FormatConfig, Style};
+
+    #[test]
+    fn options_are_built_in_order() {
+        let config = FormatConfig::builder()
+            .config_file("indent_width = 3\n")
+            .style(Style::Linux)
+            .option("brace_style", "allman")
+            .option("max_line_length", 100)
+            .option("lint.tab-indent", false)
+            .build()
+            .unwrap();
+        assert_eq!(
+            config,
+            FormatConfig {
+                brace_style: BraceStyle::Allman,
+                max_line_length: 100,
+                disabled_rules: vec!["tab-indent".to_string()],
+                ..Style::Linux.config()
+            }
+        );
+
+        let error = FormatConfig::builder()
+            .option("use_tabs", 1)
+            .option("indent", 2)
+            .build();
+        assert_eq!(
+            error,
+            Err("invalid value for `use_tabs`: expected `true` or `false`, found 1".to_string())
+        );
+        let error = FormatConfig::builder().config_file("\nindent = 2").build();
+        assert_eq!(error, Err("line 2: unknown option `indent`".to_string()));
+
+        let config = FormatConfig::builder()
+            .json("{\"based_on\": \"linux\", \"indent_width\": 4}")
+            .build();
+        assert_eq!(
+            config,
+            Ok(FormatConfig {
+                indent_width: 4,
+                ..Style::Linux.config()
+            })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn options_are_serialized_by_name() {
+        let config = FormatConfig {
+            brace_style: BraceStyle::Allman,
+            disabled_rules: vec!["tab-indent".to_string()],
+            ..Style::Linux.config()
+        };
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(json["brace_style"], "allman");
+        assert_eq!(json["lint.tab-indent"], false);
+        assert_eq!(
+            serde_json::from_value::<FormatConfig>(json).unwrap(),
+            config
+        );
+
+        let config: FormatConfig = serde_json::from_str("{\"indent_width\": 2}").unwrap();
+        assert_eq!(config.indent_width, 2);
+        let error = serde_json::from_str::<FormatConfig>("{\"indent\": 2}").unwrap_err();
+        assert!(error.to_string().starts_with("unknown option `indent`"));
+    }
+}
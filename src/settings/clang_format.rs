@@ -0,0 +1,448 @@
+//! Reading the options of clang-format from a `.clang-format` file, so that projects which use it
+//! can switch without writing their style down again. The keys which apply to C are mapped onto
+//! the closest options, and all others are ignored.
+
+use crate::formatter::config::{
+    ArgumentWrapping, BackslashAlignment, BraceStyle, DirectiveIndentation, FormatConfig,
+    IncludeSorting, LabelPlacement, LineEnding, PointerAlignment, Style,
+};
+use crate::settings::toml::TomlError;
+
+/// The names of the configuration files of clang-format.
+pub const CLANG_FORMAT_FILES: &[&str] = &[".clang-format", "_clang-format"];
+
+/// A key with its value, where the keys of nested maps are joined with a period, as in
+/// `BraceWrapping.AfterFunction`.
+struct Entry {
+    key: String,
+    value: String,
+    line: usize,
+}
+
+/// Read the keys with a scalar value from the YAML of a `.clang-format` file. Of its documents,
+/// only the first one without a `Language` or for `Cpp` is read, since that is what applies to C.
+/// Lists, such as `IncludeCategories`, are skipped.
+fn entries(text: &str) -> Result<Vec<Entry>, TomlError> {
+    let mut documents: Vec<Vec<Entry>> = vec![Vec::new()];
+    // The keys of the maps around the current line, with their indentation.
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    // The indentation of the key which holds the list being skipped.
+    let mut list: Option<usize> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = strip_comment(line).trim_end();
+        let content = line.trim_start();
+        let indent = line.len() - content.len();
+        if content.is_empty() {
+            continue;
+        }
+        if content == "---" || content == "..." {
+            documents.push(Vec::new());
+            parents.clear();
+            list = None;
+            continue;
+        }
+
+        match list {
+            Some(parent) if indent > parent || (indent == parent && content.starts_with('-')) => {
+                continue;
+            }
+            _ => list = None,
+        }
+        while parents.last().is_some_and(|(other, _)| *other >= indent) {
+            parents.pop();
+        }
+        if content.starts_with('-') {
+            list = Some(parents.last().map_or(0, |(indent, _)| *indent));
+            continue;
+        }
+
+        let Some((key, value)) = content.split_once(':') else {
+            return Err(TomlError {
+                line: i + 1,
+                message: format!("expected `key: value`, found `{}`", content),
+            });
+        };
+        let mut path: Vec<&str> = parents.iter().map(|(_, key)| key.as_str()).collect();
+        path.push(key.trim());
+        let key = path.join(".");
+        let value = value.trim();
+        let document = documents.last_mut().expect("there is always a document");
+
+        if value.is_empty() {
+            parents.push((indent, key.rsplit('.').next().unwrap_or("").to_string()));
+        } else if let Some(map) = value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+            for item in map.split(',').filter(|item| !item.trim().is_empty()) {
+                let (name, value) = item.split_once(':').unwrap_or((item, ""));
+                document.push(Entry {
+                    key: format!("{}.{}", key, name.trim()),
+                    value: unquote(value.trim()),
+                    line: i + 1,
+                });
+            }
+        } else if !value.starts_with('[') {
+            document.push(Entry {
+                key,
+                value: unquote(value),
+                line: i + 1,
+            });
+        }
+    }
+
+    let language = |document: &Vec<Entry>| {
+        document
+            .iter()
+            .find(|entry| entry.key == "Language")
+            .map(|entry| entry.value.clone())
+    };
+    Ok(documents
+        .into_iter()
+        .filter(|document| !document.is_empty())
+        .find(|document| matches!(language(document).as_deref(), None | Some("Cpp")))
+        .unwrap_or_default())
+}
+
+/// Remove a comment from a line, unless the `#` is inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) if i == 0 || line[..i].ends_with(char::is_whitespace) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn unquote(value: &str) -> String {
+    let quoted = value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"'))
+            || (value.starts_with('\'') && value.ends_with('\'')));
+    match quoted {
+        true => value[1..value.len() - 1].to_string(),
+        false => value.to_string(),
+    }
+}
+
+fn boolean(value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("expected `true` or `false`, found `{}`", value)),
+    }
+}
+
+fn number(value: &str) -> Result<usize, String> {
+    value
+        .parse()
+        .map_err(|_| format!("expected a number, found `{}`", value))
+}
+
+/// The value which a name stands for.
+fn choice<T: Copy>(value: &str, choices: &[(&str, T)]) -> Result<T, String> {
+    choices
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, choice)| *choice)
+        .ok_or_else(|| format!("`{}` is not supported", value))
+}
+
+/// Whether an alignment option such as `AlignConsecutiveAssignments` is enabled, which is either
+/// a boolean, a name, or a map with an `Enabled` key.
+fn alignment(value: &str) -> Result<bool, String> {
+    match value {
+        "None" => Ok(false),
+        "Consecutive" | "AcrossEmptyLines" | "AcrossComments" | "AcrossEmptyLinesAndComments" => {
+            Ok(true)
+        }
+        value => boolean(value),
+    }
+}
+
+/// Read the options from the text of a `.clang-format` file and apply them to other options.
+/// Options which clang-format has but the formatter does not are ignored, while values which
+/// cannot be mapped are an error.
+pub fn parse_clang_format(text: &str, base: FormatConfig) -> Result<FormatConfig, TomlError> {
+    let entries = entries(text)?;
+    let value = |key: &str| entries.iter().find(|entry| entry.key == key);
+
+    let style = match value("BasedOnStyle").map(|entry| entry.value.to_lowercase()) {
+        Some(style) if style == "llvm" => Some(Style::Llvm),
+        Some(style) if style == "google" || style == "chromium" => Some(Style::Google),
+        Some(style) if style == "gnu" => Some(Style::Gnu),
+        _ => None,
+    };
+    let mut config = match style {
+        Some(style) => style.apply(base),
+        None => base,
+    };
+
+    // Arguments are wrapped according to both the alignment and the bin packing.
+    let align = value("AlignAfterOpenBracket").map(|entry| entry.value.as_str());
+    let bin_pack = match value("BinPackArguments").or(value("BinPackParameters")) {
+        Some(entry) => Some(boolean(&entry.value).map_err(|message| TomlError {
+            line: entry.line,
+            message: format!("invalid value for `{}`: {}", entry.key, message),
+        })?),
+        None => None,
+    };
+    match (align, bin_pack) {
+        (_, Some(false)) => config.argument_wrapping = ArgumentWrapping::OnePerLine,
+        (Some("Align"), _) => config.argument_wrapping = ArgumentWrapping::Align,
+        (Some(_), _) | (None, Some(true)) => config.argument_wrapping = ArgumentWrapping::BinPack,
+        (None, None) => {}
+    }
+
+    for entry in &entries {
+        let value = entry.value.as_str();
+        let result = match entry.key.as_str() {
+            "IndentWidth" => number(value).map(|width| config.indent_width = width),
+            "ContinuationIndentWidth" => {
+                number(value).map(|width| config.continuation_indent = width)
+            }
+            "TabWidth" => number(value).map(|width| config.tab_width = width),
+            "UseTab" => choice(
+                value,
+                &[
+                    ("Never", false),
+                    ("false", false),
+                    ("Always", true),
+                    ("ForIndentation", true),
+                    ("ForContinuationAndIndentation", true),
+                    ("AlignWithSpaces", true),
+                    ("true", true),
+                ],
+            )
+            .map(|tabs| config.use_tabs = tabs),
+            // A limit of zero means that there is none, which is left to the default.
+            "ColumnLimit" => number(value).map(|limit| {
+                if limit > 0 {
+                    config.max_line_length = limit;
+                }
+            }),
+            "BreakBeforeBraces" => choice(
+                value,
+                &[
+                    ("Attach", BraceStyle::Attach),
+                    ("Custom", BraceStyle::Attach),
+                    ("Linux", BraceStyle::Linux),
+                    ("Mozilla", BraceStyle::KernighanRitchie),
+                    ("Stroustrup", BraceStyle::KernighanRitchie),
+                    ("WebKit", BraceStyle::KernighanRitchie),
+                    ("Allman", BraceStyle::Allman),
+                    ("GNU", BraceStyle::Gnu),
+                    ("Whitesmiths", BraceStyle::Whitesmiths),
+                ],
+            )
+            .map(|style| config.brace_style = style),
+            "PointerAlignment" => choice(
+                value,
+                &[
+                    ("Left", PointerAlignment::Left),
+                    ("Right", PointerAlignment::Right),
+                    ("Middle", PointerAlignment::Middle),
+                ],
+            )
+            .map(|alignment| config.pointer_alignment = alignment),
+            // The spaces after control keywords and before the arguments of calls.
+            "SpaceBeforeParens" => {
+                let spaces = match value {
+                    "Never" => (false, false),
+                    "Always" => (true, true),
+                    _ => (true, false),
+                };
+                config.space_after_control_keywords = spaces.0;
+                config.space_before_call_parentheses = spaces.1;
+                Ok(())
+            }
+            "SpaceBeforeAssignmentOperators" => {
+                boolean(value).map(|space| config.space_around_assignment_operators = space)
+            }
+            "SpacesInParentheses" => {
+                boolean(value).map(|space| config.spaces_in_parentheses = space)
+            }
+            "SpacesInSquareBrackets" => {
+                boolean(value).map(|space| config.spaces_in_brackets = space)
+            }
+            "Cpp11BracedListStyle" => boolean(value).map(|style| config.spaces_in_braces = !style),
+            "SpaceAfterCStyleCast" => boolean(value).map(|space| config.space_after_cast = space),
+            "MaxEmptyLinesToKeep" => number(value).map(|lines| config.max_blank_lines = lines),
+            "KeepEmptyLinesAtTheStartOfBlocks" => {
+                boolean(value).map(|keep| config.strip_blank_lines_at_block_edges = !keep)
+            }
+            "BreakBeforeBinaryOperators" => choice(
+                value,
+                &[
+                    ("None", false),
+                    ("false", false),
+                    ("NonAssignment", true),
+                    ("All", true),
+                    ("true", true),
+                ],
+            )
+            .map(|before| config.break_before_binary_operators = before),
+            "BreakBeforeTernaryOperators" => {
+                boolean(value).map(|before| config.break_before_ternary_operators = before)
+            }
+            "IndentCaseLabels" => boolean(value).map(|indent| config.indent_case_labels = indent),
+            "IndentGotoLabels" => boolean(value).map(|indent| {
+                config.label_placement = match indent {
+                    true => LabelPlacement::Indented,
+                    false => LabelPlacement::FirstColumn,
+                }
+            }),
+            "IndentPPDirectives" => choice(
+                value,
+                &[
+                    ("None", DirectiveIndentation::None),
+                    ("AfterHash", DirectiveIndentation::AfterHash),
+                    ("BeforeHash", DirectiveIndentation::BeforeHash),
+                ],
+            )
+            .map(|indentation| config.directive_indentation = indentation),
+            "AlignEscapedNewlines" => choice(
+                value,
+                &[
+                    ("DontAlign", BackslashAlignment::Keep),
+                    ("Left", BackslashAlignment::Left),
+                    ("Right", BackslashAlignment::Right),
+                ],
+            )
+            .map(|alignment| config.backslash_alignment = alignment),
+            // Sorted includes are only grouped with `IncludeBlocks: Regroup`.
+            "SortIncludes" => choice(
+                value,
+                &[
+                    ("Never", false),
+                    ("false", false),
+                    ("CaseSensitive", true),
+                    ("CaseInsensitive", true),
+                    ("true", true),
+                ],
+            )
+            .map(|sort| {
+                config.include_sorting = match (sort, config.include_sorting) {
+                    (false, _) => IncludeSorting::Keep,
+                    (true, IncludeSorting::Keep) => IncludeSorting::Alphabetical,
+                    (true, sorting) => sorting,
+                }
+            }),
+            "IncludeBlocks" => {
+                if value == "Regroup" && config.include_sorting != IncludeSorting::Keep {
+                    config.include_sorting = IncludeSorting::Grouped;
+                }
+                Ok(())
+            }
+            "AllowShortIfStatementsOnASingleLine" => {
+                config.allow_short_if_on_single_line = !matches!(value, "Never" | "false");
+                Ok(())
+            }
+            "AllowShortLoopsOnASingleLine" => {
+                boolean(value).map(|allow| config.allow_short_loops_on_single_line = allow)
+            }
+            "AllowShortFunctionsOnASingleLine" => {
+                config.allow_short_functions_on_single_line = !matches!(value, "None" | "false");
+                Ok(())
+            }
+            "ReflowComments" => boolean(value).map(|reflow| config.reflow_comments = reflow),
+            "BreakStringLiterals" => {
+                boolean(value).map(|split| config.split_string_literals = split)
+            }
+            "AlignConsecutiveDeclarations" | "AlignConsecutiveDeclarations.Enabled" => {
+                alignment(value).map(|align| config.align_consecutive_declarations = align)
+            }
+            "AlignConsecutiveAssignments" | "AlignConsecutiveAssignments.Enabled" => {
+                alignment(value).map(|align| config.align_consecutive_assignments = align)
+            }
+            "AlignTrailingComments" => {
+                boolean(value).map(|align| config.align_trailing_comments = align)
+            }
+            "AlignTrailingComments.Kind" => {
+                config.align_trailing_comments = value == "Always";
+                Ok(())
+            }
+            "LineEnding" => choice(
+                value,
+                &[
+                    ("LF", LineEnding::Lf),
+                    ("CRLF", LineEnding::CrLf),
+                    ("DeriveLF", LineEnding::Auto),
+                    ("DeriveCRLF", LineEnding::Auto),
+                ],
+            )
+            .map(|ending| config.line_ending = ending),
+            "InsertNewlineAtEOF" => {
+                boolean(value).map(|insert| config.insert_final_newline = insert)
+            }
+            _ => Ok(()),
+        };
+
+        result.map_err(|message| TomlError {
+            line: entry.line,
+            message: format!("invalid value for `{}`: {}", entry.key, message),
+        })?;
+    }
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{
+        ArgumentWrapping, BraceStyle, FormatConfig, LineEnding, PointerAlignment, Style,
+    };
+    use crate::settings::clang_format::parse_clang_format;
+    use crate::settings::toml::TomlError;
+
+    #[test]
+    fn keys_are_mapped() {
+        let config = parse_clang_format(
+            "---\n# A comment.\nLanguage: JavaScript\nIndentWidth: 8\n---\n\
+             BasedOnStyle: LLVM\nIndentWidth: 4 # After a value.\nUseTab: ForIndentation\n\
+             ColumnLimit: 100\nBreakBeforeBraces: Allman\nPointerAlignment: Left\n\
+             BinPackArguments: false\nIncludeCategories:\n  - Regex: '^<'\n    Priority: 1\n  \
+             - Regex: '.*'\n    Priority: 2\nAlignConsecutiveAssignments:\n  Enabled: true\n  \
+             AcrossComments: false\nBraceWrapping: { AfterFunction: true }\nStandard: c++11\n\
+             LineEnding: DeriveLF\n...\n",
+            FormatConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            FormatConfig {
+                indent_width: 4,
+                use_tabs: true,
+                max_line_length: 100,
+                brace_style: BraceStyle::Allman,
+                pointer_alignment: PointerAlignment::Left,
+                argument_wrapping: ArgumentWrapping::OnePerLine,
+                align_consecutive_assignments: true,
+                line_ending: LineEnding::Auto,
+                ..Style::Llvm.config()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert_eq!(
+            parse_clang_format(
+                "IndentWidth: 2\nPointerAlignment: Up\n",
+                FormatConfig::default()
+            ),
+            Err(TomlError {
+                line: 2,
+                message: "invalid value for `PointerAlignment`: `Up` is not supported".to_string()
+            })
+        );
+        assert_eq!(
+            parse_clang_format("IndentWidth two", FormatConfig::default()),
+            Err(TomlError {
+                line: 1,
+                message: "expected `key: value`, found `IndentWidth two`".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,322 @@
+//! EditorConfig files, which most projects already use to declare their indentation and line
+//! endings. Their properties are applied below the options of configuration files, so that a
+//! project without a configuration file is still formatted the way its editors are set up.
+
+use crate::formatter::config::{Encoding, FormatConfig, LineEnding};
+use crate::ignore::matches;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of EditorConfig files.
+pub const EDITORCONFIG_FILE: &str = ".editorconfig";
+
+/// A section of an EditorConfig file, with the properties of the files which match its glob.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Section {
+    /// The alternatives of the glob after expanding its braces, as paths relative to the
+    /// directory of the file.
+    patterns: Vec<Vec<char>>,
+    properties: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct EditorConfig {
+    /// Whether the files in the directories above are ignored.
+    root: bool,
+    sections: Vec<Section>,
+}
+
+/// Read an EditorConfig file. Lines which cannot be read are skipped, as the specification asks.
+fn parse(text: &str) -> EditorConfig {
+    let mut config = EditorConfig::default();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            // A glob without a slash matches files in every directory below.
+            let glob = match glob.trim_end_matches('/').contains('/') {
+                true => glob.trim_start_matches('/').to_string(),
+                false => format!("**/{}", glob),
+            };
+            config.sections.push(Section {
+                patterns: expand(&glob)
+                    .iter()
+                    .map(|pattern| pattern.chars().collect())
+                    .collect(),
+                properties: Vec::new(),
+            });
+        } else if let Some((key, value)) = line.split_once('=') {
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+            match config.sections.last_mut() {
+                Some(section) => section.properties.push((key, value)),
+                None if key == "root" => config.root = value == "true",
+                None => {}
+            }
+        }
+    }
+
+    config
+}
+
+/// Expand the braces of a glob into its alternatives, as in `*.{c,h}` into `*.c` and `*.h`, and
+/// `{1..3}` into `1`, `2` and `3`.
+fn expand(glob: &str) -> Vec<String> {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut depth = 0;
+    let mut open = None;
+    let mut commas = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => {
+                if depth == 0 {
+                    open = Some(i);
+                    commas.clear();
+                }
+                depth += 1;
+            }
+            ',' if depth == 1 => commas.push(i),
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let start = open.expect("an opening brace comes first");
+                    let prefix: String = chars[..start].iter().collect();
+                    let suffix: String = chars[i + 1..].iter().collect();
+                    let inner: String = chars[start + 1..i].iter().collect();
+
+                    let alternatives: Vec<String> = if commas.is_empty() {
+                        match range(&inner) {
+                            Some(numbers) => numbers,
+                            None => vec![format!("\\{{{}\\}}", inner)],
+                        }
+                    } else {
+                        let mut bounds = vec![start];
+                        bounds.extend(&commas);
+                        bounds.push(i);
+                        bounds
+                            .windows(2)
+                            .map(|pair| chars[pair[0] + 1..pair[1]].iter().collect())
+                            .collect()
+                    };
+
+                    return alternatives
+                        .iter()
+                        .flat_map(|alternative| {
+                            expand(&format!("{}{}{}", prefix, alternative, suffix))
+                        })
+                        .collect();
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    vec![glob.to_string()]
+}
+
+/// The numbers in a range such as `1..3`.
+fn range(inner: &str) -> Option<Vec<String>> {
+    let (start, end) = inner.split_once("..")?;
+    let (start, end): (i64, i64) = (start.parse().ok()?, end.parse().ok()?);
+    Some(
+        (start.min(end)..=start.max(end))
+            .map(|n| n.to_string())
+            .collect(),
+    )
+}
+
+/// The EditorConfig files found so far, so that every directory is only read once when many files
+/// are formatted.
+#[derive(Debug, Default)]
+pub struct EditorConfigs {
+    directories: HashMap<PathBuf, Option<EditorConfig>>,
+}
+
+impl EditorConfigs {
+    pub fn new() -> EditorConfigs {
+        EditorConfigs::default()
+    }
+
+    fn read(&mut self, directory: &Path) -> Option<&EditorConfig> {
+        self.directories
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| {
+                let text = fs::read_to_string(directory.join(EDITORCONFIG_FILE)).ok()?;
+                Some(parse(&text))
+            })
+            .as_ref()
+    }
+
+    /// The properties which apply to a file, given as an absolute path. Files closer to it take
+    /// precedence, and so do later sections within a file.
+    fn properties(&mut self, path: &Path) -> HashMap<String, String> {
+        let mut directories = Vec::new();
+        for directory in path.ancestors().skip(1) {
+            if let Some(config) = self.read(directory) {
+                directories.push(directory);
+                if config.root {
+                    break;
+                }
+            }
+        }
+
+        let mut properties = HashMap::new();
+        for directory in directories.into_iter().rev() {
+            let config = self.read(directory).expect("the file was read before");
+            let relative: Vec<char> = path
+                .strip_prefix(directory)
+                .unwrap_or(path)
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+                .chars()
+                .collect();
+
+            for section in &config.sections {
+                if section
+                    .patterns
+                    .iter()
+                    .any(|pattern| matches(pattern, &relative))
+                {
+                    for (key, value) in &section.properties {
+                        properties.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+
+        properties.retain(|_, value| value != "unset");
+        properties
+    }
+
+    /// Apply the properties which apply to a file, given as an absolute path, to options.
+    /// Properties with a value which is not understood are ignored.
+    pub fn apply(&mut self, path: &Path, config: &mut FormatConfig) {
+        let properties = self.properties(path);
+        let get = |key: &str| properties.get(key).map(String::as_str);
+
+        match get("indent_style") {
+            Some("tab") => config.use_tabs = true,
+            Some("space") => config.use_tabs = false,
+            _ => {}
+        }
+        // The width of indentation with tabs is the width of a tab, unless it is given.
+        let indent_size = match get("indent_size") {
+            Some("tab") | None => get("tab_width"),
+            size => size,
+        };
+        if let Some(size) = indent_size.and_then(|size| size.parse().ok()) {
+            config.indent_width = size;
+            config.continuation_indent = size;
+        }
+        if let Some(width) = get("tab_width").and_then(|width| width.parse().ok()) {
+            config.tab_width = width;
+        }
+        match get("end_of_line") {
+            Some("lf") => config.line_ending = LineEnding::Lf,
+            Some("crlf") => config.line_ending = LineEnding::CrLf,
+            _ => {}
+        }
+        match get("charset") {
+            Some("utf-8") => config.encoding = Encoding::Utf8,
+            Some("latin1") => config.encoding = Encoding::Latin1,
+            _ => {}
+        }
+        if let Some(insert) = get("insert_final_newline").and_then(|value| value.parse().ok()) {
+            config.insert_final_newline = insert;
+        }
+        if let Some(trim) = get("trim_trailing_whitespace").and_then(|value| value.parse().ok()) {
+            config.trim_trailing_whitespace = trim;
+        }
+        if let Some(length) = get("max_line_length").and_then(|value| value.parse().ok()) {
+            config.max_line_length = length;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{Encoding, FormatConfig, LineEnding};
+    use crate::settings::editorconfig::{expand, EditorConfigs, EDITORCONFIG_FILE};
+    use std::fs;
+
+    #[test]
+    fn braces_are_expanded() {
+        assert_eq!(expand("*.{c,h}"), ["*.c", "*.h"]);
+        assert_eq!(expand("{a,{b,c}}/x"), ["a/x", "b/x", "c/x"]);
+        assert_eq!(expand("v{1..3}"), ["v1", "v2", "v3"]);
+        assert_eq!(expand("{single}"), ["\\{single\\}"]);
+        assert_eq!(expand("plain"), ["plain"]);
+    }
+
+    #[test]
+    fn properties_apply_to_matching_files() {
+        let root = std::env::temp_dir().join(format!("cfmt-editorconfig-{}", std::process::id()));
+        fs::create_dir_all(root.join("project/lib")).unwrap();
+        fs::write(root.join(EDITORCONFIG_FILE), "[*]\nindent_size = 3\n").unwrap();
+        fs::write(
+            root.join("project").join(EDITORCONFIG_FILE),
+            "root = true\n\n[*]\nindent_style = space\nindent_size = 2\nend_of_line = crlf\n\n\
+             [*.{c,h}]\nmax_line_length = 100\n\n[lib/**.c]\nindent_style = tab\n\
+             indent_size = tab\ntab_width = 8\n\n[Makefile]\nindent_style = tab\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("project/lib").join(EDITORCONFIG_FILE),
+            "; Nested files take precedence.\n[*.c]\ninsert_final_newline = false\n\
+             end_of_line = unset\ncharset = latin1\n",
+        )
+        .unwrap();
+
+        let mut editorconfigs = EditorConfigs::new();
+        let config = |editorconfigs: &mut EditorConfigs, path: &str| {
+            let mut config = FormatConfig::default();
+            editorconfigs.apply(&root.join(path), &mut config);
+            config
+        };
+
+        assert_eq!(
+            config(&mut editorconfigs, "project/main.c"),
+            FormatConfig {
+                indent_width: 2,
+                continuation_indent: 2,
+                line_ending: LineEnding::CrLf,
+                max_line_length: 100,
+                ..FormatConfig::default()
+            }
+        );
+        assert_eq!(
+            config(&mut editorconfigs, "project/lib/list.c"),
+            FormatConfig {
+                use_tabs: true,
+                tab_width: 8,
+                indent_width: 8,
+                continuation_indent: 8,
+                max_line_length: 100,
+                insert_final_newline: false,
+                encoding: Encoding::Latin1,
+                ..FormatConfig::default()
+            }
+        );
+        assert_eq!(
+            config(&mut editorconfigs, "other.c"),
+            FormatConfig {
+                indent_width: 3,
+                continuation_indent: 3,
+                ..FormatConfig::default()
+            }
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
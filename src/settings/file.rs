@@ -0,0 +1,511 @@
+//! Configuration files, which hold the options of a project. The file which applies to a source
+//! file is the first one found in its directory or the directories above it, so that the options
+//! of a project are used wherever the formatter is run from. A `.clang-format` file is used if a
+//! directory has no configuration file of its own.
+//!
+//! A configuration file can also define profiles, in tables such as `[profile.firmware]`, for the
+//! parts of a project which are written in another style. The options of a profile apply on top of
+//! the other options of the file, to the files which its `paths` match, or to every file when it is
+//! chosen with `--profile`:
+//!
+//! ```toml
+//! indent_width = 4
+//!
+//! [profile.firmware]
+//! paths = ["firmware/**", "boot/*.c"]
+//! based_on = "linux"
+//! ```
+
+use crate::formatter::config::{FormatConfig, Style};
+use crate::ignore::matches;
+use crate::settings::clang_format::{parse_clang_format, CLANG_FORMAT_FILES};
+use crate::settings::options::{option_values, set_option};
+use crate::settings::toml::{parse, Entry, TomlError, Value};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of configuration files.
+pub const CONFIG_FILE: &str = ".cfmt.toml";
+
+/// A configuration file which could not be read, or which has an invalid option.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        write!(f, ": error: {}", self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The name of the tables which hold the profiles of a configuration file.
+const PROFILE_TABLE: &str = "profile.";
+
+/// The options in a `[profile.<name>]` table of a configuration file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Profile {
+    name: String,
+    /// The globs of the files the profile applies to, relative to the directory of the
+    /// configuration file. A glob without a `/` matches the name of a file in any directory.
+    paths: Vec<Vec<char>>,
+    entries: Vec<Entry>,
+}
+
+impl Profile {
+    /// Whether the profile applies to a file, given relative to the directory of the
+    /// configuration file.
+    fn matches(&self, relative: &Path) -> bool {
+        let components: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        let path: Vec<char> = components.join("/").chars().collect();
+        let name: Vec<char> = components
+            .last()
+            .map_or(Vec::new(), |name| name.chars().collect());
+
+        self.paths.iter().any(|glob| match glob.contains(&'/') {
+            true => matches(glob, &path),
+            false => matches(glob, &name),
+        })
+    }
+}
+
+/// Split the entries of a configuration file into its own options and its profiles, in the order
+/// in which they are first written. Fails if a profile has an invalid option, even if it applies
+/// to no file.
+fn split_profiles(entries: Vec<Entry>) -> Result<(Vec<Entry>, Vec<Profile>), TomlError> {
+    let mut options = Vec::new();
+    let mut profiles: Vec<Profile> = Vec::new();
+    for entry in entries {
+        let Some(key) = entry.key.strip_prefix(PROFILE_TABLE) else {
+            options.push(entry);
+            continue;
+        };
+        let Some((name, key)) = key.split_once('.') else {
+            return Err(TomlError {
+                line: entry.line,
+                message: format!("`{}` is not in the table of a profile", entry.key),
+            });
+        };
+
+        let index = match profiles.iter().position(|profile| profile.name == name) {
+            Some(index) => index,
+            None => {
+                profiles.push(Profile {
+                    name: name.to_string(),
+                    paths: Vec::new(),
+                    entries: Vec::new(),
+                });
+                profiles.len() - 1
+            }
+        };
+        let profile = &mut profiles[index];
+        match (key, &entry.value) {
+            ("paths", Value::Array(globs)) => {
+                for glob in globs {
+                    let Value::String(glob) = glob else {
+                        return Err(TomlError {
+                            line: entry.line,
+                            message: format!("expected the globs of paths, found {}", glob),
+                        });
+                    };
+                    profile
+                        .paths
+                        .push(glob.trim_start_matches('/').chars().collect());
+                }
+            }
+            ("paths", value) => {
+                return Err(TomlError {
+                    line: entry.line,
+                    message: format!("expected an array of globs for `paths`, found {}", value),
+                });
+            }
+            _ => profile.entries.push(Entry {
+                key: key.to_string(),
+                ..entry
+            }),
+        }
+    }
+
+    for profile in &profiles {
+        apply_entries(&profile.entries, FormatConfig::default())?;
+    }
+    Ok((options, profiles))
+}
+
+/// Read the options from the text of a configuration file and apply them to other options.
+/// Options which are not given keep their value, or that of the style named by `based_on`, as in
+/// `based_on = "linux"`. The profiles of the file are checked, but not applied.
+pub fn parse_config(text: &str, base: FormatConfig) -> Result<FormatConfig, TomlError> {
+    let (entries, _) = split_profiles(parse(text)?)?;
+    apply_entries(&entries, base)
+}
+
+/// Apply the entries of a configuration to other options, with the style named by `based_on`
+/// first.
+pub fn apply_entries(entries: &[Entry], base: FormatConfig) -> Result<FormatConfig, TomlError> {
+    let mut config = base;
+
+    // The style is applied first, wherever it is written, so that the other options override it.
+    if let Some(entry) = entries.iter().find(|entry| entry.key == "based_on") {
+        let style = match &entry.value {
+            Value::String(name) => Style::from_name(name),
+            _ => None,
+        };
+        config = style
+            .map(|style| style.apply(config))
+            .ok_or_else(|| TomlError {
+                line: entry.line,
+                message: format!("unknown style {} for `based_on`", entry.value),
+            })?;
+    }
+
+    for entry in entries.iter().filter(|entry| entry.key != "based_on") {
+        set_option(&mut config, &entry.key, &entry.value).map_err(|message| TomlError {
+            line: entry.line,
+            message,
+        })?;
+    }
+
+    Ok(config)
+}
+
+/// Write options as the text of a configuration file, which `parse_config` reads back into the
+/// same options. The rules of `--lint` which are turned off are written in the `[lint]` table.
+pub fn write_config(config: &FormatConfig) -> String {
+    let mut text = String::new();
+    let mut rules = String::new();
+    for (name, value) in option_values(config) {
+        match name.strip_prefix("lint.") {
+            Some(rule) => rules += &format!("{} = {}\n", rule, value),
+            None => text += &format!("{} = {}\n", name, value),
+        }
+    }
+
+    if !rules.is_empty() {
+        text += &format!("\n[lint]\n{}", rules);
+    }
+    text
+}
+
+/// Read a configuration file, or a `.clang-format` file, and apply it to other options for the
+/// source file at a path. The profile named, or otherwise the first one whose `paths` match the
+/// source file, applies on top of the other options. Fails if the named profile is not defined.
+pub fn load_config(
+    path: &Path,
+    base: FormatConfig,
+    file: &Path,
+    profile: Option<&str>,
+) -> Result<FormatConfig, ConfigError> {
+    let text = fs::read_to_string(path).map_err(|error| ConfigError {
+        path: path.to_path_buf(),
+        line: None,
+        message: format!("could not read the configuration: {}", error),
+    })?;
+    let error = |error: TomlError| ConfigError {
+        path: path.to_path_buf(),
+        line: Some(error.line),
+        message: error.message,
+    };
+
+    // A `.clang-format` file has no profiles.
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (config, profiles) = match CLANG_FORMAT_FILES.contains(&name.as_ref()) {
+        true => (parse_clang_format(&text, base).map_err(error)?, Vec::new()),
+        false => {
+            let (entries, profiles) = parse(&text).and_then(split_profiles).map_err(error)?;
+            (apply_entries(&entries, base).map_err(error)?, profiles)
+        }
+    };
+
+    let profile = match profile {
+        Some(name) => match profiles.iter().find(|profile| profile.name == name) {
+            Some(profile) => Some(profile),
+            None => {
+                return Err(ConfigError {
+                    path: path.to_path_buf(),
+                    line: None,
+                    message: format!("there is no profile `{}`", name),
+                })
+            }
+        },
+        None => {
+            // The globs are relative to the configuration file, wherever it was given from.
+            let directory = fs::canonicalize(path).ok();
+            let relative = directory
+                .as_deref()
+                .and_then(Path::parent)
+                .and_then(|directory| file.strip_prefix(directory).ok());
+            relative.and_then(|relative| profiles.iter().find(|profile| profile.matches(relative)))
+        }
+    };
+    match profile {
+        Some(profile) => apply_entries(&profile.entries, config).map_err(error),
+        None => Ok(config),
+    }
+}
+
+/// The configuration files found so far, so that every directory is only searched once when many
+/// files are formatted.
+#[derive(Debug, Default)]
+pub struct ConfigFiles {
+    /// The configuration file which applies to every directory searched, if any.
+    directories: HashMap<PathBuf, Option<PathBuf>>,
+}
+
+impl ConfigFiles {
+    pub fn new() -> ConfigFiles {
+        ConfigFiles::default()
+    }
+
+    /// Find the configuration file which applies to the files in a directory, searching upwards.
+    pub fn find(&mut self, directory: &Path) -> Option<PathBuf> {
+        if let Some(found) = self.directories.get(directory) {
+            return found.clone();
+        }
+
+        let found = match [CONFIG_FILE]
+            .iter()
+            .chain(CLANG_FORMAT_FILES)
+            .map(|name| directory.join(name))
+            .find(|path| path.is_file())
+        {
+            Some(path) => Some(path),
+            None => directory.parent().and_then(|parent| self.find(parent)),
+        };
+        self.directories
+            .insert(directory.to_path_buf(), found.clone());
+        found
+    }
+
+    /// The options for a file, given as an absolute path, from the configuration file which
+    /// applies to it applied to other options, with the profile named or the one which matches
+    /// the file. Without such a file, those other options are used.
+    pub fn config_for(
+        &mut self,
+        file: &Path,
+        base: FormatConfig,
+        profile: Option<&str>,
+    ) -> Result<FormatConfig, ConfigError> {
+        match self.find(file.parent().unwrap_or(file)) {
+            Some(path) => load_config(&path, base, file, profile),
+            None => Ok(base),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{BraceStyle, FormatConfig, Style};
+    use crate::settings::file::{
+        load_config, parse_config, write_config, ConfigError, ConfigFiles, CONFIG_FILE,
+    };
+    use crate::settings::toml::TomlError;
+    use std::fs;
+
+    #[test]
+    fn written_options_are_read_back() {
+        let mut config = Style::Gnu.config();
+        config.disabled_rules = vec!["tab-indent".to_string()];
+        let text = write_config(&config);
+        assert!(text.starts_with("indent_width = 2\ncontinuation_indent = 2\n"));
+        assert!(text.contains("brace_style = \"gnu\"\n"));
+        assert!(text.ends_with("\n[lint]\ntab-indent = false\n"));
+        assert_eq!(parse_config(&text, FormatConfig::default()), Ok(config));
+    }
+
+    #[test]
+    fn options_override_the_style() {
+        let config = parse_config(
+            "indent_width = 2\nbased_on = \"linux\"\n",
+            FormatConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            FormatConfig {
+                indent_width: 2,
+                ..Style::Linux.config()
+            }
+        );
+
+        assert_eq!(
+            parse_config("based_on = \"mine\"", FormatConfig::default()),
+            Err(TomlError {
+                line: 1,
+                message: "unknown style \"mine\" for `based_on`".to_string()
+            })
+        );
+        assert_eq!(
+            parse_config("\nbrace_style = true", FormatConfig::default()),
+            Err(TomlError {
+                line: 2,
+                message: "invalid value for `brace_style`: expected one of \"attach\", \
+                          \"kernighan_ritchie\", \"linux\", \"allman\", \"gnu\", \
+                          \"whitesmiths\", found true"
+                    .to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn files_are_found_upwards() {
+        let root = std::env::temp_dir().join(format!("cfmt-config-{}", std::process::id()));
+        fs::create_dir_all(root.join("project/src/nested")).unwrap();
+        fs::create_dir_all(root.join("project/vendor")).unwrap();
+        fs::create_dir_all(root.join("project/src/imported")).unwrap();
+        fs::write(
+            root.join("project/src/imported/.clang-format"),
+            "BasedOnStyle: GNU\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("project").join(CONFIG_FILE),
+            "brace_style = \"allman\"",
+        )
+        .unwrap();
+        fs::write(
+            root.join("project/vendor").join(CONFIG_FILE),
+            "indent_width = 8",
+        )
+        .unwrap();
+
+        let mut files = ConfigFiles::new();
+        let mut config_for = |directory: &str| {
+            let file = root.join(directory).join("main.c");
+            files.config_for(&file, FormatConfig::default(), None)
+        };
+        assert_eq!(
+            config_for("project/src/nested").unwrap().brace_style,
+            BraceStyle::Allman
+        );
+        assert_eq!(
+            config_for("project/vendor").unwrap(),
+            FormatConfig {
+                indent_width: 8,
+                ..FormatConfig::default()
+            }
+        );
+        assert_eq!(
+            config_for("project/src/imported").unwrap(),
+            Style::Gnu.config()
+        );
+        assert_eq!(files.find(&root), None);
+
+        // Options which are not given keep the value they are applied to.
+        let base = FormatConfig {
+            use_tabs: true,
+            ..FormatConfig::default()
+        };
+        let config = files
+            .config_for(&root.join("project/vendor/main.c"), base, None)
+            .unwrap();
+        assert!(config.use_tabs);
+        assert_eq!(config.indent_width, 8);
+
+        fs::write(root.join("project").join(CONFIG_FILE), "brace_style = 1").unwrap();
+        let error = ConfigFiles::new()
+            .config_for(
+                &root.join("project/src/main.c"),
+                FormatConfig::default(),
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(error.line, Some(1));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn profiles_apply_to_their_paths() {
+        let root = std::env::temp_dir().join(format!("cfmt-profiles-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(CONFIG_FILE);
+        fs::write(
+            &path,
+            "indent_width = 2
+             
+             [profile.firmware]
+             paths = [\"firmware/**\", \"/boot/*.c\"]
+             based_on = \"linux\"
+             
+             [profile.tests]
+             paths = [\"test_*.c\"]
+             max_line_length = 120
+             
+             [profile.tests.lint]
+             line-too-long = false
+",
+        )
+        .unwrap();
+
+        let config_for = |file: &str, profile| {
+            let file = fs::canonicalize(&root).unwrap().join(file);
+            load_config(&path, FormatConfig::default(), &file, profile)
+        };
+        let tests = FormatConfig {
+            indent_width: 2,
+            max_line_length: 120,
+            disabled_rules: vec!["line-too-long".to_string()],
+            ..FormatConfig::default()
+        };
+        assert_eq!(
+            config_for("firmware/usb/usb.c", None),
+            Ok(Style::Linux.config())
+        );
+        assert_eq!(config_for("boot/start.c", None), Ok(Style::Linux.config()));
+        assert_eq!(
+            config_for("src/boot/start.c", None).unwrap().indent_width,
+            2
+        );
+        assert_eq!(config_for("src/test_list.c", None), Ok(tests.clone()));
+        // A profile which is given applies to every file.
+        assert_eq!(config_for("firmware/usb/usb.c", Some("tests")), Ok(tests));
+        assert_eq!(
+            config_for("src/list.c", Some("release")),
+            Err(ConfigError {
+                path: path.clone(),
+                line: None,
+                message: "there is no profile `release`".to_string()
+            })
+        );
+
+        // The options of profiles are not those of the file itself, but they are checked.
+        assert_eq!(
+            parse_config(&fs::read_to_string(&path).unwrap(), FormatConfig::default())
+                .unwrap()
+                .indent_width,
+            2
+        );
+        assert_eq!(
+            parse_config("[profile.tests]\nindent = 2\n", FormatConfig::default()),
+            Err(TomlError {
+                line: 2,
+                message: "unknown option `indent`".to_string()
+            })
+        );
+        assert_eq!(
+            parse_config(
+                "[profile.tests]\npaths = \"tests\"\n",
+                FormatConfig::default()
+            )
+            .unwrap_err()
+            .message,
+            "expected an array of globs for `paths`, found \"tests\""
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! A reader for options written as a JSON object, as in `{"indent_width": 2, "lint": {"tab-indent":
+//! false}}`, for the bindings which are handed their options by JavaScript or another language.
+//! The keys of nested objects are joined to the key of the object with a period, like those of
+//! tables in configuration files, so that both name the options the same way.
+
+use crate::settings::toml::{Entry, TomlError, Value};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Read the entries of an object in the order in which they were written. Numbers must be whole,
+/// and `null` values are left out, so that they keep the option as it was.
+pub fn parse_json(text: &str) -> Result<Vec<Entry>, TomlError> {
+    let mut reader = Reader {
+        chars: text.chars().peekable(),
+        line: 1,
+    };
+    let mut entries = Vec::new();
+
+    reader.skip_whitespace();
+    reader.object("", &mut entries)?;
+    reader.skip_whitespace();
+    match reader.chars.peek() {
+        Some(_) => Err(reader.error("unexpected text after the object")),
+        None => Ok(entries),
+    }
+}
+
+/// The position in a document, with the line it is on.
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, message: &str) -> TomlError {
+        TomlError {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        match self.chars.peek() == Some(&c) {
+            true => self.next().is_some(),
+            false => false,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    /// An object, whose entries are added with their keys after a prefix.
+    fn object(&mut self, prefix: &str, entries: &mut Vec<Entry>) -> Result<(), TomlError> {
+        if !self.eat('{') {
+            return Err(self.error("expected an object"));
+        }
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(());
+        }
+
+        loop {
+            self.skip_whitespace();
+            let line = self.line;
+            let key = format!("{}{}", prefix, self.string()?);
+            self.skip_whitespace();
+            if !self.eat(':') {
+                return Err(self.error(&format!("expected `:` after \"{}\"", key)));
+            }
+            self.skip_whitespace();
+
+            if self.chars.peek() == Some(&'{') {
+                self.object(&format!("{}.", key), entries)?;
+            } else if let Some(value) = self.value()? {
+                if entries.iter().any(|entry| entry.key == key) {
+                    return Err(TomlError {
+                        line,
+                        message: format!("`{}` is defined more than once", key),
+                    });
+                }
+                entries.push(Entry { key, value, line });
+            }
+
+            self.skip_whitespace();
+            if self.eat('}') {
+                return Ok(());
+            }
+            if !self.eat(',') {
+                return Err(self.error("expected `,` or `}` in the object"));
+            }
+        }
+    }
+
+    /// A value other than an object, or nothing for `null`.
+    fn value(&mut self) -> Result<Option<Value>, TomlError> {
+        match self.chars.peek() {
+            Some('"') => Ok(Some(Value::String(self.string()?))),
+            Some('[') => {
+                self.next();
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.eat(']') {
+                    return Ok(Some(Value::Array(values)));
+                }
+                loop {
+                    self.skip_whitespace();
+                    match self.value()? {
+                        Some(value) => values.push(value),
+                        None => return Err(self.error("unexpected `null` in the array")),
+                    }
+                    self.skip_whitespace();
+                    if self.eat(']') {
+                        return Ok(Some(Value::Array(values)));
+                    }
+                    if !self.eat(',') {
+                        return Err(self.error("expected `,` or `]` in the array"));
+                    }
+                }
+            }
+            Some(c) if c.is_ascii_alphanumeric() || *c == '-' => {
+                let mut word = String::new();
+                while let Some(&c) = self.chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) {
+                        break;
+                    }
+                    word.push(c);
+                    self.next();
+                }
+                match word.as_str() {
+                    "true" => Ok(Some(Value::Bool(true))),
+                    "false" => Ok(Some(Value::Bool(false))),
+                    "null" => Ok(None),
+                    _ => match word.parse() {
+                        Ok(number) => Ok(Some(Value::Integer(number))),
+                        Err(_) => Err(self.error(&format!("invalid value `{}`", word))),
+                    },
+                }
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, TomlError> {
+        if !self.eat('"') {
+            return Err(self.error("expected a string"));
+        }
+
+        let mut result = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(result),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let digits: String = (0..4).filter_map(|_| self.next()).collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| self.error("invalid escape in the string"))?
+                        }
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        _ => return Err(self.error("invalid escape in the string")),
+                    };
+                    result.push(c);
+                }
+                Some(c) => result.push(c),
+                None => return Err(self.error("the string is not closed")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::json::parse_json;
+    use crate::settings::toml::{Entry, TomlError, Value};
+
+    #[test]
+    fn objects_are_read_as_entries() {
+        let text = "{\n  \"indent_width\": 2,\n  \"brace_style\": \"allman\",\n  \
+                    \"max_line_length\": null,\n  \"lint\": { \"tab-indent\": false },\n  \
+                    \"names\": [\"a\\u0062\", -1]\n}";
+        let entry = |key: &str, value, line| Entry {
+            key: key.to_string(),
+            value,
+            line,
+        };
+        assert_eq!(
+            parse_json(text),
+            Ok(vec![
+                entry("indent_width", Value::Integer(2), 2),
+                entry("brace_style", Value::String("allman".to_string()), 3),
+                entry("lint.tab-indent", Value::Bool(false), 5),
+                entry(
+                    "names",
+                    Value::Array(vec![Value::String("ab".to_string()), Value::Integer(-1)]),
+                    6
+                ),
+            ])
+        );
+
+        assert_eq!(parse_json("{}"), Ok(vec![]));
+        assert_eq!(
+            parse_json("{\n\"use_tabs\": 1.5}"),
+            Err(TomlError {
+                line: 2,
+                message: "invalid value `1.5`".to_string()
+            })
+        );
+        assert_eq!(
+            parse_json("{\"a\": 1} x").unwrap_err().message,
+            "unexpected text after the object"
+        );
+    }
+}
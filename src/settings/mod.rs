@@ -0,0 +1,9 @@
+pub mod builder;
+pub mod clang_format;
+pub mod editorconfig;
+pub mod file;
+pub mod json;
+pub mod modeline;
+pub mod options;
+pub mod resolve;
+pub mod toml;
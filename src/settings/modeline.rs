@@ -0,0 +1,136 @@
+//! Options given in a comment at the very start of a file, as in
+//! `/* cfmt: max_line_length=120 use_tabs=true */`, for the few files of a project which need
+//! different options. They override the configuration files and the style, while the options given
+//! on the command line still take precedence. Options are written as on the command line, by their
+//! names in configuration files or with hyphens. The names other formatters use in their modelines,
+//! `column_limit` and `indent`, are understood as well.
+
+use crate::formatter::config::FormatConfig;
+use crate::settings::options::set_option;
+use crate::settings::resolve::parse_value;
+use crate::settings::toml::{TomlError, Value};
+
+/// The option an alias from the modeline of another formatter stands for, with its value, as in
+/// `column_limit=120` or `indent=tab`.
+fn alias(name: &str, value: &str) -> Option<(&'static str, Value)> {
+    match (name, value) {
+        ("column_limit", _) => Some(("max_line_length", parse_value(value))),
+        ("indent", "tab" | "tabs") => Some(("use_tabs", Value::Bool(true))),
+        ("indent", "space" | "spaces") => Some(("use_tabs", Value::Bool(false))),
+        ("indent", _) => Some(("indent_width", parse_value(value))),
+        _ => None,
+    }
+}
+
+/// The text of the comment at the start of a source file, and the line it starts on.
+fn first_comment(source: &str) -> Option<(&str, usize)> {
+    let text = source.trim_start();
+    let line = source[..source.len() - text.len()].matches('\n').count() + 1;
+
+    let comment = if let Some(rest) = text.strip_prefix("//") {
+        rest.lines().next().unwrap_or_default()
+    } else {
+        let rest = text.strip_prefix("/*")?;
+        &rest[..rest.find("*/")?]
+    };
+    Some((comment, line))
+}
+
+/// Apply the options in the comment at the start of a source file, if it has one. A comment such
+/// as `// cfmt: off` which gives no options is left alone.
+pub fn apply_modeline(source: &str, config: &mut FormatConfig) -> Result<(), TomlError> {
+    let Some((comment, line)) = first_comment(source) else {
+        return Ok(());
+    };
+    let Some(options) = comment.trim().strip_prefix("cfmt:") else {
+        return Ok(());
+    };
+    if !options.contains('=') {
+        return Ok(());
+    }
+
+    for option in options.split_whitespace() {
+        let error = |message: String| TomlError { line, message };
+        let (name, value) = option
+            .split_once('=')
+            .ok_or_else(|| error(format!("expected `name=value`, found `{}`", option)))?;
+        let name = name.replace('-', "_");
+        match alias(&name, value) {
+            Some((name, value)) => set_option(config, name, &value).map_err(error)?,
+            None => set_option(config, &name, &parse_value(value)).map_err(error)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{BraceStyle, FormatConfig};
+    use crate::settings::modeline::apply_modeline;
+    use crate::settings::toml::TomlError;
+
+    fn apply(source: &str) -> Result<FormatConfig, TomlError> {
+        let mut config = FormatConfig::default();
+        apply_modeline(source, &mut config).map(|_| config)
+    }
+
+    #[test]
+    fn options_are_read_from_the_first_comment() {
+        assert_eq!(
+            apply("\n/* cfmt: max_line_length=120 use-tabs=true\n   brace-style=allman */\nint a;"),
+            Ok(FormatConfig {
+                max_line_length: 120,
+                use_tabs: true,
+                brace_style: BraceStyle::Allman,
+                ..FormatConfig::default()
+            })
+        );
+        assert_eq!(
+            apply("// cfmt: indent_width=2\n").map(|config| config.indent_width),
+            Ok(2)
+        );
+
+        for source in [
+            "// cfmt: off\nint  a;\n",
+            "int a; /* cfmt: indent_width=2 */\n",
+            "/* A file which needs no options. */\n",
+            "/* cfmt: indent_width=2",
+        ] {
+            assert_eq!(apply(source), Ok(FormatConfig::default()));
+        }
+
+        assert_eq!(
+            apply("\n\n// cfmt: indent_width=2 tabs\n"),
+            Err(TomlError {
+                line: 3,
+                message: "expected `name=value`, found `tabs`".to_string()
+            })
+        );
+        assert_eq!(
+            apply("// cfmt: indentation=2\n"),
+            Err(TomlError {
+                line: 1,
+                message: "unknown option `indentation`".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn aliases_of_other_formatters() {
+        assert_eq!(
+            apply("/* cfmt: column_limit=120 indent=tab */\n"),
+            Ok(FormatConfig {
+                max_line_length: 120,
+                use_tabs: true,
+                ..FormatConfig::default()
+            })
+        );
+        assert_eq!(
+            apply("// cfmt: indent=2 use_tabs=true indent=spaces\n"),
+            Ok(FormatConfig {
+                indent_width: 2,
+                ..FormatConfig::default()
+            })
+        );
+    }
+}
@@ -0,0 +1,342 @@
+//! Setting the options of the formatter by their names, as they are written in configuration
+//! files. Options are named after the fields of `FormatConfig`, and the variants of their enums
+//! in snake case, as in `brace_style = "kernighan_ritchie"`.
+
+use crate::formatter::config::{
+    ArgumentWrapping, BackslashAlignment, BraceStyle, CaseBraces, DirectiveIndentation, Encoding,
+    FormatConfig, HeaderGuards, IncludeSorting, InitializerLayout, KeywordPlacement,
+    LabelPlacement, LineEnding, PointerAlignment,
+};
+use crate::lint::RULES;
+use crate::settings::toml::Value;
+
+/// The value of an option, read from a configuration value and written back to one.
+trait OptionValue: Sized {
+    fn from_value(value: &Value) -> Result<Self, String>;
+
+    fn to_value(&self) -> Value;
+}
+
+impl OptionValue for bool {
+    fn from_value(value: &Value) -> Result<bool, String> {
+        match value {
+            Value::Bool(value) => Ok(*value),
+            value => Err(format!("expected `true` or `false`, found {}", value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl OptionValue for usize {
+    fn from_value(value: &Value) -> Result<usize, String> {
+        match value {
+            Value::Integer(value) => usize::try_from(*value)
+                .map_err(|_| format!("expected a number of zero or more, found {}", value)),
+            value => Err(format!("expected a number, found {}", value)),
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Integer(i64::try_from(*self).unwrap_or(i64::MAX))
+    }
+}
+
+/// Implement `OptionValue` for an enum by the names of its variants.
+macro_rules! named {
+    ($enum:ident { $($variant:ident => $name:literal),* $(,)? }) => {
+        impl OptionValue for $enum {
+            fn from_value(value: &Value) -> Result<$enum, String> {
+                match value {
+                    $(Value::String(name) if name == $name => Ok($enum::$variant),)*
+                    value => Err(format!(
+                        "expected one of {}, found {}",
+                        [$(concat!("\"", $name, "\"")),*].join(", "),
+                        value
+                    )),
+                }
+            }
+
+            fn to_value(&self) -> Value {
+                match self {
+                    $($enum::$variant => Value::String($name.to_string()),)*
+                }
+            }
+        }
+    };
+}
+
+named!(BraceStyle {
+    Attach => "attach",
+    KernighanRitchie => "kernighan_ritchie",
+    Linux => "linux",
+    Allman => "allman",
+    Gnu => "gnu",
+    Whitesmiths => "whitesmiths",
+});
+
+named!(PointerAlignment {
+    Right => "right",
+    Left => "left",
+    Middle => "middle",
+});
+
+named!(ArgumentWrapping {
+    OnePerLine => "one_per_line",
+    BinPack => "bin_pack",
+    Align => "align",
+});
+
+named!(InitializerLayout {
+    OnePerLine => "one_per_line",
+    Compact => "compact",
+    Columns => "columns",
+});
+
+named!(KeywordPlacement {
+    BraceStyle => "brace_style",
+    SameLine => "same_line",
+    NextLine => "next_line",
+});
+
+named!(CaseBraces {
+    Control => "control",
+    Attach => "attach",
+    NextLine => "next_line",
+    Indented => "indented",
+});
+
+named!(LabelPlacement {
+    Indented => "indented",
+    Outdented => "outdented",
+    FirstColumn => "first_column",
+});
+
+named!(DirectiveIndentation {
+    None => "none",
+    AfterHash => "after_hash",
+    BeforeHash => "before_hash",
+});
+
+named!(BackslashAlignment {
+    Keep => "keep",
+    Left => "left",
+    Right => "right",
+});
+
+named!(IncludeSorting {
+    Keep => "keep",
+    Alphabetical => "alphabetical",
+    Grouped => "grouped",
+});
+
+named!(HeaderGuards {
+    Keep => "keep",
+    Macro => "macro",
+    PragmaOnce => "pragma_once",
+});
+
+named!(LineEnding {
+    Lf => "lf",
+    CrLf => "crlf",
+    Native => "native",
+    Auto => "auto",
+});
+
+named!(Encoding {
+    Utf8 => "utf8",
+    Latin1 => "latin1",
+    Auto => "auto",
+});
+
+/// Turn a rule of `--lint` on or off, as in `line-too-long = false` in the `[lint]` table.
+fn set_rule(config: &mut FormatConfig, rule: &str, value: &Value) -> Result<(), String> {
+    if !RULES.contains(&rule) {
+        return Err(format!("unknown rule `{}`", rule));
+    }
+    let enabled = bool::from_value(value)
+        .map_err(|error| format!("invalid value for rule `{}`: {}", rule, error))?;
+
+    config.disabled_rules.retain(|disabled| disabled != rule);
+    if !enabled {
+        config.disabled_rules.push(rule.to_string());
+    }
+    Ok(())
+}
+
+/// Other names of options, which are accepted as well, and the options they stand for.
+pub const ALIASES: &[(&str, &str)] = &[("line_endings", "line_ending")];
+
+/// Define `set_option`, `option_values` and `OPTIONS` for the listed fields of `FormatConfig`.
+macro_rules! options {
+    ($($field:ident),* $(,)?) => {
+        /// The names of all options.
+        pub const OPTIONS: &[&str] = &[$(stringify!($field)),*];
+
+        /// Set an option by its name or one of its aliases, failing if there is no such option
+        /// or if the value does not suit it.
+        pub fn set_option(
+            config: &mut FormatConfig,
+            name: &str,
+            value: &Value,
+        ) -> Result<(), String> {
+            let name = ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == name)
+                .map_or(name, |(_, option)| option);
+            match name {
+                $(stringify!($field) => {
+                    config.$field = OptionValue::from_value(value)
+                        .map_err(|error| format!("invalid value for `{}`: {}", name, error))?;
+                })*
+                _ if name.starts_with("lint.") => set_rule(config, &name["lint.".len()..], value)?,
+                _ => return Err(format!("unknown option `{}`", name)),
+            }
+            Ok(())
+        }
+
+        /// The values of all options by their names, in the order of `OPTIONS`, followed by the
+        /// rules of `--lint` which are turned off, so that setting them again gives the same
+        /// options.
+        pub fn option_values(config: &FormatConfig) -> Vec<(String, Value)> {
+            let mut values = vec![$((stringify!($field).to_string(), config.$field.to_value())),*];
+            for rule in &config.disabled_rules {
+                values.push((format!("lint.{}", rule), Value::Bool(false)));
+            }
+            values
+        }
+    };
+}
+
+options!(
+    indent_width,
+    continuation_indent,
+    use_tabs,
+    tab_width,
+    max_line_length,
+    brace_style,
+    pointer_alignment,
+    space_around_binary_operators,
+    space_around_assignment_operators,
+    break_before_binary_operators,
+    break_before_ternary_operators,
+    space_after_comma,
+    space_after_for_semicolon,
+    space_after_control_keywords,
+    space_before_call_parentheses,
+    spaces_in_parentheses,
+    space_after_cast,
+    space_after_sizeof,
+    sizeof_parentheses,
+    spaces_in_brackets,
+    spaces_in_braces,
+    max_blank_lines,
+    strip_blank_lines_at_block_edges,
+    blank_lines_around_definitions,
+    blank_lines_between_groups,
+    argument_wrapping,
+    initializer_layout,
+    break_initializers_with_trailing_comma,
+    else_placement,
+    do_while_placement,
+    indent_case_labels,
+    indent_case_bodies,
+    case_braces,
+    label_placement,
+    directive_indentation,
+    backslash_alignment,
+    include_sorting,
+    header_guards,
+    allow_short_if_on_single_line,
+    allow_short_loops_on_single_line,
+    allow_short_functions_on_single_line,
+    reflow_comments,
+    split_string_literals,
+    normalize_number_literals,
+    align_consecutive_declarations,
+    align_struct_members,
+    max_enumerators_on_single_line,
+    align_enum_values,
+    align_consecutive_assignments,
+    align_trailing_comments,
+    trim_trailing_whitespace,
+    insert_final_newline,
+    line_ending,
+    encoding,
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{BraceStyle, FormatConfig, LineEnding};
+    use crate::settings::options::set_option;
+    use crate::settings::toml::Value;
+
+    #[test]
+    fn options_are_set_by_name() {
+        let mut config = FormatConfig::default();
+        set_option(&mut config, "indent_width", &Value::Integer(2)).unwrap();
+        set_option(&mut config, "use_tabs", &Value::Bool(true)).unwrap();
+        let style = Value::String("allman".to_string());
+        set_option(&mut config, "brace_style", &style).unwrap();
+        let line_endings = Value::String("crlf".to_string());
+        set_option(&mut config, "line_endings", &line_endings).unwrap();
+
+        assert_eq!(
+            config,
+            FormatConfig {
+                indent_width: 2,
+                use_tabs: true,
+                brace_style: BraceStyle::Allman,
+                line_ending: LineEnding::CrLf,
+                ..FormatConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_options() {
+        let mut config = FormatConfig::default();
+        let set = |config: &mut FormatConfig, name, value| set_option(config, name, &value);
+
+        assert_eq!(
+            set(&mut config, "indent", Value::Integer(2)),
+            Err("unknown option `indent`".to_string())
+        );
+        assert_eq!(
+            set(&mut config, "indent_width", Value::Integer(-1)),
+            Err(
+                "invalid value for `indent_width`: expected a number of zero or more, found -1"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            set(
+                &mut config,
+                "pointer_alignment",
+                Value::String("up".to_string())
+            ),
+            Err(
+                "invalid value for `pointer_alignment`: expected one of \"right\", \"left\", \
+                 \"middle\", found \"up\""
+                    .to_string()
+            )
+        );
+        assert_eq!(config, FormatConfig::default());
+    }
+
+    #[test]
+    fn rules_are_turned_off_by_name() {
+        let mut config = FormatConfig::default();
+        set_option(&mut config, "lint.tab-indent", &Value::Bool(false)).unwrap();
+        set_option(&mut config, "lint.line-too-long", &Value::Bool(false)).unwrap();
+        set_option(&mut config, "lint.tab-indent", &Value::Bool(true)).unwrap();
+        assert_eq!(config.disabled_rules, vec!["line-too-long".to_string()]);
+
+        assert_eq!(
+            set_option(&mut config, "lint.tabs", &Value::Bool(false)),
+            Err("unknown rule `tabs`".to_string())
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Combining the options from all of their sources into those for a single file. In increasing
+//! order of precedence, the options come from:
+//!
+//! 1. the defaults;
+//! 2. the properties of the `.editorconfig` files which apply to the file;
+//! 3. the configuration file or `.clang-format` file found upwards from the file, or the one given
+//!    with `--config-path`, or instead of either of those the style given with `--style`, with the
+//!    profile given with `--profile` or the one whose paths match the file applied on top;
+//! 4. the options given on the command line, such as `--indent-width 2`.
+
+use crate::formatter::config::{FormatConfig, Style};
+use crate::settings::editorconfig::EditorConfigs;
+use crate::settings::file::{load_config, ConfigError, ConfigFiles};
+use crate::settings::options::{set_option, ALIASES, OPTIONS};
+use crate::settings::toml::Value;
+use std::path::{Path, PathBuf};
+
+/// Where the options which are not given on the command line come from.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// The configuration file found upwards from the directory of every file.
+    Discovered,
+    /// A configuration file given on the command line.
+    File(PathBuf),
+    /// A style given on the command line.
+    Style(Style),
+}
+
+/// Whether a command line flag such as `--indent-width` names an option.
+pub fn is_option_flag(flag: &str) -> bool {
+    flag.strip_prefix("--").is_some_and(|name| {
+        let name = name.replace('-', "_");
+        OPTIONS.contains(&name.as_str()) || ALIASES.iter().any(|(alias, _)| *alias == name)
+    })
+}
+
+/// The value of an option as it is written on the command line, where the names of the variants
+/// of enums can be written with hyphens, as in `--brace-style kernighan-ritchie`.
+pub fn parse_value(text: &str) -> Value {
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match text.parse() {
+            Ok(number) => Value::Integer(number),
+            Err(_) => Value::String(text.replace('-', "_")),
+        },
+    }
+}
+
+/// Finds the options for every file from their sources.
+#[derive(Debug)]
+pub struct Resolver {
+    source: Source,
+    /// The options given on the command line, by their names in configuration files.
+    overrides: Vec<(String, Value)>,
+    /// The profile of the configuration files which applies to every file, if any.
+    profile: Option<String>,
+    files: ConfigFiles,
+    editorconfigs: EditorConfigs,
+}
+
+impl Resolver {
+    pub fn new(source: Source) -> Resolver {
+        Resolver {
+            source,
+            overrides: Vec::new(),
+            profile: None,
+            files: ConfigFiles::new(),
+            editorconfigs: EditorConfigs::new(),
+        }
+    }
+
+    /// Add an option given on the command line, as in `--indent-width` with `2`. Later options
+    /// override earlier ones. Fails if the value does not suit the option.
+    pub fn add_override(&mut self, flag: &str, text: &str) -> Result<(), String> {
+        let name = flag.trim_start_matches("--").replace('-', "_");
+        let value = parse_value(text);
+        set_option(&mut FormatConfig::default(), &name, &value)?;
+        self.overrides.push((name, value));
+        Ok(())
+    }
+
+    /// Apply the profile of a name to every file, rather than the ones whose paths match them.
+    /// Configuration files which do not define it are an error once they are used.
+    pub fn set_profile(&mut self, name: &str) {
+        self.profile = Some(name.to_string());
+    }
+
+    /// Apply the options given on the command line.
+    pub fn apply_overrides(&self, config: &mut FormatConfig) {
+        for (name, value) in &self.overrides {
+            set_option(config, name, value).expect("overrides are checked when they are added");
+        }
+    }
+
+    /// The options for a file, given as an absolute path.
+    pub fn config_for(&mut self, path: &Path) -> Result<FormatConfig, ConfigError> {
+        let mut base = FormatConfig::default();
+        self.editorconfigs.apply(path, &mut base);
+
+        let profile = self.profile.as_deref();
+        let mut config = match &self.source {
+            Source::Discovered => self.files.config_for(path, base, profile)?,
+            Source::File(file) => load_config(file, base, path, profile)?,
+            Source::Style(style) => style.apply(base),
+        };
+
+        self.apply_overrides(&mut config);
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::config::{BraceStyle, FormatConfig, Style};
+    use crate::settings::file::CONFIG_FILE;
+    use crate::settings::resolve::{is_option_flag, Resolver, Source};
+    use std::fs;
+
+    #[test]
+    fn command_line_options_take_precedence() {
+        assert!(is_option_flag("--indent-width"));
+        assert!(!is_option_flag("--indent"));
+        assert!(!is_option_flag("indent-width"));
+
+        let root = std::env::temp_dir().join(format!("cfmt-resolve-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join(CONFIG_FILE),
+            "based_on = \"kr\"\nindent_width = 3\nuse_tabs = true\n",
+        )
+        .unwrap();
+
+        let mut resolver = Resolver::new(Source::Discovered);
+        resolver.add_override("--indent-width", "2").unwrap();
+        resolver.add_override("--use-tabs", "false").unwrap();
+        resolver.add_override("--brace-style", "allman").unwrap();
+        resolver.add_override("--brace-style", "gnu").unwrap();
+        let expected = FormatConfig {
+            indent_width: 2,
+            brace_style: BraceStyle::Gnu,
+            ..Style::KernighanRitchie.config()
+        };
+        assert_eq!(resolver.config_for(&root.join("main.c")), Ok(expected));
+
+        let mut resolver = Resolver::new(Source::File(root.join(CONFIG_FILE)));
+        assert_eq!(
+            resolver
+                .config_for(&root.join("main.c"))
+                .unwrap()
+                .indent_width,
+            3
+        );
+        // A profile applies on top of the configuration file, below the command line.
+        fs::write(
+            root.join(CONFIG_FILE),
+            "indent_width = 3\n[profile.tests]\nindent_width = 8\nuse_tabs = true\n",
+        )
+        .unwrap();
+        let mut resolver = Resolver::new(Source::File(root.join(CONFIG_FILE)));
+        resolver.set_profile("tests");
+        resolver.add_override("--use-tabs", "false").unwrap();
+        let config = resolver.config_for(&root.join("main.c")).unwrap();
+        assert_eq!((config.indent_width, config.use_tabs), (8, false));
+
+        let mut resolver = Resolver::new(Source::Style(Style::Linux));
+        assert_eq!(
+            resolver.config_for(&root.join("main.c")),
+            Ok(Style::Linux.config())
+        );
+
+        let error = resolver.add_override("--brace-style", "kernighan-ritchie");
+        assert_eq!(error, Ok(()));
+        let error = resolver.add_override("--use-tabs", "2");
+        assert_eq!(
+            error,
+            Err("invalid value for `use_tabs`: expected `true` or `false`, found 2".to_string())
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,374 @@
+//! A reader for the subset of TOML which configuration files need: comments, tables, and keys
+//! with strings, integers, booleans and arrays of those as their values. Keys inside tables are
+//! joined to the name of the table with a period, as in `table.key`.
+
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Integer(i64),
+    String(String),
+    Array(Vec<Value>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{:?}", value),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Integer(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Bool(value) => serializer.serialize_bool(*value),
+            Value::Integer(value) => serializer.serialize_i64(*value),
+            Value::String(value) => serializer.serialize_str(value),
+            Value::Array(values) => serializer.collect_seq(values),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                write!(f, "a boolean, a number, a string or an array")
+            }
+
+            fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+                Ok(Value::Bool(value))
+            }
+
+            fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+                Ok(Value::Integer(value))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Value, E> {
+                i64::try_from(value)
+                    .map(Value::Integer)
+                    .map_err(|_| E::custom(format!("{} is too large", value)))
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+                Ok(Value::String(value.to_string()))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Value, A::Error> {
+                let mut values = Vec::new();
+                while let Some(value) = seq.next_element()? {
+                    values.push(value);
+                }
+                Ok(Value::Array(values))
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// A key with its value, and the line it was written on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+/// A problem in a document, on the line it was found on.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TomlError {
+    pub line: usize,
+    pub message: String,
+}
+
+/// Read the entries of a document in the order in which they were written. Keys which are
+/// defined twice are an error.
+pub fn parse(text: &str) -> Result<Vec<Entry>, TomlError> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut table = String::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message: String| TomlError {
+            line: line_number,
+            message,
+        };
+        let mut reader = Reader::new(line);
+        reader.skip_whitespace();
+        if reader.at_end() {
+            continue;
+        }
+
+        if reader.eat('[') {
+            reader.skip_whitespace();
+            table = reader.key().map_err(error)?;
+            reader.skip_whitespace();
+            if !reader.eat(']') {
+                return Err(error(
+                    "expected `]` after the name of the table".to_string(),
+                ));
+            }
+        } else {
+            let mut key = reader.key().map_err(error)?;
+            if !table.is_empty() {
+                key = format!("{}.{}", table, key);
+            }
+            reader.skip_whitespace();
+            if !reader.eat('=') {
+                return Err(error(format!("expected `=` after `{}`", key)));
+            }
+            reader.skip_whitespace();
+            let value = reader.value().map_err(error)?;
+
+            if entries.iter().any(|entry| entry.key == key) {
+                return Err(error(format!("`{}` is defined more than once", key)));
+            }
+            entries.push(Entry {
+                key,
+                value,
+                line: line_number,
+            });
+        }
+
+        reader.skip_whitespace();
+        if !reader.at_end() {
+            return Err(error("unexpected text at the end of the line".to_string()));
+        }
+    }
+
+    Ok(entries)
+}
+
+/// The position in a single line of a document. Comments count as the end of the line.
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl<'a> Reader<'a> {
+    fn new(line: &'a str) -> Reader<'a> {
+        Reader {
+            chars: line.chars().peekable(),
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        matches!(self.chars.peek(), None | Some('#'))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    /// A key made of bare or quoted parts separated by periods.
+    fn key(&mut self) -> Result<String, String> {
+        let mut parts = Vec::new();
+        loop {
+            let part = match self.chars.peek() {
+                Some('"' | '\'') => self.string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .chars
+                        .next_if(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    {
+                        part.push(c);
+                    }
+                    if part.is_empty() {
+                        return Err("expected a key".to_string());
+                    }
+                    part
+                }
+            };
+            parts.push(part);
+
+            self.skip_whitespace();
+            if !self.eat('.') {
+                return Ok(parts.join("."));
+            }
+            self.skip_whitespace();
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.chars.peek() {
+            Some('"' | '\'') => Ok(Value::String(self.string()?)),
+            Some('[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.eat(']') {
+                        return Ok(Value::Array(values));
+                    }
+                    values.push(self.value()?);
+                    self.skip_whitespace();
+                    if !self.eat(',') {
+                        self.skip_whitespace();
+                        return match self.eat(']') {
+                            true => Ok(Value::Array(values)),
+                            false => Err("expected `,` or `]` in the array".to_string()),
+                        };
+                    }
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '+')
+                {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "" => Err("expected a value".to_string()),
+                    _ => word
+                        .replace('_', "")
+                        .parse()
+                        .map(Value::Integer)
+                        .map_err(|_| format!("`{}` is not a valid value", word)),
+                }
+            }
+        }
+    }
+
+    /// A basic string in double quotes, with escapes, or a literal string in single quotes.
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.chars.next();
+        let mut string = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err("the string is not terminated".to_string()),
+                Some(c) if Some(c) == quote => return Ok(string),
+                Some('\\') if quote == Some('"') => {
+                    let escaped = match self.chars.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => {
+                            let digits: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("`\\u{}` is not a valid escape", digits))?
+                        }
+                        Some(c) => return Err(format!("`\\{}` is not a valid escape", c)),
+                        None => return Err("the string is not terminated".to_string()),
+                    };
+                    string.push(escaped);
+                }
+                Some(c) => string.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::toml::{parse, Entry, TomlError, Value};
+
+    #[test]
+    fn documents() {
+        let entries = parse(
+            "# A comment.\nindent_width = 2 # After a value.\nname = \"a \\\"b\\\"\"\n\
+             \n[table]\n'quoted key' = 'c:\\path'\nlist = [1, -2_000, true, ]\n",
+        )
+        .unwrap();
+
+        let entry = |key: &str, value, line| Entry {
+            key: key.to_string(),
+            value,
+            line,
+        };
+        assert_eq!(
+            entries,
+            vec![
+                entry("indent_width", Value::Integer(2), 2),
+                entry("name", Value::String("a \"b\"".to_string()), 3),
+                entry("table.quoted key", Value::String("c:\\path".to_string()), 6),
+                entry(
+                    "table.list",
+                    Value::Array(vec![
+                        Value::Integer(1),
+                        Value::Integer(-2000),
+                        Value::Bool(true)
+                    ]),
+                    7
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |line, message: &str| {
+            Err(TomlError {
+                line,
+                message: message.to_string(),
+            })
+        };
+        assert_eq!(
+            parse("a = 1\na = 2"),
+            error(2, "`a` is defined more than once")
+        );
+        assert_eq!(parse("a 1"), error(1, "expected `=` after `a`"));
+        assert_eq!(parse("a = yes"), error(1, "`yes` is not a valid value"));
+        assert_eq!(
+            parse("a = \"open"),
+            error(1, "the string is not terminated")
+        );
+        assert_eq!(
+            parse("a = 1 2"),
+            error(1, "unexpected text at the end of the line")
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Statistics about a run of the formatter, written with `--verbose`: how many files were read and
+//! changed, how many lines formatting changed, and how long every phase took, so that the progress
+//! of adopting the formatter can be followed and files which are slow to format stand out.
+
+use std::time::{Duration, Instant};
+
+/// The phases of formatting which are timed.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Phase {
+    Lex,
+    Parse,
+    Print,
+}
+
+/// The statistics of a single file, or of all files together.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Statistics {
+    /// The number of files which were read.
+    pub files: usize,
+    /// The number of files whose formatted code differs from their source.
+    pub changed: usize,
+    /// The number of lines of the sources which formatting changes.
+    pub lines: usize,
+    pub lex: Duration,
+    pub parse: Duration,
+    pub print: Duration,
+    /// The file which took the longest to format, and how long it took.
+    pub slowest: Option<(String, Duration)>,
+}
+
+impl Statistics {
+    /// Run a phase, adding the time it takes to that of the phase.
+    pub fn time<T>(&mut self, phase: Phase, run: impl FnOnce() -> T) -> T {
+        // WebAssembly has no clock of its own without JavaScript, so nothing is timed there.
+        if cfg!(target_arch = "wasm32") {
+            return run();
+        }
+
+        let start = Instant::now();
+        let result = run();
+        let elapsed = start.elapsed();
+        match phase {
+            Phase::Lex => self.lex += elapsed,
+            Phase::Parse => self.parse += elapsed,
+            Phase::Print => self.print += elapsed,
+        }
+        result
+    }
+
+    /// The time taken by all phases together.
+    pub fn total(&self) -> Duration {
+        self.lex + self.parse + self.print
+    }
+
+    /// Add the statistics of a single file, which is named to tell whether it was the slowest.
+    pub fn add(&mut self, path: &str, file: &Statistics) {
+        self.files += file.files;
+        self.changed += file.changed;
+        self.lines += file.lines;
+        self.lex += file.lex;
+        self.parse += file.parse;
+        self.print += file.print;
+
+        let total = file.total();
+        if file.files > 0
+            && self
+                .slowest
+                .as_ref()
+                .is_none_or(|(_, slowest)| total > *slowest)
+        {
+            self.slowest = Some((path.to_string(), total));
+        }
+    }
+
+    /// Render the statistics as lines of text for the standard error.
+    pub fn render(&self) -> String {
+        let plural = |count: usize, noun: &str| {
+            format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+        };
+        let mut result = format!(
+            "{} read, {} changed, {} reformatted\n",
+            plural(self.files, "file"),
+            self.changed,
+            plural(self.lines, "line"),
+        );
+        result += &format!(
+            "lexing {}, parsing {}, printing {}\n",
+            milliseconds(self.lex),
+            milliseconds(self.parse),
+            milliseconds(self.print),
+        );
+        if let Some((path, total)) = &self.slowest {
+            result += &format!("slowest file: {} ({})\n", path, milliseconds(*total));
+        }
+        result
+    }
+}
+
+/// A duration in milliseconds, with a single decimal.
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::statistics::Statistics;
+    use std::time::Duration;
+
+    #[test]
+    fn files_are_added_up() {
+        let file = |changed, lines, millis| Statistics {
+            files: 1,
+            changed,
+            lines,
+            lex: Duration::from_millis(millis),
+            parse: Duration::from_millis(millis),
+            print: Duration::from_millis(millis),
+            slowest: None,
+        };
+
+        let mut statistics = Statistics::default();
+        statistics.add("a.c", &file(1, 3, 1));
+        statistics.add("b.c", &file(0, 0, 2));
+        statistics.add("c.c", &Statistics::default());
+        statistics.add("d.c", &file(1, 1, 1));
+        assert_eq!(
+            statistics.render(),
+            "3 files read, 2 changed, 4 lines reformatted\n\
+             lexing 4.0 ms, parsing 4.0 ms, printing 4.0 ms\n\
+             slowest file: b.c (6.0 ms)\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! Watching directories for files which change, to format them as soon as they are saved. The
+//! files are polled rather than watched through the operating system, which needs no dependencies
+//! and works the same everywhere. Editors often write a file several times when saving it, so a
+//! file is only reported once it has stopped changing for a moment.
+
+use crate::files::collect_files;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the files are polled.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long a file has to stay unchanged before it is reported.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The files found in some paths, together with the time they were last modified.
+#[derive(Debug)]
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    extensions: Vec<String>,
+    modified: HashMap<PathBuf, SystemTime>,
+    /// The files which changed but are not reported yet, with when they were last seen changing.
+    pending: HashMap<PathBuf, Instant>,
+}
+
+impl Watcher {
+    /// Start watching the files in some paths, as they are found by `collect_files`. The files
+    /// which are there already are only reported once they change.
+    pub fn new(paths: Vec<PathBuf>, extensions: Vec<String>) -> io::Result<Watcher> {
+        let mut watcher = Watcher {
+            paths,
+            extensions,
+            modified: HashMap::new(),
+            pending: HashMap::new(),
+        };
+        watcher.modified = watcher.scan()?;
+        Ok(watcher)
+    }
+
+    /// The files which are there now, with the time they were last modified. Files which
+    /// disappear while searching are left out.
+    fn scan(&self) -> io::Result<HashMap<PathBuf, SystemTime>> {
+        let files = collect_files(&self.paths, &self.extensions)?;
+        Ok(files
+            .into_iter()
+            .filter_map(|file| {
+                let modified = fs::metadata(&file).and_then(|metadata| metadata.modified());
+                modified.ok().map(|modified| (file, modified))
+            })
+            .collect())
+    }
+
+    /// Look for files which were added or changed since the last poll, and return those which
+    /// have not changed for a while since, in order of their paths.
+    pub fn poll(&mut self, now: Instant) -> io::Result<Vec<PathBuf>> {
+        let files = self.scan()?;
+        for (file, modified) in &files {
+            if self.modified.get(file) != Some(modified) {
+                self.pending.insert(file.clone(), now);
+            }
+        }
+        self.modified = files;
+        self.pending
+            .retain(|file, _| self.modified.contains_key(file));
+
+        let mut settled: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, changed)| now.duration_since(**changed) >= DEBOUNCE)
+            .map(|(file, _)| file.clone())
+            .collect();
+        settled.sort();
+        for file in &settled {
+            self.pending.remove(file);
+        }
+        Ok(settled)
+    }
+
+    /// Take note of a file which was written by the formatter, so that writing it is not reported
+    /// as a change.
+    pub fn written(&mut self, file: &Path) {
+        if let Ok(modified) = fs::metadata(file).and_then(|metadata| metadata.modified()) {
+            self.modified.insert(file.to_path_buf(), modified);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watch::{Watcher, DEBOUNCE};
+    use std::fs::{self, File};
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[test]
+    fn changes_are_reported_once_they_settle() {
+        let root = std::env::temp_dir().join(format!("cfmt-watch-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.c"), "int a;\n").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+        let touch = |name: &str, seconds: u64| {
+            let file = File::options().write(true).open(root.join(name)).unwrap();
+            let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+            file.set_modified(time).unwrap();
+        };
+
+        let mut watcher = Watcher::new(vec![root.clone()], vec!["c".to_string()]).unwrap();
+        let start = Instant::now();
+        assert!(watcher.poll(start).unwrap().is_empty());
+
+        touch("a.c", 1);
+        fs::write(root.join("b.c"), "").unwrap();
+        touch("notes.txt", 1);
+        assert!(watcher.poll(start).unwrap().is_empty());
+        // Saving again restarts the wait.
+        touch("a.c", 2);
+        assert_eq!(
+            watcher.poll(start + DEBOUNCE).unwrap(),
+            vec![root.join("b.c")]
+        );
+        assert_eq!(
+            watcher.poll(start + DEBOUNCE * 2).unwrap(),
+            vec![root.join("a.c")]
+        );
+
+        touch("a.c", 3);
+        watcher.written(&root.join("a.c"));
+        assert!(watcher.poll(start + DEBOUNCE * 4).unwrap().is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
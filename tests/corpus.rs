@@ -0,0 +1,211 @@
+//! A regression test against well-known C projects, which is the best early warning for gaps in
+//! the grammar. It is ignored by default, as it downloads the sources, and is run with
+//!
+//! ```sh
+//! cargo test --release --test corpus -- --ignored
+//! ```
+//!
+//! The sources are downloaded with `curl` into `target/corpus`, once. Setting `CFMT_CORPUS` to a
+//! directory checks the C files in it instead. Every file must be lexed and parsed without errors,
+//! and formatted into code with the same tokens which stays the same when it is formatted again.
+//!
+//! The formatted code is also compiled with `cc -fsyntax-only`, or the compiler in `CC`, to catch
+//! bugs in the printer which keep the tokens but break the syntax, such as a lost line break after
+//! a directive. Files which the compiler rejects before they are formatted are left out.
+
+use cfmt::diagnostics::diagnostic::Diagnostic;
+use cfmt::formatter::formatter::format;
+use cfmt::formatter::verify::verify_tokens;
+use cfmt::{format_file_contents, lex, parse, Config};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs, process};
+
+/// The projects in the corpus, by name and the URL of their sources.
+const PROJECTS: [(&str, &str); 3] = [
+    ("lua", "https://www.lua.org/ftp/lua-5.4.6.tar.gz"),
+    ("zlib", "https://zlib.net/fossils/zlib-1.3.1.tar.gz"),
+    (
+        "sqlite",
+        "https://www.sqlite.org/2024/sqlite-amalgamation-3450100.zip",
+    ),
+];
+
+/// Download and unpack the sources of a project, unless that was done before.
+fn download(name: &str, url: &str) -> PathBuf {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("target/corpus")
+        .join(name);
+    if directory.is_dir() {
+        return directory;
+    }
+
+    let partial = directory.with_extension("partial");
+    let _ = fs::remove_dir_all(&partial);
+    fs::create_dir_all(&partial).unwrap();
+    let archive = partial.join(url.rsplit('/').next().unwrap());
+    let run = |command: &mut Command| {
+        let status = command.current_dir(&partial).status();
+        assert!(
+            status.is_ok_and(|status| status.success()),
+            "{:?} failed",
+            command
+        );
+    };
+
+    run(Command::new("curl")
+        .args(["-sSfL", "-o"])
+        .arg(&archive)
+        .arg(url));
+    match url.ends_with(".zip") {
+        true => run(Command::new("unzip").arg("-q").arg(&archive)),
+        false => run(Command::new("tar").arg("-xzf").arg(&archive)),
+    }
+    fs::remove_file(&archive).unwrap();
+    fs::rename(&partial, &directory).unwrap();
+    directory
+}
+
+/// The C sources and headers in a directory and its subdirectories.
+fn sources(directory: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            sources(&path, files);
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension == "c" || extension == "h")
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Check a single file, returning what went wrong with it.
+fn check(path: &Path, config: &Config) -> Result<(), String> {
+    // Sources which are not UTF-8 are left out, like the command leaves them alone.
+    let Ok(source) = fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let name = path.to_string_lossy();
+    let rendered = |diagnostic: Diagnostic, source: &str| diagnostic.render(&name, source);
+
+    let lexemes = lex(&source).map_err(|error| rendered(Diagnostic::from(&error), &source))?;
+    let (unit, errors) = parse(lexemes, &source, &name, config);
+    if let Some(error) = errors.first() {
+        return Err(rendered(Diagnostic::from(error), &source));
+    }
+
+    let formatted = format(&unit, config);
+    if let Some(diagnostic) = verify_tokens(&source, &formatted, config) {
+        return Err(rendered(diagnostic, &source));
+    }
+
+    let lexemes =
+        lex(&formatted).map_err(|error| rendered(Diagnostic::from(&error), &formatted))?;
+    let (unit, _) = parse(lexemes, &formatted, &name, config);
+    match format(&unit, config) == formatted {
+        true => Ok(()),
+        false => Err(format!(
+            "{}: the formatted code changes when it is formatted again\n",
+            name
+        )),
+    }
+}
+
+/// The files of the corpus, in order.
+fn corpus() -> Vec<PathBuf> {
+    let directories = match env::var_os("CFMT_CORPUS") {
+        Some(directory) => vec![PathBuf::from(directory)],
+        None => PROJECTS
+            .iter()
+            .map(|(name, url)| download(name, url))
+            .collect(),
+    };
+
+    let mut files = Vec::new();
+    for directory in &directories {
+        sources(directory, &mut files);
+    }
+    files.sort();
+    assert!(!files.is_empty(), "the corpus has no C files");
+    files
+}
+
+/// Check the syntax of a file with the compiler, returning its errors. Quoted includes are found
+/// next to the original file.
+fn compile(path: &Path, original: &Path) -> Result<(), String> {
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let output = Command::new(&compiler)
+        .args(["-fsyntax-only", "-w", "-x", "c", "-I"])
+        .arg(original.parent().unwrap())
+        .arg(path)
+        .output()
+        .unwrap_or_else(|error| panic!("{} cannot be run: {}", compiler, error));
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).into_owned()),
+    }
+}
+
+#[test]
+#[ignore]
+fn files_are_formatted() {
+    let files = corpus();
+    let config = Config::default();
+    let failures: Vec<String> = files
+        .iter()
+        .filter_map(|path| check(path, &config).err())
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} files failed\n\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+#[ignore]
+fn formatted_files_compile() {
+    let files = corpus();
+    let config = Config::default();
+    let directory = env::temp_dir().join(format!("cfmt-differential-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let mut compiled = 0;
+    let mut failures = Vec::new();
+    for path in &files {
+        let Ok(source) = fs::read_to_string(path) else {
+            continue;
+        };
+        let name = path.to_string_lossy();
+        // Files which cannot be formatted fail the other test.
+        let Ok(formatted) = format_file_contents(&source, &name, &config, None) else {
+            continue;
+        };
+        if compile(path, path).is_err() {
+            continue;
+        }
+
+        let copy = directory.join(path.file_name().unwrap());
+        fs::write(&copy, formatted).unwrap();
+        compiled += 1;
+        if let Err(errors) = compile(&copy, path) {
+            failures.push(format!(
+                "{}: the formatted code does not compile\n{}",
+                name, errors
+            ));
+        }
+    }
+    fs::remove_dir_all(directory).unwrap();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} files failed\n\n{}",
+        failures.len(),
+        compiled,
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,66 @@
+//! Snapshot tests of the formatter. Every directory in `tests/format/` holds an `input.c` and the
+//! `expected.c` it is formatted into, along with a `.cfmt.toml` if it needs options other than the
+//! defaults. Running the tests with `CFMT_BLESS=1` writes the formatted code to `expected.c`
+//! instead of comparing it, so that the snapshots are updated after a deliberate change.
+
+use cfmt::{format_file_contents, Config};
+use std::path::Path;
+use std::{env, fs};
+
+#[test]
+fn snapshots() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/format");
+    let bless = env::var_os("CFMT_BLESS").is_some_and(|value| value != "0");
+
+    let mut cases: Vec<_> = fs::read_dir(&root)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "{} has no snapshots", root.display());
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let name = case.file_name().unwrap().to_string_lossy();
+        let input = fs::read_to_string(case.join("input.c")).unwrap();
+        let config = match fs::read_to_string(case.join(".cfmt.toml")) {
+            Ok(text) => Config::builder().config_file(&text).build().unwrap(),
+            Err(_) => Config::default(),
+        };
+
+        let formatted = match format_file_contents(&input, "input.c", &config, None) {
+            Ok(formatted) => formatted,
+            Err(diagnostics) => {
+                failures.push(format!(
+                    "{}:\n{}",
+                    name,
+                    diagnostics.render("input.c", &input)
+                ));
+                continue;
+            }
+        };
+
+        let expected_path = case.join("expected.c");
+        if bless {
+            fs::write(&expected_path, &formatted).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == formatted => {}
+            Ok(expected) => failures.push(format!(
+                "{}: the formatted code differs from expected.c\n--- expected\n{}--- formatted\n{}",
+                name, expected, formatted
+            )),
+            Err(_) => failures.push(format!("{}: expected.c is missing", name)),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} snapshots failed, run with CFMT_BLESS=1 to update them\n\n{}",
+        failures.len(),
+        cases.len(),
+        failures.join("\n")
+    );
+}
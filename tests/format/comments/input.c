@@ -0,0 +1,8 @@
+/* A counter. */
+static int count=0; // the number of calls
+
+int next(void)
+{
+    // Every call counts.
+    return ++count;   /* after the increment */
+}
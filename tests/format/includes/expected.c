@@ -0,0 +1,8 @@
+#include <stdlib.h>
+#include <string.h>
+#include "array.h"
+#include "list.h"
+
+void clear(struct list *list) {
+    memset(list, 0, sizeof *list);
+}
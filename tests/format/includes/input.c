@@ -0,0 +1,6 @@
+#include "list.h"
+#include <string.h>
+#include "array.h"
+#include <stdlib.h>
+
+void clear(struct list *list){memset(list,0,sizeof *list);}
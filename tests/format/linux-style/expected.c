@@ -0,0 +1,12 @@
+struct point {
+	int x, y;
+};
+
+int sum(struct point *p)
+{
+	if (p->x > 0) {
+		return p->x + p->y;
+	} else {
+		return 0;
+	}
+}
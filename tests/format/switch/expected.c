@@ -0,0 +1,12 @@
+const char *name(int c) {
+    switch (c) {
+        case 0:
+            return "zero";
+        case 1:
+        case 2:
+            return "small";
+        default:
+            break;
+    }
+    return "large";
+}